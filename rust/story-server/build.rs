@@ -0,0 +1,4 @@
+fn main() {
+    // Migrations are embedded at compile time; pick up new files without touching the source
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,39 @@
+-- add_scene records scenes created with prose as 'written', which the
+-- baseline CHECK on scenes.status rejected. SQLite cannot alter a CHECK
+-- constraint, so the table is rebuilt. Migrations run before foreign keys
+-- are enabled, so dropping the old table does not cascade to the rows that
+-- reference it. Rowids are kept for the scenes_fts external content table.
+
+CREATE TABLE scenes_new (
+    id TEXT PRIMARY KEY NOT NULL,
+    chapter_id TEXT NOT NULL,
+    title TEXT,
+    position INTEGER NOT NULL,
+    location TEXT,
+    time_description TEXT,
+    content TEXT NOT NULL,
+    word_count INTEGER NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'planned' CHECK(status IN ('planned', 'draft', 'written', 'complete', 'needs_revision')),
+    scene_outline TEXT,
+    ai_generated INTEGER NOT NULL DEFAULT 0,
+    summary TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (chapter_id) REFERENCES chapters(id) ON DELETE CASCADE,
+    UNIQUE(chapter_id, position)
+);
+
+INSERT INTO scenes_new (
+    rowid, id, chapter_id, title, position, location, time_description, content, word_count,
+    status, scene_outline, ai_generated, summary, created_at, updated_at
+)
+SELECT
+    rowid, id, chapter_id, title, position, location, time_description, content, word_count,
+    status, scene_outline, ai_generated, summary, created_at, updated_at
+FROM scenes;
+
+DROP TABLE scenes;
+ALTER TABLE scenes_new RENAME TO scenes;
+
+CREATE INDEX idx_scenes_chapter ON scenes(chapter_id);
+CREATE INDEX idx_scenes_position ON scenes(chapter_id, position);
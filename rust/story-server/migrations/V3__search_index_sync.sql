@@ -0,0 +1,121 @@
+-- The baseline FTS5 tables were external-content tables over columns the
+-- content tables don't have (character_id, rule_id, ...), and nothing kept
+-- them in sync. Recreate them over real columns, keep them current with
+-- triggers, and index the existing rows.
+
+DROP TABLE IF EXISTS characters_fts;
+CREATE VIRTUAL TABLE characters_fts USING fts5(
+    id UNINDEXED,
+    name,
+    personality_traits,
+    physical_description,
+    backstory,
+    content='characters',
+    content_rowid='rowid'
+);
+
+CREATE TRIGGER characters_fts_insert AFTER INSERT ON characters BEGIN
+    INSERT INTO characters_fts (rowid, id, name, personality_traits, physical_description, backstory)
+        VALUES (new.rowid, new.id, new.name, new.personality_traits, new.physical_description, new.backstory);
+END;
+
+CREATE TRIGGER characters_fts_delete AFTER DELETE ON characters BEGIN
+    INSERT INTO characters_fts (characters_fts, rowid, id, name, personality_traits, physical_description, backstory)
+        VALUES ('delete', old.rowid, old.id, old.name, old.personality_traits, old.physical_description, old.backstory);
+END;
+
+CREATE TRIGGER characters_fts_update AFTER UPDATE OF id, name, personality_traits, physical_description, backstory ON characters BEGIN
+    INSERT INTO characters_fts (characters_fts, rowid, id, name, personality_traits, physical_description, backstory)
+        VALUES ('delete', old.rowid, old.id, old.name, old.personality_traits, old.physical_description, old.backstory);
+    INSERT INTO characters_fts (rowid, id, name, personality_traits, physical_description, backstory)
+        VALUES (new.rowid, new.id, new.name, new.personality_traits, new.physical_description, new.backstory);
+END;
+
+INSERT INTO characters_fts (characters_fts) VALUES ('rebuild');
+
+DROP TABLE IF EXISTS world_rules_fts;
+CREATE VIRTUAL TABLE world_rules_fts USING fts5(
+    id UNINDEXED,
+    name,
+    description,
+    examples,
+    keywords,
+    content='world_rules',
+    content_rowid='rowid'
+);
+
+CREATE TRIGGER world_rules_fts_insert AFTER INSERT ON world_rules BEGIN
+    INSERT INTO world_rules_fts (rowid, id, name, description, examples, keywords)
+        VALUES (new.rowid, new.id, new.name, new.description, new.examples, new.keywords);
+END;
+
+CREATE TRIGGER world_rules_fts_delete AFTER DELETE ON world_rules BEGIN
+    INSERT INTO world_rules_fts (world_rules_fts, rowid, id, name, description, examples, keywords)
+        VALUES ('delete', old.rowid, old.id, old.name, old.description, old.examples, old.keywords);
+END;
+
+CREATE TRIGGER world_rules_fts_update AFTER UPDATE OF id, name, description, examples, keywords ON world_rules BEGIN
+    INSERT INTO world_rules_fts (world_rules_fts, rowid, id, name, description, examples, keywords)
+        VALUES ('delete', old.rowid, old.id, old.name, old.description, old.examples, old.keywords);
+    INSERT INTO world_rules_fts (rowid, id, name, description, examples, keywords)
+        VALUES (new.rowid, new.id, new.name, new.description, new.examples, new.keywords);
+END;
+
+INSERT INTO world_rules_fts (world_rules_fts) VALUES ('rebuild');
+
+DROP TABLE IF EXISTS scenes_fts;
+CREATE VIRTUAL TABLE scenes_fts USING fts5(
+    id UNINDEXED,
+    title,
+    content,
+    scene_outline,
+    summary,
+    content='scenes',
+    content_rowid='rowid'
+);
+
+CREATE TRIGGER scenes_fts_insert AFTER INSERT ON scenes BEGIN
+    INSERT INTO scenes_fts (rowid, id, title, content, scene_outline, summary)
+        VALUES (new.rowid, new.id, new.title, new.content, new.scene_outline, new.summary);
+END;
+
+CREATE TRIGGER scenes_fts_delete AFTER DELETE ON scenes BEGIN
+    INSERT INTO scenes_fts (scenes_fts, rowid, id, title, content, scene_outline, summary)
+        VALUES ('delete', old.rowid, old.id, old.title, old.content, old.scene_outline, old.summary);
+END;
+
+CREATE TRIGGER scenes_fts_update AFTER UPDATE OF id, title, content, scene_outline, summary ON scenes BEGIN
+    INSERT INTO scenes_fts (scenes_fts, rowid, id, title, content, scene_outline, summary)
+        VALUES ('delete', old.rowid, old.id, old.title, old.content, old.scene_outline, old.summary);
+    INSERT INTO scenes_fts (rowid, id, title, content, scene_outline, summary)
+        VALUES (new.rowid, new.id, new.title, new.content, new.scene_outline, new.summary);
+END;
+
+INSERT INTO scenes_fts (scenes_fts) VALUES ('rebuild');
+
+DROP TABLE IF EXISTS summaries_fts;
+CREATE VIRTUAL TABLE summaries_fts USING fts5(
+    id UNINDEXED,
+    summary_text,
+    content='story_summaries',
+    content_rowid='rowid'
+);
+
+CREATE TRIGGER summaries_fts_insert AFTER INSERT ON story_summaries BEGIN
+    INSERT INTO summaries_fts (rowid, id, summary_text)
+        VALUES (new.rowid, new.id, new.summary_text);
+END;
+
+CREATE TRIGGER summaries_fts_delete AFTER DELETE ON story_summaries BEGIN
+    INSERT INTO summaries_fts (summaries_fts, rowid, id, summary_text)
+        VALUES ('delete', old.rowid, old.id, old.summary_text);
+END;
+
+CREATE TRIGGER summaries_fts_update AFTER UPDATE OF id, summary_text ON story_summaries BEGIN
+    INSERT INTO summaries_fts (summaries_fts, rowid, id, summary_text)
+        VALUES ('delete', old.rowid, old.id, old.summary_text);
+    INSERT INTO summaries_fts (rowid, id, summary_text)
+        VALUES (new.rowid, new.id, new.summary_text);
+END;
+
+INSERT INTO summaries_fts (summaries_fts) VALUES ('rebuild');
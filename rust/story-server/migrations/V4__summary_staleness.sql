@@ -0,0 +1,30 @@
+-- A summary goes stale when the text it covers changes. Editing a scene's
+-- content marks its own summary stale, along with the chapter, act and
+-- overall summaries that include it. Writing the summary again clears the
+-- mark; invalidateSummary sets it by hand.
+
+ALTER TABLE story_summaries ADD COLUMN stale INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE story_summaries ADD COLUMN stale_reason TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_summaries_stale ON story_summaries(story_project_id, stale);
+
+CREATE TRIGGER scenes_content_stales_summaries AFTER UPDATE OF content ON scenes
+WHEN old.content IS NOT new.content
+BEGIN
+    UPDATE story_summaries
+       SET stale = 1,
+           stale_reason = CASE scope
+               WHEN 'scene' THEN 'Scene content changed'
+               ELSE 'Content of a scene it covers changed'
+           END
+     WHERE stale = 0
+       AND ((scope = 'scene' AND reference_id = new.id)
+         OR (scope = 'chapter' AND reference_id = new.chapter_id)
+         OR (scope = 'act' AND reference_id = (SELECT act_id FROM chapters WHERE id = new.chapter_id))
+         OR (scope = 'overall' AND story_project_id = (
+                SELECT ps.story_project_id
+                  FROM chapters c
+                  JOIN acts a ON c.act_id = a.id
+                  JOIN plot_structures ps ON a.plot_structure_id = ps.id
+                 WHERE c.id = new.chapter_id)));
+END;
@@ -0,0 +1,7 @@
+-- Record which checker raised an alert, so a later run of the same checker
+-- can close the alerts it no longer finds and reopen ones that come back,
+-- without touching alerts recorded some other way (detected_by NULL).
+
+ALTER TABLE continuity_alerts ADD COLUMN detected_by TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_alerts_scene_detector ON continuity_alerts(scene_id, detected_by);
@@ -0,0 +1,4 @@
+-- A character has at most one state snapshot per scene; recording the
+-- state again at the same scene replaces it.
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_state_history_char_scene ON character_state_history(character_id, scene_id);
@@ -0,0 +1,21 @@
+-- A character's sheet under a progression system (level, stats, realm, tier,
+-- skills), versioned per scene like character_state_history. A system is
+-- enabled once per project under a given name.
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_progression_project_name ON progression_systems(story_project_id, system_name);
+
+CREATE TABLE IF NOT EXISTS progression_sheets (
+    id TEXT PRIMARY KEY NOT NULL,
+    progression_system_id TEXT NOT NULL,
+    character_id TEXT NOT NULL,
+    scene_id TEXT NOT NULL,
+    sheet TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (progression_system_id) REFERENCES progression_systems(id) ON DELETE CASCADE,
+    FOREIGN KEY (character_id) REFERENCES characters(id) ON DELETE CASCADE,
+    FOREIGN KEY (scene_id) REFERENCES scenes(id) ON DELETE CASCADE,
+    UNIQUE(progression_system_id, character_id, scene_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_progression_sheets_character ON progression_sheets(character_id);
+CREATE INDEX IF NOT EXISTS idx_progression_sheets_scene ON progression_sheets(scene_id);
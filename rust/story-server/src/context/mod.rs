@@ -1,13 +1,182 @@
-// Context retrieval engine will be implemented in Phase 4 (User Story 2)
-// This module will contain:
-// - Relevance scoring algorithm
-// - Context filtering with budget enforcement
-// - FTS5 search integration
+//! Context retrieval for the scene being written.
+//!
+//! Candidate material (characters, world rules, earlier summaries, open
+//! continuity alerts) is scored for relevance by the signals in [`scoring`],
+//! then the best of it is kept within the caller's token budget by [`select`],
+//! with costs from [`tokens`].
+
+pub mod scoring;
+pub mod tokens;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt;
+
+/// Kind of material that can be loaded into a scene's context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextKind {
+    Character,
+    WorldRule,
+    Summary,
+    ContinuityAlert,
+}
+
+impl fmt::Display for ContextKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ContextKind::Character => "character",
+            ContextKind::WorldRule => "world_rule",
+            ContextKind::Summary => "summary",
+            ContextKind::ContinuityAlert => "continuity_alert",
+        };
+        f.write_str(s)
+    }
+}
+
+/// One piece of candidate context, with the evidence for including it
+#[derive(Debug, Clone)]
+pub struct ContextItem {
+    pub kind: ContextKind,
+    pub id: String,
+    /// Short human-readable name, e.g. a character's name
+    pub label: String,
+    /// What is handed to the writer if the item is selected
+    pub body: Value,
+    pub score: f64,
+    pub reasons: Vec<String>,
+    /// Estimated cost of `body`
+    pub tokens: usize,
+}
+
+impl ContextItem {
+    /// `tokens` is what loading the entity costs, counted as the list tools count it
+    pub fn new(
+        kind: ContextKind,
+        id: impl Into<String>,
+        label: impl Into<String>,
+        body: Value,
+        tokens: usize,
+    ) -> Self {
+        ContextItem {
+            kind,
+            id: id.into(),
+            label: label.into(),
+            body,
+            score: 0.0,
+            reasons: Vec::new(),
+            tokens,
+        }
+    }
+
+    /// Add `points` to the score, recording why
+    pub fn credit(&mut self, points: f64, reason: impl Into<String>) {
+        self.score += points;
+        self.reasons.push(reason.into());
+    }
+
+    /// The body with the score, cost and reasons alongside it
+    pub fn to_json(&self) -> Value {
+        let mut value = self.body.clone();
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert("score".into(), json!(round(self.score)));
+            obj.insert("tokens".into(), json!(self.tokens));
+            obj.insert("reasons".into(), json!(self.reasons));
+        }
+        value
+    }
+
+    /// Just enough to say what was left out and why
+    pub fn to_omitted_json(&self) -> Value {
+        json!({
+            "entityType": self.kind,
+            "entityId": self.id,
+            "label": self.label,
+            "score": round(self.score),
+            "tokens": self.tokens
+        })
+    }
+}
+
+/// Items that fit the budget, in order of relevance, and the relevant ones that did not
+#[derive(Debug, Default)]
+pub struct Selection {
+    pub selected: Vec<ContextItem>,
+    pub omitted: Vec<ContextItem>,
+    pub tokens_used: usize,
+}
+
+/// Keep the highest-scoring items whose combined cost fits in `budget`.
+/// Items with no relevance signal at all are dropped rather than omitted;
+/// an item too large for the space left is skipped so smaller ones can still fit.
+pub fn select(mut items: Vec<ContextItem>, budget: usize) -> Selection {
+    items.retain(|item| item.score > 0.0);
+    items.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(a.tokens.cmp(&b.tokens))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+
+    let mut selection = Selection::default();
+    for item in items {
+        if selection.tokens_used + item.tokens <= budget {
+            selection.tokens_used += item.tokens;
+            selection.selected.push(item);
+        } else {
+            selection.omitted.push(item);
+        }
+    }
+    selection
+}
+
+fn round(score: f64) -> f64 {
+    (score * 100.0).round() / 100.0
+}
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    fn item(id: &str, score: f64, tokens: usize) -> ContextItem {
+        let mut item = ContextItem::new(ContextKind::Character, id, id, json!({"id": id}), tokens);
+        if score > 0.0 {
+            item.credit(score, "test");
+        }
+        item
+    }
+
+    #[test]
+    fn test_select_keeps_best_items_within_budget() {
+        let items = vec![item("a", 2.0, 40), item("b", 9.0, 50), item("c", 5.0, 30), item("d", 0.0, 1)];
+        let selection = select(items, 85);
+
+        let ids: Vec<&str> = selection.selected.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "c"]);
+        assert_eq!(selection.tokens_used, 80);
+        // Irrelevant items are not reported as omitted
+        assert_eq!(selection.omitted.len(), 1);
+        assert_eq!(selection.omitted[0].id, "a");
+    }
+
     #[test]
-    fn test_context_module_exists() {
-        assert!(true);
+    fn test_select_skips_oversized_items() {
+        let items = vec![item("big", 9.0, 500), item("small", 1.0, 10)];
+        let selection = select(items, 100);
+        assert_eq!(selection.selected[0].id, "small");
+        assert_eq!(selection.omitted[0].id, "big");
+    }
+
+    #[test]
+    fn test_item_json_carries_reasons() {
+        let mut item = ContextItem::new(ContextKind::WorldRule, "r-1", "Tides", json!({"ruleId": "r-1"}), 10);
+        item.credit(4.0, "Keyword 'tide' appears in the outline");
+        item.credit(0.5, "Universal rule");
+
+        let value = item.to_json();
+        assert_eq!(value["ruleId"], "r-1");
+        assert_eq!(value["score"], 4.5);
+        assert_eq!(value["reasons"].as_array().unwrap().len(), 2);
+        assert_eq!(item.to_omitted_json()["entityType"], "world_rule");
     }
 }
@@ -0,0 +1,131 @@
+//! Relevance signals and their weights.
+//!
+//! Weights are additive points; only their relative size matters. A direct
+//! mention in the outline outweighs history, and history fades with distance.
+
+/// A character or rule named in the outline
+pub const NAMED_IN_OUTLINE: f64 = 10.0;
+/// A character already cast in the scene being written
+pub const CAST_IN_SCENE: f64 = 10.0;
+/// Each earlier scene a character appeared in, up to [`MAX_COUNTED_APPEARANCES`]
+pub const APPEARANCE: f64 = 1.0;
+pub const MAX_COUNTED_APPEARANCES: usize = 5;
+/// A character's most recent appearance, scaled by [`recency`]
+pub const RECENT_APPEARANCE: f64 = 4.0;
+pub const PROTAGONIST: f64 = 2.0;
+/// Each world-rule keyword found in the outline
+pub const KEYWORD_MATCH: f64 = 4.0;
+pub const UNIVERSAL_RULE: f64 = 0.5;
+/// An earlier scene's summary, scaled by [`recency`]
+pub const SCENE_SUMMARY: f64 = 5.0;
+/// An earlier chapter's summary, scaled by [`recency`] in chapters
+pub const CHAPTER_SUMMARY: f64 = 3.0;
+/// An earlier scene sharing terms with the outline, by full-text search
+pub const SHARES_OUTLINE_TERMS: f64 = 3.0;
+/// An open alert raised on a scene of the same chapter
+pub const ALERT_IN_CHAPTER: f64 = 3.0;
+/// An open alert involving a character named in the outline
+pub const ALERT_INVOLVES_CHARACTER: f64 = 3.0;
+
+/// Points for an open continuity alert of the given severity
+pub fn alert_severity(severity: &str) -> f64 {
+    match severity {
+        "high" => 6.0,
+        "medium" => 4.0,
+        _ => 2.0,
+    }
+}
+
+/// Weight of something `distance` steps back: 1 for the immediately preceding one, halving after that
+pub fn recency(distance: usize) -> f64 {
+    0.5_f64.powi(distance.saturating_sub(1).min(32) as i32)
+}
+
+/// Whether `text` contains `phrase` as whole words, ignoring case
+pub fn mentions(text: &str, phrase: &str) -> bool {
+    let phrase = phrase.trim().to_lowercase();
+    if phrase.is_empty() {
+        return false;
+    }
+    let text = text.to_lowercase();
+    let is_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+
+    text.match_indices(&phrase).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + phrase.len()..].chars().next();
+        !is_word(before) && !is_word(after)
+    })
+}
+
+/// Whether `text` mentions a character, by full name or by a first name of three letters or more
+pub fn mentions_name(text: &str, name: &str) -> bool {
+    if mentions(text, name) {
+        return true;
+    }
+    let mut parts = name.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some(first), Some(_)) if first.chars().count() >= 3 => mentions(text, first),
+        _ => false,
+    }
+}
+
+/// A world rule's stored keywords: a JSON array, or a comma/semicolon-separated string
+pub fn keywords(stored: &str) -> Vec<String> {
+    let list = serde_json::from_str::<Vec<String>>(stored).unwrap_or_else(|_| {
+        stored.split([',', ';']).map(str::to_string).collect()
+    });
+    list.into_iter()
+        .map(|k| k.trim().to_string())
+        .filter(|k| !k.is_empty())
+        .collect()
+}
+
+/// Full-text expression matching any longer word of `text`, for finding related passages
+pub fn any_term_expression(text: &str) -> Option<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let word = word.to_lowercase();
+        if word.chars().count() >= 4 && !terms.contains(&word) {
+            terms.push(word);
+        }
+    }
+    (!terms.is_empty()).then(|| {
+        terms.iter().map(|t| format!("\"{}\"", t)).collect::<Vec<_>>().join(" OR ")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mentions_whole_words() {
+        assert!(mentions("Mira climbs the gate.", "mira"));
+        assert!(mentions("The Silver Gate opens", "silver gate"));
+        assert!(!mentions("Miranda climbs", "Mira"));
+        assert!(!mentions("Admiral Mira", ""));
+        assert!(mentions_name("Vale watches as Mira waits", "Mira Vale"));
+        assert!(!mentions_name("Al leaves", "Al Smith"));
+    }
+
+    #[test]
+    fn test_keywords_formats() {
+        assert_eq!(keywords(r#"["tide", " moon "]"#), vec!["tide", "moon"]);
+        assert_eq!(keywords("tide, moon;salt"), vec!["tide", "moon", "salt"]);
+        assert!(keywords(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_recency_halves() {
+        assert_eq!(recency(1), 1.0);
+        assert_eq!(recency(2), 0.5);
+        assert_eq!(recency(3), 0.25);
+        assert_eq!(recency(0), 1.0);
+    }
+
+    #[test]
+    fn test_any_term_expression() {
+        assert_eq!(any_term_expression("Mira opens the gate, the GATE!").unwrap(), "\"mira\" OR \"opens\" OR \"gate\"");
+        assert!(any_term_expression("a an to").is_none());
+    }
+}
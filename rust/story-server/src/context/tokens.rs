@@ -0,0 +1,354 @@
+//! Token counting for context budgets.
+//!
+//! By default counts are estimated from word and character counts, calibrated
+//! per language. When `STORY_TOKENIZER_VOCAB` points at a BPE vocabulary file,
+//! text is encoded with it instead and counts are exact for that vocabulary.
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Path of a BPE vocabulary in tiktoken format, one `<base64 token> <rank>` per line
+pub const VOCABULARY_ENV: &str = "STORY_TOKENIZER_VOCAB";
+/// Language the estimate is calibrated for; detected from the text when unset
+pub const LANGUAGE_ENV: &str = "STORY_TOKENIZER_LANGUAGE";
+
+/// Splits text into the pieces BPE merges within, as GPT-style tokenizers do
+const PIECE_PATTERN: &str =
+    r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+";
+
+/// Language families with distinct token densities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    /// Spanish, French, Italian, Portuguese
+    Romance,
+    /// German, Dutch, the Scandinavian languages
+    Germanic,
+    Cyrillic,
+    /// Chinese, Japanese, Korean
+    Cjk,
+}
+
+impl Language {
+    /// Average characters per token and tokens per word, measured on prose
+    fn calibration(self) -> (f64, f64) {
+        match self {
+            Language::English => (4.0, 1.3),
+            Language::Romance => (3.6, 1.5),
+            Language::Germanic => (3.4, 1.7),
+            Language::Cyrillic => (2.6, 2.4),
+            // Words are not space-separated, so only characters are meaningful
+            Language::Cjk => (1.1, 0.0),
+        }
+    }
+
+    /// Guess from the script most letters are written in. Latin text is
+    /// treated as English; other Latin languages have to be configured.
+    pub fn detect(text: &str) -> Language {
+        let (mut letters, mut cjk, mut cyrillic) = (0usize, 0usize, 0usize);
+        for c in text.chars().filter(|c| c.is_alphabetic()) {
+            letters += 1;
+            match c as u32 {
+                0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF => cjk += 1,
+                0x0400..=0x04FF => cyrillic += 1,
+                _ => {}
+            }
+        }
+        if letters > 0 && cjk * 3 >= letters {
+            Language::Cjk
+        } else if letters > 0 && cyrillic * 2 >= letters {
+            Language::Cyrillic
+        } else {
+            Language::English
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Language::English => "english",
+            Language::Romance => "romance",
+            Language::Germanic => "germanic",
+            Language::Cyrillic => "cyrillic",
+            Language::Cjk => "cjk",
+        };
+        f.write_str(s)
+    }
+}
+
+impl FromStr for Language {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "english" | "en" => Ok(Language::English),
+            "romance" | "es" | "fr" | "it" | "pt" => Ok(Language::Romance),
+            "germanic" | "de" | "nl" | "sv" | "da" | "no" => Ok(Language::Germanic),
+            "cyrillic" | "ru" | "uk" => Ok(Language::Cyrillic),
+            "cjk" | "zh" | "ja" | "ko" => Ok(Language::Cjk),
+            other => bail!("Unknown tokenizer language: {}", other),
+        }
+    }
+}
+
+/// Estimate from the larger of the word-based and character-based counts, so
+/// a budget built from estimates is rarely exceeded in practice
+pub fn estimate(text: &str, language: Language) -> usize {
+    let (chars_per_token, tokens_per_word) = language.calibration();
+    let by_chars = text.chars().count() as f64 / chars_per_token;
+    let by_words = text.split_whitespace().count() as f64 * tokens_per_word;
+    by_chars.max(by_words).ceil() as usize
+}
+
+/// A byte-level BPE vocabulary: token bytes and their merge ranks
+pub struct BpeVocabulary {
+    ranks: HashMap<Vec<u8>, u32>,
+    pieces: Regex,
+}
+
+impl BpeVocabulary {
+    /// Load a tiktoken-format vocabulary file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read tokenizer vocabulary {:?}", path))?;
+        Self::parse(&text).with_context(|| format!("Invalid tokenizer vocabulary {:?}", path))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut ranks = HashMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let mut fields = line.split_whitespace();
+            let (Some(token), Some(rank), None) = (fields.next(), fields.next(), fields.next()) else {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                bail!("line {}: expected `<base64 token> <rank>`", number + 1);
+            };
+            let bytes = decode_base64(token).with_context(|| format!("line {}: bad token", number + 1))?;
+            let rank = rank.parse().with_context(|| format!("line {}: bad rank", number + 1))?;
+            ranks.insert(bytes, rank);
+        }
+        if ranks.is_empty() {
+            bail!("no tokens");
+        }
+        Ok(BpeVocabulary {
+            ranks,
+            pieces: Regex::new(PIECE_PATTERN).expect("piece pattern is valid"),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranks.is_empty()
+    }
+
+    /// Number of tokens `text` encodes to
+    pub fn count(&self, text: &str) -> usize {
+        self.pieces.find_iter(text).map(|m| self.piece_tokens(m.as_str().as_bytes())).sum()
+    }
+
+    /// Merge adjacent parts of one piece, lowest rank first, until no pair is a token
+    fn piece_tokens(&self, piece: &[u8]) -> usize {
+        if self.ranks.contains_key(piece) {
+            return 1;
+        }
+        // Boundaries between the parts, starting with one part per byte
+        let mut bounds: Vec<usize> = (0..=piece.len()).collect();
+        loop {
+            let best = (0..bounds.len().saturating_sub(2))
+                .filter_map(|i| self.ranks.get(&piece[bounds[i]..bounds[i + 2]]).map(|&rank| (rank, i)))
+                .min();
+            match best {
+                Some((_, i)) => {
+                    bounds.remove(i + 1);
+                }
+                None => return bounds.len() - 1,
+            }
+        }
+    }
+}
+
+/// Counts tokens with a vocabulary when one is configured, by estimate otherwise
+pub enum TokenCounter {
+    /// `None` detects the language of each text
+    Estimate(Option<Language>),
+    Bpe(BpeVocabulary),
+}
+
+impl TokenCounter {
+    /// Configure from `STORY_TOKENIZER_VOCAB` and `STORY_TOKENIZER_LANGUAGE`,
+    /// falling back to estimates if the vocabulary cannot be loaded
+    pub fn from_env() -> Self {
+        if let Ok(path) = std::env::var(VOCABULARY_ENV) {
+            match BpeVocabulary::load(&path) {
+                Ok(vocabulary) => {
+                    log::info!("Counting tokens with {} ({} tokens)", path, vocabulary.len());
+                    return TokenCounter::Bpe(vocabulary);
+                }
+                Err(e) => log::warn!("{:#}; estimating token counts instead", e),
+            }
+        }
+        let language = std::env::var(LANGUAGE_ENV).ok().and_then(|s| match s.parse() {
+            Ok(language) => Some(language),
+            Err(e) => {
+                log::warn!("{}; detecting language instead", e);
+                None
+            }
+        });
+        TokenCounter::Estimate(language)
+    }
+
+    pub fn count(&self, text: &str) -> usize {
+        match self {
+            TokenCounter::Estimate(language) => estimate(text, language.unwrap_or_else(|| Language::detect(text))),
+            TokenCounter::Bpe(vocabulary) => vocabulary.count(text),
+        }
+    }
+
+    /// `bpe` if counts are exact, `estimate` otherwise
+    pub fn method(&self) -> &'static str {
+        match self {
+            TokenCounter::Estimate(_) => "estimate",
+            TokenCounter::Bpe(_) => "bpe",
+        }
+    }
+}
+
+/// The process-wide counter, configured from the environment on first use
+pub fn counter() -> &'static TokenCounter {
+    static COUNTER: OnceLock<TokenCounter> = OnceLock::new();
+    COUNTER.get_or_init(TokenCounter::from_env)
+}
+
+/// Tokens in `text`
+pub fn count(text: &str) -> usize {
+    counter().count(text)
+}
+
+/// Tokens in an entity's text fields, skipping those that are unset
+pub fn cost<'a>(fields: impl IntoIterator<Item = Option<&'a str>>) -> usize {
+    fields.into_iter().flatten().map(count).sum()
+}
+
+/// Tokens to load a character, as `list_characters` reports and context assembly charges
+pub fn character_cost(
+    name: &str,
+    personality_traits: Option<&str>,
+    physical_description: Option<&str>,
+    backstory: Option<&str>,
+    current_state: Option<&str>,
+) -> usize {
+    cost([Some(name), personality_traits, physical_description, backstory, current_state])
+}
+
+/// Tokens to load a world rule, as `list_world_rules` reports and context assembly charges
+pub fn world_rule_cost(name: &str, description: &str, examples: Option<&str>, keywords: Option<&str>) -> usize {
+    cost([Some(name), Some(description), examples, keywords])
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>> {
+    let value = |c: u8| -> Result<u32> {
+        Ok(match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => bail!("invalid base64 character {:?}", c as char),
+        } as u32)
+    };
+
+    let digits = text.trim_end_matches('=').as_bytes();
+    if digits.len() % 4 == 1 {
+        bail!("invalid base64 length");
+    }
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let mut buffer = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            buffer |= value(c)? << (18 - 6 * i);
+        }
+        bytes.extend(&buffer.to_be_bytes()[1..chunk.len()]);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// a b c space ab abc " abc" !
+    const VOCABULARY: &str = "YQ== 0\nYg== 1\nYw== 2\nIA== 3\nYWI= 4\nYWJj 5\nIGFiYw== 6\nIQ== 7\n";
+
+    #[test]
+    fn test_estimate_by_language() {
+        let english = "The lighthouse keeper climbed the stairs.";
+        assert_eq!(estimate(english, Language::English), 11);
+        assert!(estimate(english, Language::Germanic) > estimate(english, Language::English));
+        assert_eq!(estimate("", Language::English), 0);
+
+        // Word-free scripts are counted by character
+        let japanese = "灯台守は階段を登った。";
+        assert_eq!(Language::detect(japanese), Language::Cjk);
+        assert_eq!(estimate(japanese, Language::Cjk), 10);
+        assert_eq!(Language::detect("Смотритель маяка поднялся"), Language::Cyrillic);
+        assert_eq!(Language::detect(english), Language::English);
+        assert_eq!(Language::detect(""), Language::English);
+
+        assert_eq!("fr".parse::<Language>().unwrap(), Language::Romance);
+        assert!("klingon".parse::<Language>().is_err());
+    }
+
+    #[test]
+    fn test_bpe_merges_by_rank() {
+        let vocabulary = BpeVocabulary::parse(VOCABULARY).unwrap();
+        assert_eq!(vocabulary.len(), 8);
+        // Pieces "abc", " abc" and "!"
+        assert_eq!(vocabulary.count("abc abc!"), 3);
+        // a b c a b → ab c ab → abc ab
+        assert_eq!(vocabulary.count("abcab"), 2);
+        // Unknown bytes still cost a token each
+        assert_eq!(vocabulary.count("dd"), 2);
+        assert_eq!(vocabulary.count(""), 0);
+    }
+
+    #[test]
+    fn test_vocabulary_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("vocab.tiktoken");
+        std::fs::write(&path, VOCABULARY).unwrap();
+        let counter = TokenCounter::Bpe(BpeVocabulary::load(&path).unwrap());
+        assert_eq!(counter.method(), "bpe");
+        assert_eq!(counter.count("abc"), 1);
+
+        assert!(BpeVocabulary::parse("YQ== zero").is_err());
+        assert!(BpeVocabulary::parse("Y@== 0").is_err());
+        assert!(BpeVocabulary::parse("\n\n").is_err());
+        assert!(BpeVocabulary::load(dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64("IGFiYw==").unwrap(), b" abc");
+        assert_eq!(decode_base64("YWJj").unwrap(), b"abc");
+        assert_eq!(decode_base64("").unwrap(), b"");
+        assert!(decode_base64("Y").is_err());
+    }
+
+    #[test]
+    fn test_cost_skips_unset_fields() {
+        assert_eq!(cost([Some("Mira"), None, Some("Vale")]), count("Mira") + count("Vale"));
+        assert_eq!(cost([None]), 0);
+    }
+}
@@ -0,0 +1,182 @@
+//! Character state conflicts: characters who take part in a scene although
+//! their recorded state says they are dead or away, or before they first appear.
+
+use super::{CharacterFacts, Finding, SceneFacts};
+use crate::context::scoring::mentions;
+use crate::models::{AlertSeverity, AlertType};
+use serde_json::json;
+
+/// Words in a character's state meaning they cannot act in a scene
+const DEAD: &[&str] = &["dead", "died", "deceased", "killed", "slain", "murdered", "passed away"];
+/// Words meaning they are somewhere else
+const ABSENT: &[&str] = &["absent", "missing", "departed", "exiled", "imprisoned", "captured", "banished", "away"];
+
+/// Why a character's state rules them out of a scene, if it does
+fn unavailable(state: &str) -> Option<(&'static str, AlertSeverity)> {
+    if DEAD.iter().any(|word| mentions(state, word)) {
+        Some(("dead", AlertSeverity::High))
+    } else if ABSENT.iter().any(|word| mentions(state, word)) {
+        Some(("absent", AlertSeverity::Medium))
+    } else {
+        None
+    }
+}
+
+/// Characters taking part in the scene while their state going into it says they are dead or absent
+pub fn check_states(facts: &SceneFacts) -> Vec<Finding> {
+    facts
+        .characters
+        .iter()
+        .filter(|character| character.appears_in(&facts.text))
+        .filter_map(|character| {
+            let state = character.current_state.as_deref()?;
+            let (condition, severity) = unavailable(state)?;
+            // With a history, point at the snapshot that recorded the field saying so
+            let source = character
+                .state_sources
+                .iter()
+                .find(|source| unavailable(&source.value).is_some_and(|(matched, _)| matched == condition));
+            let (description, resolution) = match source.map(|source| &source.set_in) {
+                Some(set_in) => (
+                    format!(
+                        "{} appears in {} but is recorded as {} after {}: \"{}\"",
+                        character.name, facts.label, condition, set_in, state
+                    ),
+                    format!("Remove {} from the scene, or correct their state recorded at {}", character.name, set_in),
+                ),
+                None => (
+                    format!(
+                        "{} appears in {} but is recorded as {}: \"{}\"",
+                        character.name, facts.label, condition, state
+                    ),
+                    format!(
+                        "Remove {} from the scene, or update their current state if it happens before the change",
+                        character.name
+                    ),
+                ),
+            };
+            let mut conflicting = json!({
+                "characterId": character.id,
+                "name": character.name,
+                "currentState": state,
+                "appearsIn": appearance(character, &facts.text)
+            });
+            if let Some(source) = source {
+                conflicting["stateField"] = json!(source.field);
+                conflicting["stateSetIn"] = json!(source.set_in);
+            }
+            Some(Finding {
+                alert_type: AlertType::CharacterStateConflict,
+                severity,
+                description,
+                conflicting_elements: conflicting,
+                suggested_resolution: resolution,
+            })
+        })
+        .collect()
+}
+
+/// Characters taking part in the scene before the scene recorded as their first appearance
+pub fn check_first_appearances(facts: &SceneFacts) -> Vec<Finding> {
+    facts
+        .characters
+        .iter()
+        .filter(|character| character.appears_in(&facts.text))
+        .filter_map(|character| {
+            let first = character.first_appears_later_in.as_deref()?;
+            Some(Finding {
+                alert_type: AlertType::TimelineContradiction,
+                severity: AlertSeverity::Medium,
+                description: format!(
+                    "{} appears in {}, before their first appearance in {}",
+                    character.name, facts.label, first
+                ),
+                conflicting_elements: json!({
+                    "characterId": character.id,
+                    "name": character.name,
+                    "firstAppearance": first,
+                    "appearsIn": appearance(character, &facts.text)
+                }),
+                suggested_resolution: format!(
+                    "Move {}'s first appearance to {}, or take them out of this scene",
+                    character.name, facts.label
+                ),
+            })
+        })
+        .collect()
+}
+
+/// How the character shows up: in the cast, by name in the text, or both
+fn appearance(character: &CharacterFacts, text: &str) -> Vec<&'static str> {
+    let mut how = Vec::new();
+    if character.cast {
+        how.push("cast");
+    }
+    if crate::context::scoring::mentions_name(text, &character.name) {
+        how.push("named in text");
+    }
+    how
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn character(name: &str, state: &str) -> CharacterFacts {
+        CharacterFacts {
+            id: format!("id-{}", name),
+            name: name.into(),
+            current_state: Some(state.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dead_and_absent_characters() {
+        let facts = SceneFacts {
+            label: "chapter 4 scene 1".into(),
+            text: "Orrin laughed while Mira poured the wine.".into(),
+            characters: vec![
+                character("Mira", "Killed by the tide in chapter 3"),
+                character("Orrin", "Exiled to the northern isles"),
+                character("Dax", "Dead"),
+                character("Sela", "Resting at the inn"),
+            ],
+            ..Default::default()
+        };
+
+        let findings = check_states(&facts);
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].conflicting_elements["name"], "Mira");
+        assert_eq!(findings[0].severity, AlertSeverity::High);
+        assert!(findings[0].description.contains("recorded as dead"));
+        assert_eq!(findings[1].conflicting_elements["name"], "Orrin");
+        assert_eq!(findings[1].severity, AlertSeverity::Medium);
+        assert_eq!(findings[1].conflicting_elements["appearsIn"], json!(["named in text"]));
+    }
+
+    #[test]
+    fn test_cast_counts_as_appearing() {
+        let mut dax = character("Dax", "Missing since the storm");
+        dax.cast = true;
+        let facts = SceneFacts { label: "scene".into(), text: "Silence.".into(), characters: vec![dax], ..Default::default() };
+        let findings = check_states(&facts);
+        assert_eq!(findings[0].conflicting_elements["appearsIn"], json!(["cast"]));
+    }
+
+    #[test]
+    fn test_early_appearance() {
+        let mut sela = character("Sela", "");
+        sela.first_appears_later_in = Some("chapter 5 scene 2".into());
+        let facts = SceneFacts {
+            label: "chapter 1 scene 1".into(),
+            text: "Sela waved from the dock.".into(),
+            characters: vec![sela],
+            ..Default::default()
+        };
+        let findings = check_first_appearances(&facts);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].alert_type, AlertType::TimelineContradiction);
+        assert!(findings[0].description.contains("before their first appearance in chapter 5 scene 2"));
+    }
+}
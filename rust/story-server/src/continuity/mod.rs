@@ -1,14 +1,180 @@
-// Continuity checking will be implemented in Phase 7 (User Story 5)
-// This module will contain:
-// - Tier 1 continuity checker (state-based, offline)
-// - World rule violation detector
-// - Character state conflict detector
-// - Timeline contradiction detector
+//! Tier-1 continuity checking: state-based and offline.
+//!
+//! A scene's text is compared with what the database already records about
+//! the story (character states and first appearances, world rules, where
+//! the previous scene took place). Each detector works on plain [`SceneFacts`]
+//! gathered by the caller and reports [`Finding`]s; nothing here touches the
+//! database or needs a model.
+
+pub mod character;
+pub mod timeline;
+pub mod world;
+
+use crate::models::{AlertSeverity, AlertType};
+use serde_json::Value;
+
+/// What is known about the scene being checked
+#[derive(Debug, Clone, Default)]
+pub struct SceneFacts {
+    /// How to refer to the scene in descriptions, e.g. "chapter 2 scene 1"
+    pub label: String,
+    /// The prose, or the outline if nothing is written yet
+    pub text: String,
+    pub location: Option<String>,
+    pub time_description: Option<String>,
+    pub characters: Vec<CharacterFacts>,
+    pub rules: Vec<RuleFacts>,
+    /// The scene before this one in reading order
+    pub previous: Option<PreviousScene>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CharacterFacts {
+    pub id: String,
+    pub name: String,
+    /// The character's state going into the scene: their health, location and notes
+    /// as of their last state snapshots before it, or their current state if they have none
+    pub current_state: Option<String>,
+    /// The parts of that state and where each was recorded, when it came from the history
+    pub state_sources: Vec<StateSource>,
+    /// Listed in the scene's cast
+    pub cast: bool,
+    /// The recorded first appearance, when it comes after the scene being checked
+    pub first_appears_later_in: Option<String>,
+}
+
+/// One field of a character's recorded state and the scene whose snapshot set it
+#[derive(Debug, Clone, Default)]
+pub struct StateSource {
+    pub field: String,
+    pub value: String,
+    /// How to refer to the scene, e.g. "chapter 3 scene 1"
+    pub set_in: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RuleFacts {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub keywords: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PreviousScene {
+    pub label: String,
+    pub text: String,
+    pub location: Option<String>,
+    pub time_description: Option<String>,
+    /// IDs of the characters in its cast
+    pub cast: Vec<String>,
+}
+
+/// A possible continuity problem, ready to be recorded as an alert
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub alert_type: AlertType,
+    pub severity: AlertSeverity,
+    pub description: String,
+    /// The facts that disagree, as stored in `conflicting_elements`
+    pub conflicting_elements: Value,
+    pub suggested_resolution: String,
+}
+
+/// Keys of `conflicting_elements` that say what a finding is about, as opposed to
+/// how it reads today. Scene labels and state text change with renumbering and
+/// rewording; these do not.
+const SUBJECT_KEYS: &[&str] = &["characterId", "ruleId", "systemId", "stat", "skill", "requirement"];
+
+impl Finding {
+    /// The entities this finding is about, used to recognise it when a scene is
+    /// checked again even if its description reads differently
+    pub fn subject(&self) -> Value {
+        subject(&self.conflicting_elements)
+    }
+}
+
+/// The subject of stored `conflicting_elements`; see [`Finding::subject`]
+pub fn subject(elements: &Value) -> Value {
+    let subject: serde_json::Map<String, Value> = SUBJECT_KEYS
+        .iter()
+        .filter_map(|key| elements.get(*key).map(|value| (key.to_string(), value.clone())))
+        .collect();
+    Value::Object(subject)
+}
+
+impl CharacterFacts {
+    /// Whether the character takes part in a scene with this text
+    pub fn appears_in(&self, text: &str) -> bool {
+        self.cast || crate::context::scoring::mentions_name(text, &self.name)
+    }
+}
+
+/// Run every detector over the scene, most severe findings first
+pub fn check(facts: &SceneFacts) -> Vec<Finding> {
+    let mut findings = character::check_states(facts);
+    findings.extend(character::check_first_appearances(facts));
+    findings.extend(world::check_rules(facts));
+    findings.extend(timeline::check_location(facts));
+    findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+    findings
+}
+
+/// The sentences of `text`, trimmed, without empty ones
+pub(crate) fn sentences(text: &str) -> impl Iterator<Item = &str> {
+    text.split(['.', '!', '?', '\n']).map(str::trim).filter(|s| !s.is_empty())
+}
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_orders_by_severity() {
+        let facts = SceneFacts {
+            label: "chapter 1 scene 2".into(),
+            text: "Mira and Dax stood on the pier.".into(),
+            characters: vec![
+                CharacterFacts {
+                    id: "c-1".into(),
+                    name: "Dax".into(),
+                    first_appears_later_in: Some("chapter 3 scene 1".into()),
+                    ..Default::default()
+                },
+                CharacterFacts {
+                    id: "c-2".into(),
+                    name: "Mira".into(),
+                    current_state: Some("Dead, drowned off the pier".into()),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let findings = check(&facts);
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].severity, AlertSeverity::High);
+        assert_eq!(findings[0].alert_type, AlertType::CharacterStateConflict);
+        assert_eq!(findings[1].alert_type, AlertType::TimelineContradiction);
+    }
+
+    #[test]
+    fn test_subject_ignores_wording() {
+        let finding = |label: &str, state: &str| Finding {
+            alert_type: AlertType::CharacterStateConflict,
+            severity: AlertSeverity::High,
+            description: format!("Dax appears in {} but is recorded as dead", label),
+            conflicting_elements: serde_json::json!({"characterId": "c-1", "name": "Dax", "currentState": state}),
+            suggested_resolution: String::new(),
+        };
+        let before = finding("chapter 1 scene 2", "Died at the keep");
+        assert_eq!(before.subject(), finding("chapter 2 scene 1", "Killed at the keep").subject());
+        assert_eq!(before.subject(), serde_json::json!({"characterId": "c-1"}));
+    }
+
     #[test]
-    fn test_continuity_module_exists() {
-        assert!(true);
+    fn test_sentences() {
+        let split: Vec<&str> = sentences("One. Two!\nThree? ").collect();
+        assert_eq!(split, vec!["One", "Two", "Three"]);
     }
 }
@@ -0,0 +1,119 @@
+//! Location jumps: a character carried from one place to another between
+//! consecutive scenes with nothing in the text to account for the journey.
+
+use super::{Finding, SceneFacts};
+use crate::context::scoring::{mentions, mentions_name};
+use crate::models::{AlertSeverity, AlertType};
+use serde_json::json;
+
+/// Words that account for getting from one place to another, or for time passing on the way
+const TRAVEL: &[&str] = &[
+    "travel", "travelled", "traveled", "journey", "journeyed", "arrive", "arrived", "arriving", "rode", "ride",
+    "sailed", "sail", "walked", "flew", "marched", "returned", "reached", "crossed", "left", "set out", "later",
+    "next day", "next morning", "days", "weeks",
+];
+
+/// Characters shared with the previous scene who are now somewhere else, with no travel in between
+pub fn check_location(facts: &SceneFacts) -> Vec<Finding> {
+    let Some(previous) = &facts.previous else {
+        return Vec::new();
+    };
+    let (Some(here), Some(there)) = (non_empty(&facts.location), non_empty(&previous.location)) else {
+        return Vec::new();
+    };
+    if here.eq_ignore_ascii_case(there) {
+        return Vec::new();
+    }
+    // A different time of day or date is itself a transition
+    if let (Some(now), Some(then)) = (non_empty(&facts.time_description), non_empty(&previous.time_description)) {
+        if !now.eq_ignore_ascii_case(then) {
+            return Vec::new();
+        }
+    }
+    if TRAVEL.iter().any(|word| mentions(&facts.text, word)) {
+        return Vec::new();
+    }
+
+    let travellers: Vec<&str> = facts
+        .characters
+        .iter()
+        .filter(|c| c.appears_in(&facts.text))
+        .filter(|c| previous.cast.contains(&c.id) || mentions_name(&previous.text, &c.name))
+        .map(|c| c.name.as_str())
+        .collect();
+    if travellers.is_empty() {
+        return Vec::new();
+    }
+
+    vec![Finding {
+        alert_type: AlertType::TimelineContradiction,
+        severity: AlertSeverity::Low,
+        description: format!(
+            "{} moves from {} to {} between {} and {} with no travel described",
+            travellers.join(", "),
+            there,
+            here,
+            previous.label,
+            facts.label
+        ),
+        conflicting_elements: json!({
+            "characters": travellers,
+            "previousScene": previous.label,
+            "previousLocation": there,
+            "location": here
+        }),
+        suggested_resolution: "Mention the journey or the time that passed, or correct one of the scene locations".into(),
+    }]
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuity::{CharacterFacts, PreviousScene};
+
+    fn facts(text: &str) -> SceneFacts {
+        SceneFacts {
+            label: "chapter 1 scene 2".into(),
+            text: text.into(),
+            location: Some("Harbor".into()),
+            characters: vec![CharacterFacts { id: "c-1".into(), name: "Mira Vance".into(), ..Default::default() }],
+            previous: Some(PreviousScene {
+                label: "chapter 1 scene 1".into(),
+                location: Some("Mountain Keep".into()),
+                cast: vec!["c-1".into()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_location_jump() {
+        let findings = check_location(&facts("Mira watched the ships come in."));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, AlertSeverity::Low);
+        assert_eq!(findings[0].conflicting_elements["characters"], json!(["Mira Vance"]));
+        assert!(findings[0].description.contains("from Mountain Keep to Harbor"));
+    }
+
+    #[test]
+    fn test_travel_or_time_passing_explains_the_move() {
+        assert!(check_location(&facts("Mira arrived at dawn, watching the ships.")).is_empty());
+
+        let mut later = facts("Mira watched the ships come in.");
+        later.time_description = Some("Evening".into());
+        later.previous.as_mut().unwrap().time_description = Some("Morning".into());
+        assert!(check_location(&later).is_empty());
+    }
+
+    #[test]
+    fn test_no_shared_characters() {
+        let mut other = facts("Dax watched the ships come in.");
+        other.previous.as_mut().unwrap().cast.clear();
+        assert!(check_location(&other).is_empty());
+    }
+}
@@ -0,0 +1,153 @@
+//! World rule violations: a scene doing what a rule says cannot be done.
+//!
+//! Only rules phrased as prohibitions are checked ("No one can cross the
+//! Silver Gate after dusk"). The verb after the prohibition is taken as the
+//! forbidden action; a sentence that names one of the rule's keywords and
+//! uses that action, without itself being negated, is reported.
+
+use super::{sentences, Finding, SceneFacts};
+use crate::context::scoring::mentions;
+use crate::models::{AlertSeverity, AlertType};
+use serde_json::json;
+
+/// Phrases introducing what a rule forbids, longest first so "no one can" wins over "can"
+const PROHIBITIONS: &[&str] = &[
+    "no one can",
+    "nobody can",
+    "must never",
+    "must not",
+    "forbidden to",
+    "impossible to",
+    "unable to",
+    "cannot",
+    "can't",
+    "never",
+];
+
+/// Words that may sit between a prohibition and its verb
+const FILLER: &[&str] = &["be", "ever", "to", "a", "an", "the", "any", "even"];
+
+/// Words marking a sentence as itself a denial, e.g. a character restating the rule
+const NEGATIONS: &[&str] = &["not", "no", "never", "cannot", "can't", "couldn't", "didn't", "won't", "nobody"];
+
+/// The forbidden action in a rule's description, as a stem matched against the start of words
+pub fn forbidden_action(description: &str) -> Option<String> {
+    let lower = description.to_lowercase();
+    PROHIBITIONS.iter().find_map(|marker| {
+        let start = find_phrase(&lower, marker)?;
+        let rest = &lower[start + marker.len()..];
+        let verb = rest
+            .split(|c: char| !c.is_alphabetic() && c != '\'')
+            .filter(|w| !w.is_empty())
+            .find(|w| !FILLER.contains(w))?;
+        Some(stem(verb))
+    })
+}
+
+/// Sentences of the scene that do what a rule forbids
+pub fn check_rules(facts: &SceneFacts) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for rule in &facts.rules {
+        let Some(action) = forbidden_action(&rule.description) else {
+            continue;
+        };
+        let offending = sentences(&facts.text).find_map(|sentence| {
+            let keyword = rule.keywords.iter().find(|k| mentions(sentence, k))?;
+            let negated = NEGATIONS.iter().any(|n| mentions(sentence, n));
+            (!negated && uses(sentence, &action)).then_some((sentence, keyword))
+        });
+        if let Some((sentence, keyword)) = offending {
+            findings.push(Finding {
+                alert_type: AlertType::WorldRuleViolation,
+                severity: AlertSeverity::Medium,
+                description: format!("{} may break the world rule \"{}\": {}", facts.label, rule.name, rule.description),
+                conflicting_elements: json!({
+                    "ruleId": rule.id,
+                    "rule": rule.name,
+                    "keyword": keyword,
+                    "forbiddenAction": action,
+                    "passage": sentence
+                }),
+                suggested_resolution: format!(
+                    "Rewrite the passage so it respects \"{}\", or record the exception in the rule",
+                    rule.name
+                ),
+            });
+        }
+    }
+    findings
+}
+
+/// Byte offset of the first whole-word occurrence of `phrase` in already-lowercased `text`
+fn find_phrase(text: &str, phrase: &str) -> Option<usize> {
+    let is_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    text.match_indices(phrase).map(|(start, _)| start).find(|&start| {
+        !is_word(text[..start].chars().next_back()) && !is_word(text[start + phrase.len()..].chars().next())
+    })
+}
+
+/// Drop a trailing silent "e" so "cross" and "crossed", "erase" and "erased" share a stem
+fn stem(verb: &str) -> String {
+    match verb.strip_suffix('e') {
+        Some(stripped) if stripped.chars().count() >= 3 => stripped.to_string(),
+        _ => verb.to_string(),
+    }
+}
+
+/// Whether any word of `sentence` starts with `action`
+fn uses(sentence: &str, action: &str) -> bool {
+    sentence
+        .to_lowercase()
+        .split(|c: char| !c.is_alphabetic())
+        .any(|word| word.starts_with(action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuity::RuleFacts;
+
+    fn gate_rule() -> RuleFacts {
+        RuleFacts {
+            id: "r-1".into(),
+            name: "Gate Curfew".into(),
+            description: "No one can cross the Silver Gate after dusk.".into(),
+            keywords: vec!["Silver Gate".into(), "curfew".into()],
+        }
+    }
+
+    #[test]
+    fn test_forbidden_action() {
+        assert_eq!(forbidden_action("No one can cross the Silver Gate after dusk").as_deref(), Some("cross"));
+        assert_eq!(forbidden_action("Magic cannot be used near iron").as_deref(), Some("used"));
+        assert_eq!(forbidden_action("The dead must never speak").as_deref(), Some("speak"));
+        assert_eq!(forbidden_action("Mages never erase a name").as_deref(), Some("eras"));
+        assert_eq!(forbidden_action("Dragons breathe fire"), None);
+    }
+
+    #[test]
+    fn test_rule_violation_in_scene() {
+        let facts = SceneFacts {
+            label: "chapter 2 scene 3".into(),
+            text: "Night fell. Mira crossed the Silver Gate without a word.".into(),
+            rules: vec![gate_rule()],
+            ..Default::default()
+        };
+        let findings = check_rules(&facts);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].alert_type, AlertType::WorldRuleViolation);
+        assert_eq!(findings[0].conflicting_elements["keyword"], "Silver Gate");
+        assert_eq!(findings[0].conflicting_elements["passage"], "Mira crossed the Silver Gate without a word");
+    }
+
+    #[test]
+    fn test_restating_the_rule_is_not_a_violation() {
+        let facts = SceneFacts {
+            label: "scene".into(),
+            text: "Nobody crosses the Silver Gate at night, she warned. They crossed the bridge instead.".into(),
+            rules: vec![gate_rule()],
+            ..Default::default()
+        };
+        assert!(check_rules(&facts).is_empty());
+    }
+}
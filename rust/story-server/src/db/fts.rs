@@ -0,0 +1,178 @@
+//! FTS5 full-text indexes over the story tables.
+//!
+//! Each index is an external-content table kept in sync by triggers (see
+//! `migrations/V3__search_index_sync.sql`); the functions here check an index
+//! against its content table and rebuild it when they have drifted apart.
+
+use crate::error::Result;
+use rusqlite::{Connection, ErrorCode};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A full-text index and the table it covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchIndex {
+    Characters,
+    WorldRules,
+    Scenes,
+    Summaries,
+}
+
+impl SearchIndex {
+    pub const ALL: [SearchIndex; 4] = [
+        SearchIndex::Characters,
+        SearchIndex::WorldRules,
+        SearchIndex::Scenes,
+        SearchIndex::Summaries,
+    ];
+
+    /// The FTS5 virtual table
+    pub fn table(self) -> &'static str {
+        match self {
+            SearchIndex::Characters => "characters_fts",
+            SearchIndex::WorldRules => "world_rules_fts",
+            SearchIndex::Scenes => "scenes_fts",
+            SearchIndex::Summaries => "summaries_fts",
+        }
+    }
+
+    /// The table whose rows the index covers
+    pub fn content_table(self) -> &'static str {
+        match self {
+            SearchIndex::Characters => "characters",
+            SearchIndex::WorldRules => "world_rules",
+            SearchIndex::Scenes => "scenes",
+            SearchIndex::Summaries => "story_summaries",
+        }
+    }
+}
+
+impl fmt::Display for SearchIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SearchIndex::Characters => "characters",
+            SearchIndex::WorldRules => "world_rules",
+            SearchIndex::Scenes => "scenes",
+            SearchIndex::Summaries => "summaries",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Whether the index matches the current rows of its content table
+pub fn is_consistent(conn: &Connection, index: SearchIndex) -> Result<bool> {
+    // A non-zero rank makes FTS5 compare the index with the content table too
+    let check = format!("INSERT INTO {0} ({0}, rank) VALUES ('integrity-check', 1)", index.table());
+    match conn.execute(&check, []) {
+        Ok(_) => Ok(true),
+        Err(e) if e.sqlite_error_code() == Some(ErrorCode::DatabaseCorrupt) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Re-index every row of the content table from scratch
+pub fn rebuild(conn: &Connection, index: SearchIndex) -> Result<()> {
+    conn.execute(&format!("INSERT INTO {0} ({0}) VALUES ('rebuild')", index.table()), [])?;
+    log::info!("Rebuilt search index: {}", index);
+    Ok(())
+}
+
+/// Number of rows the index covers
+pub fn document_count(conn: &Connection, index: SearchIndex) -> Result<i64> {
+    let count = conn.query_row(&format!("SELECT COUNT(*) FROM {}", index.content_table()), [], |row| row.get(0))?;
+    Ok(count)
+}
+
+/// Rebuild every index that no longer matches its content table, e.g. after
+/// rows were written by a tool that bypassed the triggers. Returns the indexes rebuilt.
+pub fn repair(conn: &Connection) -> Result<Vec<SearchIndex>> {
+    let mut rebuilt = Vec::new();
+    for index in SearchIndex::ALL {
+        if !is_consistent(conn, index)? {
+            log::warn!("Search index {} is out of sync with {}", index, index.content_table());
+            rebuild(conn, index)?;
+            rebuilt.push(index);
+        }
+    }
+    Ok(rebuilt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+
+    fn project(conn: &Connection) -> String {
+        conn.execute(
+            "INSERT INTO story_projects (id, title, intended_length) VALUES ('p-1', 'Indexed', 'novel')",
+            [],
+        )
+        .unwrap();
+        "p-1".to_string()
+    }
+
+    fn matches(conn: &Connection, index: SearchIndex, query: &str) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(&format!("SELECT id FROM {0} WHERE {0} MATCH ?1 ORDER BY rank", index.table()))
+            .unwrap();
+        stmt.query_map([query], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_triggers_keep_indexes_in_sync() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let project_id = project(&conn);
+
+        conn.execute(
+            "INSERT INTO characters (id, story_project_id, name, role, backstory) VALUES ('c-1', ?1, 'Mira', 'protagonist', 'Raised by lighthouse keepers')",
+            [&project_id],
+        )
+        .unwrap();
+        assert_eq!(matches(&conn, SearchIndex::Characters, "lighthouse"), vec!["c-1"]);
+
+        conn.execute("UPDATE characters SET backstory = 'Grew up in the salt marsh' WHERE id = 'c-1'", [])
+            .unwrap();
+        assert!(matches(&conn, SearchIndex::Characters, "lighthouse").is_empty());
+        assert_eq!(matches(&conn, SearchIndex::Characters, "marsh"), vec!["c-1"]);
+
+        conn.execute(
+            "INSERT INTO world_rules (id, story_project_id, name, description, scope) VALUES ('r-1', ?1, 'Tides', 'The sea remembers names', 'universal')",
+            [&project_id],
+        )
+        .unwrap();
+        assert_eq!(matches(&conn, SearchIndex::WorldRules, "remembers"), vec!["r-1"]);
+
+        conn.execute("DELETE FROM characters WHERE id = 'c-1'", []).unwrap();
+        assert!(matches(&conn, SearchIndex::Characters, "marsh").is_empty());
+
+        for index in SearchIndex::ALL {
+            assert!(is_consistent(&conn, index).unwrap(), "{} out of sync", index);
+        }
+    }
+
+    #[test]
+    fn test_repair_rebuilds_drifted_index() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let project_id = project(&conn);
+
+        // Write behind the trigger's back
+        conn.execute("DROP TRIGGER characters_fts_insert", []).unwrap();
+        conn.execute(
+            "INSERT INTO characters (id, story_project_id, name, role) VALUES ('c-1', ?1, 'Dax', 'supporting')",
+            [&project_id],
+        )
+        .unwrap();
+        assert!(!is_consistent(&conn, SearchIndex::Characters).unwrap());
+        assert!(matches(&conn, SearchIndex::Characters, "Dax").is_empty());
+
+        assert_eq!(repair(&conn).unwrap(), vec![SearchIndex::Characters]);
+        assert!(is_consistent(&conn, SearchIndex::Characters).unwrap());
+        assert_eq!(matches(&conn, SearchIndex::Characters, "Dax"), vec!["c-1"]);
+        assert!(repair(&conn).unwrap().is_empty());
+    }
+}
@@ -1,18 +1,30 @@
+pub mod fts;
 pub mod migrations;
+pub mod pool;
+
+pub use pool::ConnectionPool;
 
 use anyhow::{Context, Result};
 use rusqlite::Connection;
 use std::path::Path;
 
 pub fn initialize_database<P: AsRef<Path>>(db_path: P) -> Result<Connection> {
-    let conn = Connection::open(&db_path)
+    let mut conn = Connection::open(&db_path)
         .with_context(|| format!("Failed to open database at {:?}", db_path.as_ref()))?;
 
+    // Migrate with foreign keys off (the bundled SQLite defaults them on):
+    // table rebuilds must not cascade into the rows that reference them
+    conn.execute("PRAGMA foreign_keys = OFF", [])?;
+    migrations::run_migrations(&mut conn)?;
+
     // Enable foreign keys
     conn.execute("PRAGMA foreign_keys = ON", [])?;
 
-    // Run migrations
-    migrations::run_migrations(&conn)?;
+    // Search must reflect the data even if an index drifted while the server was down
+    let rebuilt = fts::repair(&conn)?;
+    if !rebuilt.is_empty() {
+        log::warn!("Rebuilt {} out-of-sync search index(es)", rebuilt.len());
+    }
 
     log::info!("Database initialized at {:?}", db_path.as_ref());
     Ok(conn)
@@ -0,0 +1,209 @@
+//! SQLite connections shared by concurrent sessions.
+//!
+//! The database runs in WAL mode so readers never block the writer or each
+//! other: a fixed set of read-only connections serves queries, while every
+//! write goes through the single writer connection, one at a time.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags};
+use std::path::Path;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+/// Read connections opened by [`ConnectionPool::open`] unless configured otherwise
+pub const DEFAULT_READERS: usize = 4;
+
+/// How long a connection waits on a lock held by another connection before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct ConnectionPool {
+    writer: Mutex<Connection>,
+    readers: Mutex<Vec<Connection>>,
+    reader_returned: Condvar,
+    reader_count: usize,
+}
+
+impl ConnectionPool {
+    /// Open the database at `db_path` with one writer and `readers` read-only connections.
+    /// The writer is opened first so migrations have run before any reader sees the schema.
+    pub fn open<P: AsRef<Path>>(db_path: P, readers: usize) -> Result<Self> {
+        let db_path = db_path.as_ref();
+        let writer = super::initialize_database(db_path)?;
+        let mode: String = writer.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+        if !mode.eq_ignore_ascii_case("wal") {
+            log::warn!("Database at {:?} is in {} mode; reads will wait for writes", db_path, mode);
+        }
+        writer.busy_timeout(BUSY_TIMEOUT)?;
+
+        let readers = (0..readers)
+            .map(|_| open_reader(db_path))
+            .collect::<Result<Vec<_>>>()?;
+        log::info!("Opened connection pool with {} reader(s)", readers.len());
+
+        Ok(ConnectionPool {
+            writer: Mutex::new(writer),
+            reader_count: readers.len(),
+            readers: Mutex::new(readers),
+            reader_returned: Condvar::new(),
+        })
+    }
+
+    /// Pool over a single connection that serves both reads and writes,
+    /// e.g. an in-memory database that other connections cannot open
+    pub fn single(conn: Connection) -> Self {
+        ConnectionPool {
+            writer: Mutex::new(conn),
+            readers: Mutex::new(Vec::new()),
+            reader_returned: Condvar::new(),
+            reader_count: 0,
+        }
+    }
+
+    /// Number of read-only connections; zero means reads share the writer
+    pub fn reader_count(&self) -> usize {
+        self.reader_count
+    }
+
+    /// Run `f` on the writer connection, waiting for any write in progress.
+    /// A write that panicked does not take the writer down with it: its
+    /// transaction is rolled back and the next write proceeds.
+    pub fn write<T>(&self, f: impl FnOnce(&Connection) -> T) -> T {
+        let conn = self.writer.lock().unwrap_or_else(|poisoned| {
+            let conn = poisoned.into_inner();
+            if !conn.is_autocommit() {
+                log::warn!("Rolling back the transaction left open by a failed write");
+                if let Err(e) = conn.execute_batch("ROLLBACK") {
+                    log::error!("Failed to roll back: {}", e);
+                }
+            }
+            conn
+        });
+        f(&conn)
+    }
+
+    /// Run `f` on an idle read-only connection, waiting for one if all are busy
+    pub fn read<T>(&self, f: impl FnOnce(&Connection) -> T) -> T {
+        if self.reader_count == 0 {
+            return self.write(f);
+        }
+
+        let conn = {
+            let mut readers = self.readers.lock().unwrap();
+            loop {
+                match readers.pop() {
+                    Some(conn) => break conn,
+                    None => readers = self.reader_returned.wait(readers).unwrap(),
+                }
+            }
+        };
+        // Hand the connection back even if `f` panics
+        let checkout = Checkout { pool: self, conn: Some(conn) };
+        f(checkout.conn.as_ref().expect("connection checked out"))
+    }
+}
+
+struct Checkout<'a> {
+    pool: &'a ConnectionPool,
+    conn: Option<Connection>,
+}
+
+impl Drop for Checkout<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.readers.lock().unwrap().push(conn);
+            self.pool.reader_returned.notify_one();
+        }
+    }
+}
+
+fn open_reader(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .with_context(|| format!("Failed to open read connection to {:?}", db_path))?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_readers_see_committed_writes_and_cannot_write() {
+        let dir = tempdir().unwrap();
+        let pool = ConnectionPool::open(dir.path().join("pool.db"), 2).unwrap();
+
+        let mode: String = pool.write(|conn| conn.query_row("PRAGMA journal_mode", [], |row| row.get(0))).unwrap();
+        assert_eq!(mode, "wal");
+
+        pool.write(|conn| {
+            conn.execute(
+                "INSERT INTO story_projects (id, title, intended_length) VALUES ('p-1', 'Pooled', 'novel')",
+                [],
+            )
+        })
+        .unwrap();
+
+        let title: String = pool
+            .read(|conn| conn.query_row("SELECT title FROM story_projects WHERE id = 'p-1'", [], |row| row.get(0)))
+            .unwrap();
+        assert_eq!(title, "Pooled");
+
+        let denied = pool.read(|conn| conn.execute("DELETE FROM story_projects", []));
+        assert!(denied.is_err());
+    }
+
+    #[test]
+    fn test_reads_wait_for_a_free_connection() {
+        let dir = tempdir().unwrap();
+        let pool = Arc::new(ConnectionPool::open(dir.path().join("pool.db"), 1).unwrap());
+
+        // More concurrent readers than connections: every read still completes
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let pool = pool.clone();
+                std::thread::spawn(move || {
+                    pool.read(|conn| conn.query_row("SELECT COUNT(*) FROM story_projects", [], |row| row.get::<_, i64>(0)))
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().unwrap(), 0);
+        }
+        assert_eq!(pool.readers.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_single_connection_serves_reads() {
+        let pool = ConnectionPool::single(Connection::open_in_memory().unwrap());
+        assert_eq!(pool.reader_count(), 0);
+        let one: i64 = pool.read(|conn| conn.query_row("SELECT 1", [], |row| row.get(0))).unwrap();
+        assert_eq!(one, 1);
+    }
+
+    #[test]
+    fn test_panicked_write_does_not_block_later_writes() {
+        let pool = ConnectionPool::single(Connection::open_in_memory().unwrap());
+        pool.write(|conn| conn.execute_batch("CREATE TABLE notes (body TEXT)")).unwrap();
+
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            pool.write(|conn| {
+                conn.execute_batch("BEGIN; INSERT INTO notes VALUES ('lost');").unwrap();
+                panic!("handler failed mid-write");
+            })
+        }));
+        assert!(panicked.is_err());
+
+        // The half-done write was rolled back and the writer still works
+        pool.write(|conn| conn.execute("INSERT INTO notes VALUES ('kept')", [])).unwrap();
+        let notes: Vec<String> = pool.read(|conn| {
+            let mut stmt = conn.prepare("SELECT body FROM notes").unwrap();
+            stmt.query_map([], |row| row.get(0)).unwrap().collect::<rusqlite::Result<_>>().unwrap()
+        });
+        assert_eq!(notes, vec!["kept"]);
+    }
+}
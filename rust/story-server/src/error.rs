@@ -1,3 +1,4 @@
+use std::fmt;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -9,16 +10,16 @@ pub enum StoryError {
     SerializationError(#[from] serde_json::Error),
 
     #[error("Not found: {0}")]
-    NotFound(String),
+    NotFound(ErrorDetail),
 
     #[error("Validation error: {0}")]
-    ValidationError(String),
+    ValidationError(ErrorDetail),
 
     #[error("Duplicate entry: {0}")]
-    DuplicateEntry(String),
+    DuplicateEntry(ErrorDetail),
 
     #[error("Invalid state: {0}")]
-    InvalidState(String),
+    InvalidState(ErrorDetail),
 
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
@@ -29,21 +30,98 @@ pub enum StoryError {
 
 pub type Result<T> = std::result::Result<T, StoryError>;
 
+/// Human-readable message plus optional machine-readable context
+/// (the offending field, and the entity the error refers to)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorDetail {
+    pub message: String,
+    pub field: Option<String>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+}
+
+impl ErrorDetail {
+    pub fn new(message: impl Into<String>) -> Self {
+        ErrorDetail {
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for ErrorDetail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
 impl StoryError {
     pub fn not_found(msg: impl Into<String>) -> Self {
-        StoryError::NotFound(msg.into())
+        StoryError::NotFound(ErrorDetail::new(msg))
     }
 
     pub fn validation(msg: impl Into<String>) -> Self {
-        StoryError::ValidationError(msg.into())
+        StoryError::ValidationError(ErrorDetail::new(msg))
     }
 
     pub fn duplicate(msg: impl Into<String>) -> Self {
-        StoryError::DuplicateEntry(msg.into())
+        StoryError::DuplicateEntry(ErrorDetail::new(msg))
     }
 
     pub fn invalid_state(msg: impl Into<String>) -> Self {
-        StoryError::InvalidState(msg.into())
+        StoryError::InvalidState(ErrorDetail::new(msg))
+    }
+
+    /// Record which input field caused the error
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        if let Some(detail) = self.detail_mut() {
+            detail.field = Some(field.into());
+        }
+        self
+    }
+
+    /// Record the entity the error refers to, e.g. the existing project on a duplicate title
+    pub fn with_entity(mut self, entity_type: impl Into<String>, entity_id: impl ToString) -> Self {
+        if let Some(detail) = self.detail_mut() {
+            detail.entity_type = Some(entity_type.into());
+            detail.entity_id = Some(entity_id.to_string());
+        }
+        self
+    }
+
+    /// Structured context, for the variants that carry it
+    pub fn detail(&self) -> Option<&ErrorDetail> {
+        match self {
+            StoryError::NotFound(d)
+            | StoryError::ValidationError(d)
+            | StoryError::DuplicateEntry(d)
+            | StoryError::InvalidState(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    fn detail_mut(&mut self) -> Option<&mut ErrorDetail> {
+        match self {
+            StoryError::NotFound(d)
+            | StoryError::ValidationError(d)
+            | StoryError::DuplicateEntry(d)
+            | StoryError::InvalidState(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Stable snake_case identifier for the error variant
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StoryError::DatabaseError(_) => "database_error",
+            StoryError::SerializationError(_) => "serialization_error",
+            StoryError::NotFound(_) => "not_found",
+            StoryError::ValidationError(_) => "validation_error",
+            StoryError::DuplicateEntry(_) => "duplicate_entry",
+            StoryError::InvalidState(_) => "invalid_state",
+            StoryError::IoError(_) => "io_error",
+            StoryError::Generic(_) => "generic",
+        }
     }
 }
 
@@ -65,4 +143,21 @@ mod tests {
         let err = StoryError::not_found("Character not found");
         assert_eq!(err.to_string(), "Not found: Character not found");
     }
+
+    #[test]
+    fn test_error_context() {
+        let err = StoryError::duplicate("Title taken")
+            .with_field("title")
+            .with_entity("project", "abc");
+        let detail = err.detail().unwrap();
+
+        assert_eq!(err.kind(), "duplicate_entry");
+        assert_eq!(detail.field.as_deref(), Some("title"));
+        assert_eq!(detail.entity_type.as_deref(), Some("project"));
+        assert_eq!(detail.entity_id.as_deref(), Some("abc"));
+        assert_eq!(err.to_string(), "Duplicate entry: Title taken");
+
+        let err = StoryError::Generic("boom".to_string()).with_field("ignored");
+        assert!(err.detail().is_none());
+    }
 }
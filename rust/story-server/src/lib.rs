@@ -47,6 +47,7 @@ pub fn init_logging() {
 #[cfg(test)]
 mod tests {
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_library_loads() {
         assert!(true);
     }
@@ -1,14 +1,68 @@
 use anyhow::Result;
-use log::{error, info};
+use log::info;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use story_server::tools::project;
 use story_server::{db, mcp, init_logging};
 
+/// How the server talks to its MCP client
+#[derive(Debug, PartialEq)]
+enum Transport {
+    /// Newline-delimited JSON-RPC on stdin/stdout (the default)
+    Stdio,
+    /// Streamable HTTP on the given address
+    Http(String),
+    /// Newline-delimited JSON-RPC, one session per TCP connection
+    Tcp(String),
+    /// Newline-delimited JSON-RPC, one session per Unix socket connection
+    Unix(PathBuf),
+}
+
+/// Parse `--transport stdio|http|tcp|unix` and `--bind ADDRESS`.
+/// Network addresses default to `MCP_HOST`/`MCP_PORT`, then `localhost:3000`;
+/// the Unix transport needs an explicit socket path.
+fn parse_transport(args: impl IntoIterator<Item = String>) -> Result<Transport> {
+    let mut transport = "stdio".to_string();
+    let mut bind = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--transport" => transport = args.next().ok_or_else(|| anyhow::anyhow!("--transport needs a value"))?,
+            "--bind" => bind = Some(args.next().ok_or_else(|| anyhow::anyhow!("--bind needs a value"))?),
+            other => anyhow::bail!("Unknown argument: {} (expected --transport stdio|http|tcp|unix, --bind ADDRESS)", other),
+        }
+    }
+
+    let network_addr = |bind: Option<String>| {
+        bind.unwrap_or_else(|| {
+            let host = env::var("MCP_HOST").unwrap_or_else(|_| "localhost".to_string());
+            let port = env::var("MCP_PORT").unwrap_or_else(|_| "3000".to_string());
+            format!("{}:{}", host, port)
+        })
+    };
+
+    match transport.as_str() {
+        "stdio" if bind.is_none() => Ok(Transport::Stdio),
+        "stdio" => anyhow::bail!("--bind does not apply to --transport stdio"),
+        "http" => Ok(Transport::Http(network_addr(bind))),
+        "tcp" => Ok(Transport::Tcp(network_addr(bind))),
+        "unix" => match bind {
+            Some(path) => Ok(Transport::Unix(PathBuf::from(path))),
+            None => anyhow::bail!("--transport unix needs --bind SOCKET_PATH"),
+        },
+        other => anyhow::bail!("Unknown transport: {} (expected stdio, http, tcp or unix)", other),
+    }
+}
+
 fn main() -> Result<()> {
     // Initialize logging
     init_logging();
     info!("Story Server MCP starting...");
 
+    let transport = parse_transport(env::args().skip(1))?;
+
     // Get data directory from environment or use default
     let data_dir = env::var("STORY_DATA_DIR")
         .unwrap_or_else(|_| "data".to_string());
@@ -22,280 +76,600 @@ fn main() -> Result<()> {
 
     // Initialize default database (or could be done lazily per project)
     let db_path = data_path.join("story_server.db");
-    let conn = db::initialize_database(&db_path)?;
+    let readers = match env::var("STORY_DB_READERS") {
+        Ok(readers) => readers.parse()?,
+        Err(_) => db::pool::DEFAULT_READERS,
+    };
+    let pool = db::ConnectionPool::open(&db_path, readers)?;
     info!("Database initialized at {:?}", db_path);
 
+    // Story folders and scene files are written under the stories root
+    let stories_root = env::var(project::STORIES_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("stories"));
+
     // Create tool registry and register tools
-    let mut registry = mcp::ToolRegistry::new(conn);
-    register_tools(&mut registry)?;
-
-    // Create protocol handler
-    let mut protocol = mcp::McpProtocolHandler::new();
-
-    info!("Story Server ready - listening on stdin");
-
-    // Main event loop: read requests from stdin, execute tools, write responses to stdout
-    loop {
-        match protocol.read_request() {
-            Ok(request) => {
-                let id = request.id.clone();
-                let method = request.method.clone();
-
-                // Handle MCP methods
-                match method.as_str() {
-                    "initialize" => {
-                        // MCP initialization handshake
-                        let result = serde_json::json!({
-                            "protocolVersion": "2024-11-05",
-                            "capabilities": {
-                                "tools": {}
-                            },
-                            "serverInfo": {
-                                "name": "story-db",
-                                "version": env!("CARGO_PKG_VERSION")
-                            }
-                        });
-                        if let Err(e) = protocol.send_success(id, result) {
-                            error!("Failed to send initialize response: {}", e);
-                        }
-                    }
-                    "tools/list" => {
-                        // Return list of available tools
-                        let tools = registry.list_tools();
-                        let result = serde_json::json!({
-                            "tools": tools
-                        });
-                        if let Err(e) = protocol.send_success(id, result) {
-                            error!("Failed to send tools/list response: {}", e);
-                        }
-                    }
-                    "tools/call" => {
-                        // Call a tool
-                        if let Some(params) = request.params {
-                            if let Some(tool_name) = params.get("name").and_then(|v| v.as_str()) {
-                                let tool_params = params.get("arguments").cloned().unwrap_or(serde_json::json!({}));
-
-
-                                match registry.call_tool(tool_name, tool_params) {
-                                    Ok(result) => {
-                                        // Wrap result in MCP content format
-                                        let wrapped = serde_json::json!({
-                                            "content": [
-                                                {
-                                                    "type": "text",
-                                                    "text": serde_json::to_string(&result).unwrap_or_else(|_| result.to_string())
-                                                }
-                                            ]
-                                        });
-                                        if let Err(e) = protocol.send_success(id, wrapped) {
-                                            error!("Failed to send success response: {}", e);
-                                        }
-                                    }
-                                    Err(e) => {
-                                        error!("Tool execution error: {}", e);
-                                        if let Err(e) = protocol.send_error(
-                                            id,
-                                            mcp::types::INTERNAL_ERROR,
-                                            e.to_string(),
-                                        ) {
-                                            error!("Failed to send error response: {}", e);
-                                        }
-                                    }
-                                }
-                            } else {
-                                if let Err(e) = protocol.send_error(
-                                    id,
-                                    mcp::types::INVALID_PARAMS,
-                                    "Missing 'name' parameter".to_string(),
-                                ) {
-                                    error!("Failed to send error response: {}", e);
-                                }
-                            }
-                        } else {
-                            if let Err(e) = protocol.send_error(
-                                id,
-                                mcp::types::INVALID_PARAMS,
-                                "Missing parameters".to_string(),
-                            ) {
-                                error!("Failed to send error response: {}", e);
-                            }
-                        }
-                    }
-                    _ => {
-                        if let Err(e) = protocol.send_error(
-                            id,
-                            mcp::types::METHOD_NOT_FOUND,
-                            format!("Unknown method: {}", method),
-                        ) {
-                            error!("Failed to send error response: {}", e);
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                // Check if it's EOF (stdin closed)
-                if e.to_string().contains("No input from stdin") {
-                    info!("stdin closed - shutting down gracefully");
-                    break;
-                }
-                error!("Failed to read request: {}", e);
-                // Continue reading despite errors
-            }
+    let registry = Arc::new(mcp::ToolRegistry::with_pool(pool));
+    register_tools(&registry, &stories_root)?;
+
+    // Requests run on the runtime's blocking pool, so slow reads don't hold up the rest
+    let runtime = tokio::runtime::Runtime::new()?;
+    match transport {
+        Transport::Stdio => {
+            // Serve the MCP session over stdio until the client closes stdin
+            let mut session = mcp::McpSession::new(registry);
+
+            info!("Story Server ready - listening on stdin");
+
+            let stdin = tokio::io::BufReader::new(tokio::io::stdin());
+            runtime.block_on(session.serve_async(stdin, tokio::io::stdout()))?;
+            info!("stdin closed - shutting down gracefully");
         }
+        // Network transports run one MCP session per client, all sharing the registry
+        Transport::Http(addr) => runtime.block_on(mcp::http::serve(registry, addr))?,
+        Transport::Tcp(addr) => runtime.block_on(mcp::socket::serve_tcp(registry, addr))?,
+        #[cfg(unix)]
+        Transport::Unix(path) => runtime.block_on(mcp::socket::serve_unix(registry, path))?,
+        #[cfg(not(unix))]
+        Transport::Unix(_) => anyhow::bail!("Unix domain sockets are not supported on this platform"),
     }
 
     info!("Story Server MCP shutting down");
     Ok(())
 }
 
-fn register_tools(registry: &mut mcp::ToolRegistry) -> Result<()> {
-    use story_server::tools;
-    use serde_json::json;
+/// Register every tool; those that write story files put them under `stories_root`
+fn register_tools(registry: &mcp::ToolRegistry, stories_root: &Path) -> Result<()> {
+    use story_server::mcp::resources;
+    use story_server::tools::{self, arc, character, context, continuity, plot, progression, search, state, summary, world};
+
+    // Each tool's inputSchema is generated from the same struct its handler deserializes,
+    // so tools/list can't drift from what the handler actually reads.
 
     // Project management tools
     registry.register(
         "mcp__story-db__createStoryProject",
         "Create a new story project with title, genre, and plot structure",
-        json!({"type": "object", "properties": {"title": {"type": "string"}, "genre": {"type": "string"}, "targetLength": {"type": "string"}, "plotStructure": {"type": "string"}}, "required": ["title"]}),
-        |conn, params| {
-            tools::create_story_project(conn, params)
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
+        tools::input_schema::<project::CreateStoryProjectParams>(),
+        {
+            let stories_root = stories_root.to_path_buf();
+            move |conn, params| tools::create_story_project(conn, &stories_root, params)
         },
     );
 
-    registry.register(
+    registry.register_read_only(
         "mcp__story-db__loadStoryProject",
         "Load an existing story project by ID",
-        json!({"type": "object", "properties": {"projectId": {"type": "string"}}, "required": ["projectId"]}),
-        |conn, params| {
-            tools::load_story_project(conn, params)
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
-        },
+        tools::input_schema::<project::LoadStoryProjectParams>(),
+        tools::load_story_project,
     );
 
-    registry.register(
+    registry.register_read_only(
         "mcp__story-db__listStoryProjects",
         "List all story projects in the database",
-        json!({"type": "object"}),
-        |conn, params| {
-            tools::list_story_projects(conn, params)
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
-        },
+        tools::input_schema::<project::ListStoryProjectsParams>(),
+        tools::list_story_projects,
     );
 
     // Character management tools
     registry.register(
         "mcp__story-db__addCharacter",
         "Add a new character to a story project",
-        json!({"type": "object", "properties": {"projectId": {"type": "string"}, "name": {"type": "string"}, "role": {"type": "string"}}, "required": ["projectId", "name"]}),
-        |conn, params| {
-            tools::add_character(conn, params)
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
-        },
+        tools::input_schema::<character::AddCharacterParams>(),
+        tools::add_character,
     );
 
-    registry.register(
+    registry.register_read_only(
         "mcp__story-db__getCharacter",
         "Get a character by ID",
-        json!({"type": "object", "properties": {"characterId": {"type": "string"}}, "required": ["characterId"]}),
-        |conn, params| {
-            tools::get_character(conn, params)
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
-        },
+        tools::input_schema::<character::GetCharacterParams>(),
+        tools::get_character,
     );
 
     registry.register(
+        "mcp__story-db__updateCharacter",
+        "Update a character's profile or current state; omitted fields are left unchanged",
+        tools::input_schema::<character::UpdateCharacterParams>(),
+        tools::update_character,
+    );
+
+    registry.register_read_only(
         "mcp__story-db__listCharacters",
         "List all characters in a story project",
-        json!({"type": "object", "properties": {"projectId": {"type": "string"}}, "required": ["projectId"]}),
-        |conn, params| {
-            tools::list_characters(conn, params)
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
-        },
+        tools::input_schema::<character::ListCharactersParams>(),
+        tools::list_characters,
     );
 
     registry.register(
         "mcp__story-db__addCharacterRelationship",
         "Add a relationship between two characters",
-        json!({"type": "object", "properties": {"character1Id": {"type": "string"}, "character2Id": {"type": "string"}, "relationshipType": {"type": "string"}}, "required": ["character1Id", "character2Id", "relationshipType"]}),
-        |conn, params| {
-            tools::add_character_relationship(conn, params)
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
-        },
+        tools::input_schema::<character::AddCharacterRelationshipParams>(),
+        tools::add_character_relationship,
+    );
+
+    registry.register(
+        "mcp__story-db__recordCharacterState",
+        "Record a character's location, health, possessions, knowledge and relationships at a scene; omitted fields carry over from earlier scenes",
+        tools::input_schema::<state::RecordCharacterStateParams>(),
+        tools::record_character_state,
+    );
+
+    registry.register_read_only(
+        "mcp__story-db__getCharacterState",
+        "Get a character's state as of a scene, built from their snapshots in manuscript order",
+        tools::input_schema::<state::GetCharacterStateParams>(),
+        tools::get_character_state,
+    );
+
+    registry.register_read_only(
+        "mcp__story-db__listCharacterStates",
+        "List a character's state snapshots in manuscript order",
+        tools::input_schema::<state::ListCharacterStatesParams>(),
+        tools::list_character_states,
+    );
+
+    // Character arc tools
+    registry.register(
+        "mcp__story-db__createCharacterArc",
+        "Create a character arc from a start state to an end state, with ordered milestones targeting chapters",
+        tools::input_schema::<arc::CreateCharacterArcParams>(),
+        tools::create_character_arc,
+    );
+
+    registry.register(
+        "mcp__story-db__addArcMilestone",
+        "Add a milestone to a character arc, at the end or at a given position",
+        tools::input_schema::<arc::AddArcMilestoneParams>(),
+        tools::add_arc_milestone,
+    );
+
+    registry.register(
+        "mcp__story-db__linkMilestoneScene",
+        "Link an arc milestone to the scene where it happens, completing it and updating the arc's progress",
+        tools::input_schema::<arc::MilestoneSceneParams>(),
+        tools::link_milestone_scene,
+    );
+
+    registry.register(
+        "mcp__story-db__unlinkMilestoneScene",
+        "Remove a milestone's link to a scene; a milestone with no linked scenes is no longer complete",
+        tools::input_schema::<arc::MilestoneSceneParams>(),
+        tools::unlink_milestone_scene,
+    );
+
+    registry.register_read_only(
+        "mcp__story-db__getCharacterArc",
+        "Get a character arc with its milestones and the scenes where they happen",
+        tools::input_schema::<arc::GetCharacterArcParams>(),
+        tools::get_character_arc,
+    );
+
+    registry.register_read_only(
+        "mcp__story-db__listCharacterArcs",
+        "List a project's character arcs, optionally for one character",
+        tools::input_schema::<arc::ListCharacterArcsParams>(),
+        tools::list_character_arcs,
+    );
+
+    registry.register_read_only(
+        "mcp__story-db__getArcScheduleReport",
+        "Report character arcs whose milestones are behind schedule relative to the chapter being drafted",
+        tools::input_schema::<arc::GetArcScheduleReportParams>(),
+        tools::get_arc_schedule_report,
     );
 
     // World building tools
     registry.register(
         "mcp__story-db__addWorldRule",
         "Add a world-building rule (magic system, technology, social structure, etc.)",
-        json!({"type": "object", "properties": {"projectId": {"type": "string"}, "ruleName": {"type": "string"}, "description": {"type": "string"}}, "required": ["projectId", "ruleName", "description"]}),
-        |conn, params| {
-            tools::add_world_rule(conn, params)
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
-        },
+        tools::input_schema::<world::AddWorldRuleParams>(),
+        tools::add_world_rule,
     );
 
-    registry.register(
+    registry.register_read_only(
         "mcp__story-db__getWorldRule",
         "Get a world rule by ID",
-        json!({"type": "object", "properties": {"ruleId": {"type": "string"}}, "required": ["ruleId"]}),
-        |conn, params| {
-            tools::get_world_rule(conn, params)
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
-        },
+        tools::input_schema::<world::GetWorldRuleParams>(),
+        tools::get_world_rule,
     );
 
     registry.register(
+        "mcp__story-db__updateWorldRule",
+        "Update a world rule's description, scope, examples or keywords",
+        tools::input_schema::<world::UpdateWorldRuleParams>(),
+        tools::update_world_rule,
+    );
+
+    registry.register_read_only(
         "mcp__story-db__listWorldRules",
         "List all world rules for a story project",
-        json!({"type": "object", "properties": {"projectId": {"type": "string"}}, "required": ["projectId"]}),
-        |conn, params| {
-            tools::list_world_rules(conn, params)
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
-        },
+        tools::input_schema::<world::ListWorldRulesParams>(),
+        tools::list_world_rules,
     );
 
     // Plot structure tools
     registry.register(
         "mcp__story-db__initializePlotStructure",
         "Initialize plot structure for a story (three-act, five-act, hero's journey)",
-        json!({"type": "object", "properties": {"projectId": {"type": "string"}, "structureType": {"type": "string"}}, "required": ["projectId", "structureType"]}),
-        |conn, params| {
-            tools::initialize_plot_structure(conn, params)
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
-        },
+        tools::input_schema::<plot::InitializePlotStructureParams>(),
+        tools::initialize_plot_structure,
     );
 
     registry.register(
         "mcp__story-db__addChapter",
         "Add a chapter to the story",
-        json!({"type": "object", "properties": {"projectId": {"type": "string"}, "chapterNumber": {"type": "number"}, "title": {"type": "string"}}, "required": ["projectId", "chapterNumber"]}),
-        |conn, params| {
-            tools::add_chapter(conn, params)
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
-        },
+        tools::input_schema::<plot::AddChapterParams>(),
+        tools::add_chapter,
     );
 
     registry.register(
         "mcp__story-db__addScene",
         "Add a scene to a chapter",
-        json!({"type": "object", "properties": {"chapterId": {"type": "string"}, "sceneNumber": {"type": "number"}, "content": {"type": "string"}}, "required": ["chapterId", "sceneNumber"]}),
-        |conn, params| {
-            tools::add_scene(conn, params)
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
+        tools::input_schema::<plot::AddSceneParams>(),
+        {
+            let stories_root = stories_root.to_path_buf();
+            move |conn, params| tools::add_scene(conn, &stories_root, params)
         },
     );
 
     registry.register(
+        "mcp__story-db__updateScene",
+        "Update a scene's title, location, time, outline or prose; rewriting it re-checks its continuity alerts",
+        tools::input_schema::<plot::UpdateSceneParams>(),
+        {
+            let stories_root = stories_root.to_path_buf();
+            move |conn, params| tools::update_scene(conn, &stories_root, params)
+        },
+    );
+
+    registry.register_read_only(
         "mcp__story-db__getPlotStructure",
         "Get the plot structure for a story project",
-        json!({"type": "object", "properties": {"projectId": {"type": "string"}}, "required": ["projectId"]}),
-        |conn, params| {
-            tools::get_plot_structure(conn, params)
-                .map_err(|e| anyhow::anyhow!(e.to_string()))
-        },
+        tools::input_schema::<plot::GetPlotStructureParams>(),
+        tools::get_plot_structure,
+    );
+
+    // Progression system tools
+    registry.register_read_only(
+        "mcp__story-db__listProgressionTemplates",
+        "List the built-in templates for game_stats, cultivation, magic_tiers and skill_trees systems",
+        tools::input_schema::<progression::ListProgressionTemplatesParams>(),
+        tools::list_progression_templates,
+    );
+
+    registry.register_read_only(
+        "mcp__story-db__validateSystemDefinition",
+        "Check a custom progression system written in TOML or JSON and preview its derived stats at starting values",
+        tools::input_schema::<progression::ValidateSystemDefinitionParams>(),
+        tools::validate_system_definition,
+    );
+
+    registry.register(
+        "mcp__story-db__enableProgressionSystem",
+        "Enable a progression system for a project with the built-in template for its type, a custom template, or a custom system defined in TOML or JSON",
+        tools::input_schema::<progression::EnableProgressionSystemParams>(),
+        tools::enable_progression_system,
+    );
+
+    registry.register(
+        "mcp__story-db__disableProgressionSystem",
+        "Disable a progression system; recorded sheets are kept",
+        tools::input_schema::<progression::DisableProgressionSystemParams>(),
+        tools::disable_progression_system,
     );
 
+    registry.register_read_only(
+        "mcp__story-db__listProgressionSystems",
+        "List a project's progression systems with their templates",
+        tools::input_schema::<progression::ListProgressionSystemsParams>(),
+        tools::list_progression_systems,
+    );
+
+    registry.register(
+        "mcp__story-db__recordProgressionSheet",
+        "Record a character's level, stats, realm, tier or skills at a scene; omitted fields carry over from earlier scenes",
+        tools::input_schema::<progression::RecordProgressionSheetParams>(),
+        tools::record_progression_sheet,
+    );
+
+    registry.register_read_only(
+        "mcp__story-db__getProgressionSheet",
+        "Get a character's progression sheet as of a scene, built from their snapshots in manuscript order",
+        tools::input_schema::<progression::GetProgressionSheetParams>(),
+        tools::get_progression_sheet,
+    );
+
+    registry.register_read_only(
+        "mcp__story-db__listProgressionSheets",
+        "List a character's progression sheet snapshots in manuscript order",
+        tools::input_schema::<progression::ListProgressionSheetsParams>(),
+        tools::list_progression_sheets,
+    );
+
+    registry.register_read_only(
+        "mcp__story-db__renderStatusScreen",
+        "Render a character's level, stats and skills at a scene as a status screen (box-drawing text, Markdown or HTML) with changes since the previous snapshot",
+        tools::input_schema::<progression::RenderStatusScreenParams>(),
+        tools::render_status_screen,
+    );
+
+    registry.register(
+        "mcp__story-db__checkProgression",
+        "Check characters' progression sheets against the system's rules scene by scene and record violations as continuity alerts",
+        tools::input_schema::<progression::CheckProgressionParams>(),
+        tools::check_progression,
+    );
+
+    // Context tools
+    registry.register_read_only(
+        "mcp__story-db__getSceneContext",
+        "Assemble the characters, world rules, earlier summaries and open continuity alerts most relevant to a scene, scored and trimmed to a token budget",
+        tools::input_schema::<context::GetSceneContextParams>(),
+        tools::get_scene_context,
+    );
+
+    // Continuity tools
+    registry.register(
+        "mcp__story-db__checkSceneContinuity",
+        "Check a scene against recorded character states, first appearances, world rules and the previous scene's location, recording problems as continuity alerts",
+        tools::input_schema::<continuity::CheckSceneContinuityParams>(),
+        tools::check_scene_continuity,
+    );
+
+    registry.register_read_only(
+        "mcp__story-db__listContinuityAlerts",
+        "List a project's continuity alerts in manuscript order, filtered by chapter, scene, severity or author decision",
+        tools::input_schema::<continuity::ListContinuityAlertsParams>(),
+        tools::list_continuity_alerts,
+    );
+
+    registry.register(
+        "mcp__story-db__resolveContinuityAlert",
+        "Resolve a continuity alert as revised_content, updated_fact or dismissed, with optional notes",
+        tools::input_schema::<continuity::ResolveContinuityAlertParams>(),
+        tools::resolve_continuity_alert,
+    );
+
+    // Summary tools
+    registry.register(
+        "mcp__story-db__upsertSummary",
+        "Create or replace the summary of a scene, chapter, act or the whole project",
+        tools::input_schema::<summary::UpsertSummaryParams>(),
+        tools::upsert_summary,
+    );
+
+    registry.register_read_only(
+        "mcp__story-db__getSummary",
+        "Get the summary of a scene, chapter, act or the whole project",
+        tools::input_schema::<summary::GetSummaryParams>(),
+        tools::get_summary,
+    );
+
+    registry.register_read_only(
+        "mcp__story-db__listSummaries",
+        "List a project's summaries in story order, optionally by scope or only those that are stale",
+        tools::input_schema::<summary::ListSummariesParams>(),
+        tools::list_summaries,
+    );
+
+    registry.register(
+        "mcp__story-db__invalidateSummary",
+        "Mark a summary as stale so it gets rewritten",
+        tools::input_schema::<summary::InvalidateSummaryParams>(),
+        tools::invalidate_summary,
+    );
+
+    registry.register_read_only(
+        "mcp__story-db__getSummaryRollup",
+        "Get a project's summaries as a tree: overall, acts, chapters and scenes, with gaps and stale summaries counted",
+        tools::input_schema::<summary::GetSummaryRollupParams>(),
+        tools::get_summary_rollup,
+    );
+
+    // Search tools
+    registry.register_read_only(
+        "mcp__story-db__search",
+        "Full-text search across a project's characters, world rules, scenes and summaries, with highlighted snippets, taking the best match of each kind in turn",
+        tools::input_schema::<search::SearchParams>(),
+        tools::search,
+    );
+
+    registry.register(
+        "mcp__story-db__rebuildSearchIndex",
+        "Rebuild full-text search indexes from the story tables, reporting any that had drifted",
+        tools::input_schema::<search::RebuildSearchIndexParams>(),
+        tools::rebuild_search_index,
+    );
+
+    // Resources touched by write tools, for notifications/resources/updated
+    registry.track_changes("mcp__story-db__addCharacter", resources::character_changes);
+    registry.track_changes("mcp__story-db__updateCharacter", resources::character_changes);
+    registry.track_changes("mcp__story-db__addCharacterRelationship", resources::relationship_changes);
+    registry.track_changes("mcp__story-db__addWorldRule", resources::world_rule_changes);
+    registry.track_changes("mcp__story-db__updateWorldRule", resources::world_rule_changes);
+    registry.track_changes("mcp__story-db__initializePlotStructure", resources::project_changes);
+    registry.track_changes("mcp__story-db__addChapter", resources::chapter_changes);
+    registry.track_changes("mcp__story-db__addScene", resources::scene_changes);
+    registry.track_changes("mcp__story-db__updateScene", resources::scene_changes);
+
+    // Sheet tools are only offered, and only accept calls, while some project has a
+    // progression system enabled; the registry announces when that changes. The tool
+    // list is server-wide, so a system enabled in any project makes them available.
+    let any_enabled = |conn: &rusqlite::Connection| progression::enabled_system_count(conn).unwrap_or(0) > 0;
+    for name in [
+        "mcp__story-db__recordProgressionSheet",
+        "mcp__story-db__getProgressionSheet",
+        "mcp__story-db__listProgressionSheets",
+        "mcp__story-db__renderStatusScreen",
+        "mcp__story-db__checkProgression",
+    ] {
+        registry.list_when(name, any_enabled);
+    }
+
     info!("Registered {} MCP tools", registry.list_tools().len());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Map, Value};
+    use story_server::mcp::ToolCallError;
+    use tempfile::tempdir;
+
+    /// Build a value that satisfies a property schema so the handler gets past argument parsing
+    fn sample_value(schema: &Value) -> Value {
+        if let Some(first) = schema.get("enum").and_then(|v| v.as_array()).and_then(|v| v.first()) {
+            return first.clone();
+        }
+        if let Some(first) = schema.get("anyOf").and_then(|v| v.as_array()).and_then(|v| v.first()) {
+            return sample_value(first);
+        }
+        if schema.get("format").and_then(|v| v.as_str()) == Some("uuid") {
+            return json!(uuid::Uuid::new_v4().to_string());
+        }
+        let ty = match schema.get("type") {
+            Some(Value::Array(types)) => types.first().and_then(|t| t.as_str()).unwrap_or("string"),
+            Some(Value::String(t)) => t.as_str(),
+            _ => "string",
+        };
+        match ty {
+            "integer" | "number" => json!(1),
+            "boolean" => json!(true),
+            "array" => json!([sample_value(schema.get("items").unwrap_or(&json!({})))]),
+            "object" => sample_arguments(schema, true),
+            _ => json!("sample"),
+        }
+    }
+
+    fn sample_arguments(schema: &Value, required_only: bool) -> Value {
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(|v| v.as_array())
+            .map(|r| r.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default();
+        let mut args = Map::new();
+        if let Some(properties) = schema.get("properties").and_then(|v| v.as_object()) {
+            for (name, property) in properties {
+                if !required_only || required.contains(&name.as_str()) {
+                    args.insert(name.clone(), sample_value(property));
+                }
+            }
+        }
+        Value::Object(args)
+    }
+
+    #[test]
+    fn test_parse_transport() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(parse_transport(args(&[])).unwrap(), Transport::Stdio);
+        assert_eq!(
+            parse_transport(args(&["--transport", "http", "--bind", "127.0.0.1:8080"])).unwrap(),
+            Transport::Http("127.0.0.1:8080".to_string())
+        );
+        assert_eq!(
+            parse_transport(args(&["--transport", "unix", "--bind", "/tmp/story.sock"])).unwrap(),
+            Transport::Unix(PathBuf::from("/tmp/story.sock"))
+        );
+        assert!(parse_transport(args(&["--transport", "unix"])).is_err());
+        assert!(parse_transport(args(&["--transport", "carrier-pigeon"])).is_err());
+        assert!(parse_transport(args(&["--bind", "127.0.0.1:8080"])).is_err());
+        assert!(parse_transport(args(&["--verbose"])).is_err());
+    }
+
+    #[test]
+    fn test_registered_schemas_match_handlers() {
+        let dir = tempdir().unwrap();
+        let conn = db::initialize_database(dir.path().join("test.db")).unwrap();
+        let registry = mcp::ToolRegistry::new(conn);
+        register_tools(&registry, &dir.path().join("stories")).unwrap();
+        // Make the sheet tools callable too
+        let project = registry.call_tool("mcp__story-db__createStoryProject", json!({"title": "Drift", "targetLength": "novel"})).unwrap();
+        let enable = json!({"projectId": project["projectId"], "systemType": "game_stats"});
+        registry.call_tool("mcp__story-db__enableProgressionSystem", enable).unwrap();
+
+        for tool in registry.list_tools() {
+            for required_only in [false, true] {
+                let args = sample_arguments(&tool.input_schema, required_only);
+                match registry.call_tool(&tool.name, args.clone()) {
+                    Err(ToolCallError::InvalidParams { violations, .. }) => {
+                        panic!("{} rejected arguments built from its own schema {}: {:?}", tool.name, args, violations)
+                    }
+                    Err(ToolCallError::Execution(e)) if e.to_string().contains("Missing required field") => {
+                        panic!("{} schema has drifted from its handler: {} (args {})", tool.name, e, args)
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Send one request through the session and return its response as JSON
+    fn rpc(session: &mut mcp::McpSession, id: i64, method: &str, params: Value) -> Value {
+        let request = serde_json::from_value(json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})).unwrap();
+        serde_json::to_value(session.handle(request).unwrap()).unwrap()
+    }
+
+    /// Call a tool through the session and return its parsed result
+    fn call(session: &mut mcp::McpSession, id: i64, tool: &str, arguments: Value) -> Value {
+        let params = json!({"name": format!("mcp__story-db__{}", tool), "arguments": arguments});
+        let response = rpc(session, id, "tools/call", params);
+        serde_json::from_str(response["result"]["content"][0]["text"].as_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_enabling_progression_announces_sheet_tools() {
+        let dir = tempdir().unwrap();
+        let conn = db::initialize_database(dir.path().join("test.db")).unwrap();
+        let registry = mcp::ToolRegistry::new(conn);
+        register_tools(&registry, &dir.path().join("stories")).unwrap();
+        let mut session = mcp::McpSession::new(registry);
+        let sheet_tools_listed = |session: &mut mcp::McpSession, id: i64| {
+            let tools = rpc(session, id, "tools/list", json!({}));
+            tools["result"]["tools"].as_array().unwrap().iter().any(|t| t["name"] == "mcp__story-db__recordProgressionSheet")
+        };
+        let announced = |session: &mut mcp::McpSession| {
+            session.take_notifications().iter().filter(|n| n.method == "notifications/tools/list_changed").count()
+        };
+
+        rpc(&mut session, 1, "initialize", json!({"protocolVersion": "2024-11-05"}));
+        assert!(!sheet_tools_listed(&mut session, 2));
+
+        let project = call(&mut session, 3, "createStoryProject", json!({"title": "Levels", "targetLength": "novel"}));
+        let arguments = json!({"characterId": uuid::Uuid::new_v4(), "systemId": uuid::Uuid::new_v4()});
+        let sheets = json!({"name": "mcp__story-db__listProgressionSheets", "arguments": arguments});
+        let error = |session: &mut mcp::McpSession, id: i64, params: &Value| rpc(session, id, "tools/call", params.clone())["error"].clone();
+        assert!(error(&mut session, 4, &sheets)["message"].as_str().unwrap().starts_with("Tool not available"));
+
+        let enable = json!({"projectId": project["projectId"], "systemType": "game_stats"});
+        let system = call(&mut session, 5, "enableProgressionSystem", enable.clone());
+        assert_eq!(announced(&mut session), 1);
+        assert!(sheet_tools_listed(&mut session, 6));
+        assert!(error(&mut session, 7, &sheets)["message"].as_str().unwrap().starts_with("Not found"));
+
+        // Re-enabling it, or adding a second system, leaves the list as it was
+        call(&mut session, 8, "enableProgressionSystem", enable);
+        let second = call(&mut session, 9, "enableProgressionSystem", json!({"projectId": project["projectId"], "systemType": "cultivation"}));
+        assert_eq!(announced(&mut session), 0);
+
+        call(&mut session, 10, "disableProgressionSystem", json!({"systemId": second["systemId"]}));
+        assert_eq!(announced(&mut session), 0);
+        call(&mut session, 11, "disableProgressionSystem", json!({"systemId": system["systemId"]}));
+        assert_eq!(announced(&mut session), 1);
+        assert!(!sheet_tools_listed(&mut session, 12));
+    }
+
+    #[test]
+    fn test_every_tool_declares_a_closed_object_schema() {
+        let dir = tempdir().unwrap();
+        let conn = db::initialize_database(dir.path().join("test.db")).unwrap();
+        let registry = mcp::ToolRegistry::new(conn);
+        register_tools(&registry, &dir.path().join("stories")).unwrap();
+
+        for tool in registry.list_tools() {
+            assert_eq!(tool.input_schema["type"], "object", "{}", tool.name);
+            assert_eq!(tool.input_schema["additionalProperties"], false, "{}", tool.name);
+        }
+    }
+}
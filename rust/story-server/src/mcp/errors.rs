@@ -0,0 +1,74 @@
+use super::types::*;
+use crate::error::StoryError;
+use serde_json::{json, Map, Value};
+
+/// JSON-RPC error code for a domain error
+pub fn error_code(err: &StoryError) -> i32 {
+    match err {
+        StoryError::NotFound(_) => NOT_FOUND,
+        StoryError::ValidationError(_) => VALIDATION_FAILED,
+        StoryError::DuplicateEntry(_) => DUPLICATE_ENTRY,
+        StoryError::InvalidState(_) => INVALID_STATE,
+        _ => INTERNAL_ERROR,
+    }
+}
+
+/// Map a domain error to a JSON-RPC error object whose `data` lets clients
+/// react programmatically: `{ kind, field?, entityType?, entityId? }`
+pub fn error_object(err: &StoryError) -> ErrorObject {
+    let mut data = Map::new();
+    data.insert("kind".to_string(), json!(err.kind()));
+
+    if let Some(detail) = err.detail() {
+        if let Some(field) = &detail.field {
+            data.insert("field".to_string(), json!(field));
+        }
+        if let Some(entity_type) = &detail.entity_type {
+            data.insert("entityType".to_string(), json!(entity_type));
+        }
+        if let Some(entity_id) = &detail.entity_id {
+            data.insert("entityId".to_string(), json!(entity_id));
+        }
+    }
+
+    ErrorObject {
+        code: error_code(err),
+        message: err.to_string(),
+        data: Some(Value::Object(data)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distinct_codes_per_variant() {
+        assert_eq!(error_code(&StoryError::not_found("x")), NOT_FOUND);
+        assert_eq!(error_code(&StoryError::validation("x")), VALIDATION_FAILED);
+        assert_eq!(error_code(&StoryError::duplicate("x")), DUPLICATE_ENTRY);
+        assert_eq!(error_code(&StoryError::invalid_state("x")), INVALID_STATE);
+        assert_eq!(error_code(&StoryError::Generic("x".to_string())), INTERNAL_ERROR);
+    }
+
+    #[test]
+    fn test_error_object_data() {
+        let err = StoryError::duplicate("A project with title 'X' already exists")
+            .with_field("title")
+            .with_entity("project", "1234");
+        let obj = error_object(&err);
+
+        assert_eq!(obj.code, DUPLICATE_ENTRY);
+        assert_eq!(
+            obj.data.unwrap(),
+            json!({"kind": "duplicate_entry", "field": "title", "entityType": "project", "entityId": "1234"})
+        );
+    }
+
+    #[test]
+    fn test_error_object_without_detail() {
+        let obj = error_object(&StoryError::Generic("boom".to_string()));
+        assert_eq!(obj.code, INTERNAL_ERROR);
+        assert_eq!(obj.data.unwrap(), json!({"kind": "generic"}));
+    }
+}
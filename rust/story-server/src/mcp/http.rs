@@ -0,0 +1,682 @@
+//! MCP Streamable HTTP transport.
+//!
+//! `POST /mcp` carries client messages, `GET /mcp` opens an SSE stream for
+//! server-initiated notifications and `DELETE /mcp` ends a session. Sessions
+//! are created by `initialize` and identified by the `Mcp-Session-Id` header;
+//! each one is an [`McpSession`] over the shared [`ToolRegistry`], so HTTP and
+//! stdio go through exactly the same dispatch. Clients that go away without a
+//! `DELETE` have their sessions evicted once idle ([`SessionLimits`]).
+
+use super::registry::ToolRegistry;
+use super::session::{Dispatch, McpSession};
+use super::protocol::{self, Message, Reply, MAX_MESSAGE_BYTES};
+use super::types::{Request, Response, INVALID_REQUEST};
+use axum::extract::{DefaultBodyLimit, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response as HttpResponse};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::StreamExt;
+use uuid::Uuid;
+
+/// Path of the single MCP endpoint
+pub const MCP_PATH: &str = "/mcp";
+
+/// Header carrying the session id assigned at `initialize`
+pub const SESSION_HEADER: &str = "mcp-session-id";
+
+const EVENT_STREAM: &str = "text/event-stream";
+
+/// How many sessions the server keeps and for how long
+#[derive(Debug, Clone, Copy)]
+pub struct SessionLimits {
+    /// A session with no requests and no open stream for this long is closed
+    pub idle_timeout: Duration,
+    /// `initialize` is refused with 503 while this many sessions are open
+    pub max_sessions: usize,
+}
+
+impl Default for SessionLimits {
+    fn default() -> Self {
+        SessionLimits {
+            idle_timeout: Duration::from_secs(30 * 60),
+            max_sessions: 256,
+        }
+    }
+}
+
+struct HttpSession {
+    session: McpSession,
+    /// Open `GET` stream for server-initiated messages, if the client has one
+    stream: Option<UnboundedSender<Event>>,
+    last_seen: Instant,
+}
+
+impl HttpSession {
+    fn new(registry: Arc<ToolRegistry>) -> Self {
+        HttpSession {
+            session: McpSession::new(registry),
+            stream: None,
+            last_seen: Instant::now(),
+        }
+    }
+
+    /// A client listening on its stream is still there, however long since its last request
+    fn is_idle(&self, now: Instant, timeout: Duration) -> bool {
+        let listening = self.stream.as_ref().is_some_and(|stream| !stream.is_closed());
+        !listening && now.duration_since(self.last_seen) >= timeout
+    }
+
+    /// Push pending notifications to the open stream, if any. Without a stream
+    /// they stay queued in the session until the client's next request.
+    fn flush(&mut self) {
+        let Some(stream) = &self.stream else { return };
+        for notification in self.session.take_notifications() {
+            if stream.send(message_event(&notification)).is_err() {
+                self.stream = None;
+                break;
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct HttpState {
+    registry: Arc<ToolRegistry>,
+    sessions: Arc<Mutex<HashMap<String, HttpSession>>>,
+    limits: SessionLimits,
+}
+
+impl HttpState {
+    fn new(registry: Arc<ToolRegistry>, limits: SessionLimits) -> Self {
+        HttpState {
+            registry,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            limits,
+        }
+    }
+}
+
+/// Close idle sessions. Dropping a session drops its registry watcher, so
+/// changes stop queueing up for a client that is gone.
+fn evict_idle(sessions: &mut HashMap<String, HttpSession>, timeout: Duration) {
+    let now = Instant::now();
+    sessions.retain(|id, entry| {
+        let idle = entry.is_idle(now, timeout);
+        if idle {
+            log::info!("Closed idle HTTP session {}", id);
+        }
+        !idle
+    });
+}
+
+/// Build the HTTP router with the default [`SessionLimits`]; exposed separately
+/// from [`serve`] for in-process tests
+pub fn router(registry: Arc<ToolRegistry>) -> Router {
+    router_with_state(HttpState::new(registry, SessionLimits::default()))
+}
+
+/// Build the HTTP router with the given session limits
+pub fn router_with_limits(registry: Arc<ToolRegistry>, limits: SessionLimits) -> Router {
+    router_with_state(HttpState::new(registry, limits))
+}
+
+fn router_with_state(state: HttpState) -> Router {
+    Router::new()
+        .route(MCP_PATH, get(open_stream).post(post_message).delete(close_session))
+        .route("/health", get(health))
+        .layer(DefaultBodyLimit::max(MAX_MESSAGE_BYTES))
+        .with_state(state)
+}
+
+/// Serve MCP over HTTP until the process is stopped
+pub async fn serve(registry: Arc<ToolRegistry>, addr: impl ToSocketAddrs) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    log::info!("Story Server ready - listening on http://{}{}", listener.local_addr()?, MCP_PATH);
+    let state = HttpState::new(registry, SessionLimits::default());
+
+    // Sessions are also swept when new ones open; this catches the rest
+    let sessions = state.sessions.clone();
+    let timeout = state.limits.idle_timeout;
+    tokio::spawn(async move {
+        let mut sweep = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            sweep.tick().await;
+            evict_idle(&mut sessions.lock().unwrap(), timeout);
+        }
+    });
+
+    axum::serve(listener, router_with_state(state)).await?;
+    Ok(())
+}
+
+async fn post_message(State(state): State<HttpState>, headers: HeaderMap, body: String) -> HttpResponse {
+    if let Some(rejection) = reject_foreign_origin(&headers) {
+        return rejection;
+    }
+
+    let message = match protocol::parse_message(&body) {
+        Ok(message) => message,
+        Err(rejection) => return (StatusCode::BAD_REQUEST, Json(rejection.into_response())).into_response(),
+    };
+    let is_initialize = matches!(&message, Message::Single(request) if request.method == "initialize");
+
+    let session_id = {
+        let mut sessions = state.sessions.lock().unwrap();
+        match session_id(&headers) {
+            Some(id) => match sessions.get_mut(&id) {
+                Some(entry) => {
+                    entry.last_seen = Instant::now();
+                    id
+                }
+                None => return session_not_found(&id),
+            },
+            None if is_initialize => {
+                evict_idle(&mut sessions, state.limits.idle_timeout);
+                if sessions.len() >= state.limits.max_sessions {
+                    log::warn!("Refused new HTTP session: {} sessions open", sessions.len());
+                    return jsonrpc_error(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        INVALID_REQUEST,
+                        "Too many open sessions; try again later".to_string(),
+                    );
+                }
+                let id = Uuid::new_v4().to_string();
+                log::info!("Opened HTTP session {}", id);
+                sessions.insert(id.clone(), HttpSession::new(state.registry.clone()));
+                id
+            }
+            None => {
+                return jsonrpc_error(
+                    StatusCode::BAD_REQUEST,
+                    INVALID_REQUEST,
+                    "Missing Mcp-Session-Id header".to_string(),
+                )
+            }
+        }
+    };
+
+    let response = match handle_message(&state, &session_id, message).await {
+        Ok(response) => response,
+        Err(gone) => return gone,
+    };
+
+    let mut sessions = state.sessions.lock().unwrap();
+    let Some(entry) = sessions.get_mut(&session_id) else {
+        return session_not_found(&session_id);
+    };
+    // A long-running request counts as activity until it finishes
+    entry.last_seen = Instant::now();
+    let inline_events = accepts_event_stream(&headers) && response.is_some();
+    let notifications = if inline_events || entry.stream.is_some() {
+        entry.session.take_notifications()
+    } else {
+        Vec::new()
+    };
+
+    let reply = match response {
+        // Notifications, cancelled requests and notification-only batches have nothing to answer
+        None => {
+            if let Some(stream) = &entry.stream {
+                for notification in &notifications {
+                    let _ = stream.send(message_event(notification));
+                }
+            }
+            StatusCode::ACCEPTED.into_response()
+        }
+        // Notifications caused by this request travel in the same response stream, ahead of it
+        Some(response) if inline_events && !notifications.is_empty() => {
+            let events: Vec<Result<Event, Infallible>> = notifications
+                .iter()
+                .map(message_event)
+                .chain(std::iter::once(message_event(&response)))
+                .map(Ok)
+                .collect();
+            Sse::new(tokio_stream::iter(events)).into_response()
+        }
+        Some(response) => {
+            if let Some(stream) = &entry.stream {
+                for notification in &notifications {
+                    let _ = stream.send(message_event(notification));
+                }
+            }
+            Json(response).into_response()
+        }
+    };
+
+    // Changes made by this request may concern other clients
+    for (id, other) in sessions.iter_mut() {
+        if *id != session_id {
+            other.flush();
+        }
+    }
+
+    with_session_header(reply, &session_id)
+}
+
+/// [`McpSession::handle_message`] for an HTTP session, see [`handle_request`]
+async fn handle_message(
+    state: &HttpState,
+    session_id: &str,
+    message: Message,
+) -> std::result::Result<Option<Reply>, HttpResponse> {
+    match message {
+        Message::Single(request) => Ok(handle_request(state, session_id, request).await?.map(Reply::Single)),
+        Message::Batch(items) => {
+            let mut responses = Vec::new();
+            for item in items {
+                match item {
+                    Ok(request) => responses.extend(handle_request(state, session_id, request).await?),
+                    Err(rejection) => responses.push(rejection.into_response()),
+                }
+            }
+            Ok((!responses.is_empty()).then_some(Reply::Batch(responses)))
+        }
+    }
+}
+
+/// Dispatch one request under the sessions lock, then run its database work
+/// without the lock so other requests, on this session or others, carry on.
+/// Fails with a 404 response if the session was closed meanwhile.
+async fn handle_request(
+    state: &HttpState,
+    session_id: &str,
+    request: Request,
+) -> std::result::Result<Option<Response>, HttpResponse> {
+    let dispatched = match state.sessions.lock().unwrap().get_mut(session_id) {
+        Some(entry) => entry.session.dispatch(request),
+        None => return Err(session_not_found(session_id)),
+    };
+    let pending = match dispatched {
+        Dispatch::Done(response) => return Ok(response),
+        Dispatch::Pending(pending) => pending,
+    };
+
+    let response = pending.run_blocking().await;
+    match state.sessions.lock().unwrap().get_mut(session_id) {
+        Some(entry) => Ok(entry.session.complete(response)),
+        None => Err(session_not_found(session_id)),
+    }
+}
+
+async fn open_stream(State(state): State<HttpState>, headers: HeaderMap) -> HttpResponse {
+    if let Some(rejection) = reject_foreign_origin(&headers) {
+        return rejection;
+    }
+    if !accepts_event_stream(&headers) {
+        return jsonrpc_error(
+            StatusCode::NOT_ACCEPTABLE,
+            INVALID_REQUEST,
+            format!("GET {} requires Accept: {}", MCP_PATH, EVENT_STREAM),
+        );
+    }
+
+    let Some(session_id) = session_id(&headers) else {
+        return jsonrpc_error(
+            StatusCode::BAD_REQUEST,
+            INVALID_REQUEST,
+            "Missing Mcp-Session-Id header".to_string(),
+        );
+    };
+    let mut sessions = state.sessions.lock().unwrap();
+    let Some(entry) = sessions.get_mut(&session_id) else {
+        return session_not_found(&session_id);
+    };
+
+    // A new stream replaces any previous one for the session
+    let (tx, rx) = mpsc::unbounded_channel();
+    entry.stream = Some(tx);
+    entry.last_seen = Instant::now();
+    entry.flush();
+    log::info!("Opened event stream for HTTP session {}", session_id);
+
+    let events = UnboundedReceiverStream::new(rx).map(Ok::<_, Infallible>);
+    with_session_header(
+        Sse::new(events).keep_alive(KeepAlive::default()).into_response(),
+        &session_id,
+    )
+}
+
+async fn close_session(State(state): State<HttpState>, headers: HeaderMap) -> HttpResponse {
+    if let Some(rejection) = reject_foreign_origin(&headers) {
+        return rejection;
+    }
+    let Some(session_id) = session_id(&headers) else {
+        return jsonrpc_error(
+            StatusCode::BAD_REQUEST,
+            INVALID_REQUEST,
+            "Missing Mcp-Session-Id header".to_string(),
+        );
+    };
+
+    match state.sessions.lock().unwrap().remove(&session_id) {
+        Some(_) => {
+            log::info!("Closed HTTP session {}", session_id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        None => session_not_found(&session_id),
+    }
+}
+
+async fn health(State(state): State<HttpState>) -> HttpResponse {
+    Json(json!({
+        "status": "ok",
+        "server": "story-db",
+        "version": env!("CARGO_PKG_VERSION"),
+        "sessions": state.sessions.lock().unwrap().len()
+    }))
+    .into_response()
+}
+
+fn session_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(SESSION_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .any(|v| v.contains(EVENT_STREAM))
+}
+
+/// Browsers send `Origin`; only local pages may talk to a local server (DNS rebinding protection)
+fn reject_foreign_origin(headers: &HeaderMap) -> Option<HttpResponse> {
+    let origin = headers.get(header::ORIGIN)?.to_str().unwrap_or_default();
+    let host = origin
+        .split_once("://")
+        .map_or(origin, |(_, rest)| rest)
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+
+    if matches!(host, "localhost" | "127.0.0.1" | "::1") {
+        None
+    } else {
+        log::warn!("Rejected request from origin {}", origin);
+        Some(jsonrpc_error(
+            StatusCode::FORBIDDEN,
+            INVALID_REQUEST,
+            format!("Origin not allowed: {}", origin),
+        ))
+    }
+}
+
+fn session_not_found(session_id: &str) -> HttpResponse {
+    // 404 tells the client to start a new session with a fresh initialize
+    jsonrpc_error(
+        StatusCode::NOT_FOUND,
+        INVALID_REQUEST,
+        format!("Unknown or expired session: {}", session_id),
+    )
+}
+
+fn jsonrpc_error(status: StatusCode, code: i32, message: String) -> HttpResponse {
+    (status, Json(Response::error(None, code, message))).into_response()
+}
+
+fn with_session_header(mut response: HttpResponse, session_id: &str) -> HttpResponse {
+    if let Ok(value) = session_id.parse() {
+        response.headers_mut().insert(SESSION_HEADER, value);
+    }
+    response
+}
+
+fn message_event<T: Serialize>(message: &T) -> Event {
+    Event::default()
+        .event("message")
+        .data(serde_json::to_string(message).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::resources;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request as HttpRequest;
+    use serde_json::Value;
+    use tempfile::tempdir;
+    use tower::ServiceExt;
+
+    fn app() -> (Router, Arc<ToolRegistry>) {
+        let conn = crate::db::initialize_database(":memory:").unwrap();
+        let registry = Arc::new(ToolRegistry::new(conn));
+        registry.register("echo", "Echo tool", json!({"type": "object"}), |_conn, params| Ok(params));
+        (router(registry.clone()), registry)
+    }
+
+    async fn send(app: &Router, method: &str, session: Option<&str>, body: Option<Value>) -> HttpResponse {
+        let mut request = HttpRequest::builder()
+            .method(method)
+            .uri(MCP_PATH)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::ACCEPT, "application/json, text/event-stream");
+        if let Some(session) = session {
+            request = request.header(SESSION_HEADER, session);
+        }
+        let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+        app.clone().oneshot(request.body(body).unwrap()).await.unwrap()
+    }
+
+    async fn body_text(response: HttpResponse) -> String {
+        String::from_utf8(to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap()
+    }
+
+    async fn open_session(app: &Router) -> String {
+        let response = send(
+            app,
+            "POST",
+            None,
+            Some(json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"protocolVersion": "2025-03-26"}})),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let session = response.headers()[SESSION_HEADER].to_str().unwrap().to_string();
+        let body: Value = serde_json::from_str(&body_text(response).await).unwrap();
+        assert_eq!(body["result"]["protocolVersion"], "2025-03-26");
+
+        let response = send(app, "POST", Some(&session), Some(json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        session
+    }
+
+    #[tokio::test]
+    async fn test_session_lifecycle() {
+        let (app, _) = app();
+        let session = open_session(&app).await;
+
+        let response = send(
+            &app,
+            "POST",
+            Some(&session),
+            Some(json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {"name": "echo", "arguments": {"a": 1}}})),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[SESSION_HEADER], session.as_str());
+        let body: Value = serde_json::from_str(&body_text(response).await).unwrap();
+        assert_eq!(body["result"]["content"][0]["text"], "{\"a\":1}");
+
+        let response = send(&app, "DELETE", Some(&session), None).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = send(&app, "POST", Some(&session), Some(json!({"jsonrpc": "2.0", "id": 3, "method": "ping"}))).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_requests_need_a_session() {
+        let (app, _) = app();
+
+        let response = send(&app, "POST", None, Some(json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}))).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = send(&app, "POST", Some("nope"), Some(json!({"jsonrpc": "2.0", "id": 1, "method": "ping"}))).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = send(&app, "GET", None, None).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .clone()
+            .oneshot(HttpRequest::post(MCP_PATH).body(Body::from("{not json")).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_str(&body_text(response).await).unwrap();
+        assert_eq!(body["error"]["code"], crate::mcp::types::PARSE_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_batch_over_http() {
+        let (app, _) = app();
+        let session = open_session(&app).await;
+
+        let response = send(
+            &app,
+            "POST",
+            Some(&session),
+            Some(json!([
+                {"jsonrpc": "2.0", "id": 1, "method": "ping"},
+                {"jsonrpc": "2.0", "id": 2, "method": "tools/list"}
+            ])),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = serde_json::from_str(&body_text(response).await).unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 2);
+        assert_eq!(body[1]["result"]["tools"][0]["name"], "echo");
+
+        let response = send(&app, "POST", Some(&session), Some(json!([{"jsonrpc": "2.0", "method": "notifications/initialized"}]))).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+    }
+
+    #[tokio::test]
+    async fn test_foreign_origin_rejected() {
+        let (app, _) = app();
+        let request = |origin: &str| {
+            HttpRequest::post(MCP_PATH)
+                .header(header::ORIGIN, origin)
+                .body(Body::from(json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"}).to_string()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("https://evil.example")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app.clone().oneshot(request("http://localhost:5173")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_notifications_streamed_with_response() {
+        let stories = tempdir().unwrap();
+        let (app, registry) = app();
+        let project = registry
+            .with_connection(|conn| {
+                crate::tools::create_story_project(conn, stories.path(), json!({"title": "HTTP", "targetLength": "novel"}))
+            })
+            .unwrap();
+        let character = registry
+            .with_connection(|conn| {
+                crate::tools::add_character(
+                    conn,
+                    json!({"projectId": project["projectId"], "name": "Mira", "role": "protagonist"}),
+                )
+            })
+            .unwrap();
+        registry.register("update", "Update character", json!({"type": "object"}), crate::tools::update_character);
+        registry.track_changes("update", resources::character_changes);
+        let uri = format!("story://character/{}", character["characterId"].as_str().unwrap());
+
+        let session = open_session(&app).await;
+        let response = send(
+            &app,
+            "POST",
+            Some(&session),
+            Some(json!({"jsonrpc": "2.0", "id": 2, "method": "resources/subscribe", "params": {"uri": uri}})),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(
+            &app,
+            "POST",
+            Some(&session),
+            Some(json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {"name": "update", "arguments": {"characterId": character["characterId"], "currentState": "Asleep"}}})),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()[header::CONTENT_TYPE].to_str().unwrap().starts_with(EVENT_STREAM));
+
+        let body = body_text(response).await;
+        let messages: Vec<Value> = body
+            .lines()
+            .filter_map(|l| l.strip_prefix("data: "))
+            .map(|d| serde_json::from_str(d).unwrap())
+            .collect();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["method"], "notifications/resources/updated");
+        assert_eq!(messages[0]["params"]["uri"], uri.as_str());
+        assert_eq!(messages[1]["id"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_idle_sessions_evicted_and_count_capped() {
+        let conn = crate::db::initialize_database(":memory:").unwrap();
+        let registry = Arc::new(ToolRegistry::new(conn));
+        let limits = SessionLimits {
+            idle_timeout: Duration::from_millis(300),
+            max_sessions: 2,
+        };
+        let app = router_with_limits(registry.clone(), limits);
+        let initialize = json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}});
+
+        let first = open_session(&app).await;
+        open_session(&app).await;
+        let response = send(&app, "POST", None, Some(initialize.clone())).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        let response = send(&app, "POST", None, Some(initialize)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The abandoned sessions are gone, and so are their registry watchers
+        let response = send(&app, "POST", Some(&first), Some(json!({"jsonrpc": "2.0", "id": 2, "method": "ping"}))).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        registry.register("late", "Registered after eviction", json!({"type": "object"}), |_conn, p| Ok(p));
+        assert_eq!(registry.watcher_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_health() {
+        let (app, _) = app();
+        open_session(&app).await;
+
+        let response = app
+            .clone()
+            .oneshot(HttpRequest::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_str(&body_text(response).await).unwrap();
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["sessions"], 1);
+    }
+}
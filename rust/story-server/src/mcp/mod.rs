@@ -1,7 +1,15 @@
+pub mod errors;
+pub mod http;
+pub mod prompts;
 pub mod protocol;
 pub mod registry;
+pub mod resources;
+pub mod schema;
+pub mod session;
+pub mod socket;
 pub mod types;
 
-pub use protocol::McpProtocolHandler;
-pub use registry::ToolRegistry;
-pub use types::{Request, Response, ToolCall, ToolResult};
+pub use protocol::{AsyncMcpProtocolHandler, McpProtocolHandler};
+pub use registry::{Change, ToolCallError, ToolRegistry};
+pub use session::{McpSession, SessionState};
+pub use types::{Notification, Request, Response, ToolCall, ToolResult};
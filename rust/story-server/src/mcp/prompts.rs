@@ -0,0 +1,486 @@
+use super::types::{Prompt, PromptArgument};
+use crate::context::scoring;
+use crate::error::{Result, StoryError};
+use rusqlite::{Connection, OptionalExtension};
+use serde_json::{json, Map, Value};
+
+pub const DRAFT_NEXT_SCENE: &str = "draft_next_scene";
+pub const REVISE_SCENE_FOR_CONTINUITY: &str = "revise_scene_for_continuity";
+pub const SUMMARIZE_CHAPTER: &str = "summarize_chapter";
+
+/// Prompt templates advertised through `prompts/list`
+pub fn list_prompts() -> Vec<Prompt> {
+    let arg = |name: &str, description: &str, required: bool| PromptArgument {
+        name: name.to_string(),
+        description: description.to_string(),
+        required,
+    };
+
+    vec![
+        Prompt {
+            name: DRAFT_NEXT_SCENE.to_string(),
+            description: "Draft the next scene of a chapter using the project's genre, the POV character's \
+                          current state, relevant world rules and the previous scene's summary"
+                .to_string(),
+            arguments: vec![
+                arg("chapterId", "Chapter the new scene belongs to", true),
+                arg("povCharacterId", "Point-of-view character for the scene", false),
+                arg("outline", "What should happen in the scene", false),
+            ],
+        },
+        Prompt {
+            name: REVISE_SCENE_FOR_CONTINUITY.to_string(),
+            description: "Review an existing scene against character states, world rules and the \
+                          preceding scene, and propose continuity fixes"
+                .to_string(),
+            arguments: vec![arg("sceneId", "Scene to revise", true)],
+        },
+        Prompt {
+            name: SUMMARIZE_CHAPTER.to_string(),
+            description: "Summarize a chapter's scenes into key events and character developments".to_string(),
+            arguments: vec![arg("chapterId", "Chapter to summarize", true)],
+        },
+    ]
+}
+
+/// Render a prompt with live data from the database into an MCP `prompts/get` result
+pub fn get_prompt(conn: &Connection, name: &str, arguments: &Map<String, Value>) -> Result<Value> {
+    let (description, text) = match name {
+        DRAFT_NEXT_SCENE => draft_next_scene(conn, arguments)?,
+        REVISE_SCENE_FOR_CONTINUITY => revise_scene(conn, arguments)?,
+        SUMMARIZE_CHAPTER => summarize_chapter(conn, arguments)?,
+        _ => {
+            return Err(StoryError::not_found(format!("Prompt not found: {}", name))
+                .with_field("name")
+                .with_entity("prompt", name))
+        }
+    };
+
+    Ok(json!({
+        "description": description,
+        "messages": [
+            {
+                "role": "user",
+                "content": { "type": "text", "text": text }
+            }
+        ]
+    }))
+}
+
+fn required_arg<'a>(arguments: &'a Map<String, Value>, name: &str) -> Result<&'a str> {
+    arguments
+        .get(name)
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| StoryError::validation(format!("Missing required argument: {}", name)).with_field(name))
+}
+
+fn optional_arg<'a>(arguments: &'a Map<String, Value>, name: &str) -> Option<&'a str> {
+    arguments.get(name).and_then(|v| v.as_str()).filter(|s| !s.is_empty())
+}
+
+/// Chapter position and the project it belongs to
+struct ChapterInfo {
+    id: String,
+    number: i32,
+    title: Option<String>,
+    act_position: i32,
+    project_id: String,
+    project_title: String,
+    genre: Option<String>,
+}
+
+fn load_chapter(conn: &Connection, chapter_id: &str) -> Result<ChapterInfo> {
+    conn.query_row(
+        "SELECT c.id, c.number, c.title, a.position, sp.id, sp.title, sp.genre
+         FROM chapters c
+         JOIN acts a ON c.act_id = a.id
+         JOIN plot_structures ps ON a.plot_structure_id = ps.id
+         JOIN story_projects sp ON ps.story_project_id = sp.id
+         WHERE c.id = ?1",
+        [chapter_id],
+        |row| {
+            Ok(ChapterInfo {
+                id: row.get(0)?,
+                number: row.get(1)?,
+                title: row.get(2)?,
+                act_position: row.get(3)?,
+                project_id: row.get(4)?,
+                project_title: row.get(5)?,
+                genre: row.get(6)?,
+            })
+        },
+    )
+    .optional()?
+    .ok_or_else(|| {
+        StoryError::not_found(format!("Chapter not found: {}", chapter_id))
+            .with_field("chapterId")
+            .with_entity("chapter", chapter_id)
+    })
+}
+
+/// Summary of the scene that precedes (chapter, position) in manuscript order,
+/// preferring a stored scene summary over the scene's own summary column
+fn previous_scene_summary(conn: &Connection, chapter: &ChapterInfo, before_position: i32) -> Result<Option<String>> {
+    let summary = conn
+        .query_row(
+            "SELECT COALESCE(ss.summary_text, s.summary, s.scene_outline)
+             FROM scenes s
+             JOIN chapters c ON s.chapter_id = c.id
+             JOIN acts a ON c.act_id = a.id
+             JOIN plot_structures ps ON a.plot_structure_id = ps.id
+             LEFT JOIN story_summaries ss ON ss.scope = 'scene' AND ss.reference_id = s.id
+             WHERE ps.story_project_id = ?1
+               AND (a.position < ?2
+                    OR (a.position = ?2 AND c.number < ?3)
+                    OR (c.id = ?4 AND s.position < ?5))
+             ORDER BY a.position DESC, c.number DESC, s.position DESC
+             LIMIT 1",
+            (
+                &chapter.project_id,
+                chapter.act_position,
+                chapter.number,
+                &chapter.id,
+                before_position,
+            ),
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()?;
+    Ok(summary.flatten())
+}
+
+/// Universal rules plus any rule whose keywords appear in `text`
+fn relevant_world_rules(conn: &Connection, project_id: &str, text: &str) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT name, description, scope, keywords FROM world_rules WHERE story_project_id = ?1 ORDER BY name",
+    )?;
+    let rules = stmt
+        .query_map([project_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(rules
+        .into_iter()
+        .filter(|(_, _, scope, keywords)| {
+            scope == "universal"
+                || scoring::keywords(keywords.as_deref().unwrap_or(""))
+                    .iter()
+                    .any(|k| scoring::mentions(text, k))
+        })
+        .map(|(name, description, _, _)| (name, description))
+        .collect())
+}
+
+fn character_profile(conn: &Connection, character_id: &str) -> Result<String> {
+    conn.query_row(
+        "SELECT name, role, personality_traits, current_state FROM characters WHERE id = ?1",
+        [character_id],
+        |row| {
+            let name: String = row.get(0)?;
+            let role: String = row.get(1)?;
+            let traits: Option<String> = row.get(2)?;
+            let state: Option<String> = row.get(3)?;
+            Ok(format!(
+                "- **{}** ({}) — traits: {}; current state: {}",
+                name,
+                role,
+                traits.unwrap_or_else(|| "unspecified".to_string()),
+                state.unwrap_or_else(|| "unspecified".to_string())
+            ))
+        },
+    )
+    .optional()?
+    .ok_or_else(|| {
+        StoryError::not_found(format!("Character not found: {}", character_id))
+            .with_entity("character", character_id)
+    })
+}
+
+fn push_rules(text: &mut String, rules: &[(String, String)]) {
+    if rules.is_empty() {
+        return;
+    }
+    text.push_str("\n## World rules to respect\n");
+    for (name, description) in rules {
+        text.push_str(&format!("- **{}**: {}\n", name, description));
+    }
+}
+
+fn project_header(chapter: &ChapterInfo) -> String {
+    format!(
+        "Project: **{}** ({})\nChapter {}{}\n",
+        chapter.project_title,
+        chapter.genre.as_deref().unwrap_or("genre unspecified"),
+        chapter.number,
+        chapter.title.as_deref().map(|t| format!(": {}", t)).unwrap_or_default()
+    )
+}
+
+fn draft_next_scene(conn: &Connection, arguments: &Map<String, Value>) -> Result<(String, String)> {
+    let chapter = load_chapter(conn, required_arg(arguments, "chapterId")?)?;
+    let outline = optional_arg(arguments, "outline");
+
+    let next_position: i32 = conn.query_row(
+        "SELECT COALESCE(MAX(position), 0) + 1 FROM scenes WHERE chapter_id = ?1",
+        [&chapter.id],
+        |row| row.get(0),
+    )?;
+
+    let mut text = format!(
+        "Draft scene {} of the following chapter.\n\n{}",
+        next_position,
+        project_header(&chapter)
+    );
+
+    if let Some(genre) = &chapter.genre {
+        text.push_str(&format!("Write in a voice and with pacing suited to {}.\n", genre));
+    }
+
+    text.push_str("\n## Previous scene\n");
+    match previous_scene_summary(conn, &chapter, next_position)? {
+        Some(summary) => text.push_str(&format!("{}\n", summary)),
+        None => text.push_str("This is the opening scene of the manuscript.\n"),
+    }
+
+    if let Some(pov_id) = optional_arg(arguments, "povCharacterId") {
+        text.push_str("\n## Point-of-view character\n");
+        text.push_str(&character_profile(conn, pov_id)?);
+        text.push('\n');
+    }
+
+    if let Some(outline) = outline {
+        text.push_str(&format!("\n## Scene outline\n{}\n", outline));
+    }
+
+    push_rules(&mut text, &relevant_world_rules(conn, &chapter.project_id, outline.unwrap_or(""))?);
+
+    text.push_str(
+        "\nKeep every character consistent with their current state, and do not contradict \
+         the previous scene or the world rules above.",
+    );
+
+    Ok((
+        format!("Draft scene {} of chapter {}", next_position, chapter.number),
+        text,
+    ))
+}
+
+fn revise_scene(conn: &Connection, arguments: &Map<String, Value>) -> Result<(String, String)> {
+    let scene_id = required_arg(arguments, "sceneId")?;
+    let (chapter_id, position, content, outline): (String, i32, String, Option<String>) = conn
+        .query_row(
+            "SELECT chapter_id, position, content, scene_outline FROM scenes WHERE id = ?1",
+            [scene_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()?
+        .ok_or_else(|| {
+            StoryError::not_found(format!("Scene not found: {}", scene_id))
+                .with_field("sceneId")
+                .with_entity("scene", scene_id)
+        })?;
+    let chapter = load_chapter(conn, &chapter_id)?;
+
+    let mut text = format!(
+        "Review scene {} for continuity errors and propose a revised version.\n\n{}",
+        position,
+        project_header(&chapter)
+    );
+
+    text.push_str("\n## Previous scene\n");
+    match previous_scene_summary(conn, &chapter, position)? {
+        Some(summary) => text.push_str(&format!("{}\n", summary)),
+        None => text.push_str("This is the opening scene of the manuscript.\n"),
+    }
+
+    // Characters tagged in the scene, plus any project character named in the text
+    let mut stmt = conn.prepare(
+        "SELECT ch.id, ch.name,
+                EXISTS(SELECT 1 FROM scene_characters sc WHERE sc.scene_id = ?2 AND sc.character_id = ch.id)
+         FROM characters ch WHERE ch.story_project_id = ?1 ORDER BY ch.name",
+    )?;
+    let characters = stmt
+        .query_map((&chapter.project_id, scene_id), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let present: Vec<&String> = characters
+        .iter()
+        .filter(|(_, name, tagged)| *tagged || scoring::mentions_name(&content, name))
+        .map(|(id, _, _)| id)
+        .collect();
+    if !present.is_empty() {
+        text.push_str("\n## Characters in this scene\n");
+        for id in present {
+            text.push_str(&character_profile(conn, id)?);
+            text.push('\n');
+        }
+    }
+
+    let rule_context = format!("{} {}", content, outline.as_deref().unwrap_or(""));
+    push_rules(&mut text, &relevant_world_rules(conn, &chapter.project_id, &rule_context)?);
+
+    text.push_str(&format!("\n## Scene text\n{}\n", content));
+    text.push_str(
+        "\nList each continuity problem you find (character state, world rule, timeline), \
+         then provide the revised scene with the problems fixed and everything else unchanged.",
+    );
+
+    Ok((format!("Continuity revision for scene {}", position), text))
+}
+
+fn summarize_chapter(conn: &Connection, arguments: &Map<String, Value>) -> Result<(String, String)> {
+    let chapter = load_chapter(conn, required_arg(arguments, "chapterId")?)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT position, title, content FROM scenes WHERE chapter_id = ?1 ORDER BY position",
+    )?;
+    let scenes = stmt
+        .query_map([&chapter.id], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, String>(2)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    if scenes.is_empty() {
+        return Err(StoryError::invalid_state(format!("Chapter {} has no scenes to summarize", chapter.number))
+            .with_entity("chapter", &chapter.id));
+    }
+
+    let mut text = format!("Summarize the following chapter.\n\n{}", project_header(&chapter));
+    for (position, title, content) in scenes {
+        text.push_str(&format!(
+            "\n## Scene {}{}\n{}\n",
+            position,
+            title.map(|t| format!(": {}", t)).unwrap_or_default(),
+            content
+        ));
+    }
+    text.push_str(
+        "\nRespond with a one-paragraph summary, a bullet list of key events, and a bullet list \
+         of character developments.",
+    );
+
+    Ok((format!("Summary of chapter {}", chapter.number), text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::tools::{
+        add_chapter, add_character, add_scene, add_world_rule, create_story_project, initialize_plot_structure,
+    };
+    use tempfile::tempdir;
+
+    fn args(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_draft_next_scene_pulls_live_data() {
+        let dir = tempdir().unwrap();
+        let conn = db::initialize_database(dir.path().join("test.db")).unwrap();
+
+        let project = create_story_project(
+            &conn,
+            dir.path(),
+            json!({"title": "Prompt Test", "genre": "Grimdark Fantasy", "targetLength": "novel"}),
+        )
+        .unwrap();
+        let project_id = project["projectId"].as_str().unwrap();
+        let plot = initialize_plot_structure(&conn, json!({"projectId": project_id})).unwrap();
+        let chapter = add_chapter(&conn, json!({"actId": plot["acts"][0]["actId"], "number": 1})).unwrap();
+        add_scene(&conn, dir.path(), json!({"chapterId": chapter["chapterId"], "sceneOutline": "Kael flees the burning city"}))
+            .unwrap();
+        let kael = add_character(
+            &conn,
+            json!({"projectId": project_id, "name": "Kael", "role": "protagonist", "currentState": "Wounded, hiding in the marsh"}),
+        )
+        .unwrap();
+        add_world_rule(
+            &conn,
+            json!({"projectId": project_id, "name": "Silver Gate", "description": "Only opens at dawn", "scope": "regional", "keywords": ["gate"]}),
+        )
+        .unwrap();
+        add_world_rule(
+            &conn,
+            json!({"projectId": project_id, "name": "Tides", "description": "Sea rises at dusk", "scope": "regional", "keywords": ["sea"]}),
+        )
+        .unwrap();
+
+        let result = get_prompt(
+            &conn,
+            DRAFT_NEXT_SCENE,
+            &args(json!({"chapterId": chapter["chapterId"], "povCharacterId": kael["characterId"], "outline": "Kael reaches the gate"})),
+        )
+        .unwrap();
+        let text = result["messages"][0]["content"]["text"].as_str().unwrap();
+
+        assert_eq!(result["messages"][0]["role"], "user");
+        assert!(text.contains("Grimdark Fantasy"));
+        assert!(text.contains("Wounded, hiding in the marsh"));
+        assert!(text.contains("Kael flees the burning city"));
+        assert!(text.contains("Silver Gate"));
+        assert!(!text.contains("Tides"));
+    }
+
+    #[test]
+    fn test_prompt_errors() {
+        let dir = tempdir().unwrap();
+        let conn = db::initialize_database(dir.path().join("test.db")).unwrap();
+
+        let err = get_prompt(&conn, "nope", &Map::new()).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+
+        let err = get_prompt(&conn, SUMMARIZE_CHAPTER, &Map::new()).unwrap_err();
+        assert_eq!(err.detail().unwrap().field.as_deref(), Some("chapterId"));
+    }
+
+    #[test]
+    fn test_world_rules_match_whole_keywords() {
+        let stories = tempdir().unwrap();
+        let conn = db::initialize_database(":memory:").unwrap();
+        let project = create_story_project(&conn, stories.path(), json!({"title": "Keyword Test", "targetLength": "novel"})).unwrap();
+        let project_id = project["projectId"].as_str().unwrap();
+        for (name, keywords) in [("Silver Gate", json!(["gate"])), ("Tides", json!(["moon"]))] {
+            add_world_rule(
+                &conn,
+                json!({"projectId": project_id, "name": name, "description": "-", "scope": "regional", "keywords": keywords}),
+            )
+            .unwrap();
+        }
+        // Older rules may store keywords as a separated string
+        conn.execute("UPDATE world_rules SET keywords = 'moon; Tide' WHERE name = 'Tides'", []).unwrap();
+
+        let rules = relevant_world_rules(&conn, project_id, "Kael investigates the tide pools").unwrap();
+        let names: Vec<&str> = rules.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["Tides"]);
+    }
+
+    #[test]
+    fn test_revise_scene_matches_whole_character_names() {
+        let stories = tempdir().unwrap();
+        let conn = db::initialize_database(":memory:").unwrap();
+        let project = create_story_project(&conn, stories.path(), json!({"title": "Name Test", "targetLength": "novel"})).unwrap();
+        let project_id = project["projectId"].as_str().unwrap();
+        let plot = initialize_plot_structure(&conn, json!({"projectId": project_id})).unwrap();
+        let chapter = add_chapter(&conn, json!({"actId": plot["acts"][0]["actId"], "number": 1})).unwrap();
+        let scene = add_scene(&conn, stories.path(), json!({"chapterId": chapter["chapterId"]})).unwrap();
+        for name in ["Eli", "Mira Voss"] {
+            add_character(&conn, json!({"projectId": project_id, "name": name, "role": "supporting"})).unwrap();
+        }
+        conn.execute("UPDATE scenes SET content = 'Elias waits while Mira reads.' WHERE id = ?1", [scene["sceneId"].as_str()])
+            .unwrap();
+
+        let result = get_prompt(&conn, REVISE_SCENE_FOR_CONTINUITY, &args(json!({"sceneId": scene["sceneId"]}))).unwrap();
+        let text = result["messages"][0]["content"]["text"].as_str().unwrap();
+        assert!(text.contains("**Mira Voss**"));
+        assert!(!text.contains("**Eli**"));
+    }
+}
@@ -1,59 +1,384 @@
 use super::types::*;
 use anyhow::Result;
+use serde::Serialize;
 use serde_json::Value;
-use std::io::{self, BufRead, Write};
+use std::io::{self, BufRead, Read, StdinLock, Stdout, Write};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
-pub struct McpProtocolHandler {
-    stdin: io::Stdin,
-    stdout: io::Stdout,
+/// Default limit on a single newline-delimited message
+pub const MAX_MESSAGE_BYTES: usize = 4 * 1024 * 1024;
+
+/// Malformed input, answered with an error response instead of being handled
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejection {
+    /// The offending request's id, when it had a usable one
+    pub id: Option<Value>,
+    pub code: i32,
+    pub message: String,
+}
+
+impl Rejection {
+    fn new(id: Option<Value>, code: i32, message: String) -> Self {
+        Rejection { id, code, message }
+    }
+
+    pub fn into_response(self) -> Response {
+        Response::error(self.id, self.code, self.message)
+    }
+}
+
+/// One line read from the transport
+#[derive(Debug)]
+pub enum Frame {
+    /// A complete, non-blank line of text
+    Line(String),
+    /// A line rejected before parsing (oversized or not UTF-8)
+    Rejected(Rejection),
+}
+
+/// A line parsed as JSON-RPC: one message or a batch
+#[derive(Debug)]
+pub enum Message {
+    Single(Request),
+    /// Each entry is either a valid request or the reason it was rejected
+    Batch(Vec<std::result::Result<Request, Rejection>>),
+}
+
+/// What to send back for one line: a response, or a batch response
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Reply {
+    Single(Response),
+    Batch(Vec<Response>),
+}
+
+/// Parse a line as a JSON-RPC 2.0 message or batch, rejecting it with
+/// `PARSE_ERROR` / `INVALID_REQUEST` when it is neither
+pub fn parse_message(line: &str) -> std::result::Result<Message, Rejection> {
+    let value: Value = serde_json::from_str(line)
+        .map_err(|e| Rejection::new(None, PARSE_ERROR, format!("Parse error: {}", e)))?;
+
+    match value {
+        Value::Array(items) if items.is_empty() => Err(Rejection::new(
+            None,
+            INVALID_REQUEST,
+            "Invalid Request: empty batch".to_string(),
+        )),
+        Value::Array(items) => Ok(Message::Batch(items.into_iter().map(validate_request).collect())),
+        value => validate_request(value).map(Message::Single),
+    }
+}
+
+/// Check the JSON-RPC 2.0 envelope before deserializing into a [`Request`]
+fn validate_request(value: Value) -> std::result::Result<Request, Rejection> {
+    // Echo the id back when it is usable, otherwise answer with a null id
+    let id = value.get("id").filter(|id| id.is_string() || id.is_number()).cloned();
+    let invalid = |reason: &str| Rejection::new(id.clone(), INVALID_REQUEST, format!("Invalid Request: {}", reason));
+
+    let Value::Object(map) = &value else {
+        return Err(invalid("expected an object"));
+    };
+    if map.get("jsonrpc").and_then(|v| v.as_str()) != Some("2.0") {
+        return Err(invalid("jsonrpc must be \"2.0\""));
+    }
+    if map.get("id").is_some_and(|v| !(v.is_string() || v.is_number())) {
+        return Err(invalid("id must be a string or number"));
+    }
+    match map.get("method") {
+        Some(Value::String(_)) => {}
+        Some(_) => return Err(invalid("method must be a string")),
+        None => return Err(invalid("method is required")),
+    }
+    if map.get("params").is_some_and(|v| !(v.is_object() || v.is_array())) {
+        return Err(invalid("params must be an object or array"));
+    }
+
+    serde_json::from_value(value).map_err(|e| invalid(&e.to_string()))
 }
 
-impl McpProtocolHandler {
-    pub fn new() -> Self {
+/// Turn the bytes of one line into a frame; `None` for blank lines
+fn to_frame(mut bytes: Vec<u8>) -> Option<Frame> {
+    while matches!(bytes.last(), Some(b'\n' | b'\r')) {
+        bytes.pop();
+    }
+    match String::from_utf8(bytes) {
+        Ok(line) if line.trim().is_empty() => None,
+        Ok(line) => Some(Frame::Line(line)),
+        Err(_) => Some(Frame::Rejected(Rejection::new(
+            None,
+            PARSE_ERROR,
+            "Parse error: message is not valid UTF-8".to_string(),
+        ))),
+    }
+}
+
+fn oversized(limit: usize) -> Frame {
+    Frame::Rejected(Rejection::new(
+        None,
+        INVALID_REQUEST,
+        format!("Invalid Request: message exceeds {} bytes", limit),
+    ))
+}
+
+/// Newline-delimited JSON-RPC framing over any reader/writer pair.
+///
+/// One handler (and so one buffered reader) lives for the whole session,
+/// so no input is lost between messages.
+pub struct McpProtocolHandler<R, W> {
+    reader: R,
+    writer: W,
+    max_message_bytes: usize,
+}
+
+impl McpProtocolHandler<StdinLock<'static>, Stdout> {
+    /// Handler over the process's stdin/stdout
+    pub fn stdio() -> Self {
+        Self::new(io::stdin().lock(), io::stdout())
+    }
+}
+
+impl Default for McpProtocolHandler<StdinLock<'static>, Stdout> {
+    fn default() -> Self {
+        Self::stdio()
+    }
+}
+
+impl<R: BufRead, W: Write> McpProtocolHandler<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
         McpProtocolHandler {
-            stdin: io::stdin(),
-            stdout: io::stdout(),
+            reader,
+            writer,
+            max_message_bytes: MAX_MESSAGE_BYTES,
         }
     }
 
-    /// Read a JSON-RPC request from stdin
-    pub fn read_request(&self) -> Result<Request> {
-        let stdin = self.stdin.lock();
-        let mut lines = stdin.lines();
+    /// Reject lines longer than `limit` bytes instead of buffering them
+    pub fn with_max_message_bytes(mut self, limit: usize) -> Self {
+        self.max_message_bytes = limit;
+        self
+    }
 
-        if let Some(line) = lines.next() {
-            let line = line?;
-            let request: Request = serde_json::from_str(&line)?;
-            log::debug!("Received request: method={} id={:?}", request.method, request.id);
-            Ok(request)
-        } else {
-            Err(anyhow::anyhow!("No input from stdin"))
+    /// Read the next non-blank line, or `None` once the reader is exhausted
+    pub fn read_frame(&mut self) -> io::Result<Option<Frame>> {
+        let limit = self.max_message_bytes;
+        loop {
+            // Read at most one byte past the limit (the newline) so oversized lines never fill memory
+            let mut bytes = Vec::new();
+            if (&mut self.reader).take(limit as u64 + 1).read_until(b'\n', &mut bytes)? == 0 {
+                return Ok(None);
+            }
+
+            if bytes.len() > limit && bytes.last() != Some(&b'\n') {
+                self.skip_rest_of_line()?;
+                log::warn!("Rejected message over {} bytes", limit);
+                return Ok(Some(oversized(limit)));
+            }
+            if let Some(frame) = to_frame(bytes) {
+                return Ok(Some(frame));
+            }
         }
     }
 
-    /// Write a JSON-RPC response to stdout
-    pub fn write_response(&mut self, response: Response) -> Result<()> {
-        let json = serde_json::to_string(&response)?;
-        writeln!(self.stdout, "{}", json)?;
-        self.stdout.flush()?;
+    fn skip_rest_of_line(&mut self) -> io::Result<()> {
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(());
+            }
+            match buf.iter().position(|&b| b == b'\n') {
+                Some(i) => {
+                    self.reader.consume(i + 1);
+                    return Ok(());
+                }
+                None => {
+                    let len = buf.len();
+                    self.reader.consume(len);
+                }
+            }
+        }
+    }
+
+    /// Read the next single JSON-RPC request, or `None` once the reader is exhausted
+    pub fn read_request(&mut self) -> Result<Option<Request>> {
+        match self.read_frame()? {
+            Some(frame) => frame_to_request(frame).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Write a response or batch response
+    pub fn write_reply(&mut self, reply: &Reply) -> Result<()> {
+        self.write_message(reply)?;
+        log::debug!("Sent reply: {}", describe_reply(reply));
+        Ok(())
+    }
+
+    /// Write a JSON-RPC response
+    pub fn write_response(&mut self, response: &Response) -> Result<()> {
+        self.write_message(response)?;
         log::debug!("Sent response: id={:?}", response.id);
         Ok(())
     }
 
+    /// Write a server-initiated notification
+    pub fn write_notification(&mut self, notification: &Notification) -> Result<()> {
+        self.write_message(notification)?;
+        log::debug!("Sent notification: {}", notification.method);
+        Ok(())
+    }
+
+    fn write_message<T: Serialize>(&mut self, message: &T) -> Result<()> {
+        let json = serde_json::to_string(message)?;
+        writeln!(self.writer, "{}", json)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
     /// Send success response
     pub fn send_success(&mut self, id: Option<Value>, result: Value) -> Result<()> {
-        self.write_response(Response::success(id, result))
+        self.write_response(&Response::success(id, result))
     }
 
     /// Send error response
     pub fn send_error(&mut self, id: Option<Value>, code: i32, message: String) -> Result<()> {
-        self.write_response(Response::error(id, code, message))
+        self.write_response(&Response::error(id, code, message))
+    }
+
+    /// Send error response carrying a structured `data` payload
+    pub fn send_error_with_data(&mut self, id: Option<Value>, code: i32, message: String, data: Option<Value>) -> Result<()> {
+        self.write_response(&Response::error_with_data(id, code, message, data))
+    }
+
+    /// Give back the underlying reader and writer
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
     }
 }
 
-impl Default for McpProtocolHandler {
-    fn default() -> Self {
-        Self::new()
+/// Async counterpart of [`McpProtocolHandler`] over tokio streams
+/// (TCP and Unix sockets, `tokio::io::duplex` pipes in tests)
+pub struct AsyncMcpProtocolHandler<R, W> {
+    reader: R,
+    writer: W,
+    max_message_bytes: usize,
+}
+
+impl<R: AsyncBufRead + Unpin, W: AsyncWrite + Unpin> AsyncMcpProtocolHandler<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        AsyncMcpProtocolHandler {
+            reader,
+            writer,
+            max_message_bytes: MAX_MESSAGE_BYTES,
+        }
+    }
+
+    /// Reject lines longer than `limit` bytes instead of buffering them
+    pub fn with_max_message_bytes(mut self, limit: usize) -> Self {
+        self.max_message_bytes = limit;
+        self
+    }
+
+    /// Read the next non-blank line, or `None` once the reader is exhausted
+    pub async fn read_frame(&mut self) -> io::Result<Option<Frame>> {
+        let limit = self.max_message_bytes;
+        loop {
+            let mut bytes = Vec::new();
+            if (&mut self.reader).take(limit as u64 + 1).read_until(b'\n', &mut bytes).await? == 0 {
+                return Ok(None);
+            }
+
+            if bytes.len() > limit && bytes.last() != Some(&b'\n') {
+                self.skip_rest_of_line().await?;
+                log::warn!("Rejected message over {} bytes", limit);
+                return Ok(Some(oversized(limit)));
+            }
+            if let Some(frame) = to_frame(bytes) {
+                return Ok(Some(frame));
+            }
+        }
+    }
+
+    async fn skip_rest_of_line(&mut self) -> io::Result<()> {
+        loop {
+            let buf = self.reader.fill_buf().await?;
+            if buf.is_empty() {
+                return Ok(());
+            }
+            match buf.iter().position(|&b| b == b'\n') {
+                Some(i) => {
+                    self.reader.consume(i + 1);
+                    return Ok(());
+                }
+                None => {
+                    let len = buf.len();
+                    self.reader.consume(len);
+                }
+            }
+        }
+    }
+
+    /// Read the next single JSON-RPC request, or `None` once the reader is exhausted
+    pub async fn read_request(&mut self) -> Result<Option<Request>> {
+        match self.read_frame().await? {
+            Some(frame) => frame_to_request(frame).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Write a response or batch response
+    pub async fn write_reply(&mut self, reply: &Reply) -> Result<()> {
+        self.write_message(reply).await?;
+        log::debug!("Sent reply: {}", describe_reply(reply));
+        Ok(())
+    }
+
+    /// Write a JSON-RPC response
+    pub async fn write_response(&mut self, response: &Response) -> Result<()> {
+        self.write_message(response).await?;
+        log::debug!("Sent response: id={:?}", response.id);
+        Ok(())
+    }
+
+    /// Write a server-initiated notification
+    pub async fn write_notification(&mut self, notification: &Notification) -> Result<()> {
+        self.write_message(notification).await?;
+        log::debug!("Sent notification: {}", notification.method);
+        Ok(())
+    }
+
+    async fn write_message<T: Serialize>(&mut self, message: &T) -> Result<()> {
+        let mut json = serde_json::to_string(message)?;
+        json.push('\n');
+        self.writer.write_all(json.as_bytes()).await?;
+        self.writer.flush().await?;
+        Ok(())
+    }
+
+    /// Give back the underlying reader and writer
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+}
+
+fn frame_to_request(frame: Frame) -> Result<Request> {
+    let line = match frame {
+        Frame::Line(line) => line,
+        Frame::Rejected(rejection) => return Err(anyhow::anyhow!(rejection.message)),
+    };
+    match parse_message(&line) {
+        Ok(Message::Single(request)) => {
+            log::debug!("Received request: method={} id={:?}", request.method, request.id);
+            Ok(request)
+        }
+        Ok(Message::Batch(_)) => Err(anyhow::anyhow!("Expected a single request, got a batch")),
+        Err(rejection) => Err(anyhow::anyhow!(rejection.message)),
+    }
+}
+
+fn describe_reply(reply: &Reply) -> String {
+    match reply {
+        Reply::Single(response) => format!("id={:?}", response.id),
+        Reply::Batch(responses) => format!("batch of {}", responses.len()),
     }
 }
 
@@ -61,6 +386,12 @@ impl Default for McpProtocolHandler {
 mod tests {
     use super::*;
     use serde_json::json;
+    use std::io::Cursor;
+
+    fn rejection(result: std::result::Result<Message, Rejection>) -> (Option<Value>, i32) {
+        let rejection = result.unwrap_err();
+        (rejection.id, rejection.code)
+    }
 
     #[test]
     fn test_response_serialization() {
@@ -71,4 +402,126 @@ mod tests {
         assert!(serialized.contains("\"id\":1"));
         assert!(serialized.contains("\"result\""));
     }
+
+    #[test]
+    fn test_one_reader_for_the_whole_session() {
+        let input = "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"ping\"}\n\n\
+                     {\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"tools/list\"}\r\n";
+        let mut handler = McpProtocolHandler::new(Cursor::new(input), Vec::new());
+
+        assert_eq!(handler.read_request().unwrap().unwrap().method, "ping");
+        assert_eq!(handler.read_request().unwrap().unwrap().method, "tools/list");
+        assert!(handler.read_request().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_writes_newline_delimited_messages() {
+        let mut handler = McpProtocolHandler::new(Cursor::new(""), Vec::new());
+        handler.send_success(Some(json!(1)), json!({})).unwrap();
+        handler.write_notification(&Notification::new("notifications/tools/list_changed", None)).unwrap();
+
+        let (_, output) = handler.into_inner();
+        let lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["id"], 1);
+        assert_eq!(lines[1]["method"], "notifications/tools/list_changed");
+    }
+
+    #[test]
+    fn test_parse_error_and_invalid_requests() {
+        assert_eq!(rejection(parse_message("{\"jsonrpc\": \"2.0\", \"id\": 1")), (None, PARSE_ERROR));
+        assert_eq!(rejection(parse_message("[]")), (None, INVALID_REQUEST));
+        assert_eq!(
+            rejection(parse_message(r#"{"jsonrpc": "1.0", "id": 4, "method": "ping"}"#)),
+            (Some(json!(4)), INVALID_REQUEST)
+        );
+        assert_eq!(
+            rejection(parse_message(r#"{"jsonrpc": "2.0", "id": "a"}"#)),
+            (Some(json!("a")), INVALID_REQUEST)
+        );
+        assert_eq!(
+            rejection(parse_message(r#"{"jsonrpc": "2.0", "id": null, "method": "ping"}"#)),
+            (None, INVALID_REQUEST)
+        );
+        assert_eq!(
+            rejection(parse_message(r#"{"jsonrpc": "2.0", "id": 1, "method": "ping", "params": 3}"#)),
+            (Some(json!(1)), INVALID_REQUEST)
+        );
+        assert_eq!(rejection(parse_message("42")), (None, INVALID_REQUEST));
+    }
+
+    #[test]
+    fn test_batch_keeps_valid_entries() {
+        let line = r#"[{"jsonrpc": "2.0", "id": 1, "method": "ping"}, 1, {"jsonrpc": "2.0", "method": "notifications/initialized"}]"#;
+        let Ok(Message::Batch(items)) = parse_message(line) else {
+            panic!("expected a batch");
+        };
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_ref().unwrap().method, "ping");
+        assert_eq!(items[1].as_ref().unwrap_err().code, INVALID_REQUEST);
+        assert!(items[2].as_ref().unwrap().id.is_none());
+    }
+
+    #[test]
+    fn test_oversized_line_rejected_and_skipped() {
+        let input = format!(
+            "{{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"ping\",\"params\":{{\"pad\":\"{}\"}}}}\n\
+             {{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"ping\"}}\n",
+            "x".repeat(200)
+        );
+        let mut handler = McpProtocolHandler::new(Cursor::new(input), Vec::new()).with_max_message_bytes(64);
+
+        match handler.read_frame().unwrap().unwrap() {
+            Frame::Rejected(rejection) => assert_eq!(rejection.code, INVALID_REQUEST),
+            Frame::Line(line) => panic!("oversized line accepted: {}", line),
+        }
+        assert_eq!(handler.read_request().unwrap().unwrap().id, Some(json!(2)));
+        assert!(handler.read_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_invalid_utf8_is_a_parse_error() {
+        let mut handler = McpProtocolHandler::new(Cursor::new(b"\xff\xfe\n".to_vec()), Vec::new());
+        match handler.read_frame().unwrap().unwrap() {
+            Frame::Rejected(rejection) => assert_eq!(rejection.code, PARSE_ERROR),
+            Frame::Line(line) => panic!("invalid UTF-8 accepted: {}", line),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_handler_over_in_process_pipe() {
+        let (client, server) = tokio::io::duplex(1024);
+        let (server_read, server_write) = tokio::io::split(server);
+        let (client_read, mut client_write) = tokio::io::split(client);
+
+        let mut server = AsyncMcpProtocolHandler::new(tokio::io::BufReader::new(server_read), server_write)
+            .with_max_message_bytes(128);
+        client_write
+            .write_all(format!("{}\n", "y".repeat(300)).as_bytes())
+            .await
+            .unwrap();
+        client_write
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"id\":7,\"method\":\"ping\"}\n")
+            .await
+            .unwrap();
+
+        assert!(matches!(server.read_frame().await.unwrap(), Some(Frame::Rejected(_))));
+        let request = server.read_request().await.unwrap().unwrap();
+        server.write_response(&Response::success(request.id, json!({}))).await.unwrap();
+
+        let mut client = AsyncMcpProtocolHandler::new(tokio::io::BufReader::new(client_read), client_write);
+        let Some(Frame::Line(line)) = client.read_frame().await.unwrap() else {
+            panic!("expected a response line");
+        };
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["id"], 7);
+
+        drop(client);
+        assert!(server.read_request().await.unwrap().is_none());
+    }
 }
@@ -1,59 +1,297 @@
-use anyhow::Result;
+use crate::db::ConnectionPool;
+use crate::error::{Result, StoryError};
 use rusqlite::Connection;
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use super::types::Tool;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use super::errors;
+use super::schema::{self, SchemaViolation};
+use super::types::{ErrorObject, Tool, INVALID_PARAMS};
 
 pub type ToolHandler = Arc<dyn Fn(&Connection, Value) -> Result<Value> + Send + Sync>;
 
+/// Reports what a successful tool call changed, given its arguments and result
+pub type ChangeTracker = Arc<dyn Fn(&Connection, &Value, &Value) -> Vec<Change> + Send + Sync>;
+
+/// Whether a tool is offered to clients, given the current database
+pub type Availability = Arc<dyn Fn(&Connection) -> bool + Send + Sync>;
+
+/// Something a client may want to be notified about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// The resource at this URI was created or modified
+    ResourceUpdated(String),
+    /// Tools were registered or removed, or a tool's availability may have changed
+    ToolListChanged,
+}
+
+/// Why a `tools/call` could not produce a result
+#[derive(Debug, thiserror::Error)]
+pub enum ToolCallError {
+    #[error("Tool not found: {0}")]
+    NotFound(String),
+
+    #[error("Tool not available: {0}")]
+    Unavailable(String),
+
+    #[error("Invalid arguments for tool {tool}: {}", describe_violations(.violations))]
+    InvalidParams {
+        tool: String,
+        violations: Vec<SchemaViolation>,
+    },
+
+    #[error(transparent)]
+    Execution(#[from] StoryError),
+}
+
+impl ToolCallError {
+    /// JSON-RPC error object to send back for this failure
+    pub fn to_error_object(&self) -> ErrorObject {
+        match self {
+            ToolCallError::NotFound(_) | ToolCallError::Unavailable(_) => ErrorObject {
+                code: INVALID_PARAMS,
+                message: self.to_string(),
+                data: None,
+            },
+            ToolCallError::InvalidParams { violations, .. } => ErrorObject {
+                code: INVALID_PARAMS,
+                message: self.to_string(),
+                data: Some(json!({ "errors": violations })),
+            },
+            ToolCallError::Execution(e) => errors::error_object(e),
+        }
+    }
+}
+
+fn describe_violations(violations: &[SchemaViolation]) -> String {
+    violations
+        .iter()
+        .map(|v| {
+            if v.field.is_empty() {
+                v.message.clone()
+            } else {
+                format!("{}: {}", v.field, v.message)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+struct RegisteredTool {
+    definition: Tool,
+    handler: ToolHandler,
+    tracker: Option<ChangeTracker>,
+    /// Listed and callable only while this holds; always when `None`
+    available: Option<Availability>,
+    /// Runs on a pooled read connection, concurrently with other reads
+    read_only: bool,
+}
+
+/// Tools and the database they run against. Shared by every session on the
+/// server, so registration goes through interior locks rather than `&mut self`.
 pub struct ToolRegistry {
-    tools: HashMap<String, ToolHandler>,
-    tool_definitions: HashMap<String, Tool>,
-    conn: Arc<Mutex<Connection>>,
+    tools: RwLock<HashMap<String, RegisteredTool>>,
+    listeners: Mutex<Vec<UnboundedSender<Change>>>,
+    pool: ConnectionPool,
 }
 
 impl ToolRegistry {
+    /// Registry over a single connection that serves reads and writes alike
     pub fn new(conn: Connection) -> Self {
+        Self::with_pool(ConnectionPool::single(conn))
+    }
+
+    /// Registry whose read-only tools run on the pool's read connections
+    pub fn with_pool(pool: ConnectionPool) -> Self {
         ToolRegistry {
-            tools: HashMap::new(),
-            tool_definitions: HashMap::new(),
-            conn: Arc::new(Mutex::new(conn)),
+            tools: RwLock::new(HashMap::new()),
+            listeners: Mutex::new(Vec::new()),
+            pool,
         }
     }
 
-    pub fn register<F>(&mut self, name: &str, description: &str, input_schema: Value, handler: F)
+    /// Register a tool that may write; its calls are serialized on the writer connection
+    pub fn register<F>(&self, name: &str, description: &str, input_schema: Value, handler: F)
     where
         F: Fn(&Connection, Value) -> Result<Value> + Send + Sync + 'static,
     {
-        self.tools.insert(name.to_string(), Arc::new(handler));
-        self.tool_definitions.insert(
+        self.insert(name, description, input_schema, Arc::new(handler), false);
+    }
+
+    /// Register a tool that only queries. It runs on a read-only connection,
+    /// so an accidental write fails instead of bypassing the writer.
+    pub fn register_read_only<F>(&self, name: &str, description: &str, input_schema: Value, handler: F)
+    where
+        F: Fn(&Connection, Value) -> Result<Value> + Send + Sync + 'static,
+    {
+        self.insert(name, description, input_schema, Arc::new(handler), true);
+    }
+
+    fn insert(&self, name: &str, description: &str, input_schema: Value, handler: ToolHandler, read_only: bool) {
+        self.tools.write().unwrap().insert(
             name.to_string(),
-            Tool {
-                name: name.to_string(),
-                description: description.to_string(),
-                input_schema,
+            RegisteredTool {
+                definition: Tool {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                    input_schema,
+                },
+                handler,
+                tracker: None,
+                available: None,
+                read_only,
             },
         );
+        self.record(Change::ToolListChanged);
         log::info!("Registered tool: {}", name);
     }
 
-    pub fn call_tool(&self, name: &str, params: Value) -> Result<Value> {
-        let handler = self
-            .tools
-            .get(name)
-            .ok_or_else(|| anyhow::anyhow!("Tool not found: {}", name))?;
+    /// Remove a tool; returns whether it was registered
+    pub fn unregister(&self, name: &str) -> bool {
+        let removed = self.tools.write().unwrap().remove(name).is_some();
+        if removed {
+            self.record(Change::ToolListChanged);
+            log::info!("Unregistered tool: {}", name);
+        }
+        removed
+    }
+
+    /// Attach a tracker that reports what each successful call to `name` changed
+    pub fn track_changes<F>(&self, name: &str, tracker: F)
+    where
+        F: Fn(&Connection, &Value, &Value) -> Vec<Change> + Send + Sync + 'static,
+    {
+        match self.tools.write().unwrap().get_mut(name) {
+            Some(tool) => tool.tracker = Some(Arc::new(tracker)),
+            None => log::warn!("Cannot track changes for unregistered tool: {}", name),
+        }
+    }
+
+    /// Offer `name` in `tools/list`, and accept calls to it, only while `condition`
+    /// holds. A write call that changes the outcome reports [`Change::ToolListChanged`].
+    pub fn list_when<F>(&self, name: &str, condition: F)
+    where
+        F: Fn(&Connection) -> bool + Send + Sync + 'static,
+    {
+        match self.tools.write().unwrap().get_mut(name) {
+            Some(tool) => tool.available = Some(Arc::new(condition)),
+            None => log::warn!("Cannot set availability for unregistered tool: {}", name),
+        }
+    }
+
+    /// Receive every change recorded from now on. Each session holds its own receiver.
+    pub fn watch(&self) -> UnboundedReceiver<Change> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.listeners.lock().unwrap().push(tx);
+        rx
+    }
 
-        let conn = self.conn.lock().unwrap();
-        handler(&conn, params)
+    /// Sessions still watching for changes, as of the last change recorded
+    pub fn watcher_count(&self) -> usize {
+        self.listeners.lock().unwrap().len()
     }
 
+    fn record(&self, change: Change) {
+        // Dropped receivers belong to closed sessions
+        self.listeners
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(change.clone()).is_ok());
+    }
+
+    /// Validate `params` against the tool's registered input schema, then invoke its handler
+    pub fn call_tool(&self, name: &str, params: Value) -> std::result::Result<Value, ToolCallError> {
+        let (handler, tracker, read_only, available, conditions) = {
+            let tools = self.tools.read().unwrap();
+            let tool = tools.get(name).ok_or_else(|| ToolCallError::NotFound(name.to_string()))?;
+
+            let violations = schema::validate(&tool.definition.input_schema, &params);
+            if !violations.is_empty() {
+                log::warn!("Rejected call to {}: {} invalid argument(s)", name, violations.len());
+                return Err(ToolCallError::InvalidParams {
+                    tool: name.to_string(),
+                    violations,
+                });
+            }
+            let conditions: Vec<Availability> = tools.values().filter_map(|t| t.available.clone()).collect();
+            (tool.handler.clone(), tool.tracker.clone(), tool.read_only, tool.available.clone(), conditions)
+        };
+        let check_available = |conn: &Connection| match &available {
+            Some(available) if !available(conn) => Err(ToolCallError::Unavailable(name.to_string())),
+            _ => Ok(()),
+        };
+
+        if read_only {
+            return self.pool.read(|conn| {
+                check_available(conn)?;
+                Ok(handler(conn, params)?)
+            });
+        }
+
+        let (result, changes) = self.pool.write(|conn| {
+            check_available(conn)?;
+            let offered = |conn: &Connection| conditions.iter().map(|condition| condition(conn)).collect::<Vec<_>>();
+            let before = offered(conn);
+            let tracked = tracker.as_ref().map(|_| params.clone());
+            let result = handler(conn, params)?;
+            let mut changes = match (tracker, tracked) {
+                (Some(tracker), Some(params)) => tracker(conn, &params, &result),
+                _ => Vec::new(),
+            };
+            if offered(conn) != before {
+                changes.push(Change::ToolListChanged);
+            }
+            Ok::<_, ToolCallError>((result, changes))
+        })?;
+        for change in changes {
+            self.record(change);
+        }
+        Ok(result)
+    }
+
+    /// Whether calls to `name` run on a read connection; false for unknown tools
+    pub fn is_read_only(&self, name: &str) -> bool {
+        self.tools.read().unwrap().get(name).is_some_and(|t| t.read_only)
+    }
+
+    /// Every registered tool, whether currently offered or not
     pub fn list_tools(&self) -> Vec<Tool> {
-        self.tool_definitions.values().cloned().collect()
+        self.tools.read().unwrap().values().map(|t| t.definition.clone()).collect()
+    }
+
+    /// The tools to offer clients in `tools/list`: those without a condition or whose condition holds
+    pub fn available_tools(&self) -> Vec<Tool> {
+        let tools = self.tools.read().unwrap();
+        self.pool.read(|conn| {
+            tools
+                .values()
+                .filter(|t| match &t.available {
+                    Some(available) => available(conn),
+                    None => true,
+                })
+                .map(|t| t.definition.clone())
+                .collect()
+        })
     }
 
     pub fn has_tool(&self, name: &str) -> bool {
-        self.tools.contains_key(name)
+        self.tools.read().unwrap().contains_key(name)
+    }
+
+    /// Run `f` against the writer connection
+    pub fn with_connection<T>(&self, f: impl FnOnce(&Connection) -> T) -> T {
+        self.pool.write(f)
+    }
+
+    /// Run `f` against a read connection, alongside other reads
+    pub fn with_reader<T>(&self, f: impl FnOnce(&Connection) -> T) -> T {
+        self.pool.read(f)
+    }
+
+    /// Get the registered definition for a tool
+    pub fn get_tool(&self, name: &str) -> Option<Tool> {
+        self.tools.read().unwrap().get(name).map(|t| t.definition.clone())
     }
 }
 
@@ -63,10 +301,14 @@ mod tests {
     use rusqlite::Connection;
     use serde_json::json;
 
+    fn drain(changes: &mut UnboundedReceiver<Change>) -> Vec<Change> {
+        std::iter::from_fn(|| changes.try_recv().ok()).collect()
+    }
+
     #[test]
     fn test_tool_registration() {
         let conn = Connection::open_in_memory().unwrap();
-        let mut registry = ToolRegistry::new(conn);
+        let registry = ToolRegistry::new(conn);
 
         registry.register("test_tool", "A test tool", json!({"type": "object"}), |_conn, params| {
             Ok(json!({"received": params}))
@@ -79,7 +321,7 @@ mod tests {
     #[test]
     fn test_tool_execution() {
         let conn = Connection::open_in_memory().unwrap();
-        let mut registry = ToolRegistry::new(conn);
+        let registry = ToolRegistry::new(conn);
 
         registry.register("echo", "Echo tool", json!({"type": "object"}), |_conn, params| {
             Ok(params)
@@ -89,6 +331,104 @@ mod tests {
         assert_eq!(result, json!({"message": "hello"}));
     }
 
+    #[test]
+    fn test_changes_recorded_for_successful_calls_only() {
+        let conn = Connection::open_in_memory().unwrap();
+        let registry = ToolRegistry::new(conn);
+        let mut changes = registry.watch();
+
+        registry.register("touch", "Touch a resource", json!({"type": "object"}), |_conn, params| {
+            if params["fail"] == json!(true) {
+                Err(StoryError::validation("failed"))
+            } else {
+                Ok(json!({"id": "abc"}))
+            }
+        });
+        registry.track_changes("touch", |_conn, _params, result| {
+            vec![Change::ResourceUpdated(format!("story://scene/{}", result["id"].as_str().unwrap()))]
+        });
+        assert_eq!(drain(&mut changes), vec![Change::ToolListChanged]);
+
+        // Watchers only see changes made after they subscribed
+        let mut late = registry.watch();
+        registry.call_tool("touch", json!({"fail": true})).unwrap_err();
+        assert_eq!(drain(&mut changes).len(), 0);
+
+        registry.call_tool("touch", json!({})).unwrap();
+        let expected = Change::ResourceUpdated("story://scene/abc".to_string());
+        assert_eq!(drain(&mut changes), vec![expected.clone()]);
+        assert_eq!(drain(&mut late), vec![expected]);
+
+        assert!(registry.unregister("touch"));
+        assert!(!registry.unregister("touch"));
+        assert_eq!(drain(&mut changes), vec![Change::ToolListChanged]);
+    }
+
+    #[test]
+    fn test_conditional_tools_listed_only_when_available() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE flags (on_ INTEGER)").unwrap();
+        let registry = ToolRegistry::new(conn);
+
+        registry.register("always", "Always listed", json!({"type": "object"}), |_conn, p| Ok(p));
+        registry.register("gated", "Listed while a flag is set", json!({"type": "object"}), |_conn, p| Ok(p));
+        registry.register("flag", "Set the flag", json!({"type": "object"}), |conn, _| {
+            conn.execute("INSERT INTO flags VALUES (1)", [])?;
+            Ok(json!({}))
+        });
+        registry.list_when("gated", |conn| {
+            conn.query_row("SELECT COUNT(*) FROM flags", [], |row| row.get::<_, i64>(0)).unwrap_or(0) > 0
+        });
+        let mut changes = registry.watch();
+
+        let names = |registry: &ToolRegistry| {
+            let mut names: Vec<String> = registry.available_tools().into_iter().map(|t| t.name).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(&registry), vec!["always", "flag"]);
+        assert_eq!(registry.list_tools().len(), 3);
+        assert!(matches!(registry.call_tool("gated", json!({})), Err(ToolCallError::Unavailable(_))));
+
+        // Only the call that makes the gated tool appear changes the list
+        registry.call_tool("flag", json!({})).unwrap();
+        assert_eq!(drain(&mut changes), vec![Change::ToolListChanged]);
+        assert_eq!(names(&registry), vec!["always", "flag", "gated"]);
+        registry.call_tool("flag", json!({})).unwrap();
+        assert_eq!(drain(&mut changes).len(), 0);
+        assert!(registry.call_tool("gated", json!({})).is_ok());
+    }
+
+    #[test]
+    fn test_read_only_tools_run_on_read_connections() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = ConnectionPool::open(dir.path().join("registry.db"), 2).unwrap();
+        let registry = ToolRegistry::with_pool(pool);
+
+        let insert = |conn: &Connection, _params: Value| {
+            conn.execute(
+                "INSERT INTO story_projects (id, title, intended_length) VALUES ('p-1', 'Pooled', 'novel')",
+                [],
+            )?;
+            Ok(json!({}))
+        };
+        registry.register_read_only("sneaky_write", "Writes despite being read-only", json!({"type": "object"}), insert);
+        registry.register("write", "Writes", json!({"type": "object"}), insert);
+        registry.register_read_only("count", "Counts projects", json!({"type": "object"}), |conn, _params| {
+            let count: i64 = conn.query_row("SELECT COUNT(*) FROM story_projects", [], |row| row.get(0))?;
+            Ok(json!({"count": count}))
+        });
+
+        assert!(registry.is_read_only("count"));
+        assert!(!registry.is_read_only("write"));
+        assert!(!registry.is_read_only("nonexistent"));
+
+        // The read connection refuses the write rather than racing the writer
+        assert!(registry.call_tool("sneaky_write", json!({})).is_err());
+        registry.call_tool("write", json!({})).unwrap();
+        assert_eq!(registry.call_tool("count", json!({})).unwrap(), json!({"count": 1}));
+    }
+
     #[test]
     fn test_tool_not_found() {
         let conn = Connection::open_in_memory().unwrap();
@@ -97,4 +437,52 @@ mod tests {
         let result = registry.call_tool("nonexistent", json!({}));
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_invalid_arguments_rejected_before_handler() {
+        let conn = Connection::open_in_memory().unwrap();
+        let registry = ToolRegistry::new(conn);
+
+        registry.register(
+            "strict",
+            "Strict tool",
+            json!({
+                "type": "object",
+                "properties": {"name": {"type": "string"}, "count": {"type": "integer"}},
+                "required": ["name", "count"],
+                "additionalProperties": false
+            }),
+            |_conn, _params| panic!("handler must not run on invalid input"),
+        );
+
+        let err = registry
+            .call_tool("strict", json!({"count": "three", "extra": true}))
+            .unwrap_err()
+            .to_error_object();
+
+        assert_eq!(err.code, INVALID_PARAMS);
+        let errors = err.data.unwrap()["errors"].as_array().unwrap().clone();
+        let fields: Vec<&str> = errors.iter().map(|e| e["field"].as_str().unwrap()).collect();
+        assert_eq!(errors.len(), 3);
+        assert!(fields.contains(&"name"));
+        assert!(fields.contains(&"count"));
+        assert!(fields.contains(&"extra"));
+    }
+
+    #[test]
+    fn test_handler_error_keeps_story_error_variant() {
+        let conn = Connection::open_in_memory().unwrap();
+        let registry = ToolRegistry::new(conn);
+
+        registry.register("missing", "Always not found", json!({"type": "object"}), |_conn, _params| {
+            Err(StoryError::not_found("Project not found").with_entity("project", "p-1"))
+        });
+
+        let err = registry.call_tool("missing", json!({})).unwrap_err();
+        assert!(matches!(err, ToolCallError::Execution(StoryError::NotFound(_))));
+
+        let obj = err.to_error_object();
+        assert_eq!(obj.code, crate::mcp::types::NOT_FOUND);
+        assert_eq!(obj.data.unwrap()["entityId"], "p-1");
+    }
 }
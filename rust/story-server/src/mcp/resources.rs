@@ -0,0 +1,694 @@
+use super::registry::Change;
+use super::types::{Resource, ResourceContents, ResourceTemplate};
+use crate::error::{Result, StoryError};
+use rusqlite::{Connection, OptionalExtension};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+const JSON_MIME: &str = "application/json";
+const SCHEME: &str = "story://";
+
+/// Number of resources returned per `resources/list` page
+pub const PAGE_SIZE: usize = 100;
+
+/// A parsed `story://` resource URI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceUri {
+    Project(Uuid),
+    Chapter { project_id: Uuid, number: i32 },
+    Scene(Uuid),
+    Character(Uuid),
+    WorldRule(Uuid),
+}
+
+impl ResourceUri {
+    pub fn parse(uri: &str) -> Result<Self> {
+        let invalid = || StoryError::validation(format!("Unsupported resource URI: {}", uri)).with_field("uri");
+        let path = uri.strip_prefix(SCHEME).ok_or_else(invalid)?;
+        let segments: Vec<&str> = path.split('/').collect();
+        let uuid = |s: &str| Uuid::parse_str(s).map_err(|_| invalid());
+
+        match segments.as_slice() {
+            ["project", id] => Ok(ResourceUri::Project(uuid(id)?)),
+            ["project", id, "chapter", number] => Ok(ResourceUri::Chapter {
+                project_id: uuid(id)?,
+                number: number.parse().map_err(|_| invalid())?,
+            }),
+            ["scene", id] => Ok(ResourceUri::Scene(uuid(id)?)),
+            ["character", id] => Ok(ResourceUri::Character(uuid(id)?)),
+            ["world-rule", id] => Ok(ResourceUri::WorldRule(uuid(id)?)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl std::fmt::Display for ResourceUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResourceUri::Project(id) => write!(f, "{}project/{}", SCHEME, id),
+            ResourceUri::Chapter { project_id, number } => {
+                write!(f, "{}project/{}/chapter/{}", SCHEME, project_id, number)
+            }
+            ResourceUri::Scene(id) => write!(f, "{}scene/{}", SCHEME, id),
+            ResourceUri::Character(id) => write!(f, "{}character/{}", SCHEME, id),
+            ResourceUri::WorldRule(id) => write!(f, "{}world-rule/{}", SCHEME, id),
+        }
+    }
+}
+
+/// URI templates advertised through `resources/templates/list`
+pub fn resource_templates() -> Vec<ResourceTemplate> {
+    let template = |uri: &str, name: &str, description: &str| ResourceTemplate {
+        uri_template: uri.to_string(),
+        name: name.to_string(),
+        description: description.to_string(),
+        mime_type: JSON_MIME.to_string(),
+    };
+
+    vec![
+        template("story://project/{id}", "Story project", "Project metadata with its acts and chapters"),
+        template(
+            "story://project/{id}/chapter/{n}",
+            "Chapter",
+            "Chapter {n} of a project with its scene list",
+        ),
+        template("story://scene/{id}", "Scene", "Scene content, outline and the characters present"),
+        template("story://character/{id}", "Character", "Character profile, current state and relationships"),
+        template("story://world-rule/{id}", "World rule", "World-building rule with its scope and keywords"),
+    ]
+}
+
+/// Every resource in the database, in project order
+pub fn list_resources(conn: &Connection) -> Result<Vec<Resource>> {
+    let mut resources = Vec::new();
+
+    let mut stmt = conn.prepare("SELECT id, title, genre FROM story_projects ORDER BY title")?;
+    let projects = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    for (project_id, title, genre) in projects {
+        resources.push(Resource {
+            uri: format!("{}project/{}", SCHEME, project_id),
+            name: title.clone(),
+            description: genre.map(|g| format!("{} project", g)),
+            mime_type: JSON_MIME.to_string(),
+        });
+
+        let mut stmt = conn.prepare(
+            "SELECT c.number, c.title
+             FROM chapters c
+             JOIN acts a ON c.act_id = a.id
+             JOIN plot_structures ps ON a.plot_structure_id = ps.id
+             WHERE ps.story_project_id = ?1
+             ORDER BY a.position, c.number",
+        )?;
+        let chapters = stmt
+            .query_map([&project_id], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, Option<String>>(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        for (number, chapter_title) in chapters {
+            resources.push(Resource {
+                uri: format!("{}project/{}/chapter/{}", SCHEME, project_id, number),
+                name: chapter_title.unwrap_or_else(|| format!("Chapter {}", number)),
+                description: Some(format!("{} - chapter {}", title, number)),
+                mime_type: JSON_MIME.to_string(),
+            });
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT s.id, s.title, c.number, s.position
+             FROM scenes s
+             JOIN chapters c ON s.chapter_id = c.id
+             JOIN acts a ON c.act_id = a.id
+             JOIN plot_structures ps ON a.plot_structure_id = ps.id
+             WHERE ps.story_project_id = ?1
+             ORDER BY a.position, c.number, s.position",
+        )?;
+        let scenes = stmt
+            .query_map([&project_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, i32>(2)?,
+                    row.get::<_, i32>(3)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        for (scene_id, scene_title, chapter, position) in scenes {
+            resources.push(Resource {
+                uri: format!("{}scene/{}", SCHEME, scene_id),
+                name: scene_title.unwrap_or_else(|| format!("Chapter {} scene {}", chapter, position)),
+                description: Some(format!("{} - chapter {}, scene {}", title, chapter, position)),
+                mime_type: JSON_MIME.to_string(),
+            });
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT id, name, role FROM characters WHERE story_project_id = ?1 ORDER BY name",
+        )?;
+        let characters = stmt
+            .query_map([&project_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        for (character_id, name, role) in characters {
+            resources.push(Resource {
+                uri: format!("{}character/{}", SCHEME, character_id),
+                name,
+                description: Some(format!("{} in {}", role, title)),
+                mime_type: JSON_MIME.to_string(),
+            });
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT id, name, scope FROM world_rules WHERE story_project_id = ?1 ORDER BY name",
+        )?;
+        let rules = stmt
+            .query_map([&project_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        for (rule_id, name, scope) in rules {
+            resources.push(Resource {
+                uri: format!("{}world-rule/{}", SCHEME, rule_id),
+                name,
+                description: Some(format!("{} rule in {}", scope, title)),
+                mime_type: JSON_MIME.to_string(),
+            });
+        }
+    }
+
+    Ok(resources)
+}
+
+/// Read a single resource by URI
+pub fn read_resource(conn: &Connection, uri: &str) -> Result<ResourceContents> {
+    let body = match ResourceUri::parse(uri)? {
+        ResourceUri::Project(id) => read_project(conn, id)?,
+        ResourceUri::Chapter { project_id, number } => read_chapter(conn, project_id, number)?,
+        ResourceUri::Scene(id) => read_scene(conn, id)?,
+        ResourceUri::Character(id) => read_character(conn, id)?,
+        ResourceUri::WorldRule(id) => read_world_rule(conn, id)?,
+    };
+
+    Ok(ResourceContents {
+        uri: uri.to_string(),
+        mime_type: JSON_MIME.to_string(),
+        text: serde_json::to_string_pretty(&body)?,
+    })
+}
+
+fn read_project(conn: &Connection, project_id: Uuid) -> Result<Value> {
+    let mut project = conn
+        .query_row(
+            "SELECT title, genre, intended_length, description, status, word_count, updated_at
+             FROM story_projects WHERE id = ?1",
+            [project_id.to_string()],
+            |row| {
+                Ok(json!({
+                    "projectId": project_id.to_string(),
+                    "title": row.get::<_, String>(0)?,
+                    "genre": row.get::<_, Option<String>>(1)?,
+                    "intendedLength": row.get::<_, String>(2)?,
+                    "description": row.get::<_, Option<String>>(3)?,
+                    "status": row.get::<_, String>(4)?,
+                    "wordCount": row.get::<_, i32>(5)?,
+                    "updatedAt": row.get::<_, String>(6)?
+                }))
+            },
+        )
+        .optional()?
+        .ok_or_else(|| project_not_found(project_id))?;
+
+    let mut stmt = conn.prepare(
+        "SELECT a.name, a.position, c.number, c.title, c.status, c.word_count
+         FROM chapters c
+         JOIN acts a ON c.act_id = a.id
+         JOIN plot_structures ps ON a.plot_structure_id = ps.id
+         WHERE ps.story_project_id = ?1
+         ORDER BY a.position, c.number",
+    )?;
+    let chapters = stmt
+        .query_map([project_id.to_string()], |row| {
+            let number: i32 = row.get(2)?;
+            Ok(json!({
+                "act": row.get::<_, String>(0)?,
+                "actPosition": row.get::<_, i32>(1)?,
+                "number": number,
+                "title": row.get::<_, Option<String>>(3)?,
+                "status": row.get::<_, String>(4)?,
+                "wordCount": row.get::<_, i32>(5)?,
+                "uri": ResourceUri::Chapter { project_id, number }.to_string()
+            }))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    project["chapters"] = json!(chapters);
+    Ok(project)
+}
+
+fn read_chapter(conn: &Connection, project_id: Uuid, number: i32) -> Result<Value> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.title, c.status, c.summary, c.word_count, a.name
+         FROM chapters c
+         JOIN acts a ON c.act_id = a.id
+         JOIN plot_structures ps ON a.plot_structure_id = ps.id
+         WHERE ps.story_project_id = ?1 AND c.number = ?2
+         LIMIT 2",
+    )?;
+    let mut matches = stmt
+        .query_map((project_id.to_string(), number), |row| {
+            let chapter_id: String = row.get(0)?;
+            Ok((
+                chapter_id.clone(),
+                json!({
+                    "projectId": project_id.to_string(),
+                    "chapterId": chapter_id,
+                    "number": number,
+                    "title": row.get::<_, Option<String>>(1)?,
+                    "status": row.get::<_, String>(2)?,
+                    "summary": row.get::<_, Option<String>>(3)?,
+                    "wordCount": row.get::<_, i32>(4)?,
+                    "act": row.get::<_, String>(5)?
+                }),
+            ))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    // Stories written before chapter numbers were unique per story may repeat one
+    if matches.len() > 1 {
+        return Err(StoryError::invalid_state(format!(
+            "Chapter {} is numbered in more than one act of project {}; renumber one of them",
+            number, project_id
+        ))
+        .with_entity("project", project_id));
+    }
+    let (chapter_id, mut chapter) = matches.pop().ok_or_else(|| {
+        StoryError::not_found(format!("Chapter {} not found in project {}", number, project_id))
+            .with_entity("project", project_id)
+    })?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, title, position, location, status, word_count
+         FROM scenes WHERE chapter_id = ?1 ORDER BY position",
+    )?;
+    let scenes = stmt
+        .query_map([&chapter_id], |row| {
+            let scene_id: String = row.get(0)?;
+            Ok(json!({
+                "uri": format!("{}scene/{}", SCHEME, scene_id),
+                "sceneId": scene_id,
+                "title": row.get::<_, Option<String>>(1)?,
+                "position": row.get::<_, i32>(2)?,
+                "location": row.get::<_, Option<String>>(3)?,
+                "status": row.get::<_, String>(4)?,
+                "wordCount": row.get::<_, i32>(5)?
+            }))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    chapter["scenes"] = json!(scenes);
+    Ok(chapter)
+}
+
+fn read_scene(conn: &Connection, scene_id: Uuid) -> Result<Value> {
+    let mut scene = conn
+        .query_row(
+            "SELECT s.title, s.position, s.location, s.time_description, s.content, s.word_count,
+                    s.status, s.scene_outline, s.summary, c.number, ps.story_project_id
+             FROM scenes s
+             JOIN chapters c ON s.chapter_id = c.id
+             JOIN acts a ON c.act_id = a.id
+             JOIN plot_structures ps ON a.plot_structure_id = ps.id
+             WHERE s.id = ?1",
+            [scene_id.to_string()],
+            |row| {
+                Ok(json!({
+                    "sceneId": scene_id.to_string(),
+                    "title": row.get::<_, Option<String>>(0)?,
+                    "position": row.get::<_, i32>(1)?,
+                    "location": row.get::<_, Option<String>>(2)?,
+                    "timeDescription": row.get::<_, Option<String>>(3)?,
+                    "content": row.get::<_, String>(4)?,
+                    "wordCount": row.get::<_, i32>(5)?,
+                    "status": row.get::<_, String>(6)?,
+                    "sceneOutline": row.get::<_, Option<String>>(7)?,
+                    "summary": row.get::<_, Option<String>>(8)?,
+                    "chapterNumber": row.get::<_, i32>(9)?,
+                    "projectId": row.get::<_, String>(10)?
+                }))
+            },
+        )
+        .optional()?
+        .ok_or_else(|| {
+            StoryError::not_found(format!("Scene not found: {}", scene_id)).with_entity("scene", scene_id)
+        })?;
+
+    let mut stmt = conn.prepare(
+        "SELECT ch.id, ch.name, sc.role_in_scene
+         FROM scene_characters sc
+         JOIN characters ch ON sc.character_id = ch.id
+         WHERE sc.scene_id = ?1
+         ORDER BY ch.name",
+    )?;
+    let characters = stmt
+        .query_map([scene_id.to_string()], |row| {
+            let character_id: String = row.get(0)?;
+            Ok(json!({
+                "uri": format!("{}character/{}", SCHEME, character_id),
+                "characterId": character_id,
+                "name": row.get::<_, String>(1)?,
+                "roleInScene": row.get::<_, String>(2)?
+            }))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    scene["characters"] = json!(characters);
+    Ok(scene)
+}
+
+fn read_character(conn: &Connection, character_id: Uuid) -> Result<Value> {
+    let mut character = conn
+        .query_row(
+            "SELECT story_project_id, name, role, personality_traits, physical_description,
+                    backstory, current_state, first_appearance_scene_id
+             FROM characters WHERE id = ?1",
+            [character_id.to_string()],
+            |row| {
+                Ok(json!({
+                    "characterId": character_id.to_string(),
+                    "projectId": row.get::<_, String>(0)?,
+                    "name": row.get::<_, String>(1)?,
+                    "role": row.get::<_, String>(2)?,
+                    "personalityTraits": row.get::<_, Option<String>>(3)?,
+                    "physicalDescription": row.get::<_, Option<String>>(4)?,
+                    "backstory": row.get::<_, Option<String>>(5)?,
+                    "currentState": row.get::<_, Option<String>>(6)?,
+                    "firstAppearanceSceneId": row.get::<_, Option<String>>(7)?
+                }))
+            },
+        )
+        .optional()?
+        .ok_or_else(|| {
+            StoryError::not_found(format!("Character not found: {}", character_id))
+                .with_entity("character", character_id)
+        })?;
+
+    let mut stmt = conn.prepare(
+        "SELECT r.relationship_type, r.description, r.strength, t.id, t.name
+         FROM character_relationships r
+         JOIN characters t ON r.target_character_id = t.id
+         WHERE r.source_character_id = ?1
+         ORDER BY t.name",
+    )?;
+    let relationships = stmt
+        .query_map([character_id.to_string()], |row| {
+            let target_id: String = row.get(3)?;
+            Ok(json!({
+                "relationshipType": row.get::<_, String>(0)?,
+                "description": row.get::<_, Option<String>>(1)?,
+                "strength": row.get::<_, Option<i32>>(2)?,
+                "uri": format!("{}character/{}", SCHEME, target_id),
+                "characterId": target_id,
+                "name": row.get::<_, String>(4)?
+            }))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    character["relationships"] = json!(relationships);
+    Ok(character)
+}
+
+fn read_world_rule(conn: &Connection, rule_id: Uuid) -> Result<Value> {
+    conn.query_row(
+        "SELECT story_project_id, name, description, scope, examples, keywords, updated_at
+         FROM world_rules WHERE id = ?1",
+        [rule_id.to_string()],
+        |row| {
+            let keywords: Option<String> = row.get(5)?;
+            Ok(json!({
+                "ruleId": rule_id.to_string(),
+                "projectId": row.get::<_, String>(0)?,
+                "name": row.get::<_, String>(1)?,
+                "description": row.get::<_, String>(2)?,
+                "scope": row.get::<_, String>(3)?,
+                "examples": row.get::<_, Option<String>>(4)?,
+                "keywords": keywords.as_ref().and_then(|k| serde_json::from_str::<Value>(k).ok()),
+                "updatedAt": row.get::<_, String>(6)?
+            }))
+        },
+    )
+    .optional()?
+    .ok_or_else(|| {
+        StoryError::not_found(format!("World rule not found: {}", rule_id)).with_entity("world_rule", rule_id)
+    })
+}
+
+// Change trackers: map a successful tool call to the resources it touched.
+// Registered alongside the tools so subscribers get `notifications/resources/updated`.
+
+fn id_field(value: &Value, field: &str) -> Option<Uuid> {
+    value.get(field).and_then(|v| v.as_str()).and_then(|s| Uuid::parse_str(s).ok())
+}
+
+fn updated(uris: impl IntoIterator<Item = ResourceUri>) -> Vec<Change> {
+    uris.into_iter().map(|uri| Change::ResourceUpdated(uri.to_string())).collect()
+}
+
+/// The chapter's own resource plus its project, which lists every chapter
+fn chapter_and_project(conn: &Connection, chapter_id: &str) -> Vec<ResourceUri> {
+    conn.query_row(
+        "SELECT ps.story_project_id, c.number
+         FROM chapters c
+         JOIN acts a ON c.act_id = a.id
+         JOIN plot_structures ps ON a.plot_structure_id = ps.id
+         WHERE c.id = ?1",
+        [chapter_id],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, i32>(1)?)),
+    )
+    .ok()
+    .and_then(|(project_id, number)| Uuid::parse_str(&project_id).ok().map(|id| (id, number)))
+    .map(|(project_id, number)| {
+        vec![
+            ResourceUri::Chapter { project_id, number },
+            ResourceUri::Project(project_id),
+        ]
+    })
+    .unwrap_or_default()
+}
+
+/// `addScene` / `updateScene`: the scene, its chapter and the project
+pub fn scene_changes(conn: &Connection, params: &Value, result: &Value) -> Vec<Change> {
+    let scene_id = id_field(result, "sceneId");
+    let mut uris: Vec<ResourceUri> = scene_id.map(ResourceUri::Scene).into_iter().collect();
+    let chapter_id = match params.get("chapterId").and_then(|v| v.as_str()) {
+        Some(chapter_id) => Some(chapter_id.to_string()),
+        None => scene_id.and_then(|id| {
+            conn.query_row("SELECT chapter_id FROM scenes WHERE id = ?1", [id.to_string()], |row| row.get(0))
+                .ok()
+        }),
+    };
+    if let Some(chapter_id) = chapter_id {
+        uris.extend(chapter_and_project(conn, &chapter_id));
+    }
+    updated(uris)
+}
+
+/// `addChapter`: the new chapter and the project
+pub fn chapter_changes(conn: &Connection, _params: &Value, result: &Value) -> Vec<Change> {
+    match result.get("chapterId").and_then(|v| v.as_str()) {
+        Some(chapter_id) => updated(chapter_and_project(conn, chapter_id)),
+        None => Vec::new(),
+    }
+}
+
+/// `initializePlotStructure`: the project, which now lists its acts
+pub fn project_changes(_conn: &Connection, params: &Value, _result: &Value) -> Vec<Change> {
+    updated(id_field(params, "projectId").map(ResourceUri::Project))
+}
+
+/// `addCharacter` / `updateCharacter`: the character
+pub fn character_changes(_conn: &Connection, _params: &Value, result: &Value) -> Vec<Change> {
+    updated(id_field(result, "characterId").map(ResourceUri::Character))
+}
+
+/// `addCharacterRelationship`: both ends of the relationship
+pub fn relationship_changes(_conn: &Connection, params: &Value, _result: &Value) -> Vec<Change> {
+    updated(
+        ["sourceCharacterId", "targetCharacterId"]
+            .into_iter()
+            .filter_map(|field| id_field(params, field))
+            .map(ResourceUri::Character),
+    )
+}
+
+/// `addWorldRule` / `updateWorldRule`: the rule
+pub fn world_rule_changes(_conn: &Connection, _params: &Value, result: &Value) -> Vec<Change> {
+    updated(id_field(result, "ruleId").map(ResourceUri::WorldRule))
+}
+
+fn project_not_found(project_id: Uuid) -> StoryError {
+    StoryError::not_found(format!("Project not found: {}", project_id)).with_entity("project", project_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::tools::{
+        add_chapter, add_character, add_scene, add_world_rule, create_story_project, initialize_plot_structure,
+        update_scene,
+    };
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_uri_round_trip() {
+        let id = Uuid::new_v4();
+        for uri in [
+            ResourceUri::Project(id),
+            ResourceUri::Chapter { project_id: id, number: 3 },
+            ResourceUri::Scene(id),
+            ResourceUri::Character(id),
+            ResourceUri::WorldRule(id),
+        ] {
+            assert_eq!(ResourceUri::parse(&uri.to_string()).unwrap(), uri);
+        }
+
+        assert!(ResourceUri::parse("story://unknown/1").is_err());
+        assert!(ResourceUri::parse("file:///etc/passwd").is_err());
+        assert!(ResourceUri::parse(&format!("story://project/{}/chapter/x", id)).is_err());
+    }
+
+    #[test]
+    fn test_list_and_read_resources() {
+        let dir = tempdir().unwrap();
+        let conn = db::initialize_database(dir.path().join("test.db")).unwrap();
+
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Resource Test", "targetLength": "novel"})).unwrap();
+        let project_id = project["projectId"].as_str().unwrap();
+        let plot = initialize_plot_structure(&conn, json!({"projectId": project_id})).unwrap();
+        let act_id = plot["acts"][0]["actId"].as_str().unwrap();
+        let chapter = add_chapter(&conn, json!({"actId": act_id, "number": 1, "title": "Opening"})).unwrap();
+        let scene = add_scene(&conn, dir.path(), json!({"chapterId": chapter["chapterId"], "title": "Arrival"})).unwrap();
+        let character = add_character(&conn, json!({"projectId": project_id, "name": "Mira", "role": "protagonist"})).unwrap();
+
+        let uris: Vec<String> = list_resources(&conn).unwrap().into_iter().map(|r| r.uri).collect();
+        let scene_uri = format!("story://scene/{}", scene["sceneId"].as_str().unwrap());
+        let character_uri = format!("story://character/{}", character["characterId"].as_str().unwrap());
+        let chapter_uri = format!("story://project/{}/chapter/1", project_id);
+        assert_eq!(uris.len(), 4);
+        assert!(uris.contains(&format!("story://project/{}", project_id)));
+        assert!(uris.contains(&chapter_uri));
+        assert!(uris.contains(&scene_uri));
+        assert!(uris.contains(&character_uri));
+
+        let contents = read_resource(&conn, &chapter_uri).unwrap();
+        let body: Value = serde_json::from_str(&contents.text).unwrap();
+        assert_eq!(body["title"], "Opening");
+        assert_eq!(body["scenes"][0]["uri"], scene_uri);
+
+        let body: Value = serde_json::from_str(&read_resource(&conn, &character_uri).unwrap().text).unwrap();
+        assert_eq!(body["name"], "Mira");
+
+        let rule = add_world_rule(
+            &conn,
+            json!({"projectId": project_id, "name": "Tides", "description": "Sea rises at dusk", "scope": "regional", "keywords": ["sea"]}),
+        )
+        .unwrap();
+        let rule_uri = format!("story://world-rule/{}", rule["ruleId"].as_str().unwrap());
+        assert!(list_resources(&conn).unwrap().iter().any(|r| r.uri == rule_uri));
+        let body: Value = serde_json::from_str(&read_resource(&conn, &rule_uri).unwrap().text).unwrap();
+        assert_eq!(body["keywords"], json!(["sea"]));
+    }
+
+    #[test]
+    fn test_chapter_numbers_name_one_chapter_across_acts() {
+        let stories = tempdir().unwrap();
+        let conn = db::initialize_database(":memory:").unwrap();
+        let project = create_story_project(&conn, stories.path(), json!({"title": "Two Acts", "targetLength": "novel"})).unwrap();
+        let project_id = project["projectId"].as_str().unwrap();
+        let plot = initialize_plot_structure(&conn, json!({"projectId": project_id})).unwrap();
+        let (first_act, second_act) = (&plot["acts"][0]["actId"], &plot["acts"][1]["actId"]);
+        add_chapter(&conn, json!({"actId": first_act, "number": 1, "title": "Opening"})).unwrap();
+
+        // A second act can't reuse the number, so the URI stays unambiguous
+        let err = add_chapter(&conn, json!({"actId": second_act, "number": 1})).unwrap_err();
+        assert!(matches!(err, StoryError::DuplicateEntry(_)));
+        add_chapter(&conn, json!({"actId": second_act, "number": 2, "title": "Turn"})).unwrap();
+
+        let uris: Vec<String> = list_resources(&conn).unwrap().into_iter().map(|r| r.uri).collect();
+        let chapter_uris: Vec<&String> = uris.iter().filter(|uri| uri.contains("/chapter/")).collect();
+        assert_eq!(chapter_uris.len(), 2);
+        let turn = read_resource(&conn, &format!("story://project/{}/chapter/2", project_id)).unwrap();
+        let body: Value = serde_json::from_str(&turn.text).unwrap();
+        assert_eq!(body["title"], "Turn");
+        assert_eq!(body["act"], plot["acts"][1]["name"]);
+    }
+
+    #[test]
+    fn test_repeated_chapter_number_is_ambiguous() {
+        let stories = tempdir().unwrap();
+        let conn = db::initialize_database(":memory:").unwrap();
+        let project = create_story_project(&conn, stories.path(), json!({"title": "Legacy Acts", "targetLength": "novel"})).unwrap();
+        let project_id = project["projectId"].as_str().unwrap();
+        let plot = initialize_plot_structure(&conn, json!({"projectId": project_id})).unwrap();
+
+        // Written before chapter numbers were unique per story, so add_chapter is bypassed
+        for (id, act) in [("first", &plot["acts"][0]["actId"]), ("second", &plot["acts"][1]["actId"])] {
+            conn.execute(
+                "INSERT INTO chapters (id, act_id, number, position, status, word_count, created_at, updated_at)
+                 VALUES (?1, ?2, 1, 1, 'planned', 0, '', '')",
+                [id, act.as_str().unwrap()],
+            )
+            .unwrap();
+        }
+
+        // Reported rather than guessed at
+        let err = read_resource(&conn, &format!("story://project/{}/chapter/1", project_id)).unwrap_err();
+        assert!(matches!(err, StoryError::InvalidState(_)));
+        assert!(err.to_string().contains("more than one act"), "{}", err);
+        assert_eq!(err.detail().unwrap().entity_id.as_deref(), Some(project_id));
+    }
+
+    #[test]
+    fn test_scene_changes_cover_parents() {
+        let dir = tempdir().unwrap();
+        let conn = db::initialize_database(dir.path().join("test.db")).unwrap();
+
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Change Test", "targetLength": "novel"})).unwrap();
+        let project_id = project["projectId"].as_str().unwrap();
+        let plot = initialize_plot_structure(&conn, json!({"projectId": project_id})).unwrap();
+        let chapter = add_chapter(&conn, json!({"actId": plot["acts"][0]["actId"], "number": 2})).unwrap();
+        let params = json!({"chapterId": chapter["chapterId"]});
+        let scene = add_scene(&conn, dir.path(), params.clone()).unwrap();
+
+        let changes = scene_changes(&conn, &params, &scene);
+        assert_eq!(
+            changes,
+            vec![
+                Change::ResourceUpdated(format!("story://scene/{}", scene["sceneId"].as_str().unwrap())),
+                Change::ResourceUpdated(format!("story://project/{}/chapter/2", project_id)),
+                Change::ResourceUpdated(format!("story://project/{}", project_id)),
+            ]
+        );
+
+        // updateScene only names the scene; the chapter is looked up
+        let params = json!({"sceneId": scene["sceneId"], "title": "Renamed"});
+        let updated = update_scene(&conn, dir.path(), params.clone()).unwrap();
+        assert_eq!(scene_changes(&conn, &params, &updated), changes);
+    }
+
+    #[test]
+    fn test_read_missing_resource() {
+        let dir = tempdir().unwrap();
+        let conn = db::initialize_database(dir.path().join("test.db")).unwrap();
+
+        let err = read_resource(&conn, &format!("story://scene/{}", Uuid::new_v4())).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+    }
+}
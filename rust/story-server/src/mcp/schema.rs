@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// A single argument that failed validation against a tool's input schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaViolation {
+    /// Dotted path to the offending field (empty for the root object)
+    pub field: String,
+    pub message: String,
+}
+
+/// Validate `value` against a JSON Schema, returning every violation found.
+///
+/// Only the subset of JSON Schema used by our tool definitions is supported:
+/// `type`, `properties`, `required`, `additionalProperties`, `enum`, `items`,
+/// `format: uuid`, string length and numeric bounds, and `anyOf`/`oneOf`/`allOf`.
+pub fn validate(schema: &Value, value: &Value) -> Vec<SchemaViolation> {
+    let mut violations = Vec::new();
+    validate_at(schema, value, "", &mut violations);
+    violations
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, out: &mut Vec<SchemaViolation>) {
+    let schema = match schema {
+        Value::Object(map) => map,
+        // `true` / `false` schemas
+        Value::Bool(false) => {
+            push(out, path, "no value is allowed here");
+            return;
+        }
+        _ => return,
+    };
+
+    if let Some(expected) = schema.get("type") {
+        if !matches_type(expected, value) {
+            push(out, path, format!("expected {}, got {}", describe_type(expected), type_name(value)));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(|v| v.as_array()) {
+        if !allowed.contains(value) {
+            let options: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+            push(out, path, format!("must be one of {}", options.join(", ")));
+        }
+    }
+
+    if let Some(subschemas) = schema.get("allOf").and_then(|v| v.as_array()) {
+        for subschema in subschemas {
+            validate_at(subschema, value, path, out);
+        }
+    }
+
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(subschemas) = schema.get(keyword).and_then(|v| v.as_array()) {
+            let matched = subschemas.iter().any(|s| validate(s, value).is_empty());
+            if !matched {
+                // Report the closest alternative so the caller sees a useful message
+                let best = subschemas
+                    .iter()
+                    .map(|s| {
+                        let mut nested = Vec::new();
+                        validate_at(s, value, path, &mut nested);
+                        nested
+                    })
+                    .min_by_key(|v| v.len())
+                    .unwrap_or_default();
+                if best.is_empty() {
+                    push(out, path, "does not match any allowed schema");
+                } else {
+                    out.extend(best);
+                }
+            }
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            let properties = schema.get("properties").and_then(|v| v.as_object());
+
+            if let Some(required) = schema.get("required").and_then(|v| v.as_array()) {
+                for name in required.iter().filter_map(|v| v.as_str()) {
+                    if map.get(name).is_none_or(|v| v.is_null()) {
+                        push(out, &join(path, name), "missing required field");
+                    }
+                }
+            }
+
+            for (name, field_value) in map {
+                let field_path = join(path, name);
+                match properties.and_then(|p| p.get(name)) {
+                    Some(field_schema) => validate_at(field_schema, field_value, &field_path, out),
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => push(out, &field_path, "unknown field"),
+                        Some(extra @ Value::Object(_)) => validate_at(extra, field_value, &field_path, out),
+                        _ => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(item_schema, item, &format!("{}[{}]", path, i), out);
+                }
+            }
+        }
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(|v| v.as_u64()) {
+                if len < min {
+                    push(out, path, format!("must be at least {} characters", min));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(|v| v.as_u64()) {
+                if len > max {
+                    push(out, path, format!("must be {} characters or less", max));
+                }
+            }
+            if schema.get("format").and_then(|v| v.as_str()) == Some("uuid") && Uuid::parse_str(s).is_err() {
+                push(out, path, "must be a valid UUID");
+            }
+        }
+        Value::Number(n) => {
+            if let Some(n) = n.as_f64() {
+                if let Some(min) = schema.get("minimum").and_then(|v| v.as_f64()) {
+                    if n < min {
+                        push(out, path, format!("must be >= {}", min));
+                    }
+                }
+                if let Some(max) = schema.get("maximum").and_then(|v| v.as_f64()) {
+                    if n > max {
+                        push(out, path, format!("must be <= {}", max));
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn matches_type(expected: &Value, value: &Value) -> bool {
+    match expected {
+        Value::String(t) => is_type(t, value),
+        Value::Array(types) => types.iter().filter_map(|t| t.as_str()).any(|t| is_type(t, value)),
+        _ => true,
+    }
+}
+
+fn is_type(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    }
+}
+
+fn describe_type(expected: &Value) -> String {
+    match expected {
+        Value::Array(types) => types
+            .iter()
+            .filter_map(|t| t.as_str())
+            .collect::<Vec<_>>()
+            .join(" or "),
+        Value::String(t) => t.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+fn push(out: &mut Vec<SchemaViolation>, path: &str, message: impl Into<String>) {
+    out.push(SchemaViolation {
+        field: path.to_string(),
+        message: message.into(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "projectId": {"type": "string", "format": "uuid"},
+                "name": {"type": "string", "maxLength": 5},
+                "scope": {"type": "string", "enum": ["universal", "regional"]},
+                "number": {"type": "integer", "minimum": 1}
+            },
+            "required": ["projectId", "name"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_valid_arguments() {
+        let args = json!({"projectId": Uuid::new_v4().to_string(), "name": "Ash", "number": 3});
+        assert!(validate(&schema(), &args).is_empty());
+    }
+
+    #[test]
+    fn test_reports_every_offending_field() {
+        let args = json!({"name": "Too long a name", "scope": "galactic", "number": 0, "ruleName": "x"});
+        let violations = validate(&schema(), &args);
+        let fields: Vec<&str> = violations.iter().map(|v| v.field.as_str()).collect();
+
+        assert_eq!(violations.len(), 5);
+        for field in ["projectId", "name", "scope", "number", "ruleName"] {
+            assert!(fields.contains(&field), "expected violation for {}", field);
+        }
+    }
+
+    #[test]
+    fn test_type_mismatch_and_uuid_format() {
+        let args = json!({"projectId": "not-a-uuid", "name": 42});
+        let violations = validate(&schema(), &args);
+
+        assert_eq!(violations.len(), 2);
+        assert!(violations.iter().any(|v| v.field == "projectId" && v.message.contains("UUID")));
+        assert!(violations.iter().any(|v| v.field == "name" && v.message.contains("expected string")));
+    }
+
+    #[test]
+    fn test_any_of_accepts_nullable_values() {
+        let schema = json!({"anyOf": [{"type": "string", "enum": ["a"]}, {"type": "null"}]});
+        assert!(validate(&schema, &json!(null)).is_empty());
+        assert!(validate(&schema, &json!("a")).is_empty());
+        assert_eq!(validate(&schema, &json!("b")).len(), 1);
+    }
+}
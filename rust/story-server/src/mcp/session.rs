@@ -0,0 +1,1138 @@
+use super::errors;
+use super::prompts;
+use super::protocol::{self, AsyncMcpProtocolHandler, Frame, McpProtocolHandler, Message, Rejection, Reply};
+use super::registry::{Change, ToolCallError, ToolRegistry};
+use super::resources;
+use super::types::*;
+use crate::error::StoryError;
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncWrite};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::task::JoinSet;
+
+/// Protocol revisions this server can speak, newest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Lifecycle of an MCP connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    /// Waiting for the client's `initialize` request
+    AwaitingInitialize,
+    /// `initialize` answered, waiting for `notifications/initialized`
+    Initializing,
+    /// Handshake complete, normal operation
+    Ready,
+}
+
+/// Frames read ahead of the one being handled, on async transports
+const FRAME_QUEUE: usize = 32;
+
+/// Work for a request that touches the database, split off so it can run
+/// on a blocking thread while the session keeps reading
+pub struct PendingRequest {
+    id: Option<Value>,
+    read_only: bool,
+    work: Box<dyn FnOnce() -> Response + Send>,
+}
+
+impl PendingRequest {
+    pub fn id(&self) -> Option<&Value> {
+        self.id.as_ref()
+    }
+
+    /// Whether the work only reads, and so may overlap other requests
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Run the work on the current thread
+    pub fn run(self) -> Response {
+        (self.work)()
+    }
+
+    /// Run the work on tokio's blocking thread pool
+    pub async fn run_blocking(self) -> Response {
+        let id = self.id.clone();
+        tokio::task::spawn_blocking(move || self.run())
+            .await
+            .unwrap_or_else(|e| Response::error(id, INTERNAL_ERROR, format!("Request failed: {}", e)))
+    }
+}
+
+/// Outcome of dispatching one message
+pub enum Dispatch {
+    /// Handled on the spot; `None` for notifications and cancelled requests
+    Done(Option<Response>),
+    /// Run the work, then pass its response to [`McpSession::complete`]
+    Pending(PendingRequest),
+}
+
+impl From<Response> for Dispatch {
+    fn from(response: Response) -> Self {
+        Dispatch::Done(Some(response))
+    }
+}
+
+/// One MCP session: tracks the lifecycle state and dispatches requests
+/// and notifications to the tool registry.
+pub struct McpSession {
+    registry: Arc<ToolRegistry>,
+    changes: UnboundedReceiver<Change>,
+    state: SessionState,
+    protocol_version: Option<String>,
+    /// Requests handed out as [`PendingRequest`]s and not yet completed
+    in_flight: HashSet<String>,
+    /// In-flight requests the client has cancelled
+    cancelled: HashSet<String>,
+    subscriptions: HashSet<String>,
+    /// URIs of in-flight `resources/subscribe` requests, by request id
+    subscribing: HashMap<String, String>,
+    outbox: Vec<Notification>,
+}
+
+impl McpSession {
+    /// Start a session over a registry, which may be shared with other sessions
+    pub fn new(registry: impl Into<Arc<ToolRegistry>>) -> Self {
+        let registry = registry.into();
+        McpSession {
+            changes: registry.watch(),
+            registry,
+            state: SessionState::AwaitingInitialize,
+            protocol_version: None,
+            in_flight: HashSet::new(),
+            cancelled: HashSet::new(),
+            subscriptions: HashSet::new(),
+            subscribing: HashMap::new(),
+            outbox: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    /// Protocol version agreed during `initialize`
+    pub fn protocol_version(&self) -> Option<&str> {
+        self.protocol_version.as_deref()
+    }
+
+    pub fn registry(&self) -> &ToolRegistry {
+        &self.registry
+    }
+
+    /// URIs the client has subscribed to
+    pub fn subscriptions(&self) -> &HashSet<String> {
+        &self.subscriptions
+    }
+
+    /// Handle one incoming message. Requests always produce a response;
+    /// notifications (messages without an `id`) never do.
+    pub fn handle(&mut self, request: Request) -> Option<Response> {
+        match self.dispatch(request) {
+            Dispatch::Done(response) => response,
+            Dispatch::Pending(pending) => self.complete(pending.run()),
+        }
+    }
+
+    /// Like [`handle`](Self::handle), but database work is handed back
+    /// instead of run, so the caller decides where and when it runs
+    pub fn dispatch(&mut self, request: Request) -> Dispatch {
+        match request.id.clone() {
+            Some(id) => {
+                let dispatch = self.handle_request(id.clone(), request);
+                if matches!(dispatch, Dispatch::Pending(_)) {
+                    self.in_flight.insert(id.to_string());
+                }
+                dispatch
+            }
+            None => {
+                self.handle_notification(request);
+                Dispatch::Done(None)
+            }
+        }
+    }
+
+    /// Take the response of finished [`PendingRequest`] work; `None` if the
+    /// client cancelled the request while it ran
+    pub fn complete(&mut self, response: Response) -> Option<Response> {
+        let id = response.id.as_ref().map(Value::to_string).unwrap_or_default();
+        self.in_flight.remove(&id);
+        let subscribing = self.subscribing.remove(&id);
+        if self.cancelled.remove(&id) {
+            log::info!("Dropping response to cancelled request {:?}", response.id);
+            return None;
+        }
+        if let Some(uri) = subscribing.filter(|_| response.error.is_none()) {
+            log::info!("Client subscribed to {}", uri);
+            self.subscriptions.insert(uri);
+        }
+        Some(response)
+    }
+
+    /// Handle a parsed message or batch. A batch gets one response per request,
+    /// in order; a batch made only of notifications gets nothing back.
+    pub fn handle_message(&mut self, message: Message) -> Option<Reply> {
+        match message {
+            Message::Single(request) => self.handle(request).map(Reply::Single),
+            Message::Batch(items) => {
+                let responses: Vec<Response> = items
+                    .into_iter()
+                    .filter_map(|item| match item {
+                        Ok(request) => self.handle(request),
+                        Err(rejection) => Some(rejection.into_response()),
+                    })
+                    .collect();
+                (!responses.is_empty()).then_some(Reply::Batch(responses))
+            }
+        }
+    }
+
+    /// Handle one raw line of JSON-RPC, answering malformed input with
+    /// `PARSE_ERROR` / `INVALID_REQUEST` rather than dropping it
+    pub fn handle_line(&mut self, line: &str) -> Option<Reply> {
+        match protocol::parse_message(line) {
+            Ok(message) => self.handle_message(message),
+            Err(rejection) => {
+                log::warn!("Rejected message: {}", rejection.message);
+                Some(Reply::Single(rejection.into_response()))
+            }
+        }
+    }
+
+    /// Drain server-initiated notifications for this client, including changes
+    /// made through other sessions sharing the registry
+    pub fn take_notifications(&mut self) -> Vec<Notification> {
+        self.collect_changes();
+        std::mem::take(&mut self.outbox)
+    }
+
+    /// Turn registry changes into notifications for this client
+    fn collect_changes(&mut self) {
+        let changes: Vec<Change> = std::iter::from_fn(|| self.changes.try_recv().ok()).collect();
+        self.queue_changes(changes);
+    }
+
+    fn queue_changes(&mut self, changes: Vec<Change>) {
+        let mut list_changed = false;
+        let mut updated = HashSet::new();
+        for change in changes {
+            // Nothing is sent before the client has completed the handshake
+            if self.state == SessionState::AwaitingInitialize {
+                continue;
+            }
+            match change {
+                Change::ResourceUpdated(uri) if self.subscriptions.contains(&uri) => {
+                    if updated.insert(uri.clone()) {
+                        self.outbox.push(Notification::new(
+                            "notifications/resources/updated",
+                            Some(json!({ "uri": uri })),
+                        ));
+                    }
+                }
+                Change::ResourceUpdated(_) => {}
+                Change::ToolListChanged => list_changed = true,
+            }
+        }
+        if list_changed {
+            self.outbox.push(Notification::new("notifications/tools/list_changed", None));
+        }
+    }
+
+    fn handle_request(&mut self, id: Value, request: Request) -> Dispatch {
+        let id = Some(id);
+        let method = request.method.as_str();
+
+        // Only initialize and ping are allowed before the handshake
+        if self.state == SessionState::AwaitingInitialize && !matches!(method, "initialize" | "ping") {
+            return Response::error(
+                id,
+                SERVER_NOT_INITIALIZED,
+                format!("Server not initialized: '{}' received before 'initialize'", method),
+            )
+            .into();
+        }
+
+        match method {
+            "initialize" => self.initialize(id, request.params).into(),
+            "ping" => Response::success(id, json!({})).into(),
+            "tools/list" => Response::success(id, json!({ "tools": self.registry.available_tools() })).into(),
+            "tools/call" => self.call_tool(id, request.params),
+            "resources/list" => self.list_resources(id, request.params),
+            "resources/read" => self.read_resource(id, request.params),
+            "resources/subscribe" => self.subscribe(id, request.params),
+            "resources/unsubscribe" => self.unsubscribe(id, request.params).into(),
+            "resources/templates/list" => Response::success(
+                id,
+                json!({ "resourceTemplates": resources::resource_templates() }),
+            )
+            .into(),
+            "prompts/list" => Response::success(id, json!({ "prompts": prompts::list_prompts() })).into(),
+            "prompts/get" => self.get_prompt(id, request.params),
+            _ => Response::error(id, METHOD_NOT_FOUND, format!("Unknown method: {}", method)).into(),
+        }
+    }
+
+    /// Defer `work` against the registry as a [`PendingRequest`]
+    fn pending<F>(&self, id: Option<Value>, read_only: bool, work: F) -> Dispatch
+    where
+        F: FnOnce(&ToolRegistry, Option<Value>) -> Response + Send + 'static,
+    {
+        let registry = self.registry.clone();
+        let work_id = id.clone();
+        Dispatch::Pending(PendingRequest {
+            id,
+            read_only,
+            work: Box::new(move || work(&registry, work_id)),
+        })
+    }
+
+    fn handle_notification(&mut self, notification: Request) {
+        match notification.method.as_str() {
+            "notifications/initialized" => {
+                if self.state == SessionState::AwaitingInitialize {
+                    log::warn!("Received initialized notification before initialize request");
+                } else {
+                    self.state = SessionState::Ready;
+                    log::info!("MCP session ready");
+                }
+            }
+            "notifications/cancelled" => {
+                let params = notification.params.unwrap_or(Value::Null);
+                if let Some(request_id) = params.get("requestId") {
+                    // A request that already finished, or never arrived, has nothing to cancel;
+                    // remembering it would swallow a later request that reuses the id
+                    let request_id = request_id.to_string();
+                    if !self.in_flight.contains(&request_id) {
+                        log::debug!("Ignoring cancellation of request {}, which is not in flight", request_id);
+                        return;
+                    }
+                    log::info!(
+                        "Client cancelled request {}: {}",
+                        request_id,
+                        params.get("reason").and_then(|v| v.as_str()).unwrap_or("no reason given")
+                    );
+                    self.cancelled.insert(request_id);
+                }
+            }
+            other => log::debug!("Ignoring notification: {}", other),
+        }
+    }
+
+    fn initialize(&mut self, id: Option<Value>, params: Option<Value>) -> Response {
+        if self.state != SessionState::AwaitingInitialize {
+            return Response::error(id, INVALID_REQUEST, "Session already initialized".to_string());
+        }
+
+        // Echo the client's version when we support it, otherwise offer our newest
+        let requested = params
+            .as_ref()
+            .and_then(|p| p.get("protocolVersion"))
+            .and_then(|v| v.as_str());
+        let version = requested
+            .filter(|v| SUPPORTED_PROTOCOL_VERSIONS.contains(v))
+            .unwrap_or(SUPPORTED_PROTOCOL_VERSIONS[0])
+            .to_string();
+
+        self.state = SessionState::Initializing;
+        self.protocol_version = Some(version.clone());
+
+        Response::success(
+            id,
+            json!({
+                "protocolVersion": version,
+                "capabilities": {
+                    "tools": { "listChanged": true },
+                    "resources": { "subscribe": true },
+                    "prompts": {}
+                },
+                "serverInfo": {
+                    "name": "story-db",
+                    "version": env!("CARGO_PKG_VERSION")
+                }
+            }),
+        )
+    }
+
+    fn call_tool(&mut self, id: Option<Value>, params: Option<Value>) -> Dispatch {
+        let Some(params) = params else {
+            return Response::error(id, INVALID_PARAMS, "Missing parameters".to_string()).into();
+        };
+        let Some(tool_name) = params.get("name").and_then(|v| v.as_str()) else {
+            return Response::error(id, INVALID_PARAMS, "Missing 'name' parameter".to_string()).into();
+        };
+        let tool_name = tool_name.to_string();
+        let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+
+        let read_only = self.registry.is_read_only(&tool_name);
+        self.pending(id, read_only, move |registry, id| {
+            tool_response(id, registry.call_tool(&tool_name, arguments))
+        })
+    }
+
+    fn list_resources(&mut self, id: Option<Value>, params: Option<Value>) -> Dispatch {
+        // Cursors are opaque to clients; ours is the offset of the next page
+        let cursor = params.as_ref().and_then(|p| p.get("cursor")).and_then(|v| v.as_str());
+        let offset = match cursor.map(|c| c.parse::<usize>()) {
+            None => 0,
+            Some(Ok(offset)) => offset,
+            Some(Err(_)) => return Response::error(id, INVALID_PARAMS, "Invalid cursor".to_string()).into(),
+        };
+
+        self.pending(id, true, move |registry, id| match registry.with_reader(resources::list_resources) {
+            Ok(all) => {
+                let page: Vec<_> = all.iter().skip(offset).take(resources::PAGE_SIZE).cloned().collect();
+                let mut result = json!({ "resources": page });
+                if offset + resources::PAGE_SIZE < all.len() {
+                    result["nextCursor"] = json!((offset + resources::PAGE_SIZE).to_string());
+                }
+                Response::success(id, result)
+            }
+            Err(e) => story_error(id, e),
+        })
+    }
+
+    fn read_resource(&mut self, id: Option<Value>, params: Option<Value>) -> Dispatch {
+        let Some(uri) = params.as_ref().and_then(|p| p.get("uri")).and_then(|v| v.as_str()) else {
+            return Response::error(id, INVALID_PARAMS, "Missing 'uri' parameter".to_string()).into();
+        };
+        let uri = uri.to_string();
+
+        self.pending(id, true, move |registry, id| {
+            match registry.with_reader(|conn| resources::read_resource(conn, &uri)) {
+                Ok(contents) => Response::success(id, json!({ "contents": [contents] })),
+                Err(e) => story_error(id, e),
+            }
+        })
+    }
+
+    fn subscribe(&mut self, id: Option<Value>, params: Option<Value>) -> Dispatch {
+        let Some(uri) = params.as_ref().and_then(|p| p.get("uri")).and_then(|v| v.as_str()) else {
+            return Response::error(id, INVALID_PARAMS, "Missing 'uri' parameter".to_string()).into();
+        };
+        let uri = uri.to_string();
+
+        // Only URIs we know how to serve can be subscribed to; the subscription
+        // starts when the check completes. Not read-only, so that requests sent
+        // after it, such as an unsubscribe, wait for it.
+        if let Some(id) = &id {
+            self.subscribing.insert(id.to_string(), uri.clone());
+        }
+        self.pending(id, false, move |registry, id| {
+            match registry.with_reader(|conn| resources::read_resource(conn, &uri)) {
+                Ok(_) => Response::success(id, json!({})),
+                Err(e) => story_error(id, e),
+            }
+        })
+    }
+
+    fn unsubscribe(&mut self, id: Option<Value>, params: Option<Value>) -> Response {
+        let Some(uri) = params.as_ref().and_then(|p| p.get("uri")).and_then(|v| v.as_str()) else {
+            return Response::error(id, INVALID_PARAMS, "Missing 'uri' parameter".to_string());
+        };
+
+        self.subscriptions.remove(uri);
+        log::info!("Client unsubscribed from {}", uri);
+        Response::success(id, json!({}))
+    }
+
+    fn get_prompt(&mut self, id: Option<Value>, params: Option<Value>) -> Dispatch {
+        let params = params.unwrap_or(Value::Null);
+        let Some(name) = params.get("name").and_then(|v| v.as_str()) else {
+            return Response::error(id, INVALID_PARAMS, "Missing 'name' parameter".to_string()).into();
+        };
+        let name = name.to_string();
+        let arguments = params.get("arguments").and_then(|v| v.as_object()).cloned().unwrap_or_default();
+
+        self.pending(id, true, move |registry, id| {
+            match registry.with_reader(|conn| prompts::get_prompt(conn, &name, &arguments)) {
+                Ok(result) => Response::success(id, result),
+                Err(e) => story_error(id, e),
+            }
+        })
+    }
+
+    /// Serve newline-delimited JSON-RPC over a reader/writer pair until the reader is exhausted
+    pub fn serve<R: BufRead, W: Write>(&mut self, reader: R, writer: W) -> Result<()> {
+        let mut protocol = McpProtocolHandler::new(reader, writer);
+        while let Some(frame) = protocol.read_frame()? {
+            let reply = match frame {
+                Frame::Line(line) => self.handle_line(&line),
+                Frame::Rejected(rejection) => Some(Reply::Single(rejection.into_response())),
+            };
+            if let Some(reply) = reply {
+                protocol.write_reply(&reply)?;
+            }
+            for notification in self.take_notifications() {
+                protocol.write_notification(&notification)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Async [`serve`](Self::serve) over tokio streams such as TCP or Unix sockets.
+    ///
+    /// Read-only requests run concurrently on blocking threads, so a slow query
+    /// does not hold up the requests behind it; their responses are written as
+    /// they finish and matched to requests by `id`. A request that may write
+    /// first waits for the reads sent before it, and runs before anything sent
+    /// after it, so every request sees the effects of the writes that preceded it.
+    /// Notifications of changes made through other sessions are written as they
+    /// happen, without waiting for the client's next request.
+    pub async fn serve_async<R, W>(&mut self, reader: R, writer: W) -> Result<()>
+    where
+        R: AsyncBufRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin,
+    {
+        // Read frames on their own task so the next request is picked up while others run
+        let (frames_tx, mut frames) = mpsc::channel(FRAME_QUEUE);
+        let reading = tokio::spawn(async move {
+            let mut protocol = AsyncMcpProtocolHandler::new(reader, tokio::io::sink());
+            while let Some(frame) = protocol.read_frame().await.transpose() {
+                let failed = frame.is_err();
+                if frames_tx.send(frame).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        let mut protocol = AsyncMcpProtocolHandler::new(tokio::io::empty(), writer);
+        let mut reads = JoinSet::new();
+        loop {
+            tokio::select! {
+                Some(done) = reads.join_next(), if !reads.is_empty() => {
+                    if let Some(response) = self.complete(done?) {
+                        protocol.write_response(&response).await?;
+                    }
+                }
+                // Changes made through other sessions reach a client even while it sends nothing
+                Some(change) = self.changes.recv() => {
+                    let mut changes = vec![change];
+                    changes.extend(std::iter::from_fn(|| self.changes.try_recv().ok()));
+                    self.queue_changes(changes);
+                }
+                frame = frames.recv() => {
+                    let Some(frame) = frame else { break };
+                    let message = match frame? {
+                        Frame::Line(line) => protocol::parse_message(&line),
+                        Frame::Rejected(rejection) => Err(rejection),
+                    };
+                    match message {
+                        Ok(Message::Single(request)) => match self.dispatch(request) {
+                            Dispatch::Done(response) => {
+                                if let Some(response) = response {
+                                    protocol.write_response(&response).await?;
+                                }
+                            }
+                            Dispatch::Pending(pending) if pending.is_read_only() => {
+                                reads.spawn(pending.run_blocking());
+                            }
+                            Dispatch::Pending(pending) => {
+                                self.finish_reads(&mut reads, &mut protocol).await?;
+                                if let Some(response) = self.complete(pending.run_blocking().await) {
+                                    protocol.write_response(&response).await?;
+                                }
+                            }
+                        },
+                        Ok(Message::Batch(items)) => {
+                            self.finish_reads(&mut reads, &mut protocol).await?;
+                            if let Some(reply) = self.handle_batch_async(items).await {
+                                protocol.write_reply(&reply).await?;
+                            }
+                        }
+                        Err(rejection) => {
+                            log::warn!("Rejected message: {}", rejection.message);
+                            protocol.write_response(&rejection.into_response()).await?;
+                        }
+                    }
+                }
+            }
+            for notification in self.take_notifications() {
+                protocol.write_notification(&notification).await?;
+            }
+        }
+
+        // The client may stop sending but still be waiting on answers
+        self.finish_reads(&mut reads, &mut protocol).await?;
+        for notification in self.take_notifications() {
+            protocol.write_notification(&notification).await?;
+        }
+        reading.await?;
+        Ok(())
+    }
+
+    /// Wait for every in-flight read and write its response
+    async fn finish_reads<R, W>(
+        &mut self,
+        reads: &mut JoinSet<Response>,
+        protocol: &mut AsyncMcpProtocolHandler<R, W>,
+    ) -> Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        while let Some(done) = reads.join_next().await {
+            if let Some(response) = self.complete(done?) {
+                protocol.write_response(&response).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// [`handle_message`](Self::handle_message) for a batch, running each
+    /// entry's database work on a blocking thread, in order
+    async fn handle_batch_async(&mut self, items: Vec<std::result::Result<Request, Rejection>>) -> Option<Reply> {
+        let mut responses = Vec::new();
+        for item in items {
+            let response = match item {
+                Ok(request) => match self.dispatch(request) {
+                    Dispatch::Done(response) => response,
+                    Dispatch::Pending(pending) => {
+                        let response = pending.run_blocking().await;
+                        self.complete(response)
+                    }
+                },
+                Err(rejection) => Some(rejection.into_response()),
+            };
+            responses.extend(response);
+        }
+        (!responses.is_empty()).then_some(Reply::Batch(responses))
+    }
+}
+
+fn tool_response(id: Option<Value>, result: std::result::Result<Value, ToolCallError>) -> Response {
+    match result {
+        Ok(result) => {
+            // Wrap result in MCP content format
+            Response::success(
+                id,
+                json!({
+                    "content": [
+                        {
+                            "type": "text",
+                            "text": serde_json::to_string(&result).unwrap_or_else(|_| result.to_string())
+                        }
+                    ]
+                }),
+            )
+        }
+        Err(e) => {
+            log::error!("Tool execution error: {}", e);
+            let obj = e.to_error_object();
+            Response::error_with_data(id, obj.code, obj.message, obj.data)
+        }
+    }
+}
+
+fn story_error(id: Option<Value>, err: StoryError) -> Response {
+    let obj = errors::error_object(&err);
+    Response::error_with_data(id, obj.code, obj.message, obj.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+    use std::io::Cursor;
+    use tempfile::tempdir;
+
+    fn session() -> McpSession {
+        let registry = ToolRegistry::new(Connection::open_in_memory().unwrap());
+        registry.register("echo", "Echo tool", json!({"type": "object"}), |_conn, params| Ok(params));
+        McpSession::new(registry)
+    }
+
+    /// Feed newline-delimited messages through the session and collect the responses
+    fn drive(session: &mut McpSession, messages: &[Value]) -> Vec<Value> {
+        let input: String = messages.iter().map(|m| format!("{}\n", m)).collect();
+        let mut output = Vec::new();
+        session.serve(Cursor::new(input), &mut output).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    fn initialize() -> Value {
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"protocolVersion": "2024-11-05"}})
+    }
+
+    fn initialized() -> Value {
+        json!({"jsonrpc": "2.0", "method": "notifications/initialized"})
+    }
+
+    #[test]
+    fn test_full_handshake() {
+        let mut session = session();
+        let responses = drive(
+            &mut session,
+            &[
+                initialize(),
+                initialized(),
+                json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}),
+                json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {"name": "echo", "arguments": {"a": 1}}}),
+            ],
+        );
+
+        // The initialized notification gets no response
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"]["protocolVersion"], "2024-11-05");
+        assert_eq!(responses[1]["result"]["tools"][0]["name"], "echo");
+        assert_eq!(responses[2]["result"]["content"][0]["text"], "{\"a\":1}");
+        assert_eq!(session.state(), SessionState::Ready);
+    }
+
+    #[test]
+    fn test_tool_call_rejected_before_initialize() {
+        let mut session = session();
+        let responses = drive(
+            &mut session,
+            &[json!({"jsonrpc": "2.0", "id": 7, "method": "tools/call", "params": {"name": "echo"}})],
+        );
+
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0]["id"], 7);
+        assert_eq!(responses[0]["error"]["code"], SERVER_NOT_INITIALIZED);
+        assert_eq!(session.state(), SessionState::AwaitingInitialize);
+    }
+
+    #[test]
+    fn test_ping_allowed_in_any_state() {
+        let mut session = session();
+        let responses = drive(&mut session, &[json!({"jsonrpc": "2.0", "id": "p", "method": "ping"})]);
+
+        assert_eq!(responses[0]["id"], "p");
+        assert_eq!(responses[0]["result"], json!({}));
+    }
+
+    #[test]
+    fn test_notifications_never_get_responses() {
+        let mut session = session();
+        let responses = drive(
+            &mut session,
+            &[
+                initialize(),
+                initialized(),
+                json!({"jsonrpc": "2.0", "method": "notifications/cancelled", "params": {"requestId": 99, "reason": "user abort"}}),
+                json!({"jsonrpc": "2.0", "method": "notifications/unknown"}),
+            ],
+        );
+
+        assert_eq!(responses.len(), 1);
+    }
+
+    fn request(value: Value) -> Request {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_cancelled_request_gets_no_response() {
+        let mut session = session();
+        drive(&mut session, &[initialize(), initialized()]);
+        let call = json!({"jsonrpc": "2.0", "id": 5, "method": "tools/call", "params": {"name": "echo", "arguments": {}}});
+        let cancel = json!({"jsonrpc": "2.0", "method": "notifications/cancelled", "params": {"requestId": 5}});
+
+        let Dispatch::Pending(pending) = session.dispatch(request(call.clone())) else { panic!("tool calls are deferred") };
+        assert!(matches!(session.dispatch(request(cancel)), Dispatch::Done(None)));
+        assert!(session.complete(pending.run()).is_none());
+
+        // The id is free again once the cancelled request has finished
+        let responses = drive(&mut session, &[call]);
+        assert_eq!(responses[0]["id"], 5);
+    }
+
+    #[test]
+    fn test_cancelling_finished_request_does_not_drop_reused_id() {
+        let mut session = session();
+        let responses = drive(
+            &mut session,
+            &[
+                initialize(),
+                initialized(),
+                json!({"jsonrpc": "2.0", "id": 5, "method": "tools/list"}),
+                json!({"jsonrpc": "2.0", "method": "notifications/cancelled", "params": {"requestId": 5}}),
+                json!({"jsonrpc": "2.0", "method": "notifications/cancelled", "params": {"requestId": 6}}),
+                json!({"jsonrpc": "2.0", "id": 5, "method": "tools/list"}),
+                json!({"jsonrpc": "2.0", "id": 6, "method": "tools/call", "params": {"name": "echo", "arguments": {}}}),
+            ],
+        );
+
+        let ids: Vec<&Value> = responses.iter().map(|r| &r["id"]).collect();
+        assert_eq!(ids, vec![&json!(1), &json!(5), &json!(5), &json!(6)]);
+    }
+
+    #[test]
+    fn test_unknown_method_and_repeated_initialize() {
+        let mut session = session();
+        let responses = drive(
+            &mut session,
+            &[
+                initialize(),
+                json!({"jsonrpc": "2.0", "id": 2, "method": "initialize", "params": {}}),
+                json!({"jsonrpc": "2.0", "id": 3, "method": "bogus/method"}),
+            ],
+        );
+
+        assert_eq!(responses[1]["error"]["code"], INVALID_REQUEST);
+        assert_eq!(responses[2]["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_resources_advertised_and_served() {
+        let mut session = McpSession::new(ToolRegistry::new(
+            crate::db::initialize_database(":memory:").unwrap(),
+        ));
+        let missing = format!("story://character/{}", uuid::Uuid::new_v4());
+        let responses = drive(
+            &mut session,
+            &[
+                initialize(),
+                json!({"jsonrpc": "2.0", "id": 2, "method": "resources/list"}),
+                json!({"jsonrpc": "2.0", "id": 3, "method": "resources/templates/list"}),
+                json!({"jsonrpc": "2.0", "id": 4, "method": "resources/read", "params": {"uri": missing}}),
+                json!({"jsonrpc": "2.0", "id": 5, "method": "resources/read", "params": {"uri": "story://nope"}}),
+            ],
+        );
+
+        assert!(responses[0]["result"]["capabilities"]["resources"].is_object());
+        assert_eq!(responses[1]["result"]["resources"], json!([]));
+        assert_eq!(responses[2]["result"]["resourceTemplates"].as_array().unwrap().len(), 5);
+        assert_eq!(responses[3]["error"]["code"], NOT_FOUND);
+        assert_eq!(responses[4]["error"]["code"], VALIDATION_FAILED);
+    }
+
+    #[test]
+    fn test_prompts_advertised_and_served() {
+        let mut session = McpSession::new(ToolRegistry::new(
+            crate::db::initialize_database(":memory:").unwrap(),
+        ));
+        let responses = drive(
+            &mut session,
+            &[
+                initialize(),
+                json!({"jsonrpc": "2.0", "id": 2, "method": "prompts/list"}),
+                json!({"jsonrpc": "2.0", "id": 3, "method": "prompts/get", "params": {"name": "summarize_chapter", "arguments": {"chapterId": "missing"}}}),
+            ],
+        );
+
+        assert!(responses[0]["result"]["capabilities"]["prompts"].is_object());
+        assert_eq!(responses[1]["result"]["prompts"].as_array().unwrap().len(), 3);
+        assert_eq!(responses[2]["error"]["code"], NOT_FOUND);
+    }
+
+    #[test]
+    fn test_subscribed_resources_get_update_notifications() {
+        let stories = tempdir().unwrap();
+        let conn = crate::db::initialize_database(":memory:").unwrap();
+        let project = crate::tools::create_story_project(&conn, stories.path(), json!({"title": "Subs", "targetLength": "novel"})).unwrap();
+        let mira = crate::tools::add_character(
+            &conn,
+            json!({"projectId": project["projectId"], "name": "Mira", "role": "protagonist"}),
+        )
+        .unwrap();
+        let dax = crate::tools::add_character(
+            &conn,
+            json!({"projectId": project["projectId"], "name": "Dax", "role": "supporting"}),
+        )
+        .unwrap();
+        let mira_uri = format!("story://character/{}", mira["characterId"].as_str().unwrap());
+
+        let registry = ToolRegistry::new(conn);
+        registry.register("update", "Update character", json!({"type": "object"}), crate::tools::update_character);
+        registry.track_changes("update", resources::character_changes);
+        let mut session = McpSession::new(registry);
+
+        let update = |id: i32, character: &Value| {
+            json!({"jsonrpc": "2.0", "id": id, "method": "tools/call", "params": {"name": "update", "arguments": {"characterId": character["characterId"], "currentState": "Asleep"}}})
+        };
+        let responses = drive(
+            &mut session,
+            &[
+                initialize(),
+                initialized(),
+                json!({"jsonrpc": "2.0", "id": 2, "method": "resources/subscribe", "params": {"uri": mira_uri}}),
+                update(3, &dax),
+                update(4, &mira),
+                json!({"jsonrpc": "2.0", "id": 5, "method": "resources/unsubscribe", "params": {"uri": mira_uri}}),
+                update(6, &mira),
+            ],
+        );
+
+        assert_eq!(responses[0]["result"]["capabilities"]["resources"]["subscribe"], true);
+        let notifications: Vec<&Value> = responses.iter().filter(|r| r.get("id").is_none()).collect();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0]["method"], "notifications/resources/updated");
+        assert_eq!(notifications[0]["params"]["uri"], mira_uri.as_str());
+
+        // The notification follows the response to the call that caused it
+        let position = responses.iter().position(|r| r.get("id").is_none()).unwrap();
+        assert_eq!(responses[position - 1]["id"], 4);
+    }
+
+    #[test]
+    fn test_subscribe_checks_resource_outside_dispatch() {
+        let stories = tempdir().unwrap();
+        let conn = crate::db::initialize_database(":memory:").unwrap();
+        let project = crate::tools::create_story_project(&conn, stories.path(), json!({"title": "Subs", "targetLength": "novel"})).unwrap();
+        let uri = format!("story://project/{}", project["projectId"].as_str().unwrap());
+        let mut session = McpSession::new(ToolRegistry::new(conn));
+        drive(&mut session, &[initialize(), initialized()]);
+        let subscribe = |id: i32, uri: &str| {
+            request(json!({"jsonrpc": "2.0", "id": id, "method": "resources/subscribe", "params": {"uri": uri}}))
+        };
+
+        let Dispatch::Pending(pending) = session.dispatch(subscribe(2, "story://scene/x")) else {
+            panic!("subscriptions are checked as deferred work")
+        };
+        assert!(!pending.is_read_only());
+        assert!(session.subscriptions().is_empty());
+        assert!(session.complete(pending.run()).unwrap().error.is_some());
+        assert!(session.subscriptions().is_empty());
+
+        // A cancelled subscription never starts
+        let Dispatch::Pending(pending) = session.dispatch(subscribe(3, &uri)) else { panic!("deferred") };
+        session.dispatch(request(json!({"jsonrpc": "2.0", "method": "notifications/cancelled", "params": {"requestId": 3}})));
+        assert!(session.complete(pending.run()).is_none());
+        assert!(session.subscriptions().is_empty());
+
+        let Dispatch::Pending(pending) = session.dispatch(subscribe(4, &uri)) else { panic!("deferred") };
+        assert!(session.complete(pending.run()).unwrap().error.is_none());
+        assert!(session.subscriptions().contains(&uri));
+    }
+
+    #[tokio::test]
+    async fn test_idle_subscriber_notified_of_changes_made_elsewhere() {
+        let stories = tempdir().unwrap();
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let conn = crate::db::initialize_database(":memory:").unwrap();
+        let project = crate::tools::create_story_project(&conn, stories.path(), json!({"title": "Idle", "targetLength": "novel"})).unwrap();
+        let mira = crate::tools::add_character(
+            &conn,
+            json!({"projectId": project["projectId"], "name": "Mira", "role": "protagonist"}),
+        )
+        .unwrap();
+        let mira_uri = format!("story://character/{}", mira["characterId"].as_str().unwrap());
+
+        let registry = Arc::new(ToolRegistry::new(conn));
+        registry.register("update", "Update character", json!({"type": "object"}), crate::tools::update_character);
+        registry.track_changes("update", resources::character_changes);
+
+        let (client, server) = tokio::io::duplex(4096);
+        let (server_reader, server_writer) = tokio::io::split(server);
+        let mut session = McpSession::new(registry.clone());
+        let serving = tokio::spawn(async move { session.serve_async(BufReader::new(server_reader), server_writer).await });
+
+        let (client_reader, mut client_writer) = tokio::io::split(client);
+        let mut lines = BufReader::new(client_reader).lines();
+        for message in [
+            initialize(),
+            initialized(),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "resources/subscribe", "params": {"uri": mira_uri}}),
+        ] {
+            client_writer.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+        }
+        for id in [1, 2] {
+            let response: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+            assert_eq!(response["id"], id);
+        }
+
+        // Another session changes the character while this client sends nothing
+        registry
+            .call_tool("update", json!({"characterId": mira["characterId"], "currentState": "Asleep"}))
+            .unwrap();
+        let line = tokio::time::timeout(std::time::Duration::from_secs(5), lines.next_line()).await;
+        let notification: Value = serde_json::from_str(&line.expect("notification sent while idle").unwrap().unwrap()).unwrap();
+        assert_eq!(notification["method"], "notifications/resources/updated");
+        assert_eq!(notification["params"]["uri"], mira_uri.as_str());
+
+        client_writer.shutdown().await.unwrap();
+        serving.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_subscribe_to_unknown_resource_fails() {
+        let mut session = McpSession::new(ToolRegistry::new(
+            crate::db::initialize_database(":memory:").unwrap(),
+        ));
+        let missing = format!("story://scene/{}", uuid::Uuid::new_v4());
+        let responses = drive(
+            &mut session,
+            &[
+                initialize(),
+                json!({"jsonrpc": "2.0", "id": 2, "method": "resources/subscribe", "params": {"uri": missing}}),
+            ],
+        );
+
+        assert_eq!(responses[1]["error"]["code"], NOT_FOUND);
+        assert!(session.subscriptions().is_empty());
+    }
+
+    #[test]
+    fn test_tool_list_changes_are_announced() {
+        let mut session = session();
+        drive(&mut session, &[initialize(), initialized()]);
+        assert!(session.take_notifications().is_empty());
+
+        session.registry().register("late", "Registered mid-session", json!({"type": "object"}), |_conn, p| Ok(p));
+        let responses = drive(&mut session, &[json!({"jsonrpc": "2.0", "id": 2, "method": "ping"})]);
+
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[1]["method"], "notifications/tools/list_changed");
+        assert!(responses[1].get("params").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_idle_client_told_of_tool_list_changes() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let registry = Arc::new(ToolRegistry::new(Connection::open_in_memory().unwrap()));
+        let (client, server) = tokio::io::duplex(4096);
+        let (server_reader, server_writer) = tokio::io::split(server);
+        let mut session = McpSession::new(registry.clone());
+        let serving = tokio::spawn(async move { session.serve_async(BufReader::new(server_reader), server_writer).await });
+
+        let (client_reader, mut client_writer) = tokio::io::split(client);
+        let mut lines = BufReader::new(client_reader).lines();
+        for message in [initialize(), initialized()] {
+            client_writer.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+        }
+        assert_eq!(serde_json::from_str::<Value>(&lines.next_line().await.unwrap().unwrap()).unwrap()["id"], 1);
+
+        registry.register("late", "Registered mid-session", json!({"type": "object"}), |_conn, p| Ok(p));
+        let line = tokio::time::timeout(std::time::Duration::from_secs(5), lines.next_line()).await;
+        let notification: Value = serde_json::from_str(&line.expect("notification sent while idle").unwrap().unwrap()).unwrap();
+        assert_eq!(notification["method"], "notifications/tools/list_changed");
+
+        client_writer.shutdown().await.unwrap();
+        serving.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_malformed_lines_get_error_responses() {
+        let mut session = session();
+        let input = "{not json\n{\"jsonrpc\":\"1.0\",\"id\":2,\"method\":\"ping\"}\n{\"jsonrpc\":\"2.0\",\"id\":3}\n";
+        let mut output = Vec::new();
+        session.serve(Cursor::new(input), &mut output).unwrap();
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["id"], Value::Null);
+        assert_eq!(responses[0]["error"]["code"], PARSE_ERROR);
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(responses[1]["error"]["code"], INVALID_REQUEST);
+        assert_eq!(responses[2]["id"], 3);
+        assert_eq!(responses[2]["error"]["code"], INVALID_REQUEST);
+    }
+
+    #[test]
+    fn test_batch_request_gets_batch_response() {
+        let mut session = session();
+        let responses = drive(
+            &mut session,
+            &[
+                initialize(),
+                json!([
+                    initialized(),
+                    {"jsonrpc": "2.0", "id": "a", "method": "ping"},
+                    {"jsonrpc": "2.0", "id": "b", "method": "tools/call", "params": {"name": "echo", "arguments": {"n": 1}}},
+                    {"jsonrpc": "2.0", "id": "c"}
+                ]),
+                json!([initialized()]),
+            ],
+        );
+
+        // The notification-only batch gets nothing back
+        assert_eq!(responses.len(), 2);
+        let batch = responses[1].as_array().unwrap();
+        let ids: Vec<&Value> = batch.iter().map(|r| &r["id"]).collect();
+        assert_eq!(ids, vec![&json!("a"), &json!("b"), &json!("c")]);
+        assert_eq!(batch[1]["result"]["content"][0]["text"], "{\"n\":1}");
+        assert_eq!(batch[2]["error"]["code"], INVALID_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_reads_overlap_but_writes_keep_their_place() {
+        use std::sync::Mutex;
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let registry = ToolRegistry::new(Connection::open_in_memory().unwrap());
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let released = Mutex::new(released);
+
+        let read_log = log.clone();
+        registry.register_read_only("slow_read", "Blocks until released", json!({"type": "object"}), move |_conn, _| {
+            released.lock().unwrap().recv().unwrap();
+            read_log.lock().unwrap().push("read");
+            Ok(json!({}))
+        });
+        let write_log = log.clone();
+        registry.register("write", "Records a write", json!({"type": "object"}), move |_conn, _| {
+            write_log.lock().unwrap().push("write");
+            Ok(json!({}))
+        });
+
+        let (client, server) = tokio::io::duplex(4096);
+        let (server_reader, server_writer) = tokio::io::split(server);
+        let mut session = McpSession::new(registry);
+        let serving = tokio::spawn(async move { session.serve_async(BufReader::new(server_reader), server_writer).await });
+
+        let (client_reader, mut client_writer) = tokio::io::split(client);
+        let mut lines = BufReader::new(client_reader).lines();
+        async fn send(writer: &mut (impl AsyncWrite + Unpin), message: Value) {
+            writer.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+        }
+        let call = |id: i32, name: &str| {
+            json!({"jsonrpc": "2.0", "id": id, "method": "tools/call", "params": {"name": name, "arguments": {}}})
+        };
+
+        send(&mut client_writer, initialize()).await;
+        send(&mut client_writer, initialized()).await;
+        assert_eq!(serde_json::from_str::<Value>(&lines.next_line().await.unwrap().unwrap()).unwrap()["id"], 1);
+
+        // tools/list is answered while the slow read is still running
+        send(&mut client_writer, call(2, "slow_read")).await;
+        send(&mut client_writer, json!({"jsonrpc": "2.0", "id": 3, "method": "tools/list"})).await;
+        let next: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(next["id"], 3);
+
+        // The write sent after the read waits for it
+        send(&mut client_writer, call(4, "write")).await;
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(log.lock().unwrap().is_empty());
+        release.send(()).unwrap();
+
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let response: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+            ids.push(response["id"].clone());
+        }
+        assert_eq!(ids, vec![json!(2), json!(4)]);
+        assert_eq!(*log.lock().unwrap(), vec!["read", "write"]);
+
+        client_writer.shutdown().await.unwrap();
+        serving.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_unsupported_protocol_version_gets_latest() {
+        let mut session = session();
+        let responses = drive(
+            &mut session,
+            &[json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"protocolVersion": "1999-01-01"}})],
+        );
+
+        assert_eq!(responses[0]["result"]["protocolVersion"], SUPPORTED_PROTOCOL_VERSIONS[0]);
+        assert_eq!(session.protocol_version(), Some(SUPPORTED_PROTOCOL_VERSIONS[0]));
+    }
+}
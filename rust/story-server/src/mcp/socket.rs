@@ -0,0 +1,117 @@
+//! Newline-delimited JSON-RPC over TCP and Unix domain sockets.
+//!
+//! Every accepted connection gets its own [`McpSession`] over the shared
+//! [`ToolRegistry`], served by the async protocol handler.
+
+use super::registry::ToolRegistry;
+use super::session::McpSession;
+use anyhow::Result;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite, BufReader};
+use tokio::net::{TcpListener, ToSocketAddrs};
+
+/// Accept MCP clients on a TCP port until the process is stopped
+pub async fn serve_tcp(registry: Arc<ToolRegistry>, addr: impl ToSocketAddrs) -> Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    log::info!("Story Server ready - listening on tcp://{}", listener.local_addr()?);
+    accept_tcp(listener, registry).await
+}
+
+async fn accept_tcp(listener: TcpListener, registry: Arc<ToolRegistry>) -> Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        log::info!("Accepted MCP connection from {}", peer);
+        let (reader, writer) = stream.into_split();
+        tokio::spawn(run_session(registry.clone(), reader, writer, peer.to_string()));
+    }
+}
+
+/// Accept MCP clients on a Unix domain socket until the process is stopped
+#[cfg(unix)]
+pub async fn serve_unix(registry: Arc<ToolRegistry>, path: impl AsRef<std::path::Path>) -> Result<()> {
+    let path = path.as_ref();
+    let listener = bind_unix(path)?;
+    log::info!("Story Server ready - listening on unix://{}", path.display());
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        log::info!("Accepted MCP connection on {}", path.display());
+        let (reader, writer) = stream.into_split();
+        tokio::spawn(run_session(registry.clone(), reader, writer, path.display().to_string()));
+    }
+}
+
+/// Bind a Unix socket at `path`, replacing a socket file left by a previous run.
+/// Anything else already at the path is left alone and reported as an error.
+#[cfg(unix)]
+fn bind_unix(path: &std::path::Path) -> Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)?,
+        Ok(_) => anyhow::bail!("Address in use: {} exists and is not a socket", path.display()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(tokio::net::UnixListener::bind(path)?)
+}
+
+async fn run_session<R, W>(registry: Arc<ToolRegistry>, reader: R, writer: W, peer: String)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin,
+{
+    let mut session = McpSession::new(registry);
+    match session.serve_async(BufReader::new(reader), writer).await {
+        Ok(()) => log::info!("MCP connection from {} closed", peer),
+        Err(e) => log::warn!("MCP connection from {} failed: {}", peer, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+    use serde_json::{json, Value};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    #[tokio::test]
+    async fn test_each_tcp_connection_gets_its_own_session() {
+        let registry = Arc::new(ToolRegistry::new(Connection::open_in_memory().unwrap()));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(accept_tcp(listener, registry));
+
+        for _ in 0..2 {
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+
+            // A fresh session accepts initialize on every connection
+            let initialize = json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}});
+            writer.write_all(format!("{}\n", initialize).as_bytes()).await.unwrap();
+            let response: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+            assert_eq!(response["id"], 1);
+            assert!(response["result"]["protocolVersion"].is_string());
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_bind_only_replaces_sockets() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // A socket left behind by an earlier run is replaced
+        let stale = dir.path().join("story.sock");
+        drop(std::os::unix::net::UnixListener::bind(&stale).unwrap());
+        assert!(bind_unix(&stale).is_ok());
+
+        // A regular file at the bind path is never deleted
+        let file = dir.path().join("notes.txt");
+        std::fs::write(&file, "chapter outline").unwrap();
+        let err = bind_unix(&file).unwrap_err();
+        assert!(err.to_string().contains("not a socket"));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "chapter outline");
+    }
+}
@@ -62,6 +62,34 @@ impl Response {
             }),
         }
     }
+
+    pub fn error_with_data(id: Option<Value>, code: i32, message: String, data: Option<Value>) -> Self {
+        Response {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: None,
+            error: Some(ErrorObject { code, message, data }),
+        }
+    }
+}
+
+/// Server-initiated JSON-RPC notification (no `id`, no response expected)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+}
+
+impl Notification {
+    pub fn new(method: &str, params: Option<Value>) -> Self {
+        Notification {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+        }
+    }
 }
 
 // MCP Error codes
@@ -71,6 +99,15 @@ pub const METHOD_NOT_FOUND: i32 = -32601;
 pub const INVALID_PARAMS: i32 = -32602;
 pub const INTERNAL_ERROR: i32 = -32603;
 
+// Server-defined error codes for domain failures (JSON-RPC reserves -32000 to -32099)
+pub const NOT_FOUND: i32 = -32001;
+pub const VALIDATION_FAILED: i32 = -32002;
+pub const DUPLICATE_ENTRY: i32 = -32003;
+pub const INVALID_STATE: i32 = -32004;
+
+// Request arrived before the initialize handshake
+pub const SERVER_NOT_INITIALIZED: i32 = -32000;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -118,3 +155,44 @@ pub struct Tool {
     #[serde(rename = "inputSchema")]
     pub input_schema: Value,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    pub uri: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub mime_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceTemplate {
+    pub uri_template: String,
+    pub name: String,
+    pub description: String,
+    pub mime_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceContents {
+    pub uri: String,
+    pub mime_type: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Prompt {
+    pub name: String,
+    pub description: String,
+    pub arguments: Vec<PromptArgument>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptArgument {
+    pub name: String,
+    pub description: String,
+    pub required: bool,
+}
@@ -0,0 +1,137 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What kind of continuity problem an alert reports
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertType {
+    WorldRuleViolation,
+    CharacterStateConflict,
+    TimelineContradiction,
+    FactualInconsistency,
+}
+
+impl fmt::Display for AlertType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            AlertType::WorldRuleViolation => "world_rule_violation",
+            AlertType::CharacterStateConflict => "character_state_conflict",
+            AlertType::TimelineContradiction => "timeline_contradiction",
+            AlertType::FactualInconsistency => "factual_inconsistency",
+        };
+        f.write_str(s)
+    }
+}
+
+impl AlertType {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "world_rule_violation" => Some(AlertType::WorldRuleViolation),
+            "character_state_conflict" => Some(AlertType::CharacterStateConflict),
+            "timeline_contradiction" => Some(AlertType::TimelineContradiction),
+            "factual_inconsistency" => Some(AlertType::FactualInconsistency),
+            _ => None,
+        }
+    }
+}
+
+/// How likely an alert is to be a real problem
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertSeverity {
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for AlertSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            AlertSeverity::Low => "low",
+            AlertSeverity::Medium => "medium",
+            AlertSeverity::High => "high",
+        };
+        f.write_str(s)
+    }
+}
+
+impl AlertSeverity {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "low" => Some(AlertSeverity::Low),
+            "medium" => Some(AlertSeverity::Medium),
+            "high" => Some(AlertSeverity::High),
+            _ => None,
+        }
+    }
+}
+
+/// The author's verdict on an alert; anything but `Pending` resolves it.
+/// `RevisedContent`: the scene was rewritten to remove the problem;
+/// `UpdatedFact`: the recorded fact was wrong and has been corrected;
+/// `Dismissed`: not a problem, or an intended exception.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthorDecision {
+    Pending,
+    RevisedContent,
+    UpdatedFact,
+    Dismissed,
+}
+
+impl fmt::Display for AuthorDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            AuthorDecision::Pending => "pending",
+            AuthorDecision::RevisedContent => "revised_content",
+            AuthorDecision::UpdatedFact => "updated_fact",
+            AuthorDecision::Dismissed => "dismissed",
+        };
+        f.write_str(s)
+    }
+}
+
+impl AuthorDecision {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(AuthorDecision::Pending),
+            "revised_content" => Some(AuthorDecision::RevisedContent),
+            "updated_fact" => Some(AuthorDecision::UpdatedFact),
+            "dismissed" => Some(AuthorDecision::Dismissed),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_enums_round_trip() {
+        for alert_type in [
+            AlertType::WorldRuleViolation,
+            AlertType::CharacterStateConflict,
+            AlertType::TimelineContradiction,
+            AlertType::FactualInconsistency,
+        ] {
+            assert_eq!(serde_json::to_string(&alert_type).unwrap(), format!("\"{}\"", alert_type));
+            assert_eq!(AlertType::from_str(&alert_type.to_string()), Some(alert_type));
+        }
+        assert_eq!(AlertSeverity::from_str("high"), Some(AlertSeverity::High));
+        assert!(AlertSeverity::High > AlertSeverity::Low);
+        for decision in [
+            AuthorDecision::Pending,
+            AuthorDecision::RevisedContent,
+            AuthorDecision::UpdatedFact,
+            AuthorDecision::Dismissed,
+        ] {
+            assert_eq!(serde_json::to_string(&decision).unwrap(), format!("\"{}\"", decision));
+            assert_eq!(AuthorDecision::from_str(&decision.to_string()), Some(decision));
+        }
+    }
+}
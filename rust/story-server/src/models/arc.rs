@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterArc {
+    pub id: Uuid,
+    pub story_project_id: Uuid,
+    pub character_id: Uuid,
+    pub arc_name: String,
+    pub start_state: String,
+    pub end_state: String,
+    /// Percentage of milestones completed
+    pub current_progress: i32,
+    pub status: ArcStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArcMilestone {
+    pub id: Uuid,
+    pub character_arc_id: Uuid,
+    pub position: i32,
+    pub description: String,
+    /// Number of the chapter the milestone should happen by
+    pub target_chapter: Option<i32>,
+    pub completed: bool,
+    pub completed_at_scene_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Where a character arc stands
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ArcStatus {
+    Planned,
+    InProgress,
+    Complete,
+    Abandoned,
+}
+
+impl fmt::Display for ArcStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ArcStatus::Planned => "planned",
+            ArcStatus::InProgress => "in_progress",
+            ArcStatus::Complete => "complete",
+            ArcStatus::Abandoned => "abandoned",
+        };
+        f.write_str(s)
+    }
+}
+
+impl ArcStatus {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "planned" => Some(ArcStatus::Planned),
+            "in_progress" => Some(ArcStatus::InProgress),
+            "complete" => Some(ArcStatus::Complete),
+            "abandoned" => Some(ArcStatus::Abandoned),
+            _ => None,
+        }
+    }
+
+    /// The status implied by a new progress figure. Abandoned arcs stay
+    /// abandoned, and an arc with nothing done keeps its status unless it had been complete.
+    pub fn after_progress(self, progress: i32) -> Self {
+        match self {
+            ArcStatus::Abandoned => ArcStatus::Abandoned,
+            _ if progress >= 100 => ArcStatus::Complete,
+            ArcStatus::Complete => ArcStatus::InProgress,
+            _ if progress > 0 => ArcStatus::InProgress,
+            status => status,
+        }
+    }
+}
+
+/// Percentage of milestones completed, rounded down so 100 means all of them
+pub fn arc_progress(completed: usize, total: usize) -> i32 {
+    (completed * 100).checked_div(total).unwrap_or(0) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arc_status_round_trip() {
+        for status in [ArcStatus::Planned, ArcStatus::InProgress, ArcStatus::Complete, ArcStatus::Abandoned] {
+            assert_eq!(serde_json::to_string(&status).unwrap(), format!("\"{}\"", status));
+            assert_eq!(ArcStatus::from_str(&status.to_string()), Some(status));
+        }
+    }
+
+    #[test]
+    fn test_progress_and_status() {
+        assert_eq!(arc_progress(0, 0), 0);
+        assert_eq!(arc_progress(2, 3), 66);
+        assert_eq!(arc_progress(3, 3), 100);
+
+        assert_eq!(ArcStatus::Planned.after_progress(0), ArcStatus::Planned);
+        assert_eq!(ArcStatus::Planned.after_progress(33), ArcStatus::InProgress);
+        assert_eq!(ArcStatus::InProgress.after_progress(100), ArcStatus::Complete);
+        assert_eq!(ArcStatus::Complete.after_progress(50), ArcStatus::InProgress);
+        assert_eq!(ArcStatus::Abandoned.after_progress(100), ArcStatus::Abandoned);
+    }
+}
@@ -1,5 +1,7 @@
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,7 +19,8 @@ pub struct Character {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Narrative role a character plays in the story
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum CharacterRole {
     Protagonist,
@@ -26,18 +29,20 @@ pub enum CharacterRole {
     Minor,
 }
 
-impl ToString for CharacterRole {
-    fn to_string(&self) -> String {
-        match self {
-            CharacterRole::Protagonist => "protagonist".to_string(),
-            CharacterRole::Antagonist => "antagonist".to_string(),
-            CharacterRole::Supporting => "supporting".to_string(),
-            CharacterRole::Minor => "minor".to_string(),
-        }
+impl fmt::Display for CharacterRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            CharacterRole::Protagonist => "protagonist",
+            CharacterRole::Antagonist => "antagonist",
+            CharacterRole::Supporting => "supporting",
+            CharacterRole::Minor => "minor",
+        };
+        f.write_str(s)
     }
 }
 
 impl CharacterRole {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "protagonist" => Some(CharacterRole::Protagonist),
@@ -61,7 +66,8 @@ pub struct CharacterRelationship {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Kind of relationship between two characters
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum RelationshipType {
     Ally,
@@ -74,22 +80,24 @@ pub enum RelationshipType {
     Unknown,
 }
 
-impl ToString for RelationshipType {
-    fn to_string(&self) -> String {
-        match self {
-            RelationshipType::Ally => "ally".to_string(),
-            RelationshipType::Enemy => "enemy".to_string(),
-            RelationshipType::Family => "family".to_string(),
-            RelationshipType::Romantic => "romantic".to_string(),
-            RelationshipType::Mentor => "mentor".to_string(),
-            RelationshipType::Rival => "rival".to_string(),
-            RelationshipType::Neutral => "neutral".to_string(),
-            RelationshipType::Unknown => "unknown".to_string(),
-        }
+impl fmt::Display for RelationshipType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            RelationshipType::Ally => "ally",
+            RelationshipType::Enemy => "enemy",
+            RelationshipType::Family => "family",
+            RelationshipType::Romantic => "romantic",
+            RelationshipType::Mentor => "mentor",
+            RelationshipType::Rival => "rival",
+            RelationshipType::Neutral => "neutral",
+            RelationshipType::Unknown => "unknown",
+        };
+        f.write_str(s)
     }
 }
 
 impl RelationshipType {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "ally" => Some(RelationshipType::Ally),
@@ -105,6 +113,77 @@ impl RelationshipType {
     }
 }
 
+/// Where a character stands at one point in the story, stored as JSON in
+/// `character_state_history`. A snapshot only needs the fields that changed:
+/// omitted fields carry over from earlier snapshots. A list replaces the
+/// earlier list as a whole.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CharacterState {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// e.g. "Unhurt", "Broken arm", "Dead"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<String>,
+    /// What they carry or own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub possessions: Option<Vec<String>>,
+    /// What they know, including secrets learned so far
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub knowledge: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relationships: Option<Vec<RelationshipState>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+/// How a character stands with someone else at a point in the story
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RelationshipState {
+    /// Name of the other character
+    pub character: String,
+    /// e.g. "Trusts her", "Owes him a debt"
+    pub standing: String,
+}
+
+impl CharacterState {
+    /// Names of the fields this snapshot sets
+    pub fn fields(&self) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        if self.location.is_some() {
+            fields.push("location");
+        }
+        if self.health.is_some() {
+            fields.push("health");
+        }
+        if self.possessions.is_some() {
+            fields.push("possessions");
+        }
+        if self.knowledge.is_some() {
+            fields.push("knowledge");
+        }
+        if self.relationships.is_some() {
+            fields.push("relationships");
+        }
+        if self.notes.is_some() {
+            fields.push("notes");
+        }
+        fields
+    }
+
+    /// Overlay a later snapshot: the fields it sets replace ours
+    pub fn apply(&mut self, later: &CharacterState) {
+        let later = later.clone();
+        self.location = later.location.or(self.location.take());
+        self.health = later.health.or(self.health.take());
+        self.possessions = later.possessions.or(self.possessions.take());
+        self.knowledge = later.knowledge.or(self.knowledge.take());
+        self.relationships = later.relationships.or(self.relationships.take());
+        self.notes = later.notes.or(self.notes.take());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -122,4 +201,27 @@ mod tests {
         assert_eq!(RelationshipType::from_str("enemy"), Some(RelationshipType::Enemy));
         assert_eq!(RelationshipType::from_str("invalid"), None);
     }
+
+    #[test]
+    fn test_character_state_apply() {
+        let mut state: CharacterState =
+            serde_json::from_value(serde_json::json!({"location": "Keep", "possessions": ["Map", "Knife"]})).unwrap();
+        let later = CharacterState {
+            location: Some("Harbor".into()),
+            possessions: Some(vec!["Map".into()]),
+            health: Some("Bruised".into()),
+            ..Default::default()
+        };
+        state.apply(&later);
+
+        assert_eq!(state.location.as_deref(), Some("Harbor"));
+        assert_eq!(state.possessions, Some(vec!["Map".to_string()]));
+        assert_eq!(state.health.as_deref(), Some("Bruised"));
+        assert_eq!(later.fields(), vec!["location", "health", "possessions"]);
+        // Unset fields are left out of the stored JSON
+        assert_eq!(
+            serde_json::to_value(&later).unwrap(),
+            serde_json::json!({"location": "Harbor", "health": "Bruised", "possessions": ["Map"]})
+        );
+    }
 }
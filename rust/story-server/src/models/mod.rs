@@ -1,9 +1,17 @@
+pub mod alert;
+pub mod arc;
 pub mod character;
+pub mod progression;
 pub mod project;
 pub mod scene;
+pub mod summary;
 pub mod world_rule;
 
-pub use character::{Character, CharacterRelationship, CharacterRole, RelationshipType};
+pub use alert::{AlertSeverity, AlertType, AuthorDecision};
+pub use arc::{ArcMilestone, ArcStatus, CharacterArc};
+pub use character::{Character, CharacterRelationship, CharacterRole, CharacterState, RelationshipState, RelationshipType};
+pub use progression::{ProgressionSheet, ProgressionSystem, SystemType};
 pub use project::{ProjectLength, ProjectStatus, StoryProject};
 pub use scene::{PlotStructure, Scene, SceneStatus, StructureType};
+pub use summary::SummaryScope;
 pub use world_rule::{RuleScope, WorldRule};
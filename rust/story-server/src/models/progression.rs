@@ -0,0 +1,158 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgressionSystem {
+    pub id: Uuid,
+    pub story_project_id: Uuid,
+    pub system_name: String,
+    pub system_type: SystemType,
+    /// The system's template, as JSON in the shape its type expects
+    pub template_data: Option<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Kind of progression system: `game_stats` (LitRPG levels and attributes),
+/// `cultivation` (realms with stages), `magic_tiers` (ranked tiers of power),
+/// `skill_trees` (skills unlocked through prerequisites) or `custom`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemType {
+    GameStats,
+    Cultivation,
+    MagicTiers,
+    SkillTrees,
+    Custom,
+}
+
+impl fmt::Display for SystemType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SystemType::GameStats => "game_stats",
+            SystemType::Cultivation => "cultivation",
+            SystemType::MagicTiers => "magic_tiers",
+            SystemType::SkillTrees => "skill_trees",
+            SystemType::Custom => "custom",
+        };
+        f.write_str(s)
+    }
+}
+
+impl SystemType {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "game_stats" => Some(SystemType::GameStats),
+            "cultivation" => Some(SystemType::Cultivation),
+            "magic_tiers" => Some(SystemType::MagicTiers),
+            "skill_trees" => Some(SystemType::SkillTrees),
+            "custom" => Some(SystemType::Custom),
+            _ => None,
+        }
+    }
+}
+
+/// A character's standing under a progression system at one point in the
+/// story, stored as JSON in `progression_sheets`. Like `CharacterState`, a
+/// snapshot only needs what changed: omitted fields carry over, stats are
+/// merged one by one, and a skill list replaces the earlier list.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ProgressionSheet {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub experience: Option<i64>,
+    /// Attribute values by name, e.g. {"STR": 12}
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub stats: BTreeMap<String, i64>,
+    /// Cultivation realm
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realm: Option<String>,
+    /// Stage within the realm, starting at 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stage: Option<i64>,
+    /// Magic tier
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tier: Option<String>,
+    /// Every skill unlocked so far
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skills: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl ProgressionSheet {
+    /// Names of the fields this snapshot sets, with stats as `stats.<name>`
+    pub fn fields(&self) -> Vec<String> {
+        let mut fields = Vec::new();
+        let mut push = |set: bool, name: &str| {
+            if set {
+                fields.push(name.to_string());
+            }
+        };
+        push(self.level.is_some(), "level");
+        push(self.experience.is_some(), "experience");
+        push(self.realm.is_some(), "realm");
+        push(self.stage.is_some(), "stage");
+        push(self.tier.is_some(), "tier");
+        push(self.skills.is_some(), "skills");
+        push(self.notes.is_some(), "notes");
+        fields.extend(self.stats.keys().map(|name| format!("stats.{}", name)));
+        fields
+    }
+
+    /// Overlay a later snapshot: the fields and stats it sets replace ours
+    pub fn apply(&mut self, later: &ProgressionSheet) {
+        let later = later.clone();
+        self.level = later.level.or(self.level.take());
+        self.experience = later.experience.or(self.experience.take());
+        self.realm = later.realm.or(self.realm.take());
+        self.stage = later.stage.or(self.stage.take());
+        self.tier = later.tier.or(self.tier.take());
+        self.skills = later.skills.or(self.skills.take());
+        self.notes = later.notes.or(self.notes.take());
+        self.stats.extend(later.stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_type_round_trip() {
+        for system_type in [
+            SystemType::GameStats,
+            SystemType::Cultivation,
+            SystemType::MagicTiers,
+            SystemType::SkillTrees,
+            SystemType::Custom,
+        ] {
+            assert_eq!(serde_json::to_string(&system_type).unwrap(), format!("\"{}\"", system_type));
+            assert_eq!(SystemType::from_str(&system_type.to_string()), Some(system_type));
+        }
+    }
+
+    #[test]
+    fn test_sheet_apply_merges_stats() {
+        let mut sheet: ProgressionSheet =
+            serde_json::from_value(serde_json::json!({"level": 3, "stats": {"STR": 10, "AGI": 8}, "skills": ["Slash"]}))
+                .unwrap();
+        let later: ProgressionSheet =
+            serde_json::from_value(serde_json::json!({"level": 4, "stats": {"STR": 12}})).unwrap();
+        assert_eq!(later.fields(), vec!["level", "stats.STR"]);
+
+        sheet.apply(&later);
+        assert_eq!(sheet.level, Some(4));
+        assert_eq!(sheet.stats["STR"], 12);
+        assert_eq!(sheet.stats["AGI"], 8);
+        assert_eq!(sheet.skills, Some(vec!["Slash".to_string()]));
+    }
+}
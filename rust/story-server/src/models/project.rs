@@ -1,5 +1,7 @@
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,7 +18,8 @@ pub struct StoryProject {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Intended length of a story project
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ProjectLength {
     ShortStory,
@@ -25,18 +28,20 @@ pub enum ProjectLength {
     Series,
 }
 
-impl ToString for ProjectLength {
-    fn to_string(&self) -> String {
-        match self {
-            ProjectLength::ShortStory => "short_story".to_string(),
-            ProjectLength::Novella => "novella".to_string(),
-            ProjectLength::Novel => "novel".to_string(),
-            ProjectLength::Series => "series".to_string(),
-        }
+impl fmt::Display for ProjectLength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ProjectLength::ShortStory => "short_story",
+            ProjectLength::Novella => "novella",
+            ProjectLength::Novel => "novel",
+            ProjectLength::Series => "series",
+        };
+        f.write_str(s)
     }
 }
 
 impl ProjectLength {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "short_story" => Some(ProjectLength::ShortStory),
@@ -48,7 +53,7 @@ impl ProjectLength {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ProjectStatus {
     Draft,
@@ -57,18 +62,20 @@ pub enum ProjectStatus {
     Archived,
 }
 
-impl ToString for ProjectStatus {
-    fn to_string(&self) -> String {
-        match self {
-            ProjectStatus::Draft => "draft".to_string(),
-            ProjectStatus::InProgress => "in_progress".to_string(),
-            ProjectStatus::Complete => "complete".to_string(),
-            ProjectStatus::Archived => "archived".to_string(),
-        }
+impl fmt::Display for ProjectStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ProjectStatus::Draft => "draft",
+            ProjectStatus::InProgress => "in_progress",
+            ProjectStatus::Complete => "complete",
+            ProjectStatus::Archived => "archived",
+        };
+        f.write_str(s)
     }
 }
 
 impl ProjectStatus {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "draft" => Some(ProjectStatus::Draft),
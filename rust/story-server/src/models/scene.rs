@@ -1,5 +1,7 @@
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,7 +22,7 @@ pub struct Scene {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum SceneStatus {
     Planned,
@@ -29,18 +31,20 @@ pub enum SceneStatus {
     NeedsRevision,
 }
 
-impl ToString for SceneStatus {
-    fn to_string(&self) -> String {
-        match self {
-            SceneStatus::Planned => "planned".to_string(),
-            SceneStatus::Draft => "draft".to_string(),
-            SceneStatus::Complete => "complete".to_string(),
-            SceneStatus::NeedsRevision => "needs_revision".to_string(),
-        }
+impl fmt::Display for SceneStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SceneStatus::Planned => "planned",
+            SceneStatus::Draft => "draft",
+            SceneStatus::Complete => "complete",
+            SceneStatus::NeedsRevision => "needs_revision",
+        };
+        f.write_str(s)
     }
 }
 
 impl SceneStatus {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "planned" => Some(SceneStatus::Planned),
@@ -61,7 +65,8 @@ pub struct PlotStructure {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Plot structure template used to seed acts
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum StructureType {
     ThreeAct,
@@ -70,18 +75,20 @@ pub enum StructureType {
     Custom,
 }
 
-impl ToString for StructureType {
-    fn to_string(&self) -> String {
-        match self {
-            StructureType::ThreeAct => "three_act".to_string(),
-            StructureType::FiveAct => "five_act".to_string(),
-            StructureType::HeroJourney => "hero_journey".to_string(),
-            StructureType::Custom => "custom".to_string(),
-        }
+impl fmt::Display for StructureType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            StructureType::ThreeAct => "three_act",
+            StructureType::FiveAct => "five_act",
+            StructureType::HeroJourney => "hero_journey",
+            StructureType::Custom => "custom",
+        };
+        f.write_str(s)
     }
 }
 
 impl StructureType {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "three_act" => Some(StructureType::ThreeAct),
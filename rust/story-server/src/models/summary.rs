@@ -0,0 +1,52 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The part of the story a summary covers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryScope {
+    Scene,
+    Chapter,
+    Act,
+    Overall,
+}
+
+impl fmt::Display for SummaryScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SummaryScope::Scene => "scene",
+            SummaryScope::Chapter => "chapter",
+            SummaryScope::Act => "act",
+            SummaryScope::Overall => "overall",
+        };
+        f.write_str(s)
+    }
+}
+
+impl SummaryScope {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "scene" => Some(SummaryScope::Scene),
+            "chapter" => Some(SummaryScope::Chapter),
+            "act" => Some(SummaryScope::Act),
+            "overall" => Some(SummaryScope::Overall),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_scope_round_trip() {
+        for scope in [SummaryScope::Scene, SummaryScope::Chapter, SummaryScope::Act, SummaryScope::Overall] {
+            let serialized = serde_json::to_string(&scope).unwrap();
+            assert_eq!(serialized, format!("\"{}\"", scope));
+            assert_eq!(SummaryScope::from_str(&scope.to_string()), Some(scope));
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,7 +17,8 @@ pub struct WorldRule {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// How broadly a world rule applies
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum RuleScope {
     Universal,
@@ -23,17 +26,19 @@ pub enum RuleScope {
     Situational,
 }
 
-impl ToString for RuleScope {
-    fn to_string(&self) -> String {
-        match self {
-            RuleScope::Universal => "universal".to_string(),
-            RuleScope::Regional => "regional".to_string(),
-            RuleScope::Situational => "situational".to_string(),
-        }
+impl fmt::Display for RuleScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            RuleScope::Universal => "universal",
+            RuleScope::Regional => "regional",
+            RuleScope::Situational => "situational",
+        };
+        f.write_str(s)
     }
 }
 
 impl RuleScope {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "universal" => Some(RuleScope::Universal),
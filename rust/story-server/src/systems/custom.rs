@@ -0,0 +1,323 @@
+//! Custom progression systems, declared in TOML or JSON:
+//!
+//! ```toml
+//! maxLevel = 50
+//! pointsPerLevel = 3
+//! formulas = ["max_hp = 10 * vitality + level * 5"]
+//!
+//! [[attributes]]
+//! name = "vitality"
+//! starting = 5
+//!
+//! [[tiers]]
+//! name = "E-Rank"
+//!
+//! [[tiers]]
+//! name = "D-Rank"
+//! requirements = ["level >= 10"]
+//! ```
+//!
+//! Formulas derive stats from attributes, `level`, `experience`, `tier` (the
+//! tier's place in the list, from 1) and earlier formulas. Requirements are
+//! conditions a character meets on reaching a tier.
+
+use super::expr::Expr;
+use super::templates::StatDefinition;
+use crate::error::{Result, StoryError};
+use crate::models::ProgressionSheet;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Syntax of a custom system definition
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DefinitionFormat {
+    Toml,
+    Json,
+}
+
+/// A rank in a custom system, and what it takes to reach it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CustomTier {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Conditions such as `level >= 10 && strength >= 20`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requirements: Vec<String>,
+}
+
+/// A system defined by the author: attributes, tiers in ascending order,
+/// formulas for derived stats and the rules for levelling up
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CustomTemplate {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<StatDefinition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tiers: Vec<CustomTier>,
+    /// `name = expression`, each able to use the ones before it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub formulas: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_level: Option<i64>,
+    #[serde(default)]
+    pub points_per_level: i64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub gains_per_level: BTreeMap<String, i64>,
+}
+
+/// Variables every formula and requirement can use besides the attributes
+const BUILT_INS: &[&str] = &["level", "experience", "tier"];
+
+/// Longest formula or requirement a template may hold, in characters
+const MAX_EXPRESSION_LENGTH: usize = 500;
+
+fn invalid(message: String) -> StoryError {
+    StoryError::validation(message)
+}
+
+impl CustomTemplate {
+    /// Parse a definition written in TOML or JSON, and validate it
+    pub fn parse(definition: &str, format: DefinitionFormat) -> Result<Self> {
+        let template: CustomTemplate = match format {
+            DefinitionFormat::Toml => {
+                toml::from_str(definition).map_err(|e| {
+                    invalid(format!("Invalid TOML definition: {}", e.message())).with_field("definition")
+                })?
+            }
+            DefinitionFormat::Json => serde_json::from_str(definition)
+                .map_err(|e| invalid(format!("Invalid JSON definition: {}", e)).with_field("definition"))?,
+        };
+        template.validate().map_err(|e| e.with_field("definition"))?;
+        Ok(template)
+    }
+
+    /// The formulas parsed, in order
+    fn parsed_formulas(&self) -> Result<Vec<(String, Expr)>> {
+        self.formulas
+            .iter()
+            .map(|formula| Expr::parse_assignment(formula).map_err(|e| invalid(format!("Formula '{}': {}", formula, e))))
+            .collect()
+    }
+
+    /// Check names are usable in expressions and every expression only uses names defined before it
+    pub fn validate(&self) -> Result<()> {
+        if self.attributes.is_empty() && self.tiers.is_empty() {
+            return Err(invalid("A custom system needs at least one attribute or tier".to_string()));
+        }
+        if self.max_level.is_some_and(|max| max < 1) {
+            return Err(invalid("maxLevel must be 1 or more".to_string()));
+        }
+        if self.points_per_level < 0 {
+            return Err(invalid("pointsPerLevel must not be negative".to_string()));
+        }
+
+        let mut known: Vec<String> = BUILT_INS.iter().map(|name| name.to_string()).collect();
+        for attribute in &self.attributes {
+            let name = &attribute.name;
+            if !is_identifier(name) {
+                return Err(invalid(format!(
+                    "Attribute '{}' must be a single word of letters, digits and underscores to be used in formulas",
+                    name
+                )));
+            }
+            if known.contains(name) {
+                return Err(invalid(format!("Attribute '{}' is defined twice or clashes with a built-in name", name)));
+            }
+            known.push(name.clone());
+        }
+        if let Some(name) = self.gains_per_level.keys().find(|name| !self.attributes.iter().any(|a| &a.name == *name)) {
+            return Err(invalid(format!("gainsPerLevel names unknown attribute '{}'", name)));
+        }
+
+        if let Some(i) = self.formulas.iter().position(|f| f.chars().count() > MAX_EXPRESSION_LENGTH) {
+            return Err(invalid(format!("Formula {} is longer than {} characters", i + 1, MAX_EXPRESSION_LENGTH)));
+        }
+        for (name, expr) in self.parsed_formulas()? {
+            if let Some(unknown) = expr.variables().into_iter().find(|v| !known.iter().any(|k| k == v)) {
+                return Err(invalid(format!("Formula for '{}' uses '{}', which is not defined before it", name, unknown)));
+            }
+            if known.contains(&name) {
+                return Err(invalid(format!("Formula '{}' reuses a name that is already defined", name)));
+            }
+            known.push(name);
+        }
+
+        let mut tiers: Vec<&str> = Vec::new();
+        for tier in &self.tiers {
+            if tier.name.trim().is_empty() || tiers.contains(&tier.name.as_str()) {
+                return Err(invalid(format!("Tier names must be present and unique ('{}')", tier.name)));
+            }
+            tiers.push(&tier.name);
+            for requirement in &tier.requirements {
+                if requirement.chars().count() > MAX_EXPRESSION_LENGTH {
+                    return Err(invalid(format!(
+                        "A requirement of tier {} is longer than {} characters",
+                        tier.name, MAX_EXPRESSION_LENGTH
+                    )));
+                }
+                let expr = Expr::parse(requirement)
+                    .map_err(|e| invalid(format!("Requirement '{}' of tier {}: {}", requirement, tier.name, e)))?;
+                if let Some(unknown) = expr.variables().into_iter().find(|v| !known.iter().any(|k| k == v)) {
+                    return Err(invalid(format!(
+                        "Requirement '{}' of tier {} uses unknown name '{}'",
+                        requirement, tier.name, unknown
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Attribute values, with starting values for those not recorded yet
+    fn attribute_values(&self, sheet: &ProgressionSheet) -> Vec<(String, f64)> {
+        self.attributes
+            .iter()
+            .map(|a| (a.name.clone(), sheet.stats.get(&a.name).copied().unwrap_or(a.starting) as f64))
+            .collect()
+    }
+
+    /// Every value formulas and requirements can use for a sheet: attributes, built-ins and derived stats
+    fn values(&self, sheet: &ProgressionSheet) -> Vec<(String, f64)> {
+        let tier = sheet
+            .tier
+            .as_ref()
+            .and_then(|name| self.tiers.iter().position(|t| &t.name == name))
+            .map_or(0.0, |i| (i + 1) as f64);
+        let mut values = self.attribute_values(sheet);
+        values.push(("level".to_string(), sheet.level.unwrap_or(1) as f64));
+        values.push(("experience".to_string(), sheet.experience.unwrap_or(0) as f64));
+        values.push(("tier".to_string(), tier));
+        // Formulas were checked when the template was stored; a failing one is left out
+        for (name, expr) in self.parsed_formulas().unwrap_or_default() {
+            let lookup = |v: &str| values.iter().find(|(n, _)| n == v).map(|(_, value)| *value);
+            match expr.eval(&lookup) {
+                Ok(value) => values.push((name, value)),
+                Err(e) => log::warn!("Formula for '{}' could not be evaluated: {}", name, e),
+            }
+        }
+        values
+    }
+
+    /// The derived stats of a sheet, in formula order
+    pub fn derived(&self, sheet: &ProgressionSheet) -> Vec<(String, f64)> {
+        let skip = self.attributes.len() + BUILT_INS.len();
+        self.values(sheet).into_iter().skip(skip).collect()
+    }
+
+    /// Requirements of a tier the sheet does not meet
+    pub fn unmet_requirements(&self, tier: &str, sheet: &ProgressionSheet) -> Vec<String> {
+        let Some(tier) = self.tiers.iter().find(|t| t.name == tier) else { return Vec::new() };
+        let values = self.values(sheet);
+        let lookup = |v: &str| values.iter().find(|(n, _)| n == v).map(|(_, value)| *value);
+        tier.requirements
+            .iter()
+            .filter(|requirement| {
+                Expr::parse(requirement)
+                    .and_then(|expr| expr.eval(&lookup))
+                    .map_or(true, |met| met == 0.0)
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_') && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// A number as written on a sheet: whole numbers without decimals, others to two places
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const HUNTER: &str = r#"
+maxLevel = 50
+pointsPerLevel = 3
+formulas = [
+    "max_hp = 10 * vitality + level * 5",
+    "max_mp = floor(max_hp / 4) + intellect",
+]
+
+[[attributes]]
+name = "vitality"
+starting = 5
+
+[[attributes]]
+name = "intellect"
+starting = 3
+
+[[tiers]]
+name = "E-Rank"
+
+[[tiers]]
+name = "D-Rank"
+requirements = ["level >= 10", "max_hp >= 150"]
+"#;
+
+    fn sheet(value: serde_json::Value) -> ProgressionSheet {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_parse_toml_and_json() {
+        let template = CustomTemplate::parse(HUNTER, DefinitionFormat::Toml).unwrap();
+        assert_eq!(template.attributes.len(), 2);
+        assert_eq!(template.tiers[1].requirements.len(), 2);
+
+        // The JSON form stored in template_data reads back the same
+        let json = serde_json::to_string(&template).unwrap();
+        assert_eq!(CustomTemplate::parse(&json, DefinitionFormat::Json).unwrap(), template);
+    }
+
+    #[test]
+    fn test_derived_stats_and_requirements() {
+        let template = CustomTemplate::parse(HUNTER, DefinitionFormat::Toml).unwrap();
+        let hunter = sheet(json!({"level": 3, "stats": {"vitality": 8}}));
+        assert_eq!(template.derived(&hunter), vec![("max_hp".to_string(), 95.0), ("max_mp".to_string(), 26.0)]);
+
+        let unmet = template.unmet_requirements("D-Rank", &hunter);
+        assert_eq!(unmet, vec!["level >= 10", "max_hp >= 150"]);
+        let ready = sheet(json!({"level": 10, "stats": {"vitality": 10}}));
+        assert!(template.unmet_requirements("D-Rank", &ready).is_empty());
+
+        assert_eq!(format_number(95.0), "95");
+        assert_eq!(format_number(2.5), "2.50");
+    }
+
+    #[test]
+    fn test_invalid_definitions() {
+        let invalid = |definition: &str| CustomTemplate::parse(definition, DefinitionFormat::Toml).is_err();
+        assert!(invalid("maxLevel = 10"));
+        assert!(invalid("[[attributes]]\nname = \"max hp\""));
+        assert!(invalid("[[attributes]]\nname = \"level\""));
+        assert!(invalid("formulas = [\"hp = 10 * vitality\"]\n[[attributes]]\nname = \"strength\""));
+        assert!(invalid("formulas = [\"b = a\", \"a = strength\"]\n[[attributes]]\nname = \"strength\""));
+        assert!(invalid("formulas = [\"hp = (strength\"]\n[[attributes]]\nname = \"strength\""));
+        assert!(invalid("[[tiers]]\nname = \"E\"\nrequirements = [\"charisma > 3\"]"));
+        assert!(invalid("[[tiers]]\nname = \"E\"\n[[tiers]]\nname = \"E\""));
+        assert!(invalid("[[attributes]]\nname = \"strength\"\ncolour = \"red\""));
+        assert!(CustomTemplate::parse("{\"attributes\": [", DefinitionFormat::Json).is_err());
+
+        // Overlong or deeply nested expressions are refused rather than parsed
+        let long = format!("hp = strength{}", " + 1".repeat(MAX_EXPRESSION_LENGTH));
+        assert!(invalid(&format!("formulas = [\"{}\"]\n[[attributes]]\nname = \"strength\"", long)));
+        let deep = format!("{}strength{}", "(".repeat(100), ")".repeat(100));
+        assert!(invalid(&format!("[[attributes]]\nname = \"strength\"\n[[tiers]]\nname = \"E\"\nrequirements = [\"{}\"]", deep)));
+        let long = format!("strength > {}", "1".repeat(MAX_EXPRESSION_LENGTH));
+        assert!(invalid(&format!("[[attributes]]\nname = \"strength\"\n[[tiers]]\nname = \"E\"\nrequirements = [\"{}\"]", long)));
+    }
+}
@@ -0,0 +1,424 @@
+//! A small expression language for custom progression systems: arithmetic
+//! formulas such as `max_hp = 10 * vitality + level * 5` and requirements
+//! such as `level >= 10 && strength >= 20`.
+//!
+//! Values are numbers; comparisons and `&&`, `||`, `!` treat zero as false
+//! and give 1 or 0. Functions: `min`, `max`, `floor`, `ceil`, `round`, `abs`.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Variable(String),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Box<Expr>, Op, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+/// Why an expression could not be parsed or evaluated
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExprError(pub String);
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// How deeply parentheses, calls, prefix operators and operator chains may
+/// nest; parsing and evaluation both recurse once per level
+const MAX_DEPTH: usize = 64;
+
+const FUNCTIONS: &[(&str, usize)] = &[("min", 2), ("max", 2), ("floor", 1), ("ceil", 1), ("round", 1), ("abs", 1)];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+    Open,
+    Close,
+    Comma,
+    Assign,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ExprError> {
+    const OPERATORS: &[&str] = &["<=", ">=", "==", "!=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "!"];
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())) {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let number = text.parse().map_err(|_| ExprError(format!("Invalid number '{}'", text)))?;
+            tokens.push(Token::Number(number));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c == '(' {
+            tokens.push(Token::Open);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::Close);
+            i += 1;
+        } else if c == ',' {
+            tokens.push(Token::Comma);
+            i += 1;
+        } else if let Some(op) = OPERATORS.iter().find(|op| chars[i..].starts_with(&op.chars().collect::<Vec<_>>())) {
+            tokens.push(Token::Op(op));
+            i += op.len();
+        } else if c == '=' {
+            tokens.push(Token::Assign);
+            i += 1;
+        } else {
+            return Err(ExprError(format!("Unexpected '{}' at position {}", c, i + 1)));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>) -> Self {
+        Parser { tokens, pos: 0, depth: 0 }
+    }
+
+    /// Go one level deeper, failing past `MAX_DEPTH`
+    fn enter(&mut self) -> Result<(), ExprError> {
+        if self.depth == MAX_DEPTH {
+            return Err(ExprError(format!("Expression is nested more than {} levels deep", MAX_DEPTH)));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Expr, ExprError>) -> Result<Expr, ExprError> {
+        self.enter()?;
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_op(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.pos += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn binary(&mut self, ops: &[&'static str], operand: fn(&mut Self) -> Result<Expr, ExprError>) -> Result<Expr, ExprError> {
+        let depth = self.depth;
+        let mut left = operand(self)?;
+        while let Some(op) = self.eat_op(ops) {
+            // Each operator in a chain puts the terms before it a level deeper
+            self.enter()?;
+            let right = operand(self)?;
+            left = Expr::Binary(Box::new(left), operator(op), Box::new(right));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&["||"], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&["&&"], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ExprError> {
+        let left = self.sum()?;
+        match self.eat_op(&["<", "<=", ">", ">=", "==", "!="]) {
+            Some(op) => Ok(Expr::Binary(Box::new(left), operator(op), Box::new(self.sum()?))),
+            None => Ok(left),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&["+", "-"], Self::product)
+    }
+
+    fn product(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&["*", "/", "%"], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        match self.eat_op(&["-", "!"]) {
+            Some("-") => Ok(Expr::Negate(Box::new(self.nested(Self::unary)?))),
+            Some(_) => Ok(Expr::Not(Box::new(self.nested(Self::unary)?))),
+            None => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, ExprError> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::Open) => {
+                self.pos += 1;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::Close) {
+                    args.push(self.nested(Self::or)?);
+                    while self.peek() == Some(&Token::Comma) {
+                        self.pos += 1;
+                        args.push(self.nested(Self::or)?);
+                    }
+                }
+                if self.next() != Some(Token::Close) {
+                    return Err(ExprError(format!("Missing ')' after the arguments of {}", name)));
+                }
+                match FUNCTIONS.iter().find(|(f, _)| *f == name) {
+                    Some((_, arity)) if *arity == args.len() => Ok(Expr::Call(name, args)),
+                    Some((_, arity)) => Err(ExprError(format!("{} takes {} argument(s), not {}", name, arity, args.len()))),
+                    None => Err(ExprError(format!("Unknown function '{}'", name))),
+                }
+            }
+            Some(Token::Ident(name)) => Ok(Expr::Variable(name)),
+            Some(Token::Open) => {
+                let inner = self.nested(Self::or)?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err(ExprError("Missing ')'".to_string())),
+                }
+            }
+            Some(token) => Err(ExprError(format!("Unexpected {}", describe(&token)))),
+            None => Err(ExprError("Expression ends too soon".to_string())),
+        }
+    }
+
+    fn finish(&self) -> Result<(), ExprError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(token) => Err(ExprError(format!("Unexpected {} after the end of the expression", describe(token)))),
+        }
+    }
+}
+
+fn operator(op: &str) -> Op {
+    match op {
+        "+" => Op::Add,
+        "-" => Op::Subtract,
+        "*" => Op::Multiply,
+        "/" => Op::Divide,
+        "%" => Op::Remainder,
+        "<" => Op::Less,
+        "<=" => Op::LessEqual,
+        ">" => Op::Greater,
+        ">=" => Op::GreaterEqual,
+        "==" => Op::Equal,
+        "!=" => Op::NotEqual,
+        "&&" => Op::And,
+        _ => Op::Or,
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(n) => format!("number {}", n),
+        Token::Ident(name) => format!("'{}'", name),
+        Token::Op(op) => format!("'{}'", op),
+        Token::Open => "'('".to_string(),
+        Token::Close => "')'".to_string(),
+        Token::Comma => "','".to_string(),
+        Token::Assign => "'='".to_string(),
+    }
+}
+
+fn truth(value: bool) -> f64 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Expr, ExprError> {
+        let mut parser = Parser::new(tokenize(source)?);
+        let expr = parser.or()?;
+        parser.finish()?;
+        Ok(expr)
+    }
+
+    /// Parse `name = expression`
+    pub fn parse_assignment(source: &str) -> Result<(String, Expr), ExprError> {
+        let tokens = tokenize(source)?;
+        match tokens.as_slice() {
+            [Token::Ident(name), Token::Assign, ..] => {
+                let mut parser = Parser::new(tokens[2..].to_vec());
+                let expr = parser.or()?;
+                parser.finish()?;
+                Ok((name.clone(), expr))
+            }
+            _ => Err(ExprError(format!("Expected 'name = expression', got '{}'", source.trim()))),
+        }
+    }
+
+    /// Names of the variables the expression reads
+    pub fn variables(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_variables(&mut names);
+        names
+    }
+
+    fn collect_variables<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Variable(name) => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+            Expr::Negate(inner) | Expr::Not(inner) => inner.collect_variables(names),
+            Expr::Binary(left, _, right) => {
+                left.collect_variables(names);
+                right.collect_variables(names);
+            }
+            Expr::Call(_, args) => args.iter().for_each(|arg| arg.collect_variables(names)),
+        }
+    }
+
+    pub fn eval(&self, lookup: &dyn Fn(&str) -> Option<f64>) -> Result<f64, ExprError> {
+        let value = match self {
+            Expr::Number(n) => *n,
+            Expr::Variable(name) => lookup(name).ok_or_else(|| ExprError(format!("Unknown variable '{}'", name)))?,
+            Expr::Negate(inner) => -inner.eval(lookup)?,
+            Expr::Not(inner) => truth(inner.eval(lookup)? == 0.0),
+            Expr::Binary(left, Op::And, right) => truth(left.eval(lookup)? != 0.0 && right.eval(lookup)? != 0.0),
+            Expr::Binary(left, Op::Or, right) => truth(left.eval(lookup)? != 0.0 || right.eval(lookup)? != 0.0),
+            Expr::Binary(left, op, right) => {
+                let (a, b) = (left.eval(lookup)?, right.eval(lookup)?);
+                match op {
+                    Op::Add => a + b,
+                    Op::Subtract => a - b,
+                    Op::Multiply => a * b,
+                    Op::Divide | Op::Remainder if b == 0.0 => return Err(ExprError("Division by zero".to_string())),
+                    Op::Divide => a / b,
+                    Op::Remainder => a % b,
+                    Op::Less => truth(a < b),
+                    Op::LessEqual => truth(a <= b),
+                    Op::Greater => truth(a > b),
+                    Op::GreaterEqual => truth(a >= b),
+                    Op::Equal => truth(a == b),
+                    Op::NotEqual => truth(a != b),
+                    Op::And | Op::Or => unreachable!("handled above"),
+                }
+            }
+            Expr::Call(name, args) => {
+                let args = args.iter().map(|arg| arg.eval(lookup)).collect::<Result<Vec<_>, _>>()?;
+                match name.as_str() {
+                    "min" => args[0].min(args[1]),
+                    "max" => args[0].max(args[1]),
+                    "floor" => args[0].floor(),
+                    "ceil" => args[0].ceil(),
+                    "round" => args[0].round(),
+                    _ => args[0].abs(),
+                }
+            }
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str) -> Result<f64, ExprError> {
+        let vars = |name: &str| match name {
+            "vitality" => Some(8.0),
+            "level" => Some(3.0),
+            _ => None,
+        };
+        Expr::parse(source)?.eval(&vars)
+    }
+
+    #[test]
+    fn test_arithmetic_and_precedence() {
+        assert_eq!(eval("10 * vitality + level * 5").unwrap(), 95.0);
+        assert_eq!(eval("(1 + 2) * 3 - -1").unwrap(), 10.0);
+        assert_eq!(eval("7 % 4 + 1.5").unwrap(), 4.5);
+        assert_eq!(eval("floor(vitality / 3) + max(level, 10)").unwrap(), 12.0);
+        assert_eq!(eval("level >= 3 && vitality > 10 || !0").unwrap(), 1.0);
+        assert_eq!(eval("level == 3 && vitality < 5").unwrap(), 0.0);
+
+        let (name, expr) = Expr::parse_assignment("max_hp = 10 * vitality + level * 5").unwrap();
+        assert_eq!(name, "max_hp");
+        assert_eq!(expr.variables(), vec!["vitality", "level"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(eval("1 / (level - 3)").is_err());
+        assert!(eval("strength * 2").is_err());
+        assert!(eval("1 +").is_err());
+        assert!(eval("(1 + 2").is_err());
+        assert!(eval("sqrt(4)").is_err());
+        assert!(eval("min(1)").is_err());
+        assert!(eval("1 2").is_err());
+        assert!(eval("level # 2").is_err());
+        assert!(Expr::parse_assignment("10 * vitality").is_err());
+        assert!(Expr::parse("hp = 1").is_err());
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        assert_eq!(eval(&format!("{}level{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH))).unwrap(), 3.0);
+        assert_eq!(eval(&format!("{}1", "-".repeat(MAX_DEPTH))).unwrap(), 1.0);
+
+        // Rejected before the parser or evaluator can exhaust the stack
+        let n = 100_000;
+        for source in ["(".repeat(n), "-".repeat(n) + "1", "!".repeat(n) + "1", "abs(".repeat(n), "1 + ".repeat(n) + "1"] {
+            let err = Expr::parse(&source).unwrap_err();
+            assert!(err.0.contains("nested more than 64 levels"), "{}", err);
+        }
+    }
+}
@@ -1,15 +1,23 @@
-// Progression system templates will be implemented in Phase 8 (Optional Features)
-// This module will contain:
-// - LitRPG system templates
-// - Cultivation system templates
-// - Magic tier templates
-// - Skill tree templates
-// - Custom system builder
+//! Progression systems for LitRPG and progression fantasy.
+//!
+//! A project enables a system under a name, with a typed template describing
+//! its stats, realms, tiers or skills ([`SystemTemplate`]). Built-in
+//! templates give each type a sensible starting point; a project's own
+//! template replaces it. Characters' sheets are checked against the template
+//! before they are stored, and each step from one sheet to the next is
+//! checked against the system's rules ([`validation`]). Sheets can be
+//! rendered as status screens ([`render`]).
 
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn test_systems_module_exists() {
-        assert!(true);
-    }
-}
+pub mod custom;
+pub mod expr;
+pub mod render;
+pub mod templates;
+pub mod validation;
+
+pub use custom::{CustomTemplate, CustomTier, DefinitionFormat};
+pub use render::{StatusFormat, StatusScreen};
+pub use templates::{
+    CultivationTemplate, GameStatsTemplate, MagicTiersTemplate, RealmDefinition, SkillDefinition, SkillTreeTemplate,
+    StatDefinition, SystemTemplate, TierDefinition,
+};
+pub use validation::{check_advancement, Advancement};
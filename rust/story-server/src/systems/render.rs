@@ -0,0 +1,425 @@
+//! Status screens: a character's sheet laid out as the stat block readers of
+//! LitRPG expect, with what changed since their previous snapshot.
+
+use super::custom::format_number;
+use super::templates::SystemTemplate;
+use crate::models::ProgressionSheet;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::fmt;
+
+/// How to format a status screen: `text` (a box-drawing panel),
+/// `markdown` (a table) or `html` (a table with CSS class hooks)
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusFormat {
+    #[default]
+    Text,
+    Markdown,
+    Html,
+}
+
+impl fmt::Display for StatusFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            StatusFormat::Text => "text",
+            StatusFormat::Markdown => "markdown",
+            StatusFormat::Html => "html",
+        };
+        f.write_str(s)
+    }
+}
+
+/// What goes on a status screen
+#[derive(Debug, Clone)]
+pub struct StatusScreen<'a> {
+    /// Usually the character's name
+    pub title: &'a str,
+    pub system_name: &'a str,
+    pub template: &'a SystemTemplate,
+    pub sheet: &'a ProgressionSheet,
+    /// The sheet as of the snapshot before, to show changes against
+    pub previous: Option<&'a ProgressionSheet>,
+}
+
+/// One line of the screen
+#[derive(Debug, Clone, PartialEq)]
+struct Row {
+    label: String,
+    value: String,
+    change: Option<String>,
+}
+
+impl StatusScreen<'_> {
+    /// Stats in the template's order with starting values for any not recorded yet,
+    /// followed by any others the sheet has
+    fn stats(&self, sheet: &ProgressionSheet) -> Vec<(String, i64)> {
+        let defined = self.template.stats();
+        let mut stats: Vec<(String, i64)> = defined
+            .iter()
+            .map(|stat| (stat.name.clone(), sheet.stats.get(&stat.name).copied().unwrap_or(stat.starting)))
+            .collect();
+        for (name, value) in &sheet.stats {
+            if !defined.iter().any(|stat| &stat.name == name) {
+                stats.push((name.clone(), *value));
+            }
+        }
+        stats
+    }
+
+    /// Stats worked out from a custom system's formulas; other systems have none
+    fn derived(&self, sheet: &ProgressionSheet) -> Vec<(String, f64)> {
+        match self.template {
+            SystemTemplate::Custom(t) => t.derived(sheet),
+            _ => Vec::new(),
+        }
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        let previous = self.previous;
+        let number = |label: &str, value: Option<i64>, before: Option<i64>| {
+            value.map(|value| Row {
+                label: label.to_string(),
+                value: value.to_string(),
+                change: before.and_then(|before| signed(value.saturating_sub(before))),
+            })
+        };
+        rows.extend(number("Level", self.sheet.level, previous.and_then(|p| p.level)));
+        rows.extend(number("Experience", self.sheet.experience, previous.and_then(|p| p.experience)));
+
+        if let Some(realm) = &self.sheet.realm {
+            let value = match (self.sheet.stage, self.template) {
+                (Some(stage), SystemTemplate::Cultivation(t)) => match t.realms.iter().find(|r| &r.name == realm) {
+                    Some(definition) => format!("{} (stage {}/{})", realm, stage, definition.stages),
+                    None => format!("{} (stage {})", realm, stage),
+                },
+                (Some(stage), _) => format!("{} (stage {})", realm, stage),
+                (None, _) => realm.clone(),
+            };
+            let before = previous.and_then(|p| p.realm.as_ref().map(|realm| (realm, p.stage)));
+            let change = match before {
+                Some((before, _)) if before != realm => Some(format!("from {}", before)),
+                Some((_, Some(stage))) => self.sheet.stage.and_then(|now| signed(now.saturating_sub(stage))).map(|s| format!("{} stage", s)),
+                _ => None,
+            };
+            rows.push(Row { label: "Realm".to_string(), value, change });
+        }
+        if let Some(tier) = &self.sheet.tier {
+            let change = previous
+                .and_then(|p| p.tier.as_ref())
+                .filter(|before| *before != tier)
+                .map(|before| format!("from {}", before));
+            rows.push(Row { label: "Tier".to_string(), value: tier.clone(), change });
+        }
+
+        let before = previous.map(|p| self.stats(p));
+        for (name, value) in self.stats(self.sheet) {
+            let old = before.as_ref().and_then(|b| b.iter().find(|(n, _)| *n == name)).map(|(_, v)| *v);
+            rows.push(Row {
+                label: name,
+                value: value.to_string(),
+                change: old.and_then(|old| signed(value.saturating_sub(old))),
+            });
+        }
+
+        let before = previous.map(|p| self.derived(p));
+        for (name, value) in self.derived(self.sheet) {
+            let old = before.as_ref().and_then(|b| b.iter().find(|(n, _)| *n == name)).map(|(_, v)| *v);
+            rows.push(Row {
+                label: name,
+                value: format_number(value),
+                change: old.and_then(|old| signed_number(value - old)),
+            });
+        }
+        rows
+    }
+
+    /// Unlocked skills, each marked if it is new since the previous snapshot
+    fn skills(&self) -> Option<Vec<(String, bool)>> {
+        let had = self.previous.and_then(|p| p.skills.as_ref());
+        self.sheet.skills.as_ref().map(|skills| {
+            skills
+                .iter()
+                .map(|skill| (skill.clone(), self.previous.is_some() && !had.is_some_and(|had| had.contains(skill))))
+                .collect()
+        })
+    }
+
+    /// What changed since the previous snapshot, for callers that want numbers rather than text
+    pub fn deltas(&self) -> Value {
+        let Some(previous) = self.previous else { return Value::Null };
+        let mut deltas = Map::new();
+        if let (Some(now), Some(before)) = (self.sheet.level, previous.level) {
+            deltas.insert("level".to_string(), json!(now.saturating_sub(before)));
+        }
+        if let (Some(now), Some(before)) = (self.sheet.experience, previous.experience) {
+            deltas.insert("experience".to_string(), json!(now.saturating_sub(before)));
+        }
+        let before = self.stats(previous);
+        let stats: Map<String, Value> = self
+            .stats(self.sheet)
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let old = before.iter().find(|(n, _)| *n == name).map(|(_, v)| *v)?;
+                (value != old).then(|| (name, json!(value.saturating_sub(old))))
+            })
+            .collect();
+        deltas.insert("stats".to_string(), Value::Object(stats));
+        if matches!(self.template, SystemTemplate::Custom(_)) {
+            let before = self.derived(previous);
+            let derived: Map<String, Value> = self
+                .derived(self.sheet)
+                .into_iter()
+                .filter_map(|(name, value)| {
+                    let old = before.iter().find(|(n, _)| *n == name).map(|(_, v)| *v)?;
+                    (value != old).then(|| (name, json!(value - old)))
+                })
+                .collect();
+            deltas.insert("derived".to_string(), Value::Object(derived));
+        }
+        if previous.realm != self.sheet.realm {
+            deltas.insert("realm".to_string(), json!({"from": previous.realm, "to": self.sheet.realm}));
+        }
+        if previous.tier != self.sheet.tier {
+            deltas.insert("tier".to_string(), json!({"from": previous.tier, "to": self.sheet.tier}));
+        }
+        let unlocked: Vec<String> = self.skills().unwrap_or_default().into_iter().filter(|(_, new)| *new).map(|(s, _)| s).collect();
+        deltas.insert("skillsUnlocked".to_string(), json!(unlocked));
+        Value::Object(deltas)
+    }
+
+    pub fn render(&self, format: StatusFormat) -> String {
+        let heading = format!("{} — {}", self.title, self.system_name);
+        let rows = self.rows();
+        let skills = self.skills();
+        match format {
+            StatusFormat::Text => text(&heading, &rows, skills.as_deref()),
+            StatusFormat::Markdown => markdown(&heading, &rows, skills.as_deref()),
+            StatusFormat::Html => html(&heading, &rows, skills.as_deref()),
+        }
+    }
+}
+
+/// A difference as "+n" or "-n", or nothing when there is none
+fn signed(difference: i64) -> Option<String> {
+    match difference {
+        0 => None,
+        d if d > 0 => Some(format!("+{}", d)),
+        d => Some(d.to_string()),
+    }
+}
+
+/// The same for derived stats, which need not be whole numbers
+fn signed_number(difference: f64) -> Option<String> {
+    if difference == 0.0 {
+        None
+    } else if difference > 0.0 {
+        Some(format!("+{}", format_number(difference)))
+    } else {
+        Some(format_number(difference))
+    }
+}
+
+fn text(heading: &str, rows: &[Row], skills: Option<&[(String, bool)]>) -> String {
+    let label_width = rows.iter().map(|row| row.label.chars().count()).max().unwrap_or(0);
+    let mut lines = Vec::new();
+    let mut body = Vec::new();
+    for row in rows {
+        let mut line = format!("{:<width$}  {}", row.label, row.value, width = label_width);
+        if let Some(change) = &row.change {
+            line.push_str(&format!(" ({})", change));
+        }
+        body.push(line);
+    }
+    let skill_lines: Option<Vec<String>> = skills.map(|skills| {
+        let mut lines = vec!["Skills".to_string()];
+        if skills.is_empty() {
+            lines.push("  (none)".to_string());
+        }
+        lines.extend(skills.iter().map(|(skill, new)| format!("  • {}{}", skill, if *new { " (new)" } else { "" })));
+        lines
+    });
+
+    let width = std::iter::once(heading)
+        .chain(body.iter().map(String::as_str))
+        .chain(skill_lines.iter().flatten().map(String::as_str))
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    let pad = |line: &str| format!("║ {}{} ║", line, " ".repeat(width - line.chars().count()));
+    let rule = |left: char, right: char| format!("{}{}{}", left, "═".repeat(width + 2), right);
+
+    lines.push(rule('╔', '╗'));
+    lines.push(pad(heading));
+    lines.push(rule('╠', '╣'));
+    lines.extend(body.iter().map(|line| pad(line)));
+    if let Some(skill_lines) = skill_lines {
+        lines.push(rule('╠', '╣'));
+        lines.extend(skill_lines.iter().map(|line| pad(line)));
+    }
+    lines.push(rule('╚', '╝'));
+    lines.join("\n")
+}
+
+fn markdown(heading: &str, rows: &[Row], skills: Option<&[(String, bool)]>) -> String {
+    let cell = |s: &str| s.replace('|', "\\|");
+    let mut out = format!("**{}**\n\n| Attribute | Value | Change |\n|---|---:|---:|\n", cell(heading));
+    for row in rows {
+        out.push_str(&format!(
+            "| {} | {} | {} |\n",
+            cell(&row.label),
+            cell(&row.value),
+            row.change.as_deref().map(cell).unwrap_or_default()
+        ));
+    }
+    if let Some(skills) = skills {
+        let list: Vec<String> =
+            skills.iter().map(|(skill, new)| format!("{}{}", cell(skill), if *new { " *(new)*" } else { "" })).collect();
+        out.push_str(&format!("\n**Skills:** {}\n", if list.is_empty() { "none".to_string() } else { list.join(", ") }));
+    }
+    out.trim_end().to_string()
+}
+
+fn html(heading: &str, rows: &[Row], skills: Option<&[(String, bool)]>) -> String {
+    let mut out = format!(
+        "<div class=\"status-screen\">\n  <h3>{}</h3>\n  <table>\n    <tr><th>Attribute</th><th>Value</th><th>Change</th></tr>\n",
+        escape(heading)
+    );
+    for row in rows {
+        out.push_str(&format!(
+            "    <tr><td>{}</td><td>{}</td><td class=\"change\">{}</td></tr>\n",
+            escape(&row.label),
+            escape(&row.value),
+            row.change.as_deref().map(escape).unwrap_or_default()
+        ));
+    }
+    out.push_str("  </table>\n");
+    if let Some(skills) = skills {
+        out.push_str("  <ul class=\"skills\">\n");
+        for (skill, new) in skills {
+            let class = if *new { " class=\"new\"" } else { "" };
+            out.push_str(&format!("    <li{}>{}</li>\n", class, escape(skill)));
+        }
+        out.push_str("  </ul>\n");
+    }
+    out.push_str("</div>");
+    out
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SystemType;
+
+    fn sheet(value: Value) -> ProgressionSheet {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_text_screen_with_deltas() {
+        let template = SystemTemplate::builtin(SystemType::GameStats).unwrap();
+        let previous = sheet(json!({"level": 4, "stats": {"STR": 10}, "skills": ["Slash"]}));
+        let current = sheet(json!({"level": 5, "stats": {"STR": 12, "AGI": 8}, "skills": ["Slash", "Dash"]}));
+        let screen = StatusScreen {
+            title: "Kai",
+            system_name: "System",
+            template: &template,
+            sheet: &current,
+            previous: Some(&previous),
+        };
+
+        let text = screen.render(StatusFormat::Text);
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].starts_with('╔') && lines[0].ends_with('╗'));
+        assert!(lines.last().unwrap().starts_with('╚'));
+        // Every line of the box is the same width
+        assert!(lines.iter().all(|line| line.chars().count() == lines[0].chars().count()));
+        assert!(text.contains("Kai — System"));
+        assert!(text.contains("Level  5 (+1)"));
+        assert!(text.contains("STR    12 (+2)"));
+        assert!(text.contains("AGI    8 (+3)"));
+        // Stats never recorded show their starting value and no change
+        let luck = lines.iter().find(|line| line.contains("LUK")).unwrap();
+        assert!(luck.contains("LUK    5") && !luck.contains('('));
+        assert!(text.contains("• Dash (new)"));
+        assert!(!text.contains("Slash (new)"));
+
+        let deltas = screen.deltas();
+        assert_eq!(deltas["level"], 1);
+        assert_eq!(deltas["stats"], json!({"STR": 2, "AGI": 3}));
+        assert_eq!(deltas["skillsUnlocked"], json!(["Dash"]));
+
+        // Stored values too far apart to subtract saturate rather than overflow
+        let previous = sheet(json!({"level": 1, "stats": {"STR": i64::MIN}}));
+        let current = sheet(json!({"level": i64::MAX, "stats": {"STR": i64::MAX}}));
+        let screen = StatusScreen { sheet: &current, previous: Some(&previous), ..screen };
+        assert!(screen.render(StatusFormat::Text).contains(&format!("STR    {} (+{})", i64::MAX, i64::MAX)));
+        assert_eq!(screen.deltas()["level"], i64::MAX - 1);
+        assert_eq!(screen.deltas()["stats"]["STR"], i64::MAX);
+    }
+
+    #[test]
+    fn test_markdown_and_html() {
+        let template = SystemTemplate::builtin(SystemType::Cultivation).unwrap();
+        let previous = sheet(json!({"realm": "Qi Condensation", "stage": 9, "stats": {"Qi": 90}}));
+        let current = sheet(json!({"realm": "Foundation Establishment", "stage": 1, "stats": {"Qi": 120}}));
+        let screen = StatusScreen {
+            title: "Lin <Wei>",
+            system_name: "Cultivation",
+            template: &template,
+            sheet: &current,
+            previous: Some(&previous),
+        };
+
+        let markdown = screen.render(StatusFormat::Markdown);
+        assert!(markdown.starts_with("**Lin <Wei> — Cultivation**"));
+        assert!(markdown.contains("| Realm | Foundation Establishment (stage 1/3) | from Qi Condensation |"));
+        assert!(markdown.contains("| Qi | 120 | +30 |"));
+        assert!(!markdown.contains("Skills"));
+
+        let html = screen.render(StatusFormat::Html);
+        assert!(html.contains("<h3>Lin &lt;Wei&gt; — Cultivation</h3>"));
+        assert!(html.contains("<tr><td>Qi</td><td>120</td><td class=\"change\">+30</td></tr>"));
+        assert!(html.ends_with("</div>"));
+
+        // Without an earlier snapshot there is nothing to compare against
+        let first = StatusScreen { previous: None, ..screen };
+        assert!(first.deltas().is_null());
+        assert!(first.render(StatusFormat::Markdown).contains("| Qi | 120 |  |"));
+    }
+
+    #[test]
+    fn test_custom_derived_stats() {
+        let template = SystemTemplate::from_value(
+            SystemType::Custom,
+            json!({
+                "attributes": [{"name": "vitality", "starting": 5}],
+                "formulas": ["max_hp = 10 * vitality + level * 5", "regen = max_hp / 20"],
+                "tiers": [{"name": "E-Rank"}, {"name": "D-Rank"}]
+            }),
+        )
+        .unwrap();
+        let previous = sheet(json!({"level": 2, "tier": "E-Rank"}));
+        let current = sheet(json!({"level": 3, "tier": "D-Rank", "stats": {"vitality": 7}}));
+        let screen = StatusScreen {
+            title: "Kai",
+            system_name: "Hunter",
+            template: &template,
+            sheet: &current,
+            previous: Some(&previous),
+        };
+
+        let markdown = screen.render(StatusFormat::Markdown);
+        assert!(markdown.contains("| Tier | D-Rank | from E-Rank |"));
+        assert!(markdown.contains("| vitality | 7 | +2 |"));
+        assert!(markdown.contains("| max_hp | 85 | +25 |"));
+        assert!(markdown.contains("| regen | 4.25 | +1.25 |"));
+        assert_eq!(screen.deltas()["derived"]["max_hp"], 25.0);
+    }
+}
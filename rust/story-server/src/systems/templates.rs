@@ -0,0 +1,480 @@
+use super::custom::CustomTemplate;
+use crate::error::{Result, StoryError};
+use crate::models::{ProgressionSheet, SystemType};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+
+/// Largest level, stat or experience a sheet may record, in either direction,
+/// so point budgets can be worked out without overflowing
+pub const MAX_SHEET_VALUE: i64 = 1_000_000_000;
+
+/// An attribute characters have a value for, e.g. Strength or Qi
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct StatDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Value at level 1, or before any advancement
+    #[serde(default)]
+    pub starting: i64,
+}
+
+/// LitRPG levels and attributes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GameStatsTemplate {
+    pub stats: Vec<StatDefinition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_level: Option<i64>,
+    /// Free points a character distributes on each level up
+    #[serde(default)]
+    pub points_per_level: i64,
+    /// Automatic increase of each named stat on each level up
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub gains_per_level: BTreeMap<String, i64>,
+}
+
+/// A cultivation realm, climbed stage by stage
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RealmDefinition {
+    pub name: String,
+    /// Number of stages within the realm
+    #[serde(default = "one")]
+    pub stages: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Realms in ascending order, with optional attributes such as Qi
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CultivationTemplate {
+    pub realms: Vec<RealmDefinition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<StatDefinition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TierDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Tiers of magical power in ascending order, with optional attributes such as Mana
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MagicTiersTemplate {
+    pub tiers: Vec<TierDefinition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<StatDefinition>,
+}
+
+/// A skill and what must come before it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SkillDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Skills that must be unlocked first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prerequisites: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_level: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SkillTreeTemplate {
+    pub skills: Vec<SkillDefinition>,
+}
+
+/// The `template_data` of a progression system, typed by its `system_type`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum SystemTemplate {
+    GameStats(GameStatsTemplate),
+    Cultivation(CultivationTemplate),
+    MagicTiers(MagicTiersTemplate),
+    SkillTrees(SkillTreeTemplate),
+    Custom(CustomTemplate),
+}
+
+fn one() -> i64 {
+    1
+}
+
+fn stat(name: &str, description: &str, starting: i64) -> StatDefinition {
+    StatDefinition {
+        name: name.to_string(),
+        description: Some(description.to_string()),
+        starting,
+    }
+}
+
+fn skill(name: &str, prerequisites: &[&str], min_level: Option<i64>) -> SkillDefinition {
+    SkillDefinition {
+        name: name.to_string(),
+        description: None,
+        prerequisites: prerequisites.iter().map(|p| p.to_string()).collect(),
+        min_level,
+    }
+}
+
+impl SystemTemplate {
+    /// The built-in template for a system type; custom systems have none
+    pub fn builtin(system_type: SystemType) -> Option<Self> {
+        let template = match system_type {
+            SystemType::GameStats => SystemTemplate::GameStats(GameStatsTemplate {
+                stats: vec![
+                    stat("STR", "Strength", 5),
+                    stat("AGI", "Agility", 5),
+                    stat("VIT", "Vitality", 5),
+                    stat("INT", "Intelligence", 5),
+                    stat("WIS", "Wisdom", 5),
+                    stat("LUK", "Luck", 5),
+                ],
+                max_level: Some(100),
+                points_per_level: 5,
+                gains_per_level: BTreeMap::new(),
+            }),
+            SystemType::Cultivation => SystemTemplate::Cultivation(CultivationTemplate {
+                realms: [
+                    ("Body Tempering", 9),
+                    ("Qi Condensation", 9),
+                    ("Foundation Establishment", 3),
+                    ("Core Formation", 3),
+                    ("Nascent Soul", 3),
+                    ("Soul Transformation", 3),
+                    ("Immortal Ascension", 1),
+                ]
+                .into_iter()
+                .map(|(name, stages)| RealmDefinition {
+                    name: name.to_string(),
+                    stages,
+                    description: None,
+                })
+                .collect(),
+                attributes: vec![stat("Qi", "Refined spiritual energy", 0)],
+            }),
+            SystemType::MagicTiers => SystemTemplate::MagicTiers(MagicTiersTemplate {
+                tiers: ["Novice", "Apprentice", "Adept", "Expert", "Master", "Archmage"]
+                    .into_iter()
+                    .map(|name| TierDefinition {
+                        name: name.to_string(),
+                        description: None,
+                    })
+                    .collect(),
+                attributes: vec![stat("Mana", "Spell-casting reserve", 10)],
+            }),
+            SystemType::SkillTrees => SystemTemplate::SkillTrees(SkillTreeTemplate {
+                skills: vec![
+                    skill("Basic Strike", &[], None),
+                    skill("Power Strike", &["Basic Strike"], Some(5)),
+                    skill("Whirlwind", &["Power Strike"], Some(10)),
+                    skill("Parry", &[], None),
+                    skill("Riposte", &["Parry", "Basic Strike"], Some(8)),
+                ],
+            }),
+            SystemType::Custom => return None,
+        };
+        Some(template)
+    }
+
+    /// Parse and validate a template given for a system type
+    pub fn from_value(system_type: SystemType, data: Value) -> Result<Self> {
+        let invalid = |e: serde_json::Error| {
+            StoryError::validation(format!("Invalid {} template: {}", system_type, e)).with_field("templateData")
+        };
+        let template = match system_type {
+            SystemType::GameStats => SystemTemplate::GameStats(serde_json::from_value(data).map_err(invalid)?),
+            SystemType::Cultivation => SystemTemplate::Cultivation(serde_json::from_value(data).map_err(invalid)?),
+            SystemType::MagicTiers => SystemTemplate::MagicTiers(serde_json::from_value(data).map_err(invalid)?),
+            SystemType::SkillTrees => SystemTemplate::SkillTrees(serde_json::from_value(data).map_err(invalid)?),
+            SystemType::Custom => SystemTemplate::Custom(serde_json::from_value(data).map_err(invalid)?),
+        };
+        template.validate()?;
+        Ok(template)
+    }
+
+    pub fn system_type(&self) -> SystemType {
+        match self {
+            SystemTemplate::GameStats(_) => SystemType::GameStats,
+            SystemTemplate::Cultivation(_) => SystemType::Cultivation,
+            SystemTemplate::MagicTiers(_) => SystemType::MagicTiers,
+            SystemTemplate::SkillTrees(_) => SystemType::SkillTrees,
+            SystemTemplate::Custom(_) => SystemType::Custom,
+        }
+    }
+
+    /// Attributes characters have values for under this system
+    pub fn stats(&self) -> &[StatDefinition] {
+        match self {
+            SystemTemplate::GameStats(t) => &t.stats,
+            SystemTemplate::Cultivation(t) => &t.attributes,
+            SystemTemplate::MagicTiers(t) => &t.attributes,
+            SystemTemplate::SkillTrees(_) => &[],
+            SystemTemplate::Custom(t) => &t.attributes,
+        }
+    }
+
+    /// Check the template is internally consistent
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: String| Err(StoryError::validation(message).with_field("templateData"));
+        unique_names("stat", self.stats().iter().map(|s| s.name.as_str()))?;
+
+        match self {
+            SystemTemplate::GameStats(t) => {
+                if t.stats.is_empty() {
+                    return invalid("A game_stats template needs at least one stat".to_string());
+                }
+                if t.max_level.is_some_and(|max| max < 1) {
+                    return invalid("maxLevel must be 1 or more".to_string());
+                }
+                if t.points_per_level < 0 {
+                    return invalid("pointsPerLevel must not be negative".to_string());
+                }
+                if let Some(name) = t.gains_per_level.keys().find(|name| !t.stats.iter().any(|s| &s.name == *name)) {
+                    return invalid(format!("gainsPerLevel names unknown stat '{}'", name));
+                }
+            }
+            SystemTemplate::Cultivation(t) => {
+                if t.realms.is_empty() {
+                    return invalid("A cultivation template needs at least one realm".to_string());
+                }
+                unique_names("realm", t.realms.iter().map(|r| r.name.as_str()))?;
+                if let Some(realm) = t.realms.iter().find(|r| r.stages < 1) {
+                    return invalid(format!("Realm '{}' must have at least one stage", realm.name));
+                }
+            }
+            SystemTemplate::MagicTiers(t) => {
+                if t.tiers.is_empty() {
+                    return invalid("A magic_tiers template needs at least one tier".to_string());
+                }
+                unique_names("tier", t.tiers.iter().map(|r| r.name.as_str()))?;
+            }
+            SystemTemplate::SkillTrees(t) => {
+                if t.skills.is_empty() {
+                    return invalid("A skill_trees template needs at least one skill".to_string());
+                }
+                unique_names("skill", t.skills.iter().map(|s| s.name.as_str()))?;
+                for skill in &t.skills {
+                    if let Some(missing) = skill.prerequisites.iter().find(|p| !t.skills.iter().any(|s| &s.name == *p)) {
+                        return invalid(format!("Skill '{}' requires unknown skill '{}'", skill.name, missing));
+                    }
+                }
+                if let Some(name) = prerequisite_cycle(t) {
+                    return invalid(format!("Skill '{}' is its own prerequisite", name));
+                }
+            }
+            SystemTemplate::Custom(t) => t.validate().map_err(|e| e.with_field("templateData"))?,
+        }
+        Ok(())
+    }
+
+    /// Check a sheet snapshot only names things this system defines
+    pub fn check_sheet(&self, sheet: &ProgressionSheet) -> Result<()> {
+        let invalid = |message: String| Err(StoryError::validation(message).with_field("sheet"));
+        let stats = self.stats();
+        if let Some(name) = sheet.stats.keys().find(|name| !stats.iter().any(|s| &s.name == *name)) {
+            let known: Vec<&str> = stats.iter().map(|s| s.name.as_str()).collect();
+            return invalid(format!("Unknown stat '{}'; this system has: {}", name, known.join(", ")));
+        }
+        if let Some((name, value)) = sheet.stats.iter().find(|(_, value)| !(-MAX_SHEET_VALUE..=MAX_SHEET_VALUE).contains(*value)) {
+            return invalid(format!("{} is {}; stats must be within ±{}", name, value, MAX_SHEET_VALUE));
+        }
+        if let Some(level) = sheet.level {
+            if level > MAX_SHEET_VALUE {
+                return invalid(format!("Level {} is above the maximum of {}", level, MAX_SHEET_VALUE));
+            }
+            if level < 1 {
+                return invalid("Level must be 1 or more".to_string());
+            }
+            let max_level = match self {
+                SystemTemplate::GameStats(t) => t.max_level,
+                SystemTemplate::Custom(t) => t.max_level,
+                _ => None,
+            };
+            if let Some(max) = max_level {
+                if level > max {
+                    return invalid(format!("Level {} is above the maximum of {}", level, max));
+                }
+            }
+        }
+        if sheet.experience.is_some_and(|xp| xp < 0) {
+            return invalid("Experience must not be negative".to_string());
+        }
+        if sheet.experience.is_some_and(|xp| xp > MAX_SHEET_VALUE) {
+            return invalid(format!("Experience must not be above {}", MAX_SHEET_VALUE));
+        }
+
+        match self {
+            SystemTemplate::Cultivation(t) => {
+                let realm = match &sheet.realm {
+                    Some(name) => match t.realms.iter().find(|r| &r.name == name) {
+                        Some(realm) => Some(realm),
+                        None => return invalid(format!("Unknown realm '{}'", name)),
+                    },
+                    None => None,
+                };
+                if let Some(stage) = sheet.stage {
+                    let stages = realm.map_or(i64::MAX, |r| r.stages);
+                    if !(1..=stages).contains(&stage) {
+                        return invalid(format!("Stage {} is outside the realm's stages", stage));
+                    }
+                }
+            }
+            _ if sheet.realm.is_some() || sheet.stage.is_some() => {
+                return invalid("Only cultivation systems have realms and stages".to_string())
+            }
+            _ => {}
+        }
+        if let Some(name) = &sheet.tier {
+            let known = match self {
+                SystemTemplate::MagicTiers(t) => t.tiers.iter().any(|tier| &tier.name == name),
+                SystemTemplate::Custom(t) => t.tiers.iter().any(|tier| &tier.name == name),
+                _ => return invalid("Only magic_tiers and custom systems have tiers".to_string()),
+            };
+            if !known {
+                return invalid(format!("Unknown tier '{}'", name));
+            }
+        }
+        if let (SystemTemplate::SkillTrees(t), Some(skills)) = (self, &sheet.skills) {
+            if let Some(name) = skills.iter().find(|name| !t.skills.iter().any(|s| &s.name == *name)) {
+                return invalid(format!("Unknown skill '{}'", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unique_names<'a>(kind: &str, names: impl Iterator<Item = &'a str>) -> Result<()> {
+    let mut seen = HashSet::new();
+    for name in names {
+        if name.trim().is_empty() {
+            return Err(StoryError::validation(format!("Every {} needs a name", kind)).with_field("templateData"));
+        }
+        if !seen.insert(name) {
+            return Err(StoryError::validation(format!("Duplicate {} '{}'", kind, name)).with_field("templateData"));
+        }
+    }
+    Ok(())
+}
+
+/// A skill that depends on itself through its prerequisites, if any
+fn prerequisite_cycle(tree: &SkillTreeTemplate) -> Option<String> {
+    fn visit<'a>(tree: &'a SkillTreeTemplate, name: &'a str, path: &mut Vec<&'a str>, done: &mut HashSet<&'a str>) -> bool {
+        if done.contains(name) {
+            return false;
+        }
+        if path.contains(&name) {
+            return true;
+        }
+        path.push(name);
+        let prerequisites = tree.skills.iter().filter(|s| s.name == name).flat_map(|s| &s.prerequisites);
+        for prerequisite in prerequisites {
+            if visit(tree, prerequisite, path, done) {
+                return true;
+            }
+        }
+        path.pop();
+        done.insert(name);
+        false
+    }
+
+    let mut done = HashSet::new();
+    for skill in &tree.skills {
+        let mut path = Vec::new();
+        if visit(tree, &skill.name, &mut path, &mut done) {
+            return path.last().map(|name| name.to_string());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_builtin_templates_are_valid() {
+        for system_type in [SystemType::GameStats, SystemType::Cultivation, SystemType::MagicTiers, SystemType::SkillTrees] {
+            let template = SystemTemplate::builtin(system_type).unwrap();
+            template.validate().unwrap();
+            assert_eq!(template.system_type(), system_type);
+
+            // What we store reads back as the same template
+            let stored = serde_json::to_value(&template).unwrap();
+            assert_eq!(SystemTemplate::from_value(system_type, stored).unwrap(), template);
+        }
+        assert!(SystemTemplate::builtin(SystemType::Custom).is_none());
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        let invalid = |system_type, data| SystemTemplate::from_value(system_type, data).is_err();
+        assert!(invalid(SystemType::GameStats, json!({"stats": []})));
+        assert!(invalid(SystemType::GameStats, json!({"stats": [{"name": "STR"}], "gainsPerLevel": {"DEX": 1}})));
+        assert!(invalid(SystemType::GameStats, json!({"stats": [{"name": "STR"}, {"name": "STR"}]})));
+        assert!(invalid(SystemType::Cultivation, json!({"realms": [{"name": "Mortal", "stages": 0}]})));
+        assert!(invalid(SystemType::MagicTiers, json!({"tiers": [{"name": "Novice"}], "colour": "blue"})));
+        assert!(invalid(SystemType::SkillTrees, json!({"skills": [{"name": "Fireball", "prerequisites": ["Spark"]}]})));
+        assert!(invalid(
+            SystemType::SkillTrees,
+            json!({"skills": [{"name": "A", "prerequisites": ["B"]}, {"name": "B", "prerequisites": ["A"]}]})
+        ));
+        assert!(invalid(SystemType::Custom, json!({})));
+        assert!(invalid(SystemType::Custom, json!({"attributes": [{"name": "vitality"}], "formulas": ["hp = 10 * vigor"]})));
+        let custom = SystemTemplate::from_value(
+            SystemType::Custom,
+            json!({"attributes": [{"name": "vitality"}], "tiers": [{"name": "E-Rank"}], "formulas": ["hp = 10 * vitality"]}),
+        )
+        .unwrap();
+        assert!(custom.check_sheet(&serde_json::from_value(json!({"tier": "E-Rank", "stats": {"vitality": 3}})).unwrap()).is_ok());
+        assert!(custom.check_sheet(&serde_json::from_value(json!({"tier": "S-Rank"})).unwrap()).is_err());
+
+        let tree = SystemTemplate::from_value(
+            SystemType::SkillTrees,
+            json!({"skills": [{"name": "Spark"}, {"name": "Fireball", "prerequisites": ["Spark"], "minLevel": 3}]}),
+        )
+        .unwrap();
+        assert!(matches!(tree, SystemTemplate::SkillTrees(ref t) if t.skills[1].min_level == Some(3)));
+    }
+
+    #[test]
+    fn test_check_sheet() {
+        let sheet = |value: Value| serde_json::from_value::<ProgressionSheet>(value).unwrap();
+        let stats = SystemTemplate::builtin(SystemType::GameStats).unwrap();
+        assert!(stats.check_sheet(&sheet(json!({"level": 3, "stats": {"STR": 9}, "skills": ["Anything"]}))).is_ok());
+        assert!(stats.check_sheet(&sheet(json!({"stats": {"DEX": 9}}))).is_err());
+        assert!(stats.check_sheet(&sheet(json!({"level": 101}))).is_err());
+        assert!(stats.check_sheet(&sheet(json!({"realm": "Body Tempering"}))).is_err());
+        for extreme in [i64::MIN, i64::MAX] {
+            assert!(stats.check_sheet(&sheet(json!({"level": 1, "stats": {"STR": extreme}}))).is_err());
+        }
+        let unbounded = SystemTemplate::from_value(SystemType::GameStats, json!({"stats": [{"name": "STR"}]})).unwrap();
+        assert!(unbounded.check_sheet(&sheet(json!({"level": i64::MAX}))).is_err());
+        assert!(unbounded.check_sheet(&sheet(json!({"level": MAX_SHEET_VALUE, "stats": {"STR": -MAX_SHEET_VALUE}}))).is_ok());
+
+        let cultivation = SystemTemplate::builtin(SystemType::Cultivation).unwrap();
+        assert!(cultivation.check_sheet(&sheet(json!({"realm": "Core Formation", "stage": 3}))).is_ok());
+        assert!(cultivation.check_sheet(&sheet(json!({"realm": "Core Formation", "stage": 4}))).is_err());
+        assert!(cultivation.check_sheet(&sheet(json!({"realm": "Golden Core"}))).is_err());
+        assert!(cultivation.check_sheet(&sheet(json!({"tier": "Adept"}))).is_err());
+
+        let tiers = SystemTemplate::builtin(SystemType::MagicTiers).unwrap();
+        assert!(tiers.check_sheet(&sheet(json!({"tier": "Adept", "stats": {"Mana": 40}}))).is_ok());
+        assert!(tiers.check_sheet(&sheet(json!({"tier": "Grandmaster"}))).is_err());
+
+        let tree = SystemTemplate::builtin(SystemType::SkillTrees).unwrap();
+        assert!(tree.check_sheet(&sheet(json!({"level": 5, "skills": ["Basic Strike", "Power Strike"]}))).is_ok());
+        assert!(tree.check_sheet(&sheet(json!({"skills": ["Fireball"]}))).is_err());
+        assert!(tree.check_sheet(&sheet(json!({"stats": {"STR": 1}}))).is_err());
+    }
+}
@@ -0,0 +1,447 @@
+//! Progression rule checks between a character's consecutive sheets: realms
+//! and tiers climbed one at a time, stat gains that the levels gained pay
+//! for, skills unlocked only once their prerequisites are, and a custom
+//! system's tiers reached only once their requirements are met. Like the
+//! continuity detectors, these work on plain values and report [`Finding`]s.
+
+use super::custom::CustomTemplate;
+use super::templates::{SkillTreeTemplate, StatDefinition, SystemTemplate};
+use crate::continuity::Finding;
+use crate::models::{AlertSeverity, AlertType, ProgressionSheet};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// One step in a character's progression: their sheet after a scene, and the
+/// sheet before it (none for their first snapshot)
+#[derive(Debug, Clone)]
+pub struct Advancement<'a> {
+    pub system_id: &'a str,
+    pub character_id: &'a str,
+    pub character: &'a str,
+    /// How to refer to the scene, e.g. "chapter 2 scene 1"
+    pub label: &'a str,
+    pub previous: Option<&'a ProgressionSheet>,
+    pub current: &'a ProgressionSheet,
+}
+
+impl Advancement<'_> {
+    fn finding(&self, alert_type: AlertType, severity: AlertSeverity, description: String, details: Value, resolution: String) -> Finding {
+        let mut elements = json!({
+            "systemId": self.system_id,
+            "characterId": self.character_id,
+            "name": self.character
+        });
+        if let (Some(elements), Value::Object(details)) = (elements.as_object_mut(), details) {
+            elements.extend(details);
+        }
+        Finding {
+            alert_type,
+            severity,
+            description,
+            conflicting_elements: elements,
+            suggested_resolution: resolution,
+        }
+    }
+}
+
+/// Check one step against the system's rules, most severe findings first
+pub fn check_advancement(template: &SystemTemplate, step: &Advancement) -> Vec<Finding> {
+    let mut findings = match template {
+        SystemTemplate::GameStats(t) => check_stats(&t.stats, t.points_per_level, &t.gains_per_level, step),
+        SystemTemplate::Cultivation(t) => {
+            let ranks: Vec<(&str, i64)> = t.realms.iter().map(|r| (r.name.as_str(), r.stages)).collect();
+            let rank = |sheet: &ProgressionSheet| sheet.realm.clone();
+            check_ranks("realm", &ranks, rank, step)
+        }
+        SystemTemplate::MagicTiers(t) => {
+            let ranks: Vec<(&str, i64)> = t.tiers.iter().map(|t| (t.name.as_str(), 1)).collect();
+            let rank = |sheet: &ProgressionSheet| sheet.tier.clone();
+            check_ranks("tier", &ranks, rank, step)
+        }
+        SystemTemplate::SkillTrees(t) => check_skills(t, step),
+        SystemTemplate::Custom(t) => {
+            let ranks: Vec<(&str, i64)> = t.tiers.iter().map(|t| (t.name.as_str(), 1)).collect();
+            let rank = |sheet: &ProgressionSheet| sheet.tier.clone();
+            let mut findings = check_ranks("tier", &ranks, rank, step);
+            findings.extend(check_stats(&t.attributes, t.points_per_level, &t.gains_per_level, step));
+            findings.extend(check_requirements(t, step));
+            findings
+        }
+    };
+    findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+    findings
+}
+
+/// Realms or tiers must be climbed one at a time, and a realm's stages
+/// finished before breaking through to the next
+fn check_ranks(
+    kind: &str,
+    ranks: &[(&str, i64)],
+    rank: impl Fn(&ProgressionSheet) -> Option<String>,
+    step: &Advancement,
+) -> Vec<Finding> {
+    let Some(previous) = step.previous else { return Vec::new() };
+    let (Some(before), Some(after)) = (rank(previous), rank(step.current)) else { return Vec::new() };
+    let index = |name: &str| ranks.iter().position(|(rank, _)| *rank == name);
+    let (Some(from), Some(to)) = (index(&before), index(&after)) else { return Vec::new() };
+    let details = json!({"previous": before, "current": after, "previousStage": previous.stage});
+
+    if to > from + 1 {
+        let skipped: Vec<&str> = ranks[from + 1..to].iter().map(|(name, _)| *name).collect();
+        return vec![step.finding(
+            AlertType::WorldRuleViolation,
+            AlertSeverity::High,
+            format!(
+                "{} advances from {} to {} in {}, skipping the {} {}",
+                step.character,
+                before,
+                after,
+                step.label,
+                kind,
+                skipped.join(", ")
+            ),
+            details,
+            format!("Show {} passing through {} first, or record the {} they actually reach", step.character, skipped.join(", "), kind),
+        )];
+    }
+    if to < from {
+        return vec![step.finding(
+            AlertType::CharacterStateConflict,
+            AlertSeverity::Medium,
+            format!("{} falls from {} back to {} in {}", step.character, before, after, step.label),
+            details,
+            "If the setback is deliberate, dismiss this alert; otherwise correct the sheet".to_string(),
+        )];
+    }
+    let stages = ranks[from].1;
+    match previous.stage {
+        Some(stage) if to == from + 1 && stage < stages => vec![step.finding(
+            AlertType::WorldRuleViolation,
+            AlertSeverity::Medium,
+            format!(
+                "{} breaks through to {} in {} from stage {} of {}, before reaching its peak (stage {})",
+                step.character, after, step.label, stage, before, stages
+            ),
+            details,
+            format!("Show {} reaching the peak of {} first, or explain the early breakthrough", step.character, before),
+        )],
+        _ => Vec::new(),
+    }
+}
+
+/// Stat increases must be paid for by levels gained: each level brings the
+/// automatic gains plus a number of free points to distribute. A stat not
+/// recorded before, even on the first sheet, is measured from its starting value.
+/// Sums saturate: stored sheets and templates may predate the bounds on their values
+fn check_stats(
+    defined: &[StatDefinition],
+    points_per_level: i64,
+    gains_per_level: &BTreeMap<String, i64>,
+    step: &Advancement,
+) -> Vec<Finding> {
+    let previous = step.previous;
+    let starting = |name: &str| defined.iter().find(|stat| stat.name == name).map(|stat| stat.starting);
+    let (before, after) = (previous.and_then(|p| p.level).unwrap_or(1), step.current.level.unwrap_or(1));
+    let gained = after.saturating_sub(before);
+    let mut findings = Vec::new();
+
+    if gained < 0 {
+        findings.push(step.finding(
+            AlertType::CharacterStateConflict,
+            AlertSeverity::Medium,
+            format!("{} drops from level {} to {} in {}", step.character, before, after, step.label),
+            json!({"previousLevel": before, "currentLevel": after}),
+            "If the level loss is deliberate, dismiss this alert; otherwise correct the sheet".to_string(),
+        ));
+        return findings;
+    }
+
+    let mut spent: i64 = 0;
+    let mut increases = Vec::new();
+    for (name, value) in &step.current.stats {
+        let Some(old) = previous.and_then(|p| p.stats.get(name).copied()).or_else(|| starting(name)) else { continue };
+        let automatic = gains_per_level.get(name).copied().unwrap_or(0).saturating_mul(gained);
+        let increase = value.saturating_sub(old);
+        if increase > 0 {
+            increases.push(format!("{} +{}", name, increase));
+        }
+        spent = increase.saturating_sub(automatic).max(0).saturating_add(spent);
+        if increase < automatic {
+            findings.push(step.finding(
+                AlertType::WorldRuleViolation,
+                AlertSeverity::Low,
+                format!(
+                    "{}'s {} rises by {} in {}, but {} level(s) gained should add {}",
+                    step.character, name, increase.max(0), step.label, gained, automatic
+                ),
+                json!({"stat": name, "previous": old, "current": value, "levelsGained": gained, "automaticGain": automatic}),
+                format!("Raise {} to at least {}, or explain what held it back", name, old.saturating_add(automatic)),
+            ));
+        }
+    }
+
+    let allowance = points_per_level.saturating_mul(gained);
+    if spent > allowance {
+        findings.push(step.finding(
+            AlertType::WorldRuleViolation,
+            AlertSeverity::Medium,
+            format!(
+                "{} gains {} free stat point(s) in {} ({}), but {} level(s) gained allow {}",
+                step.character,
+                spent,
+                step.label,
+                increases.join(", "),
+                gained,
+                allowance
+            ),
+            json!({
+                "previousLevel": before,
+                "currentLevel": after,
+                "pointsSpent": spent,
+                "pointsAllowed": allowance,
+                "previousStats": previous.map(|p| &p.stats),
+                "currentStats": step.current.stats
+            }),
+            "Record the level-ups that pay for these stats, or attribute the extra points to an item or title in the sheet notes"
+                .to_string(),
+        ));
+    }
+    findings
+}
+
+/// A character reaching a tier of a custom system must meet its requirements
+fn check_requirements(template: &CustomTemplate, step: &Advancement) -> Vec<Finding> {
+    let Some(tier) = &step.current.tier else { return Vec::new() };
+    if step.previous.is_some_and(|p| p.tier.as_ref() == Some(tier)) {
+        return Vec::new();
+    }
+    let unmet = template.unmet_requirements(tier, step.current);
+    if unmet.is_empty() {
+        return Vec::new();
+    }
+    vec![step.finding(
+        AlertType::WorldRuleViolation,
+        AlertSeverity::Medium,
+        format!(
+            "{} reaches {} in {} without meeting its requirement(s) {}",
+            step.character,
+            tier,
+            step.label,
+            unmet.join(", ")
+        ),
+        json!({"tier": tier, "unmetRequirements": unmet, "level": step.current.level, "stats": step.current.stats}),
+        format!("Record the progress that meets {}'s requirements first, or move the advancement later", tier),
+    )]
+}
+
+/// Newly unlocked skills need their prerequisites and, when set, a minimum level
+fn check_skills(template: &SkillTreeTemplate, step: &Advancement) -> Vec<Finding> {
+    let Some(skills) = &step.current.skills else { return Vec::new() };
+    let had = step.previous.and_then(|p| p.skills.as_ref());
+    let mut findings = Vec::new();
+
+    for name in skills.iter().filter(|name| !had.is_some_and(|had| had.contains(name))) {
+        let Some(skill) = template.skills.iter().find(|s| &s.name == name) else { continue };
+        let missing: Vec<&str> = skill
+            .prerequisites
+            .iter()
+            .filter(|p| !skills.contains(p))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            findings.push(step.finding(
+                AlertType::WorldRuleViolation,
+                AlertSeverity::High,
+                format!(
+                    "{} unlocks {} in {} without its prerequisite(s) {}",
+                    step.character,
+                    name,
+                    step.label,
+                    missing.join(", ")
+                ),
+                json!({"skill": name, "requirement": "prerequisites", "missingPrerequisites": missing, "skills": skills}),
+                format!("Have {} unlock {} first, or move {} later", step.character, missing.join(", "), name),
+            ));
+        }
+        if let (Some(required), Some(level)) = (skill.min_level, step.current.level) {
+            if level < required {
+                findings.push(step.finding(
+                    AlertType::WorldRuleViolation,
+                    AlertSeverity::Medium,
+                    format!(
+                        "{} unlocks {} in {} at level {}, below its required level {}",
+                        step.character, name, step.label, level, required
+                    ),
+                    json!({"skill": name, "requirement": "minLevel", "level": level, "minLevel": required}),
+                    format!("Raise {} to level {} first, or move {} later", step.character, required, name),
+                ));
+            }
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SystemType;
+
+    fn sheet(value: Value) -> ProgressionSheet {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn check(system_type: SystemType, previous: Option<Value>, current: Value) -> Vec<Finding> {
+        let template = SystemTemplate::builtin(system_type).unwrap();
+        let previous = previous.map(sheet);
+        let current = sheet(current);
+        check_advancement(
+            &template,
+            &Advancement {
+                system_id: "system",
+                character_id: "kai",
+                character: "Kai",
+                label: "chapter 2 scene 1",
+                previous: previous.as_ref(),
+                current: &current,
+            },
+        )
+    }
+
+    #[test]
+    fn test_cultivation_realms() {
+        let realm = |realm: &str, stage: i64| json!({"realm": realm, "stage": stage});
+        assert!(check(SystemType::Cultivation, Some(realm("Qi Condensation", 9)), realm("Foundation Establishment", 1)).is_empty());
+        assert!(check(SystemType::Cultivation, None, realm("Core Formation", 1)).is_empty());
+
+        let skipped = check(SystemType::Cultivation, Some(realm("Qi Condensation", 9)), realm("Core Formation", 1));
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].severity, AlertSeverity::High);
+        assert!(skipped[0].description.contains("skipping the realm Foundation Establishment"));
+        assert_eq!(skipped[0].conflicting_elements["characterId"], "kai");
+
+        let early = check(SystemType::Cultivation, Some(realm("Qi Condensation", 4)), realm("Foundation Establishment", 1));
+        assert_eq!(early[0].severity, AlertSeverity::Medium);
+        assert!(early[0].description.contains("before reaching its peak (stage 9)"));
+
+        let fallen = check(SystemType::Cultivation, Some(realm("Core Formation", 1)), realm("Qi Condensation", 9));
+        assert_eq!(fallen[0].alert_type, AlertType::CharacterStateConflict);
+
+        let tiers = check(SystemType::MagicTiers, Some(json!({"tier": "Novice"})), json!({"tier": "Adept"}));
+        assert!(tiers[0].description.contains("skipping the tier Apprentice"));
+    }
+
+    #[test]
+    fn test_stat_gains_follow_levels() {
+        let stats = |level: i64, str: i64, agi: i64| json!({"level": level, "stats": {"STR": str, "AGI": agi}});
+        // Five points per level in the built-in template
+        assert!(check(SystemType::GameStats, Some(stats(1, 5, 5)), stats(2, 8, 7)).is_empty());
+        assert!(check(SystemType::GameStats, Some(stats(1, 5, 5)), stats(3, 15, 5)).is_empty());
+
+        let over = check(SystemType::GameStats, Some(stats(1, 5, 5)), stats(2, 9, 8));
+        assert_eq!(over.len(), 1);
+        assert!(over[0].description.contains("gains 7 free stat point(s)"));
+        assert_eq!(over[0].conflicting_elements["pointsAllowed"], 5);
+
+        let unearned = check(SystemType::GameStats, Some(stats(4, 5, 5)), stats(4, 6, 5));
+        assert_eq!(unearned[0].conflicting_elements["pointsAllowed"], 0);
+
+        let dropped = check(SystemType::GameStats, Some(stats(4, 5, 5)), stats(3, 5, 5));
+        assert_eq!(dropped[0].alert_type, AlertType::CharacterStateConflict);
+
+        // Automatic gains are owed as well as limited
+        let template = SystemTemplate::from_value(
+            SystemType::GameStats,
+            json!({"stats": [{"name": "VIT"}, {"name": "STR"}], "pointsPerLevel": 1, "gainsPerLevel": {"VIT": 2}}),
+        )
+        .unwrap();
+        let (before, after) = (sheet(json!({"level": 1, "stats": {"VIT": 5, "STR": 5}})), sheet(json!({"level": 2, "stats": {"VIT": 5, "STR": 6}})));
+        let step = Advancement {
+            system_id: "system",
+            character_id: "kai",
+            character: "Kai",
+            label: "chapter 2 scene 1",
+            previous: Some(&before),
+            current: &after,
+        };
+        let findings = check_advancement(&template, &step);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, AlertSeverity::Low);
+        assert!(findings[0].description.contains("VIT rises by 0"));
+
+        // A stat recorded late, or on the first sheet, counts from its starting value of 5
+        let late = check(SystemType::GameStats, Some(json!({"level": 4, "stats": {"STR": 5}})), stats(4, 5, 99));
+        assert_eq!(late.len(), 1);
+        assert!(late[0].description.contains("gains 94 free stat point(s)"));
+        assert!(check(SystemType::GameStats, None, stats(3, 10, 10)).is_empty());
+        let first = check(SystemType::GameStats, None, stats(1, 5, 40));
+        assert_eq!(first[0].conflicting_elements["pointsSpent"], 35);
+
+        // Values stored before sheets were bounded saturate instead of overflowing
+        let extreme = check(SystemType::GameStats, Some(stats(1, i64::MIN, 5)), stats(1, i64::MAX, i64::MIN));
+        let budget = extreme.iter().find(|f| f.conflicting_elements.get("pointsSpent").is_some()).unwrap();
+        assert_eq!(budget.conflicting_elements["pointsSpent"], i64::MAX);
+        assert!(check(SystemType::GameStats, Some(stats(i64::MAX, 5, 5)), stats(1, i64::MIN, 5))[0]
+            .description
+            .contains("drops from level"));
+    }
+
+    #[test]
+    fn test_custom_tier_requirements() {
+        let template = SystemTemplate::from_value(
+            SystemType::Custom,
+            json!({
+                "attributes": [{"name": "vitality", "starting": 5}],
+                "formulas": ["max_hp = 10 * vitality + level * 5"],
+                "pointsPerLevel": 2,
+                "tiers": [{"name": "E-Rank"}, {"name": "D-Rank", "requirements": ["level >= 10", "max_hp >= 150"]}, {"name": "C-Rank"}]
+            }),
+        )
+        .unwrap();
+        let check = |previous: Value, current: Value| {
+            let (previous, current) = (sheet(previous), sheet(current));
+            check_advancement(
+                &template,
+                &Advancement {
+                    system_id: "system",
+                    character_id: "kai",
+                    character: "Kai",
+                    label: "chapter 2 scene 1",
+                    previous: Some(&previous),
+                    current: &current,
+                },
+            )
+        };
+
+        let early = check(json!({"tier": "E-Rank", "level": 8}), json!({"tier": "D-Rank", "level": 9}));
+        assert_eq!(early.len(), 1);
+        assert!(early[0].description.contains("without meeting its requirement(s) level >= 10, max_hp >= 150"));
+
+        let ready = check(
+            json!({"tier": "E-Rank", "level": 9, "stats": {"vitality": 10}}),
+            json!({"tier": "D-Rank", "level": 10, "stats": {"vitality": 10}}),
+        );
+        assert!(ready.is_empty());
+
+        let skipped = check(json!({"tier": "E-Rank", "level": 9}), json!({"tier": "C-Rank", "level": 10, "stats": {}}));
+        assert_eq!(skipped[0].severity, AlertSeverity::High);
+
+        let greedy = check(json!({"level": 3, "stats": {"vitality": 5}}), json!({"level": 4, "stats": {"vitality": 9}}));
+        assert_eq!(greedy[0].conflicting_elements["pointsAllowed"], 2);
+    }
+
+    #[test]
+    fn test_skill_prerequisites() {
+        let skills = |level: i64, skills: &[&str]| json!({"level": level, "skills": skills});
+        assert!(check(SystemType::SkillTrees, Some(skills(5, &["Basic Strike"])), skills(5, &["Basic Strike", "Power Strike"])).is_empty());
+
+        let missing = check(SystemType::SkillTrees, Some(skills(12, &["Basic Strike"])), skills(12, &["Basic Strike", "Whirlwind"]));
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].severity, AlertSeverity::High);
+        assert!(missing[0].description.contains("without its prerequisite(s) Power Strike"));
+
+        // A first sheet is checked too, and unlocked skills are only checked once
+        let early = check(SystemType::SkillTrees, None, skills(3, &["Basic Strike", "Power Strike"]));
+        assert!(early[0].description.contains("below its required level 5"));
+        assert!(check(SystemType::SkillTrees, Some(skills(3, &["Basic Strike", "Power Strike"])), skills(3, &["Basic Strike", "Power Strike"])).is_empty());
+    }
+}
@@ -0,0 +1,788 @@
+use super::parse_params;
+use crate::error::{Result, StoryError};
+use crate::models::arc::arc_progress;
+use crate::models::{ArcMilestone, ArcStatus, CharacterArc};
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// A milestone listed when creating an arc
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MilestoneInput {
+    pub description: String,
+    /// Number of the chapter it should happen by
+    #[schemars(range(min = 1))]
+    pub target_chapter: Option<i32>,
+}
+
+/// Parameters for `createCharacterArc`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CreateCharacterArcParams {
+    pub character_id: Uuid,
+    #[schemars(length(max = 100))]
+    pub arc_name: String,
+    /// Who the character is at the start of the arc
+    pub start_state: String,
+    /// Who they become by the end
+    pub end_state: String,
+    /// Milestones in the order they should happen
+    pub milestones: Option<Vec<MilestoneInput>>,
+}
+
+/// Parameters for `addArcMilestone`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AddArcMilestoneParams {
+    pub arc_id: Uuid,
+    pub description: String,
+    /// Number of the chapter it should happen by
+    #[schemars(range(min = 1))]
+    pub target_chapter: Option<i32>,
+    /// Where it falls among the arc's milestones, starting at 1; defaults to last
+    #[schemars(range(min = 1))]
+    pub position: Option<i32>,
+}
+
+/// Parameters for `linkMilestoneScene` and `unlinkMilestoneScene`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MilestoneSceneParams {
+    pub milestone_id: Uuid,
+    pub scene_id: Uuid,
+}
+
+/// Parameters for `getCharacterArc`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GetCharacterArcParams {
+    pub arc_id: Uuid,
+}
+
+/// Parameters for `listCharacterArcs`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ListCharacterArcsParams {
+    pub project_id: Uuid,
+    /// Only this character's arcs
+    pub character_id: Option<Uuid>,
+}
+
+/// Parameters for `getArcScheduleReport`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GetArcScheduleReportParams {
+    pub project_id: Uuid,
+    /// Chapter being drafted; defaults to the last chapter with written scenes
+    #[schemars(range(min = 1))]
+    pub current_chapter: Option<i32>,
+}
+
+/// Create a character arc, optionally with its milestones
+pub fn create_character_arc(conn: &Connection, params: Value) -> Result<Value> {
+    let params: CreateCharacterArcParams = parse_params(params)?;
+    if params.arc_name.trim().is_empty() || params.arc_name.chars().count() > 100 {
+        return Err(StoryError::validation("Arc name must be 1 to 100 characters").with_field("arcName"));
+    }
+    let milestones = params.milestones.unwrap_or_default();
+    for milestone in &milestones {
+        check_milestone(&milestone.description, milestone.target_chapter)?;
+    }
+
+    let project_id: String = conn
+        .query_row("SELECT story_project_id FROM characters WHERE id = ?1", [params.character_id.to_string()], |row| {
+            row.get(0)
+        })
+        .optional()?
+        .ok_or_else(|| {
+            StoryError::not_found(format!("Character not found: {}", params.character_id))
+                .with_field("characterId")
+                .with_entity("character", params.character_id)
+        })?;
+
+    let arc = CharacterArc {
+        id: Uuid::new_v4(),
+        story_project_id: Uuid::parse_str(&project_id).map_err(|e| StoryError::validation(e.to_string()))?,
+        character_id: params.character_id,
+        arc_name: params.arc_name,
+        start_state: params.start_state,
+        end_state: params.end_state,
+        current_progress: 0,
+        status: ArcStatus::Planned,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO character_arcs (id, story_project_id, character_id, arc_name, start_state, end_state,
+             current_progress, status, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        (
+            arc.id.to_string(),
+            arc.story_project_id.to_string(),
+            arc.character_id.to_string(),
+            &arc.arc_name,
+            &arc.start_state,
+            &arc.end_state,
+            arc.current_progress,
+            arc.status.to_string(),
+            arc.created_at.to_rfc3339(),
+            arc.updated_at.to_rfc3339(),
+        ),
+    )?;
+    for (i, milestone) in milestones.iter().enumerate() {
+        insert_milestone(&tx, arc.id, i as i32 + 1, &milestone.description, milestone.target_chapter)?;
+    }
+    tx.commit()?;
+
+    log::info!("Created arc '{}' ({}) with {} milestone(s)", arc.arc_name, arc.id, milestones.len());
+
+    load_arc(conn, &arc.id.to_string())
+}
+
+/// Add a milestone to an arc, after the others or at a given position
+pub fn add_arc_milestone(conn: &Connection, params: Value) -> Result<Value> {
+    let params: AddArcMilestoneParams = parse_params(params)?;
+    let arc_id = params.arc_id.to_string();
+    check_milestone(&params.description, params.target_chapter)?;
+
+    let count: i32 = conn.query_row("SELECT COUNT(*) FROM arc_milestones WHERE character_arc_id = ?1", [&arc_id], |row| {
+        row.get(0)
+    })?;
+    ensure_arc(conn, &arc_id)?;
+
+    let position = params.position.unwrap_or(count + 1);
+    if !(1..=count + 1).contains(&position) {
+        return Err(StoryError::validation(format!("Position must be between 1 and {}", count + 1)).with_field("position"));
+    }
+
+    // Make room, going through negative positions so the unique index never sees a clash
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "UPDATE arc_milestones SET position = -(position + 1) WHERE character_arc_id = ?1 AND position >= ?2",
+        (&arc_id, position),
+    )?;
+    tx.execute(
+        "UPDATE arc_milestones SET position = -position WHERE character_arc_id = ?1 AND position < 0",
+        [&arc_id],
+    )?;
+    let milestone = insert_milestone(&tx, params.arc_id, position, &params.description, params.target_chapter)?;
+    recompute_progress(&tx, &arc_id)?;
+    tx.commit()?;
+
+    log::info!("Added milestone {} to arc {} at position {}", milestone.id, arc_id, position);
+
+    load_arc(conn, &arc_id)
+}
+
+/// Record that a milestone happens in a scene, completing it
+pub fn link_milestone_scene(conn: &Connection, params: Value) -> Result<Value> {
+    let params: MilestoneSceneParams = parse_params(params)?;
+    let (milestone_id, scene_id) = (params.milestone_id.to_string(), params.scene_id.to_string());
+    let arc_id = milestone_arc(conn, &milestone_id)?;
+
+    let same_project: Option<bool> = conn
+        .query_row(
+            "SELECT ps.story_project_id = ca.story_project_id
+             FROM scenes s
+             JOIN chapters c ON s.chapter_id = c.id
+             JOIN acts a ON c.act_id = a.id
+             JOIN plot_structures ps ON a.plot_structure_id = ps.id
+             JOIN character_arcs ca ON ca.id = ?2
+             WHERE s.id = ?1",
+            (&scene_id, &arc_id),
+            |row| row.get(0),
+        )
+        .optional()?;
+    match same_project {
+        None => {
+            return Err(StoryError::not_found(format!("Scene not found: {}", scene_id))
+                .with_field("sceneId")
+                .with_entity("scene", &scene_id))
+        }
+        Some(false) => {
+            return Err(StoryError::validation("Scene belongs to a different project than the arc")
+                .with_field("sceneId")
+                .with_entity("scene", &scene_id))
+        }
+        Some(true) => {}
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT OR IGNORE INTO scene_milestones (id, scene_id, arc_milestone_id) VALUES (?1, ?2, ?3)",
+        (Uuid::new_v4().to_string(), &scene_id, &milestone_id),
+    )?;
+    refresh_milestone(&tx, &milestone_id)?;
+    recompute_progress(&tx, &arc_id)?;
+    tx.commit()?;
+
+    log::info!("Linked milestone {} to scene {}", milestone_id, scene_id);
+
+    load_arc(conn, &arc_id)
+}
+
+/// Remove a milestone's link to a scene; with no scenes left it is no longer complete
+pub fn unlink_milestone_scene(conn: &Connection, params: Value) -> Result<Value> {
+    let params: MilestoneSceneParams = parse_params(params)?;
+    let (milestone_id, scene_id) = (params.milestone_id.to_string(), params.scene_id.to_string());
+    let arc_id = milestone_arc(conn, &milestone_id)?;
+
+    let tx = conn.unchecked_transaction()?;
+    let removed = tx.execute(
+        "DELETE FROM scene_milestones WHERE scene_id = ?1 AND arc_milestone_id = ?2",
+        (&scene_id, &milestone_id),
+    )?;
+    if removed == 0 {
+        return Err(StoryError::not_found("Milestone is not linked to this scene")
+            .with_field("sceneId")
+            .with_entity("scene", &scene_id));
+    }
+    refresh_milestone(&tx, &milestone_id)?;
+    recompute_progress(&tx, &arc_id)?;
+    tx.commit()?;
+
+    log::info!("Unlinked milestone {} from scene {}", milestone_id, scene_id);
+
+    load_arc(conn, &arc_id)
+}
+
+/// Get an arc with its milestones and the scenes where they happen
+pub fn get_character_arc(conn: &Connection, params: Value) -> Result<Value> {
+    let params: GetCharacterArcParams = parse_params(params)?;
+    load_arc(conn, &params.arc_id.to_string())
+}
+
+/// List a project's arcs, optionally for one character
+pub fn list_character_arcs(conn: &Connection, params: Value) -> Result<Value> {
+    let params: ListCharacterArcsParams = parse_params(params)?;
+    let project_id = params.project_id.to_string();
+    ensure_project(conn, &project_id)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT ca.id FROM character_arcs ca
+         JOIN characters ch ON ca.character_id = ch.id
+         WHERE ca.story_project_id = ?1 AND (?2 IS NULL OR ca.character_id = ?2)
+         ORDER BY ch.name, ca.created_at",
+    )?;
+    let ids = stmt
+        .query_map((&project_id, params.character_id.map(|id| id.to_string())), |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let arcs = ids.iter().map(|id| load_arc(conn, id)).collect::<Result<Vec<_>>>()?;
+
+    log::info!("Listed {} arcs for project {}", arcs.len(), project_id);
+
+    Ok(json!({
+        "projectId": project_id,
+        "arcs": arcs
+    }))
+}
+
+/// Arcs with milestones that should have happened before the chapter being drafted
+pub fn get_arc_schedule_report(conn: &Connection, params: Value) -> Result<Value> {
+    let params: GetArcScheduleReportParams = parse_params(params)?;
+    let project_id = params.project_id.to_string();
+    ensure_project(conn, &project_id)?;
+
+    let (current_chapter, source) = match params.current_chapter {
+        Some(chapter) => (chapter, "given"),
+        None => {
+            let latest: Option<i32> = conn.query_row(
+                "SELECT MAX(c.number)
+                 FROM scenes s
+                 JOIN chapters c ON s.chapter_id = c.id
+                 JOIN acts a ON c.act_id = a.id
+                 JOIN plot_structures ps ON a.plot_structure_id = ps.id
+                 WHERE ps.story_project_id = ?1 AND TRIM(s.content) != ''",
+                [&project_id],
+                |row| row.get(0),
+            )?;
+            let chapter = latest.ok_or_else(|| {
+                StoryError::validation("No scenes have been written yet; pass currentChapter").with_field("currentChapter")
+            })?;
+            (chapter, "latest_written")
+        }
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT ca.id FROM character_arcs ca
+         JOIN characters ch ON ca.character_id = ch.id
+         WHERE ca.story_project_id = ?1 AND ca.status IN ('planned', 'in_progress')
+         ORDER BY ch.name, ca.created_at",
+    )?;
+    let ids = stmt
+        .query_map([&project_id], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut behind = Vec::new();
+    let mut due = Vec::new();
+    let mut on_schedule = 0;
+    for id in &ids {
+        let arc = load_arc(conn, id)?;
+        let milestones = arc["milestones"].as_array().cloned().unwrap_or_default();
+        let open_targeting = |keep: fn(i64, i64) -> bool| -> Vec<Value> {
+            milestones
+                .iter()
+                .filter(|m| m["completed"] == false)
+                .filter(|m| m["targetChapter"].as_i64().is_some_and(|t| keep(t, current_chapter as i64)))
+                .cloned()
+                .collect()
+        };
+        let overdue = open_targeting(|target, current| target < current);
+        let due_now = open_targeting(|target, current| target == current);
+        let expected = milestones
+            .iter()
+            .filter(|m| m["targetChapter"].as_i64().is_some_and(|t| t < current_chapter as i64))
+            .count();
+
+        let summary = |milestones: Vec<Value>| {
+            json!({
+                "arcId": arc["arcId"],
+                "arcName": arc["arcName"],
+                "characterId": arc["characterId"],
+                "characterName": arc["characterName"],
+                "currentProgress": arc["currentProgress"],
+                "expectedProgress": arc_progress(expected, milestones_len(&arc)),
+                "milestones": milestones
+            })
+        };
+        if !due_now.is_empty() {
+            due.push(summary(due_now));
+        }
+        if overdue.is_empty() {
+            on_schedule += 1;
+        } else {
+            behind.push(summary(overdue));
+        }
+    }
+
+    log::info!(
+        "Arc schedule for project {} at chapter {}: {} behind, {} on schedule",
+        project_id,
+        current_chapter,
+        behind.len(),
+        on_schedule
+    );
+
+    Ok(json!({
+        "projectId": project_id,
+        "currentChapter": current_chapter,
+        "currentChapterSource": source,
+        "behindSchedule": behind,
+        "dueThisChapter": due,
+        "onScheduleCount": on_schedule
+    }))
+}
+
+fn milestones_len(arc: &Value) -> usize {
+    arc["milestones"].as_array().map_or(0, Vec::len)
+}
+
+fn check_milestone(description: &str, target_chapter: Option<i32>) -> Result<()> {
+    if description.trim().is_empty() {
+        return Err(StoryError::validation("Milestone description must not be empty").with_field("description"));
+    }
+    if target_chapter.is_some_and(|chapter| chapter < 1) {
+        return Err(StoryError::validation("Target chapter must be 1 or more").with_field("targetChapter"));
+    }
+    Ok(())
+}
+
+fn insert_milestone(
+    conn: &Connection,
+    arc_id: Uuid,
+    position: i32,
+    description: &str,
+    target_chapter: Option<i32>,
+) -> Result<ArcMilestone> {
+    let milestone = ArcMilestone {
+        id: Uuid::new_v4(),
+        character_arc_id: arc_id,
+        position,
+        description: description.to_string(),
+        target_chapter,
+        completed: false,
+        completed_at_scene_id: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    conn.execute(
+        "INSERT INTO arc_milestones (id, character_arc_id, position, description, target_chapter, completed,
+             created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        (
+            milestone.id.to_string(),
+            milestone.character_arc_id.to_string(),
+            milestone.position,
+            &milestone.description,
+            milestone.target_chapter,
+            milestone.completed,
+            milestone.created_at.to_rfc3339(),
+            milestone.updated_at.to_rfc3339(),
+        ),
+    )?;
+    Ok(milestone)
+}
+
+/// A milestone is complete when linked to any scene, at the earliest of them in manuscript order
+fn refresh_milestone(conn: &Connection, milestone_id: &str) -> Result<()> {
+    let first_scene: Option<String> = conn
+        .query_row(
+            "SELECT sm.scene_id
+             FROM scene_milestones sm
+             JOIN scenes s ON sm.scene_id = s.id
+             JOIN chapters c ON s.chapter_id = c.id
+             JOIN acts a ON c.act_id = a.id
+             WHERE sm.arc_milestone_id = ?1
+             ORDER BY a.position, c.number, s.position
+             LIMIT 1",
+            [milestone_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    conn.execute(
+        "UPDATE arc_milestones SET completed = ?2, completed_at_scene_id = ?3, updated_at = ?4 WHERE id = ?1",
+        (milestone_id, first_scene.is_some(), &first_scene, Utc::now().to_rfc3339()),
+    )?;
+    Ok(())
+}
+
+/// Set an arc's progress from its completed milestones, and its status to match
+fn recompute_progress(conn: &Connection, arc_id: &str) -> Result<()> {
+    let (total, completed, status): (usize, usize, String) = conn.query_row(
+        "SELECT COUNT(m.id), COALESCE(SUM(m.completed), 0), ca.status
+         FROM character_arcs ca LEFT JOIN arc_milestones m ON m.character_arc_id = ca.id
+         WHERE ca.id = ?1",
+        [arc_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    let progress = arc_progress(completed, total);
+    let status = ArcStatus::from_str(&status).unwrap_or(ArcStatus::Planned).after_progress(progress);
+    conn.execute(
+        "UPDATE character_arcs SET current_progress = ?2, status = ?3, updated_at = ?4 WHERE id = ?1",
+        (arc_id, progress, status.to_string(), Utc::now().to_rfc3339()),
+    )?;
+    Ok(())
+}
+
+fn ensure_project(conn: &Connection, project_id: &str) -> Result<()> {
+    let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM story_projects WHERE id = ?1)", [project_id], |row| {
+        row.get(0)
+    })?;
+    if !exists {
+        return Err(StoryError::not_found(format!("Project not found: {}", project_id))
+            .with_field("projectId")
+            .with_entity("project", project_id));
+    }
+    Ok(())
+}
+
+fn ensure_arc(conn: &Connection, arc_id: &str) -> Result<()> {
+    let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM character_arcs WHERE id = ?1)", [arc_id], |row| {
+        row.get(0)
+    })?;
+    if !exists {
+        return Err(arc_not_found(arc_id));
+    }
+    Ok(())
+}
+
+fn arc_not_found(arc_id: &str) -> StoryError {
+    StoryError::not_found(format!("Character arc not found: {}", arc_id))
+        .with_field("arcId")
+        .with_entity("character_arc", arc_id)
+}
+
+fn milestone_arc(conn: &Connection, milestone_id: &str) -> Result<String> {
+    conn.query_row("SELECT character_arc_id FROM arc_milestones WHERE id = ?1", [milestone_id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| {
+            StoryError::not_found(format!("Milestone not found: {}", milestone_id))
+                .with_field("milestoneId")
+                .with_entity("arc_milestone", milestone_id)
+        })
+}
+
+fn load_arc(conn: &Connection, arc_id: &str) -> Result<Value> {
+    let mut arc = conn
+        .query_row(
+            "SELECT ca.id, ca.character_id, ch.name, ca.arc_name, ca.start_state, ca.end_state,
+                    ca.current_progress, ca.status, ca.created_at, ca.updated_at
+             FROM character_arcs ca JOIN characters ch ON ca.character_id = ch.id
+             WHERE ca.id = ?1",
+            [arc_id],
+            |row| {
+                Ok(json!({
+                    "arcId": row.get::<_, String>(0)?,
+                    "characterId": row.get::<_, String>(1)?,
+                    "characterName": row.get::<_, String>(2)?,
+                    "arcName": row.get::<_, String>(3)?,
+                    "startState": row.get::<_, String>(4)?,
+                    "endState": row.get::<_, String>(5)?,
+                    "currentProgress": row.get::<_, i32>(6)?,
+                    "status": row.get::<_, String>(7)?,
+                    "createdAt": row.get::<_, String>(8)?,
+                    "updatedAt": row.get::<_, String>(9)?
+                }))
+            },
+        )
+        .optional()?
+        .ok_or_else(|| arc_not_found(arc_id))?;
+
+    let mut stmt = conn.prepare(
+        "SELECT m.id, m.position, m.description, m.target_chapter, m.completed, m.completed_at_scene_id,
+                c.number, s.position
+         FROM arc_milestones m
+         LEFT JOIN scenes s ON m.completed_at_scene_id = s.id
+         LEFT JOIN chapters c ON s.chapter_id = c.id
+         WHERE m.character_arc_id = ?1
+         ORDER BY m.position",
+    )?;
+    let mut milestones = stmt
+        .query_map([arc_id], |row| {
+            let completed_at: Option<String> = row.get(5)?;
+            Ok(json!({
+                "milestoneId": row.get::<_, String>(0)?,
+                "position": row.get::<_, i32>(1)?,
+                "description": row.get::<_, String>(2)?,
+                "targetChapter": row.get::<_, Option<i32>>(3)?,
+                "completed": row.get::<_, bool>(4)?,
+                "completedAt": match completed_at {
+                    Some(scene_id) => json!({
+                        "sceneId": scene_id,
+                        "chapterNumber": row.get::<_, Option<i32>>(6)?,
+                        "scenePosition": row.get::<_, Option<i32>>(7)?
+                    }),
+                    None => Value::Null,
+                }
+            }))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut scenes = conn.prepare("SELECT scene_id FROM scene_milestones WHERE arc_milestone_id = ?1")?;
+    for milestone in &mut milestones {
+        let id = milestone["milestoneId"].as_str().unwrap_or_default().to_string();
+        let linked = scenes.query_map([&id], |row| row.get::<_, String>(0))?.collect::<rusqlite::Result<Vec<_>>>()?;
+        milestone["sceneIds"] = json!(linked);
+    }
+    arc["milestones"] = json!(milestones);
+    Ok(arc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::tools::{add_chapter, add_character, add_scene, create_story_project, initialize_plot_structure, update_scene};
+    use tempfile::{tempdir, TempDir};
+
+    struct Story {
+        project_id: String,
+        mira: String,
+        /// First scene of each of three chapters
+        scenes: Vec<String>,
+        stories: TempDir,
+    }
+
+    fn story(conn: &Connection) -> Story {
+        let stories = tempdir().unwrap();
+        let project = create_story_project(conn, stories.path(), json!({"title": "Arcs", "targetLength": "novel"})).unwrap();
+        let project_id = project["projectId"].as_str().unwrap().to_string();
+        let mira = add_character(conn, json!({"projectId": project_id, "name": "Mira", "role": "protagonist"}))
+            .unwrap()["characterId"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let plot = initialize_plot_structure(conn, json!({"projectId": project_id})).unwrap();
+        let scenes = (1..=3)
+            .map(|number| {
+                let chapter = add_chapter(conn, json!({"actId": plot["acts"][0]["actId"], "number": number})).unwrap();
+                add_scene(conn, stories.path(), json!({"chapterId": chapter["chapterId"]})).unwrap()["sceneId"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        Story { project_id, mira, scenes, stories }
+    }
+
+    fn create_arc(conn: &Connection, story: &Story) -> Value {
+        create_character_arc(
+            conn,
+            json!({
+                "characterId": story.mira,
+                "arcName": "From coward to captain",
+                "startState": "Hides from every fight",
+                "endState": "Leads the defense of the harbor",
+                "milestones": [
+                    {"description": "Refuses the call", "targetChapter": 1},
+                    {"description": "Saves Dax", "targetChapter": 2},
+                    {"description": "Takes command", "targetChapter": 3}
+                ]
+            }),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_linking_scenes_drives_progress() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn);
+        let arc = create_arc(&conn, &story);
+        assert_eq!(arc["status"], "planned");
+        assert_eq!(arc["milestones"].as_array().unwrap().len(), 3);
+        let milestone = |i: usize| arc["milestones"][i]["milestoneId"].clone();
+
+        let link = |milestone: Value, scene: &str| {
+            link_milestone_scene(&conn, json!({"milestoneId": milestone, "sceneId": scene})).unwrap()
+        };
+        let updated = link(milestone(0), &story.scenes[0]);
+        assert_eq!(updated["currentProgress"], 33);
+        assert_eq!(updated["status"], "in_progress");
+        assert_eq!(updated["milestones"][0]["completedAt"]["chapterNumber"], 1);
+
+        // Linking again changes nothing; a second scene keeps the earliest as the completion point
+        link(milestone(0), &story.scenes[0]);
+        let updated = link(milestone(0), &story.scenes[1]);
+        assert_eq!(updated["milestones"][0]["sceneIds"].as_array().unwrap().len(), 2);
+        assert_eq!(updated["milestones"][0]["completedAt"]["sceneId"], story.scenes[0]);
+
+        link(milestone(1), &story.scenes[1]);
+        let done = link(milestone(2), &story.scenes[2]);
+        assert_eq!(done["currentProgress"], 100);
+        assert_eq!(done["status"], "complete");
+
+        let undone = unlink_milestone_scene(&conn, json!({"milestoneId": milestone(2), "sceneId": story.scenes[2]})).unwrap();
+        assert_eq!(undone["currentProgress"], 66);
+        assert_eq!(undone["status"], "in_progress");
+        assert_eq!(undone["milestones"][2]["completed"], false);
+        assert!(undone["milestones"][2]["completedAt"].is_null());
+
+        let err = unlink_milestone_scene(&conn, json!({"milestoneId": milestone(2), "sceneId": story.scenes[2]})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+
+        let other = create_story_project(&conn, story.stories.path(), json!({"title": "Elsewhere", "targetLength": "novel"})).unwrap();
+        let plot = initialize_plot_structure(&conn, json!({"projectId": other["projectId"]})).unwrap();
+        let chapter = add_chapter(&conn, json!({"actId": plot["acts"][0]["actId"], "number": 1})).unwrap();
+        let stranger = add_scene(&conn, story.stories.path(), json!({"chapterId": chapter["chapterId"]})).unwrap();
+        let err = link_milestone_scene(&conn, json!({"milestoneId": milestone(2), "sceneId": stranger["sceneId"]})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+    }
+
+    #[test]
+    fn test_add_milestone_at_position() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn);
+        let arc = create_arc(&conn, &story);
+        let arc_id = arc["arcId"].clone();
+        link_milestone_scene(&conn, json!({"milestoneId": arc["milestones"][0]["milestoneId"], "sceneId": story.scenes[0]}))
+            .unwrap();
+
+        let updated = add_arc_milestone(
+            &conn,
+            json!({"arcId": arc_id, "description": "Loses her nerve again", "targetChapter": 2, "position": 2}),
+        )
+        .unwrap();
+        let descriptions: Vec<&str> = updated["milestones"].as_array().unwrap().iter().map(|m| m["description"].as_str().unwrap()).collect();
+        assert_eq!(descriptions, vec!["Refuses the call", "Loses her nerve again", "Saves Dax", "Takes command"]);
+        let positions: Vec<i64> = updated["milestones"].as_array().unwrap().iter().map(|m| m["position"].as_i64().unwrap()).collect();
+        assert_eq!(positions, vec![1, 2, 3, 4]);
+        assert_eq!(updated["currentProgress"], 25);
+
+        let appended = add_arc_milestone(&conn, json!({"arcId": arc_id, "description": "Epilogue"})).unwrap();
+        assert_eq!(appended["milestones"][4]["position"], 5);
+
+        let err = add_arc_milestone(&conn, json!({"arcId": arc_id, "description": "Too far", "position": 9})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+        let err = add_arc_milestone(&conn, json!({"arcId": Uuid::new_v4(), "description": "Lost"})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_failed_writes_leave_arcs_unchanged() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn);
+        let arc = create_arc(&conn, &story);
+        // Any milestone written at position 2 from here on fails
+        conn.execute_batch(
+            "CREATE TEMP TRIGGER fail_second BEFORE INSERT ON arc_milestones WHEN NEW.position = 2
+             BEGIN SELECT RAISE(ABORT, 'disk full'); END",
+        )
+        .unwrap();
+
+        // The arc is not left behind without its later milestones
+        let milestones = json!([{"description": "Doubts"}, {"description": "Decides"}]);
+        let params = json!({
+            "characterId": story.mira,
+            "arcName": "Second thoughts",
+            "startState": "Sure of herself",
+            "endState": "Sure of her crew",
+            "milestones": milestones
+        });
+        let err = create_character_arc(&conn, params).unwrap_err();
+        assert!(matches!(err, StoryError::DatabaseError(ref e) if e.to_string().contains("disk full")), "{}", err);
+        let arcs: i64 = conn.query_row("SELECT COUNT(*) FROM character_arcs", [], |row| row.get(0)).unwrap();
+        assert_eq!(arcs, 1);
+
+        // Nor are the milestones after the insertion point left shifted
+        let params = json!({"arcId": arc["arcId"], "description": "Loses her nerve again", "position": 2});
+        let err = add_arc_milestone(&conn, params).unwrap_err();
+        assert!(matches!(err, StoryError::DatabaseError(ref e) if e.to_string().contains("disk full")), "{}", err);
+        let reloaded = get_character_arc(&conn, json!({"arcId": arc["arcId"]})).unwrap();
+        assert_eq!(reloaded["milestones"], arc["milestones"]);
+    }
+
+    #[test]
+    fn test_arc_name_length_counts_characters() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn);
+        let params = |name: String| {
+            json!({"characterId": story.mira, "arcName": name, "startState": "Lost", "endState": "Found"})
+        };
+
+        // 100 characters, 200 bytes
+        create_character_arc(&conn, params("é".repeat(100))).unwrap();
+        let err = create_character_arc(&conn, params("é".repeat(101))).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+    }
+
+    #[test]
+    fn test_schedule_report() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn);
+        let arc = create_arc(&conn, &story);
+
+        // Nothing written yet, so the current chapter must be given
+        let err = get_arc_schedule_report(&conn, json!({"projectId": story.project_id})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+
+        // Drafting chapter 3 with only the first milestone done
+        link_milestone_scene(&conn, json!({"milestoneId": arc["milestones"][0]["milestoneId"], "sceneId": story.scenes[0]}))
+            .unwrap();
+        update_scene(&conn, story.stories.path(), json!({"sceneId": story.scenes[2], "content": "The harbor burned."})).unwrap();
+        let report = get_arc_schedule_report(&conn, json!({"projectId": story.project_id})).unwrap();
+        assert_eq!(report["currentChapter"], 3);
+        assert_eq!(report["currentChapterSource"], "latest_written");
+        let behind = report["behindSchedule"].as_array().unwrap();
+        assert_eq!(behind.len(), 1);
+        assert_eq!(behind[0]["characterName"], "Mira");
+        assert_eq!(behind[0]["currentProgress"], 33);
+        assert_eq!(behind[0]["expectedProgress"], 66);
+        assert_eq!(behind[0]["milestones"][0]["description"], "Saves Dax");
+        assert_eq!(report["dueThisChapter"][0]["milestones"][0]["description"], "Takes command");
+
+        // Back at chapter 2 the arc is on schedule
+        let report = get_arc_schedule_report(&conn, json!({"projectId": story.project_id, "currentChapter": 2})).unwrap();
+        assert!(report["behindSchedule"].as_array().unwrap().is_empty());
+        assert_eq!(report["onScheduleCount"], 1);
+
+        let arcs = list_character_arcs(&conn, json!({"projectId": story.project_id, "characterId": story.mira})).unwrap();
+        assert_eq!(arcs["arcs"].as_array().unwrap().len(), 1);
+        assert_eq!(get_character_arc(&conn, json!({"arcId": arc["arcId"]})).unwrap()["currentProgress"], 33);
+    }
+}
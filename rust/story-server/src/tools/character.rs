@@ -1,41 +1,87 @@
+use super::parse_params;
+use crate::context::tokens;
 use crate::error::{Result, StoryError};
 use crate::models::{Character, CharacterRelationship, CharacterRole, RelationshipType};
 use chrono::Utc;
 use rusqlite::Connection;
+use schemars::JsonSchema;
+use serde::Deserialize;
 use serde_json::{json, Value};
 use uuid::Uuid;
 
-/// Add a new character to a story project
-pub fn add_character(conn: &Connection, params: Value) -> Result<Value> {
-    let project_id_str = params
-        .get("projectId")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: projectId"))?;
+/// Parameters for `addCharacter`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AddCharacterParams {
+    pub project_id: Uuid,
+    #[schemars(length(max = 100))]
+    pub name: String,
+    pub role: CharacterRole,
+    pub personality_traits: Option<String>,
+    pub physical_description: Option<String>,
+    pub backstory: Option<String>,
+    /// Free-text description of where the character is and what they are doing
+    pub current_state: Option<String>,
+}
 
-    let project_id = Uuid::parse_str(project_id_str)
-        .map_err(|_| StoryError::validation("Invalid UUID format for projectId"))?;
+/// Parameters for `getCharacter`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GetCharacterParams {
+    pub character_id: Uuid,
+}
 
-    let name = params
-        .get("name")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: name"))?;
+/// Parameters for `updateCharacter`; omitted fields are left unchanged
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdateCharacterParams {
+    pub character_id: Uuid,
+    #[schemars(length(max = 100))]
+    pub name: Option<String>,
+    pub role: Option<CharacterRole>,
+    pub personality_traits: Option<String>,
+    pub physical_description: Option<String>,
+    pub backstory: Option<String>,
+    /// Free-text description of where the character is and what they are doing
+    pub current_state: Option<String>,
+}
 
-    let role_str = params
-        .get("role")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: role"))?;
+/// Parameters for `listCharacters`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ListCharactersParams {
+    pub project_id: Uuid,
+    /// Also report what each profile costs to load into context
+    pub include_tokens: Option<bool>,
+}
 
-    let role = CharacterRole::from_str(role_str)
-        .ok_or_else(|| StoryError::validation(format!("Invalid role: {}", role_str)))?;
+/// Parameters for `addCharacterRelationship`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AddCharacterRelationshipParams {
+    pub source_character_id: Uuid,
+    pub target_character_id: Uuid,
+    pub relationship_type: RelationshipType,
+    pub description: Option<String>,
+    /// Relationship strength from 1 (weak) to 10 (unbreakable)
+    #[schemars(range(min = 1, max = 10))]
+    pub strength: Option<i32>,
+}
 
-    let personality_traits = params.get("personalityTraits").and_then(|v| v.as_str());
-    let physical_description = params.get("physicalDescription").and_then(|v| v.as_str());
-    let backstory = params.get("backstory").and_then(|v| v.as_str());
-    let current_state = params.get("currentState").and_then(|v| v.as_str());
+/// Add a new character to a story project
+pub fn add_character(conn: &Connection, params: Value) -> Result<Value> {
+    let params: AddCharacterParams = parse_params(params)?;
+    let project_id = params.project_id;
+    let name = params.name.as_str();
+    let role = params.role;
+    let personality_traits = params.personality_traits.as_deref();
+    let physical_description = params.physical_description.as_deref();
+    let backstory = params.backstory.as_deref();
+    let current_state = params.current_state.as_deref();
 
     // Validate name length
-    if name.len() > 100 {
-        return Err(StoryError::validation("Name must be 100 characters or less"));
+    if name.chars().count() > 100 {
+        return Err(StoryError::validation("Name must be 100 characters or less").with_field("name"));
     }
 
     let character = Character {
@@ -70,7 +116,16 @@ pub fn add_character(conn: &Connection, params: Value) -> Result<Value> {
         ),
     ).map_err(|e| {
         if e.to_string().contains("UNIQUE constraint failed") {
-            StoryError::duplicate(format!("Character '{}' already exists in this project", name))
+            let err = StoryError::duplicate(format!("Character '{}' already exists in this project", name))
+                .with_field("name");
+            match conn.query_row(
+                "SELECT id FROM characters WHERE story_project_id = ?1 AND name = ?2",
+                (project_id.to_string(), name),
+                |row| row.get::<_, String>(0),
+            ) {
+                Ok(existing_id) => err.with_entity("character", existing_id),
+                Err(_) => err,
+            }
         } else {
             StoryError::DatabaseError(e)
         }
@@ -92,13 +147,7 @@ pub fn add_character(conn: &Connection, params: Value) -> Result<Value> {
 
 /// Get a character by ID
 pub fn get_character(conn: &Connection, params: Value) -> Result<Value> {
-    let character_id_str = params
-        .get("characterId")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: characterId"))?;
-
-    let character_id = Uuid::parse_str(character_id_str)
-        .map_err(|_| StoryError::validation("Invalid UUID format for characterId"))?;
+    let GetCharacterParams { character_id } = parse_params(params)?;
 
     let mut stmt = conn.prepare(
         "SELECT id, story_project_id, name, role, personality_traits, physical_description, backstory, current_state, created_at, updated_at
@@ -126,6 +175,8 @@ pub fn get_character(conn: &Connection, params: Value) -> Result<Value> {
     }).map_err(|e| {
         if e == rusqlite::Error::QueryReturnedNoRows {
             StoryError::not_found(format!("Character not found: {}", character_id))
+                .with_field("characterId")
+                .with_entity("character", character_id)
         } else {
             StoryError::DatabaseError(e)
         }
@@ -143,18 +194,77 @@ pub fn get_character(conn: &Connection, params: Value) -> Result<Value> {
     }))
 }
 
+/// Update a character's profile or current state
+pub fn update_character(conn: &Connection, params: Value) -> Result<Value> {
+    let params: UpdateCharacterParams = parse_params(params)?;
+    let character_id = params.character_id;
+
+    if params.name.is_none()
+        && params.role.is_none()
+        && params.personality_traits.is_none()
+        && params.physical_description.is_none()
+        && params.backstory.is_none()
+        && params.current_state.is_none()
+    {
+        return Err(StoryError::validation("No fields to update"));
+    }
+
+    if let Some(name) = &params.name {
+        if name.chars().count() > 100 {
+            return Err(StoryError::validation("Name must be 100 characters or less").with_field("name"));
+        }
+    }
+
+    let updated = conn.execute(
+        "UPDATE characters SET
+            name = COALESCE(?2, name),
+            role = COALESCE(?3, role),
+            personality_traits = COALESCE(?4, personality_traits),
+            physical_description = COALESCE(?5, physical_description),
+            backstory = COALESCE(?6, backstory),
+            current_state = COALESCE(?7, current_state),
+            updated_at = ?8
+         WHERE id = ?1",
+        (
+            character_id.to_string(),
+            &params.name,
+            params.role.as_ref().map(|r| r.to_string()),
+            &params.personality_traits,
+            &params.physical_description,
+            &params.backstory,
+            &params.current_state,
+            Utc::now().to_rfc3339(),
+        ),
+    ).map_err(|e| {
+        if e.to_string().contains("UNIQUE constraint failed") {
+            StoryError::duplicate(format!(
+                "Character '{}' already exists in this project",
+                params.name.as_deref().unwrap_or_default()
+            ))
+            .with_field("name")
+        } else {
+            StoryError::DatabaseError(e)
+        }
+    })?;
+
+    if updated == 0 {
+        return Err(StoryError::not_found(format!("Character not found: {}", character_id))
+            .with_field("characterId")
+            .with_entity("character", character_id));
+    }
+
+    log::info!("Updated character: {}", character_id);
+
+    get_character(conn, json!({ "characterId": character_id }))
+}
+
 /// List all characters in a project
 pub fn list_characters(conn: &Connection, params: Value) -> Result<Value> {
-    let project_id_str = params
-        .get("projectId")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: projectId"))?;
-
-    let project_id = Uuid::parse_str(project_id_str)
-        .map_err(|_| StoryError::validation("Invalid UUID format for projectId"))?;
+    let ListCharactersParams { project_id, include_tokens } = parse_params(params)?;
+    let include_tokens = include_tokens.unwrap_or(false);
 
     let mut stmt = conn.prepare(
-        "SELECT id, name, role, personality_traits, current_state
+        "SELECT id, name, role, personality_traits, current_state, physical_description, backstory
          FROM characters
          WHERE story_project_id = ?1
          ORDER BY role, name"
@@ -162,55 +272,55 @@ pub fn list_characters(conn: &Connection, params: Value) -> Result<Value> {
 
     let characters = stmt
         .query_map([project_id.to_string()], |row| {
-            Ok(json!({
+            let name: String = row.get(1)?;
+            let personality_traits: Option<String> = row.get(3)?;
+            let current_state: Option<String> = row.get(4)?;
+            let mut character = json!({
                 "characterId": row.get::<_, String>(0)?,
-                "name": row.get::<_, String>(1)?,
+                "name": name,
                 "role": row.get::<_, String>(2)?,
-                "personalityTraits": row.get::<_, Option<String>>(3)?,
-                "currentState": row.get::<_, Option<String>>(4)?
-            }))
+                "personalityTraits": personality_traits,
+                "currentState": current_state
+            });
+            if include_tokens {
+                let physical_description: Option<String> = row.get(5)?;
+                let backstory: Option<String> = row.get(6)?;
+                character["tokens"] = json!(tokens::character_cost(
+                    &name,
+                    personality_traits.as_deref(),
+                    physical_description.as_deref(),
+                    backstory.as_deref(),
+                    current_state.as_deref(),
+                ));
+            }
+            Ok(character)
         })?
         .collect::<std::result::Result<Vec<_>, _>>()?;
 
     log::info!("Listed {} characters for project {}", characters.len(), project_id);
 
-    Ok(json!({
+    let mut response = json!({
         "characters": characters
-    }))
+    });
+    if include_tokens {
+        response["tokenCounting"] = json!(tokens::counter().method());
+    }
+    Ok(response)
 }
 
 /// Add a relationship between two characters
 pub fn add_character_relationship(conn: &Connection, params: Value) -> Result<Value> {
-    let source_id_str = params
-        .get("sourceCharacterId")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: sourceCharacterId"))?;
-
-    let target_id_str = params
-        .get("targetCharacterId")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: targetCharacterId"))?;
-
-    let source_id = Uuid::parse_str(source_id_str)
-        .map_err(|_| StoryError::validation("Invalid UUID format for sourceCharacterId"))?;
-
-    let target_id = Uuid::parse_str(target_id_str)
-        .map_err(|_| StoryError::validation("Invalid UUID format for targetCharacterId"))?;
+    let params: AddCharacterRelationshipParams = parse_params(params)?;
+    let source_id = params.source_character_id;
+    let target_id = params.target_character_id;
 
     if source_id == target_id {
-        return Err(StoryError::validation("Cannot create relationship with self"));
+        return Err(StoryError::validation("Cannot create relationship with self").with_field("targetCharacterId"));
     }
 
-    let relationship_type_str = params
-        .get("relationshipType")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: relationshipType"))?;
-
-    let relationship_type = RelationshipType::from_str(relationship_type_str)
-        .ok_or_else(|| StoryError::validation(format!("Invalid relationshipType: {}", relationship_type_str)))?;
-
-    let description = params.get("description").and_then(|v| v.as_str());
-    let strength = params.get("strength").and_then(|v| v.as_i64()).map(|n| n as i32);
+    let relationship_type = params.relationship_type;
+    let description = params.description.as_deref();
+    let strength = params.strength;
 
     let relationship = CharacterRelationship {
         id: Uuid::new_v4(),
@@ -238,7 +348,15 @@ pub fn add_character_relationship(conn: &Connection, params: Value) -> Result<Va
         ),
     ).map_err(|e| {
         if e.to_string().contains("UNIQUE constraint failed") {
-            StoryError::duplicate("Relationship already exists between these characters")
+            let err = StoryError::duplicate("Relationship already exists between these characters");
+            match conn.query_row(
+                "SELECT id FROM character_relationships WHERE source_character_id = ?1 AND target_character_id = ?2",
+                (source_id.to_string(), target_id.to_string()),
+                |row| row.get::<_, String>(0),
+            ) {
+                Ok(existing_id) => err.with_entity("relationship", existing_id),
+                Err(_) => err,
+            }
         } else {
             StoryError::DatabaseError(e)
         }
@@ -267,7 +385,7 @@ mod tests {
         let db_path = dir.path().join("test.db");
         let conn = db::initialize_database(&db_path).unwrap();
 
-        let project = create_story_project(&conn, json!({"title": "Test", "targetLength": "novel"})).unwrap();
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Test", "targetLength": "novel"})).unwrap();
         let project_id = project.get("projectId").unwrap().as_str().unwrap();
 
         let params = json!({
@@ -286,13 +404,55 @@ mod tests {
         assert_eq!(response.get("role").unwrap(), "protagonist");
     }
 
+    #[test]
+    fn test_update_character() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let conn = db::initialize_database(&db_path).unwrap();
+
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Test", "targetLength": "novel"})).unwrap();
+        let project_id = project.get("projectId").unwrap().as_str().unwrap();
+        let hero = add_character(&conn, json!({"projectId": project_id, "name": "Hero", "role": "protagonist", "backstory": "Orphan"})).unwrap();
+
+        let response = update_character(
+            &conn,
+            json!({"characterId": hero["characterId"], "currentState": "Captured by the guard"}),
+        )
+        .unwrap();
+        assert_eq!(response["currentState"], "Captured by the guard");
+        assert_eq!(response["backstory"], "Orphan");
+
+        let err = update_character(&conn, json!({"characterId": hero["characterId"]})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+
+        let err = update_character(&conn, json!({"characterId": Uuid::new_v4(), "backstory": "x"})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_name_length_counts_characters() {
+        let dir = tempdir().unwrap();
+        let conn = db::initialize_database(dir.path().join("test.db")).unwrap();
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Test", "targetLength": "novel"})).unwrap();
+        let named = |name: String| json!({"projectId": project["projectId"], "name": name, "role": "minor"});
+
+        // 100 characters, 300 bytes
+        let character = add_character(&conn, named("林".repeat(100))).unwrap();
+        let err = add_character(&conn, named("森".repeat(101))).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+
+        update_character(&conn, json!({"characterId": character["characterId"], "name": "森".repeat(100)})).unwrap();
+        let err = update_character(&conn, json!({"characterId": character["characterId"], "name": "森".repeat(101)})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+    }
+
     #[test]
     fn test_list_characters() {
         let dir = tempdir().unwrap();
         let db_path = dir.path().join("test.db");
         let conn = db::initialize_database(&db_path).unwrap();
 
-        let project = create_story_project(&conn, json!({"title": "Test", "targetLength": "novel"})).unwrap();
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Test", "targetLength": "novel"})).unwrap();
         let project_id = project.get("projectId").unwrap().as_str().unwrap();
 
         add_character(&conn, json!({"projectId": project_id, "name": "Hero", "role": "protagonist"})).unwrap();
@@ -305,4 +465,26 @@ mod tests {
         let chars = response.get("characters").unwrap().as_array().unwrap();
         assert_eq!(chars.len(), 2);
     }
+
+    #[test]
+    fn test_list_characters_with_tokens() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let conn = db::initialize_database(&db_path).unwrap();
+
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Test", "targetLength": "novel"})).unwrap();
+        let project_id = project.get("projectId").unwrap().as_str().unwrap();
+
+        let backstory = "Raised by lighthouse keepers on a rock in the northern sea";
+        add_character(&conn, json!({"projectId": project_id, "name": "Hero", "role": "protagonist", "backstory": backstory})).unwrap();
+
+        let plain = list_characters(&conn, json!({"projectId": project_id})).unwrap();
+        assert!(plain["characters"][0].get("tokens").is_none());
+        assert!(plain.get("tokenCounting").is_none());
+
+        // The backstory is not listed but still counts
+        let response = list_characters(&conn, json!({"projectId": project_id, "includeTokens": true})).unwrap();
+        assert_eq!(response["characters"][0]["tokens"], tokens::count("Hero") + tokens::count(backstory));
+        assert_eq!(response["tokenCounting"], tokens::counter().method());
+    }
 }
@@ -0,0 +1,695 @@
+use super::parse_params;
+use super::summary::summary_tokens;
+use crate::context::{self, scoring, tokens, ContextItem, ContextKind};
+use crate::error::{Result, StoryError};
+use rusqlite::{Connection, OptionalExtension};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Parameters for `getSceneContext`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GetSceneContextParams {
+    /// Chapter the scene is in
+    pub chapter_id: Uuid,
+    /// Position of the scene within the chapter; defaults to the next new scene
+    #[schemars(range(min = 1))]
+    pub scene_position: Option<i32>,
+    /// What happens in the scene
+    pub outline: String,
+    /// Most tokens of context to return
+    #[schemars(range(min = 1, max = 200000))]
+    pub token_budget: u32,
+}
+
+/// An earlier scene of the project, in reading order
+struct PriorScene {
+    id: String,
+    title: Option<String>,
+    chapter_id: String,
+    chapter_number: i32,
+    position: i32,
+    summary: Option<String>,
+}
+
+/// Assemble the characters, world rules, earlier summaries and open continuity
+/// alerts most relevant to a scene, trimmed to a token budget
+pub fn get_scene_context(conn: &Connection, params: Value) -> Result<Value> {
+    let params: GetSceneContextParams = parse_params(params)?;
+    let chapter_id = params.chapter_id;
+    let outline = params.outline.as_str();
+    let budget = params.token_budget as usize;
+
+    let (project_id, act_position, chapter_number): (String, i32, i32) = conn
+        .query_row(
+            "SELECT ps.story_project_id, a.position, c.number
+             FROM chapters c
+             JOIN acts a ON c.act_id = a.id
+             JOIN plot_structures ps ON a.plot_structure_id = ps.id
+             WHERE c.id = ?1",
+            [chapter_id.to_string()],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?
+        .ok_or_else(|| {
+            StoryError::not_found(format!("Chapter not found: {}", chapter_id))
+                .with_field("chapterId")
+                .with_entity("chapter", chapter_id)
+        })?;
+
+    let scene_position = match params.scene_position {
+        Some(position) => position,
+        None => conn.query_row(
+            "SELECT COALESCE(MAX(position), 0) + 1 FROM scenes WHERE chapter_id = ?1",
+            [chapter_id.to_string()],
+            |row| row.get(0),
+        )?,
+    };
+    let scene_id: Option<String> = conn
+        .query_row(
+            "SELECT id FROM scenes WHERE chapter_id = ?1 AND position = ?2",
+            (chapter_id.to_string(), scene_position),
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    let prior = prior_scenes(conn, &project_id, (act_position, chapter_number, scene_position))?;
+
+    let mut items = character_items(conn, &project_id, outline, &prior, scene_id.as_deref())?;
+    items.extend(world_rule_items(conn, &project_id, outline)?);
+    items.extend(summary_items(conn, &project_id, outline, &prior, chapter_id)?);
+    let named: Vec<String> = items
+        .iter()
+        .filter(|item| item.kind == ContextKind::Character && scoring::mentions_name(outline, &item.label))
+        .map(|item| item.label.clone())
+        .collect();
+    items.extend(alert_items(conn, &project_id, &named, chapter_id, &prior)?);
+
+    let selection = context::select(items, budget);
+    let section = |kind: ContextKind| {
+        selection
+            .selected
+            .iter()
+            .filter(|item| item.kind == kind)
+            .map(ContextItem::to_json)
+            .collect::<Vec<_>>()
+    };
+
+    log::info!(
+        "Assembled context for chapter {} scene {}: {} item(s), {} of {} tokens",
+        chapter_id,
+        scene_position,
+        selection.selected.len(),
+        selection.tokens_used,
+        budget
+    );
+
+    Ok(json!({
+        "projectId": project_id,
+        "chapterId": chapter_id.to_string(),
+        "scenePosition": scene_position,
+        "tokenBudget": budget,
+        "tokensUsed": selection.tokens_used,
+        "characters": section(ContextKind::Character),
+        "worldRules": section(ContextKind::WorldRule),
+        "summaries": section(ContextKind::Summary),
+        "continuityAlerts": section(ContextKind::ContinuityAlert),
+        "omitted": selection.omitted.iter().map(ContextItem::to_omitted_json).collect::<Vec<_>>()
+    }))
+}
+
+/// Scenes of the project before `target` (act position, chapter number, scene position), oldest first
+fn prior_scenes(conn: &Connection, project_id: &str, target: (i32, i32, i32)) -> Result<Vec<PriorScene>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.id, s.title, c.id, a.position, c.number, s.position,
+                COALESCE(ss.summary_text, s.summary)
+         FROM scenes s
+         JOIN chapters c ON s.chapter_id = c.id
+         JOIN acts a ON c.act_id = a.id
+         JOIN plot_structures ps ON a.plot_structure_id = ps.id
+         LEFT JOIN story_summaries ss
+                ON ss.story_project_id = ps.story_project_id AND ss.scope = 'scene' AND ss.reference_id = s.id
+         WHERE ps.story_project_id = ?1
+         ORDER BY a.position, c.number, s.position",
+    )?;
+    let rows = stmt.query_map([project_id], |row| {
+        let order: (i32, i32, i32) = (row.get(3)?, row.get(4)?, row.get(5)?);
+        Ok((
+            order,
+            PriorScene {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                chapter_id: row.get(2)?,
+                chapter_number: order.1,
+                position: order.2,
+                summary: row.get(6)?,
+            },
+        ))
+    })?;
+
+    let mut scenes = Vec::new();
+    for row in rows {
+        let (order, scene) = row?;
+        if order < target {
+            scenes.push(scene);
+        }
+    }
+    Ok(scenes)
+}
+
+/// Scenes back from the one being written, keyed by scene ID: 1 for the previous scene
+fn distances(prior: &[PriorScene]) -> HashMap<&str, usize> {
+    prior
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, scene)| (scene.id.as_str(), i + 1))
+        .collect()
+}
+
+fn scene_label(chapter_number: i32, position: i32) -> String {
+    format!("chapter {} scene {}", chapter_number, position)
+}
+
+fn character_items(
+    conn: &Connection,
+    project_id: &str,
+    outline: &str,
+    prior: &[PriorScene],
+    scene_id: Option<&str>,
+) -> Result<Vec<ContextItem>> {
+    let mut appearances: HashMap<String, Vec<String>> = HashMap::new();
+    let mut cast: HashSet<String> = HashSet::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT sc.character_id, sc.scene_id
+             FROM scene_characters sc
+             JOIN characters ch ON sc.character_id = ch.id
+             WHERE ch.story_project_id = ?1",
+        )?;
+        let rows = stmt.query_map([project_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        for row in rows {
+            let (character_id, appeared_in) = row?;
+            if Some(appeared_in.as_str()) == scene_id {
+                cast.insert(character_id);
+            } else {
+                appearances.entry(character_id).or_default().push(appeared_in);
+            }
+        }
+    }
+
+    let distance = distances(prior);
+
+    let mut stmt = conn.prepare(
+        "SELECT id, name, role, personality_traits, physical_description, backstory, current_state
+         FROM characters WHERE story_project_id = ?1 ORDER BY name",
+    )?;
+    let rows = stmt.query_map([project_id], |row| {
+        let id: String = row.get(0)?;
+        let name: String = row.get(1)?;
+        let role: String = row.get(2)?;
+        let personality_traits: Option<String> = row.get(3)?;
+        let physical_description: Option<String> = row.get(4)?;
+        let backstory: Option<String> = row.get(5)?;
+        let current_state: Option<String> = row.get(6)?;
+        let cost = tokens::character_cost(
+            &name,
+            personality_traits.as_deref(),
+            physical_description.as_deref(),
+            backstory.as_deref(),
+            current_state.as_deref(),
+        );
+        let body = json!({
+            "characterId": id,
+            "name": name,
+            "role": role,
+            "personalityTraits": personality_traits,
+            "physicalDescription": physical_description,
+            "backstory": backstory,
+            "currentState": current_state
+        });
+        Ok((id, name, role, body, cost))
+    })?;
+
+    let mut items = Vec::new();
+    for row in rows {
+        let (id, name, role, body, cost) = row?;
+        let mut item = ContextItem::new(ContextKind::Character, id.as_str(), name.as_str(), body, cost);
+
+        if cast.contains(&id) {
+            item.credit(scoring::CAST_IN_SCENE, "Cast in this scene");
+        }
+        if scoring::mentions_name(outline, &name) {
+            item.credit(scoring::NAMED_IN_OUTLINE, "Named in the outline");
+        }
+
+        // Only appearances before this scene count
+        let earlier: Vec<usize> = appearances
+            .get(&id)
+            .map(|scenes| scenes.iter().filter_map(|s| distance.get(s.as_str()).copied()).collect())
+            .unwrap_or_default();
+        if let Some(&latest) = earlier.iter().min() {
+            let counted = earlier.len().min(scoring::MAX_COUNTED_APPEARANCES);
+            item.credit(
+                scoring::APPEARANCE * counted as f64,
+                format!("Appeared in {} earlier scene(s)", earlier.len()),
+            );
+            let last = &prior[prior.len() - latest];
+            item.credit(
+                scoring::RECENT_APPEARANCE * scoring::recency(latest),
+                format!("Last appeared in {}", scene_label(last.chapter_number, last.position)),
+            );
+        }
+        if role == "protagonist" {
+            item.credit(scoring::PROTAGONIST, "Protagonist");
+        }
+        items.push(item);
+    }
+    Ok(items)
+}
+
+fn world_rule_items(conn: &Connection, project_id: &str, outline: &str) -> Result<Vec<ContextItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, description, scope, examples, keywords
+         FROM world_rules WHERE story_project_id = ?1 ORDER BY name",
+    )?;
+    let rows = stmt.query_map([project_id], |row| {
+        let id: String = row.get(0)?;
+        let name: String = row.get(1)?;
+        let scope: String = row.get(3)?;
+        let description: String = row.get(2)?;
+        let examples: Option<String> = row.get(4)?;
+        let keywords: Option<String> = row.get(5)?;
+        let cost = tokens::world_rule_cost(&name, &description, examples.as_deref(), keywords.as_deref());
+        let body = json!({
+            "ruleId": id,
+            "name": name,
+            "description": description,
+            "scope": scope,
+            "examples": examples
+        });
+        Ok((id, name, scope, keywords, body, cost))
+    })?;
+
+    let mut items = Vec::new();
+    for row in rows {
+        let (id, name, scope, keywords, body, cost) = row?;
+        let mut item = ContextItem::new(ContextKind::WorldRule, id, name.as_str(), body, cost);
+
+        if scoring::mentions(outline, &name) {
+            item.credit(scoring::NAMED_IN_OUTLINE, "Named in the outline");
+        }
+        for keyword in scoring::keywords(keywords.as_deref().unwrap_or("")) {
+            if scoring::mentions(outline, &keyword) {
+                item.credit(scoring::KEYWORD_MATCH, format!("Keyword '{}' appears in the outline", keyword));
+            }
+        }
+        if scope == "universal" {
+            item.credit(scoring::UNIVERSAL_RULE, "Universal rule");
+        }
+        items.push(item);
+    }
+    Ok(items)
+}
+
+fn summary_items(
+    conn: &Connection,
+    project_id: &str,
+    outline: &str,
+    prior: &[PriorScene],
+    chapter_id: Uuid,
+) -> Result<Vec<ContextItem>> {
+    // Earlier scenes that share terms with the outline
+    let related: HashSet<String> = match scoring::any_term_expression(outline) {
+        Some(expression) => {
+            let mut stmt = conn.prepare(
+                "SELECT f.id FROM scenes_fts f
+                 JOIN scenes s ON s.rowid = f.rowid
+                 JOIN chapters c ON s.chapter_id = c.id
+                 JOIN acts a ON c.act_id = a.id
+                 JOIN plot_structures ps ON a.plot_structure_id = ps.id
+                 WHERE ps.story_project_id = ?2 AND f.scenes_fts MATCH ?1",
+            )?;
+            let ids = stmt.query_map((expression, project_id), |row| row.get(0))?;
+            ids.collect::<rusqlite::Result<_>>()?
+        }
+        None => HashSet::new(),
+    };
+
+    let mut items = Vec::new();
+    for (i, scene) in prior.iter().enumerate() {
+        let Some(summary) = &scene.summary else { continue };
+        let distance = prior.len() - i;
+        let label = scene.title.clone().unwrap_or_else(|| scene_label(scene.chapter_number, scene.position));
+        let body = json!({
+            "scope": "scene",
+            "referenceId": scene.id,
+            "title": label,
+            "chapterNumber": scene.chapter_number,
+            "scenePosition": scene.position,
+            "summaryText": summary
+        });
+        let cost = summary_tokens(&body);
+        let mut item = ContextItem::new(ContextKind::Summary, scene.id.as_str(), label.as_str(), body, cost);
+        let reason = match distance {
+            1 => "Summary of the previous scene".to_string(),
+            n => format!("Summary of the scene {} scenes back", n),
+        };
+        item.credit(scoring::SCENE_SUMMARY * scoring::recency(distance), reason);
+        if related.contains(&scene.id) {
+            item.credit(scoring::SHARES_OUTLINE_TERMS, "Shares terms with the outline");
+        }
+        items.push(item);
+    }
+
+    // Summaries of earlier chapters, most recent first
+    let current = chapter_id.to_string();
+    let mut chapters: Vec<(&str, i32)> = Vec::new();
+    for scene in prior.iter().rev() {
+        if scene.chapter_id != current && !chapters.iter().any(|(id, _)| *id == scene.chapter_id) {
+            chapters.push((scene.chapter_id.as_str(), scene.chapter_number));
+        }
+    }
+    for (i, (id, number)) in chapters.into_iter().enumerate() {
+        let summary: Option<(String, Option<String>, Option<String>)> = conn
+            .query_row(
+                "SELECT summary_text, key_events, character_developments FROM story_summaries
+                 WHERE story_project_id = ?1 AND scope = 'chapter' AND reference_id = ?2",
+                (project_id, id),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        let Some((summary, key_events, developments)) = summary else { continue };
+        let list = |text: Option<String>| text.and_then(|t| serde_json::from_str::<Value>(&t).ok());
+        let label = format!("chapter {}", number);
+        let body = json!({
+            "scope": "chapter",
+            "referenceId": id,
+            "title": label,
+            "chapterNumber": number,
+            "summaryText": summary,
+            "keyEvents": list(key_events),
+            "characterDevelopments": list(developments)
+        });
+        let cost = summary_tokens(&body);
+        let mut item = ContextItem::new(ContextKind::Summary, id, label.as_str(), body, cost);
+        item.credit(
+            scoring::CHAPTER_SUMMARY * scoring::recency(i + 1),
+            format!("Summary of chapter {}", number),
+        );
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Open alerts, ranked by severity and by how close they are to this scene's cast and chapter
+fn alert_items(
+    conn: &Connection,
+    project_id: &str,
+    named: &[String],
+    chapter_id: Uuid,
+    prior: &[PriorScene],
+) -> Result<Vec<ContextItem>> {
+    let chapter_id = chapter_id.to_string();
+    let in_chapter: HashSet<&str> = prior
+        .iter()
+        .filter(|scene| scene.chapter_id == chapter_id)
+        .map(|scene| scene.id.as_str())
+        .collect();
+
+    let mut stmt = conn.prepare(
+        "SELECT id, scene_id, alert_type, severity, description, conflicting_elements, suggested_resolution
+         FROM continuity_alerts
+         WHERE story_project_id = ?1 AND author_decision = 'pending'
+         ORDER BY created_at",
+    )?;
+    let rows = stmt.query_map([project_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, Option<String>>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, Option<String>>(5)?,
+            row.get::<_, Option<String>>(6)?,
+        ))
+    })?;
+
+    let mut items = Vec::new();
+    for row in rows {
+        let (id, scene_id, alert_type, severity, description, conflicting, resolution) = row?;
+        let text = format!("{} {}", description, conflicting.as_deref().unwrap_or(""));
+        let cost = tokens::cost([Some(description.as_str()), conflicting.as_deref(), resolution.as_deref()]);
+        let mut item = ContextItem::new(
+            ContextKind::ContinuityAlert,
+            id.as_str(),
+            alert_type.as_str(),
+            json!({
+                "alertId": id,
+                "sceneId": scene_id,
+                "alertType": alert_type,
+                "severity": severity,
+                "description": description,
+                "conflictingElements": conflicting.map(|c| serde_json::from_str::<Value>(&c).unwrap_or(Value::String(c))),
+                "suggestedResolution": resolution
+            }),
+            cost,
+        );
+        item.credit(scoring::alert_severity(&severity), format!("Open {}-severity alert", severity));
+        if scene_id.as_deref().is_some_and(|s| in_chapter.contains(s)) {
+            item.credit(scoring::ALERT_IN_CHAPTER, "Raised earlier in this chapter");
+        }
+        for name in named {
+            if scoring::mentions_name(&text, name) {
+                item.credit(scoring::ALERT_INVOLVES_CHARACTER, format!("Involves {}, named in the outline", name));
+            }
+        }
+        items.push(item);
+    }
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::tools::{
+        add_chapter, add_character, add_scene, add_world_rule, create_story_project, initialize_plot_structure, list_characters,
+        list_world_rules,
+    };
+    use tempfile::tempdir;
+
+    struct Fixture {
+        chapter_ids: Vec<String>,
+        scene_ids: Vec<String>,
+        mira: String,
+        dax: String,
+        project_id: String,
+    }
+
+    /// Two chapters of two scenes each; Mira is in all but the last, which has no cast yet, and Dax only in the first
+    fn fixture(conn: &Connection) -> Fixture {
+        let stories = tempdir().unwrap();
+        let project = create_story_project(conn, stories.path(), json!({"title": "Context", "targetLength": "novel"})).unwrap();
+        let project_id = project["projectId"].as_str().unwrap().to_string();
+        let character = |name: &str, role: &str| {
+            add_character(conn, json!({"projectId": project_id, "name": name, "role": role, "currentState": "At the harbor"}))
+                .unwrap()["characterId"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        let mira = character("Mira Vale", "protagonist");
+        let dax = character("Dax", "supporting");
+        character("Orrin", "minor");
+
+        add_world_rule(
+            conn,
+            json!({"projectId": project_id, "name": "Tidecalling", "description": "The sea answers sung names", "scope": "regional", "keywords": ["tide", "song"]}),
+        )
+        .unwrap();
+        add_world_rule(
+            conn,
+            json!({"projectId": project_id, "name": "Iron", "description": "Cold iron breaks spells", "scope": "situational", "keywords": "iron, forge"}),
+        )
+        .unwrap();
+
+        let plot = initialize_plot_structure(conn, json!({"projectId": project_id})).unwrap();
+        let act_id = plot["acts"][0]["actId"].clone();
+        let mut chapter_ids = Vec::new();
+        let mut scene_ids = Vec::new();
+        for number in 1..=2 {
+            let chapter = add_chapter(conn, json!({"actId": act_id, "number": number})).unwrap();
+            let chapter_id = chapter["chapterId"].as_str().unwrap().to_string();
+            for outline in ["A storm at the lighthouse", "Quiet morning at the market"] {
+                let scene = add_scene(conn, stories.path(), json!({"chapterId": chapter_id, "sceneOutline": outline})).unwrap();
+                let scene_id = scene["sceneId"].as_str().unwrap().to_string();
+                conn.execute(
+                    "UPDATE scenes SET summary = ?2 WHERE id = ?1",
+                    (&scene_id, format!("Summary: {}", outline)),
+                )
+                .unwrap();
+                scene_ids.push(scene_id);
+            }
+            chapter_ids.push(chapter_id);
+        }
+
+        let cast = |scene: &str, character: &str| {
+            conn.execute(
+                "INSERT INTO scene_characters (id, scene_id, character_id) VALUES (?1, ?2, ?3)",
+                (Uuid::new_v4().to_string(), scene, character),
+            )
+            .unwrap();
+        };
+        for scene in &scene_ids[..3] {
+            cast(scene, &mira);
+        }
+        cast(&scene_ids[0], &dax);
+
+        Fixture { chapter_ids, scene_ids, mira, dax, project_id }
+    }
+
+    fn ids(section: &Value, key: &str) -> Vec<String> {
+        section.as_array().unwrap().iter().map(|i| i[key].as_str().unwrap().to_string()).collect()
+    }
+
+    #[test]
+    fn test_scene_context_scores_and_explains() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let f = fixture(&conn);
+        conn.execute(
+            "INSERT INTO continuity_alerts (id, story_project_id, scene_id, alert_type, severity, description)
+             VALUES ('al-1', ?1, ?2, 'character_state_conflict', 'high', 'Dax is in two places at once')",
+            (&f.project_id, &f.scene_ids[2]),
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO continuity_alerts (id, story_project_id, alert_type, description, author_decision)
+             VALUES ('al-2', ?1, 'timeline_contradiction', 'Resolved long ago', 'dismissed')",
+            [&f.project_id],
+        )
+        .unwrap();
+
+        // Writing the second scene of chapter 2
+        let context = get_scene_context(
+            &conn,
+            json!({"chapterId": f.chapter_ids[1], "scenePosition": 2, "outline": "Dax sings to the tide at the lighthouse", "tokenBudget": 10000}),
+        )
+        .unwrap();
+        assert_eq!(context["scenePosition"], 2);
+
+        // Dax is named; Mira has been in every scene; Orrin has no signal at all
+        let characters = ids(&context["characters"], "characterId");
+        assert_eq!(characters, vec![f.dax.clone(), f.mira.clone()]);
+        let dax = &context["characters"][0];
+        let reasons: Vec<&str> = dax["reasons"].as_array().unwrap().iter().map(|r| r.as_str().unwrap()).collect();
+        assert_eq!(reasons, vec!["Named in the outline", "Appeared in 1 earlier scene(s)", "Last appeared in chapter 1 scene 1"]);
+        let mira_reasons = context["characters"][1]["reasons"].to_string();
+        assert!(mira_reasons.contains("Appeared in 3 earlier scene(s)"));
+        assert!(mira_reasons.contains("Last appeared in chapter 2 scene 1"));
+
+        // Only the rule whose keyword appears in the outline
+        let rules = &context["worldRules"];
+        assert_eq!(rules.as_array().unwrap().len(), 1);
+        assert_eq!(rules[0]["name"], "Tidecalling");
+        assert_eq!(rules[0]["reasons"][0], "Keyword 'tide' appears in the outline");
+
+        // Earlier scenes only; the lighthouse scenes share terms with the outline,
+        // which lifts the first scene above the more recent market scene
+        let summaries = &context["summaries"];
+        assert_eq!(ids(summaries, "referenceId"), vec![f.scene_ids[2].clone(), f.scene_ids[0].clone(), f.scene_ids[1].clone()]);
+        assert_eq!(summaries[0]["reasons"], json!(["Summary of the previous scene", "Shares terms with the outline"]));
+
+        // The open alert only, boosted by its chapter and by naming Dax
+        let alerts = &context["continuityAlerts"];
+        assert_eq!(ids(alerts, "alertId"), vec!["al-1"]);
+        assert_eq!(alerts[0]["reasons"].as_array().unwrap().len(), 3);
+
+        assert_eq!(context["omitted"], json!([]));
+        let used: u64 = ["characters", "worldRules", "summaries", "continuityAlerts"]
+            .iter()
+            .flat_map(|k| context[*k].as_array().unwrap().iter().map(|i| i["tokens"].as_u64().unwrap()))
+            .sum();
+        assert_eq!(context["tokensUsed"], used);
+    }
+
+    #[test]
+    fn test_scene_context_respects_budget() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let f = fixture(&conn);
+
+        let full = get_scene_context(
+            &conn,
+            json!({"chapterId": f.chapter_ids[1], "outline": "Dax by the tide", "tokenBudget": 10000}),
+        )
+        .unwrap();
+        // Defaults to a new scene after the last one
+        assert_eq!(full["scenePosition"], 3);
+        let dax_tokens = full["characters"][0]["tokens"].as_u64().unwrap();
+
+        let tight = get_scene_context(
+            &conn,
+            json!({"chapterId": f.chapter_ids[1], "outline": "Dax by the tide", "tokenBudget": dax_tokens}),
+        )
+        .unwrap();
+        assert_eq!(ids(&tight["characters"], "characterId"), vec![f.dax]);
+        assert_eq!(tight["tokensUsed"], dax_tokens);
+        assert!(tight["omitted"].as_array().unwrap().iter().any(|o| o["entityId"] == f.mira.as_str()));
+    }
+
+    #[test]
+    fn test_scene_context_costs_match_listings() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let f = fixture(&conn);
+        let context = get_scene_context(
+            &conn,
+            json!({"chapterId": f.chapter_ids[1], "outline": "Dax by the tide", "tokenBudget": 10000}),
+        )
+        .unwrap();
+
+        let listed = |response: Value, key: &str, id: &str| -> HashMap<String, Value> {
+            let entries = response[key].as_array().unwrap().iter();
+            entries.map(|e| (e[id].as_str().unwrap().to_string(), e["tokens"].clone())).collect()
+        };
+        let characters = listed(
+            list_characters(&conn, json!({"projectId": f.project_id, "includeTokens": true})).unwrap(),
+            "characters",
+            "characterId",
+        );
+        for item in context["characters"].as_array().unwrap() {
+            assert_eq!(item["tokens"], characters[item["characterId"].as_str().unwrap()]);
+        }
+        let rules = listed(
+            list_world_rules(&conn, json!({"projectId": f.project_id, "includeTokens": true})).unwrap(),
+            "rules",
+            "ruleId",
+        );
+        for item in context["worldRules"].as_array().unwrap() {
+            assert_eq!(item["tokens"], rules[item["ruleId"].as_str().unwrap()]);
+        }
+    }
+
+    #[test]
+    fn test_scene_context_for_first_scene_and_cast() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let f = fixture(&conn);
+
+        // Nothing comes before the first scene, but its own cast is known
+        let context = get_scene_context(
+            &conn,
+            json!({"chapterId": f.chapter_ids[0], "scenePosition": 1, "outline": "", "tokenBudget": 10000}),
+        )
+        .unwrap();
+        assert_eq!(context["summaries"], json!([]));
+        let characters = ids(&context["characters"], "characterId");
+        assert_eq!(characters.len(), 2);
+        assert!(characters.contains(&f.dax) && characters.contains(&f.mira));
+        assert_eq!(context["characters"][0]["reasons"][0], "Cast in this scene");
+
+        let err = get_scene_context(&conn, json!({"chapterId": Uuid::new_v4(), "outline": "", "tokenBudget": 10})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+    }
+}
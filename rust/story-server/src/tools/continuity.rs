@@ -0,0 +1,864 @@
+use super::parse_params;
+use super::state::state_before;
+use crate::context::scoring;
+use crate::continuity::{self, CharacterFacts, Finding, PreviousScene, RuleFacts, SceneFacts, StateSource};
+use crate::error::{Result, StoryError};
+use crate::models::{AlertSeverity, AuthorDecision};
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// `detected_by` value of the alerts raised by `checkSceneContinuity`
+pub const DETECTOR: &str = "continuity_check";
+
+/// Parameters for `checkSceneContinuity`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CheckSceneContinuityParams {
+    pub scene_id: Uuid,
+}
+
+/// Parameters for `listContinuityAlerts`; filters combine
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ListContinuityAlertsParams {
+    pub project_id: Uuid,
+    /// Only alerts about scenes in this chapter
+    pub chapter_id: Option<Uuid>,
+    /// Only alerts about this scene
+    pub scene_id: Option<Uuid>,
+    pub severity: Option<AlertSeverity>,
+    /// Defaults to every decision, including pending
+    pub decision: Option<AuthorDecision>,
+    /// Defaults to 50
+    #[schemars(range(min = 1, max = 200))]
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Parameters for `resolveContinuityAlert`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ResolveContinuityAlertParams {
+    pub alert_id: Uuid,
+    /// revised_content, updated_fact or dismissed
+    pub decision: AuthorDecision,
+    /// Why, e.g. what was changed or why the alert does not apply
+    pub notes: Option<String>,
+}
+
+/// A scene of the project, with its place in reading order
+struct StoryScene {
+    id: String,
+    order: (i32, i32, i32),
+    label: String,
+    location: Option<String>,
+    time_description: Option<String>,
+    text: String,
+    /// Whether `text` is prose rather than the outline
+    written: bool,
+}
+
+/// Check a scene against recorded character states, first appearances, world
+/// rules and the previous scene's location, recording what it finds as continuity alerts
+pub fn check_scene_continuity(conn: &Connection, params: Value) -> Result<Value> {
+    let params: CheckSceneContinuityParams = parse_params(params)?;
+    let scene_id = params.scene_id.to_string();
+    let recheck = recheck_scene(conn, &scene_id, false)?;
+
+    log::info!(
+        "Checked continuity of scene {}: {} finding(s), {} new, {} reopened, {} closed",
+        scene_id,
+        recheck.alerts.len(),
+        recheck.raised,
+        recheck.reopened,
+        recheck.closed.len()
+    );
+
+    Ok(json!({
+        "sceneId": scene_id,
+        "projectId": recheck.project_id,
+        "checkedText": recheck.checked_text,
+        "alerts": recheck.alerts,
+        "newAlerts": recheck.raised,
+        "reopenedAlerts": recheck.reopened,
+        "closedAlerts": recheck.closed
+    }))
+}
+
+/// List a project's continuity alerts in manuscript order, most severe first within a scene
+pub fn list_continuity_alerts(conn: &Connection, params: Value) -> Result<Value> {
+    let params: ListContinuityAlertsParams = parse_params(params)?;
+    let project_id = params.project_id.to_string();
+    let limit = params.limit.unwrap_or(50);
+    if !(1..=200).contains(&limit) {
+        return Err(StoryError::validation("limit must be between 1 and 200").with_field("limit"));
+    }
+    let offset = params.offset.unwrap_or(0);
+
+    let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM story_projects WHERE id = ?1)", [&project_id], |row| {
+        row.get(0)
+    })?;
+    if !exists {
+        return Err(StoryError::not_found(format!("Project not found: {}", project_id))
+            .with_field("projectId")
+            .with_entity("project", &project_id));
+    }
+
+    // Everything but the decision filter, so the counts show how far the pass has got
+    let filters = "ca.story_project_id = ?1
+           AND (?2 IS NULL OR s.chapter_id = ?2)
+           AND (?3 IS NULL OR ca.scene_id = ?3)
+           AND (?4 IS NULL OR ca.severity = ?4)";
+    let filter_args = (
+        &project_id,
+        params.chapter_id.map(|id| id.to_string()),
+        params.scene_id.map(|id| id.to_string()),
+        params.severity.map(|s| s.to_string()),
+    );
+
+    let mut by_decision = json!({"pending": 0, "revised_content": 0, "updated_fact": 0, "dismissed": 0});
+    {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT ca.author_decision, COUNT(*) FROM continuity_alerts ca
+             LEFT JOIN scenes s ON ca.scene_id = s.id
+             WHERE {} GROUP BY ca.author_decision",
+            filters
+        ))?;
+        let rows = stmt.query_map(filter_args.clone(), |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+        for row in rows {
+            let (decision, count) = row?;
+            by_decision[decision] = json!(count);
+        }
+    }
+    let decision = params.decision.map(|d| d.to_string());
+    let total = match &decision {
+        Some(d) => by_decision[d.as_str()].as_i64().unwrap_or(0),
+        None => by_decision.as_object().unwrap().values().filter_map(Value::as_i64).sum(),
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE {} AND (?5 IS NULL OR ca.author_decision = ?5)
+         ORDER BY ca.scene_id IS NULL, a.position, c.number, s.position,
+                  CASE ca.severity WHEN 'high' THEN 0 WHEN 'medium' THEN 1 ELSE 2 END, ca.created_at, ca.id
+         LIMIT ?6 OFFSET ?7",
+        ALERT_QUERY, filters
+    ))?;
+    let (project, chapter, scene, severity) = filter_args;
+    let alerts = stmt
+        .query_map((project, chapter, scene, severity, decision, limit, offset), alert_json)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    log::info!("Listed {} of {} continuity alerts for project {}", alerts.len(), total, project_id);
+
+    Ok(json!({
+        "projectId": project_id,
+        "alerts": alerts,
+        "total": total,
+        "byDecision": by_decision,
+        "limit": limit,
+        "offset": offset
+    }))
+}
+
+/// Record the author's decision on an alert, with optional notes
+pub fn resolve_continuity_alert(conn: &Connection, params: Value) -> Result<Value> {
+    let params: ResolveContinuityAlertParams = parse_params(params)?;
+    let alert_id = params.alert_id.to_string();
+    if params.decision == AuthorDecision::Pending {
+        return Err(StoryError::validation("decision must be revised_content, updated_fact or dismissed")
+            .with_field("decision"));
+    }
+
+    let updated = conn.execute(
+        "UPDATE continuity_alerts
+         SET author_decision = ?2, author_notes = COALESCE(?3, author_notes), resolved_at = ?4
+         WHERE id = ?1",
+        (&alert_id, params.decision.to_string(), &params.notes, Utc::now().to_rfc3339()),
+    )?;
+    if updated == 0 {
+        return Err(StoryError::not_found(format!("Continuity alert not found: {}", alert_id))
+            .with_field("alertId")
+            .with_entity("continuity_alert", &alert_id));
+    }
+
+    log::info!("Resolved continuity alert {} as {}", alert_id, params.decision);
+
+    Ok(conn.query_row(&format!("{} WHERE ca.id = ?1", ALERT_QUERY), [&alert_id], alert_json)?)
+}
+
+/// Columns read by [`alert_json`], with the scene's place in the manuscript
+const ALERT_QUERY: &str = "SELECT ca.id, ca.scene_id, c.number, s.position, ca.alert_type, ca.severity, ca.description,
+            ca.conflicting_elements, ca.suggested_resolution, ca.author_decision, ca.author_notes,
+            ca.detected_by, ca.created_at, ca.resolved_at
+     FROM continuity_alerts ca
+     LEFT JOIN scenes s ON ca.scene_id = s.id
+     LEFT JOIN chapters c ON s.chapter_id = c.id
+     LEFT JOIN acts a ON c.act_id = a.id";
+
+fn alert_json(row: &rusqlite::Row) -> rusqlite::Result<Value> {
+    // Stored as JSON by the checker, but free text is allowed
+    let conflicting: Option<String> = row.get(7)?;
+    let conflicting = conflicting.map(|text| serde_json::from_str(&text).unwrap_or(Value::String(text)));
+    Ok(json!({
+        "alertId": row.get::<_, String>(0)?,
+        "sceneId": row.get::<_, Option<String>>(1)?,
+        "chapterNumber": row.get::<_, Option<i32>>(2)?,
+        "scenePosition": row.get::<_, Option<i32>>(3)?,
+        "alertType": row.get::<_, String>(4)?,
+        "severity": row.get::<_, String>(5)?,
+        "description": row.get::<_, String>(6)?,
+        "conflictingElements": conflicting,
+        "suggestedResolution": row.get::<_, Option<String>>(8)?,
+        "authorDecision": row.get::<_, String>(9)?,
+        "authorNotes": row.get::<_, Option<String>>(10)?,
+        "detectedBy": row.get::<_, Option<String>>(11)?,
+        "createdAt": row.get::<_, String>(12)?,
+        "resolvedAt": row.get::<_, Option<String>>(13)?
+    }))
+}
+
+/// Outcome of checking a scene again
+pub(crate) struct Recheck {
+    pub project_id: String,
+    pub checked_text: &'static str,
+    /// Every current finding, as its alert with a `status`
+    pub alerts: Vec<Value>,
+    pub raised: usize,
+    pub reopened: usize,
+    /// Alerts this checker raised earlier that it no longer finds
+    pub closed: Vec<Value>,
+}
+
+/// Run the checker over a scene and bring its alerts up to date: record new
+/// findings, reopen resolved alerts that are found again (unless dismissed),
+/// and close pending ones that are no longer found. Closed alerts count as
+/// `revised_content` after a rewrite, and as `updated_fact` otherwise, since
+/// then only the recorded facts can have changed.
+pub(crate) fn recheck_scene(conn: &Connection, scene_id: &str, rewritten: bool) -> Result<Recheck> {
+    let project_id: String = conn
+        .query_row(
+            "SELECT ps.story_project_id
+             FROM scenes s
+             JOIN chapters c ON s.chapter_id = c.id
+             JOIN acts a ON c.act_id = a.id
+             JOIN plot_structures ps ON a.plot_structure_id = ps.id
+             WHERE s.id = ?1",
+            [scene_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| {
+            StoryError::not_found(format!("Scene not found: {}", scene_id))
+                .with_field("sceneId")
+                .with_entity("scene", scene_id)
+        })?;
+
+    let scenes = story_scenes(conn, &project_id)?;
+    let index = scenes.iter().position(|s| s.id == scene_id).expect("scene belongs to its project");
+    let scene = &scenes[index];
+
+    let previous = match index.checked_sub(1) {
+        Some(i) => {
+            let before = &scenes[i];
+            Some(PreviousScene {
+                label: before.label.clone(),
+                text: before.text.clone(),
+                location: before.location.clone(),
+                time_description: before.time_description.clone(),
+                cast: cast(conn, &before.id)?,
+            })
+        }
+        None => None,
+    };
+
+    let facts = SceneFacts {
+        label: scene.label.clone(),
+        text: scene.text.clone(),
+        location: scene.location.clone(),
+        time_description: scene.time_description.clone(),
+        characters: characters(conn, &project_id, scene, &scenes)?,
+        rules: rules(conn, &project_id)?,
+        previous,
+    };
+
+    let now = Utc::now().to_rfc3339();
+    let mut recheck = Recheck {
+        project_id,
+        checked_text: if scene.written { "content" } else { "outline" },
+        alerts: Vec::new(),
+        raised: 0,
+        reopened: 0,
+        closed: Vec::new(),
+    };
+    let mut found = Vec::new();
+    for finding in continuity::check(&facts) {
+        let (alert_id, status) = record(conn, &recheck.project_id, scene_id, DETECTOR, &finding, &now)?;
+        match status {
+            "new" => recheck.raised += 1,
+            "reopened" => recheck.reopened += 1,
+            _ => {}
+        }
+        recheck.alerts.push(json!({
+            "alertId": alert_id,
+            "alertType": finding.alert_type,
+            "severity": finding.severity,
+            "description": finding.description,
+            "conflictingElements": finding.conflicting_elements,
+            "suggestedResolution": finding.suggested_resolution,
+            "status": status
+        }));
+        found.push(alert_id);
+    }
+
+    let decision = if rewritten { AuthorDecision::RevisedContent } else { AuthorDecision::UpdatedFact };
+    let mut stmt = conn.prepare(
+        "SELECT id, alert_type, description FROM continuity_alerts
+         WHERE scene_id = ?1 AND detected_by = ?2 AND author_decision = 'pending'",
+    )?;
+    let open = stmt.query_map((scene_id, DETECTOR), |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+    })?;
+    for row in open {
+        let (alert_id, alert_type, description) = row?;
+        if found.contains(&alert_id) {
+            continue;
+        }
+        conn.execute(
+            "UPDATE continuity_alerts
+             SET author_decision = ?2, resolved_at = ?3,
+                 author_notes = COALESCE(author_notes || char(10), '') || ?4
+             WHERE id = ?1",
+            (&alert_id, decision.to_string(), &now, "Closed automatically: no longer found when the scene was checked again"),
+        )?;
+        recheck.closed.push(json!({
+            "alertId": alert_id,
+            "alertType": alert_type,
+            "description": description,
+            "authorDecision": decision
+        }));
+    }
+    Ok(recheck)
+}
+
+/// Every scene of the project in reading order; a scene's text is its prose, or its outline until it is written
+fn story_scenes(conn: &Connection, project_id: &str) -> Result<Vec<StoryScene>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.id, a.position, c.number, s.position, s.location, s.time_description,
+                s.content, COALESCE(s.scene_outline, '')
+         FROM scenes s
+         JOIN chapters c ON s.chapter_id = c.id
+         JOIN acts a ON c.act_id = a.id
+         JOIN plot_structures ps ON a.plot_structure_id = ps.id
+         WHERE ps.story_project_id = ?1
+         ORDER BY a.position, c.number, s.position",
+    )?;
+    let rows = stmt.query_map([project_id], |row| {
+        let order: (i32, i32, i32) = (row.get(1)?, row.get(2)?, row.get(3)?);
+        let content: String = row.get(6)?;
+        let written = !content.trim().is_empty();
+        Ok(StoryScene {
+            id: row.get(0)?,
+            order,
+            label: format!("chapter {} scene {}", order.1, order.2),
+            location: row.get(4)?,
+            time_description: row.get(5)?,
+            text: if written { content } else { row.get(7)? },
+            written,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// IDs of the characters cast in a scene
+fn cast(conn: &Connection, scene_id: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT character_id FROM scene_characters WHERE scene_id = ?1")?;
+    let ids = stmt.query_map([scene_id], |row| row.get(0))?;
+    Ok(ids.collect::<rusqlite::Result<_>>()?)
+}
+
+fn characters(conn: &Connection, project_id: &str, scene: &StoryScene, scenes: &[StoryScene]) -> Result<Vec<CharacterFacts>> {
+    let in_cast = cast(conn, &scene.id)?;
+    let later: HashMap<&str, &str> = scenes
+        .iter()
+        .filter(|s| s.order > scene.order)
+        .map(|s| (s.id.as_str(), s.label.as_str()))
+        .collect();
+
+    let mut stmt = conn.prepare(
+        "SELECT id, name, current_state, first_appearance_scene_id
+         FROM characters WHERE story_project_id = ?1 ORDER BY name",
+    )?;
+    let rows = stmt.query_map([project_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, Option<String>>(3)?,
+        ))
+    })?;
+
+    let mut characters = Vec::new();
+    for row in rows {
+        let (id, name, current_state, first_appearance) = row?;
+        // With a state history, what counts is the state as of this point in the
+        // story; a death recorded later must not flag the scenes before it
+        let (current_state, state_sources) = match state_before(conn, &id, scene.order)? {
+            Some((state, set_at)) => {
+                let sources: Vec<StateSource> = [("health", state.health), ("location", state.location), ("notes", state.notes)]
+                    .into_iter()
+                    .filter_map(|(field, value)| {
+                        let order = set_at.get(field)?;
+                        Some(StateSource {
+                            field: field.to_string(),
+                            value: value?,
+                            set_in: format!("chapter {} scene {}", order.1, order.2),
+                        })
+                    })
+                    .collect();
+                let text = sources.iter().map(|source| source.value.as_str()).collect::<Vec<_>>().join("; ");
+                ((!text.is_empty()).then_some(text), sources)
+            }
+            None => (current_state, Vec::new()),
+        };
+        characters.push(CharacterFacts {
+            cast: in_cast.contains(&id),
+            first_appears_later_in: first_appearance
+                .and_then(|first| later.get(first.as_str()).map(|label| label.to_string())),
+            id,
+            name,
+            current_state,
+            state_sources,
+        });
+    }
+    Ok(characters)
+}
+
+fn rules(conn: &Connection, project_id: &str) -> Result<Vec<RuleFacts>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, description, keywords FROM world_rules WHERE story_project_id = ?1 ORDER BY name",
+    )?;
+    let rows = stmt.query_map([project_id], |row| {
+        let keywords: Option<String> = row.get(3)?;
+        Ok(RuleFacts {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            keywords: scoring::keywords(keywords.as_deref().unwrap_or("")),
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Store a finding as an alert raised by `detector`, unless that checker already
+/// raised one about the same subject for the scene (see [`Finding::subject`]);
+/// that alert keeps its ID and author decision and takes the finding's current
+/// wording. Returns the alert ID and its status: `new`, `open` (still pending),
+/// `reopened` (it had been marked fixed) or `dismissed` (left as the author decided).
+pub(crate) fn record(
+    conn: &Connection,
+    project_id: &str,
+    scene_id: &str,
+    detector: &str,
+    finding: &Finding,
+    now: &str,
+) -> Result<(String, &'static str)> {
+    let alert_type = finding.alert_type.to_string();
+    let subject = finding.subject();
+    let mut stmt = conn.prepare(
+        "SELECT id, author_decision, conflicting_elements FROM continuity_alerts
+         WHERE scene_id = ?1 AND detected_by = ?2 AND alert_type = ?3
+         ORDER BY created_at",
+    )?;
+    let candidates = stmt
+        .query_map((scene_id, detector, &alert_type), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let existing = candidates.into_iter().find_map(|(id, decision, elements)| {
+        let elements: Value = elements.and_then(|e| serde_json::from_str(&e).ok()).unwrap_or_default();
+        (continuity::subject(&elements) == subject).then_some((id, decision))
+    });
+    if let Some((id, _)) = &existing {
+        conn.execute(
+            "UPDATE continuity_alerts
+             SET severity = ?2, description = ?3, conflicting_elements = ?4, suggested_resolution = ?5
+             WHERE id = ?1",
+            (
+                id,
+                finding.severity.to_string(),
+                &finding.description,
+                finding.conflicting_elements.to_string(),
+                &finding.suggested_resolution,
+            ),
+        )?;
+    }
+
+    match existing.map(|(id, decision)| (id, AuthorDecision::from_str(&decision))) {
+        Some((id, Some(AuthorDecision::Pending))) => Ok((id, "open")),
+        Some((id, Some(AuthorDecision::Dismissed))) => Ok((id, "dismissed")),
+        Some((id, _)) => {
+            conn.execute(
+                "UPDATE continuity_alerts
+                 SET author_decision = 'pending', resolved_at = NULL,
+                     author_notes = COALESCE(author_notes || char(10), '') || ?2
+                 WHERE id = ?1",
+                (&id, "Reopened automatically: found again when the scene was checked"),
+            )?;
+            Ok((id, "reopened"))
+        }
+        None => {
+            let id = Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO continuity_alerts (id, story_project_id, scene_id, alert_type, severity, description,
+                     conflicting_elements, suggested_resolution, detected_by, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                (
+                    &id,
+                    project_id,
+                    scene_id,
+                    &alert_type,
+                    finding.severity.to_string(),
+                    &finding.description,
+                    finding.conflicting_elements.to_string(),
+                    &finding.suggested_resolution,
+                    detector,
+                    now,
+                ),
+            )?;
+            Ok((id, "new"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::tools::{
+        add_chapter, add_character, add_scene, add_world_rule, create_story_project, initialize_plot_structure, update_scene,
+    };
+    use tempfile::{tempdir, TempDir};
+
+    struct Fixture {
+        project_id: String,
+        chapter_id: String,
+        scene_ids: Vec<String>,
+        mira: String,
+        stories: TempDir,
+    }
+
+    /// One chapter: Mira and Dax at the keep, then a scene at the harbor
+    fn fixture(conn: &Connection, second_scene: &str) -> Fixture {
+        let stories = tempdir().unwrap();
+        let project = create_story_project(conn, stories.path(), json!({"title": "Continuity", "targetLength": "novel"})).unwrap();
+        let project_id = project["projectId"].as_str().unwrap().to_string();
+        let character = |name: &str, state: &str| {
+            add_character(conn, json!({"projectId": project_id, "name": name, "role": "supporting", "currentState": state}))
+                .unwrap()["characterId"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        let mira = character("Mira Vale", "Travelling with Dax");
+        character("Dax", "Died defending the keep");
+        character("Sela", "At the harbor");
+        add_world_rule(
+            conn,
+            json!({"projectId": project_id, "name": "Gate Curfew", "description": "No one can cross the Silver Gate after dusk", "scope": "regional", "keywords": ["Silver Gate"]}),
+        )
+        .unwrap();
+
+        let plot = initialize_plot_structure(conn, json!({"projectId": project_id})).unwrap();
+        let chapter = add_chapter(conn, json!({"actId": plot["acts"][0]["actId"], "number": 1})).unwrap();
+        let chapter_id = chapter["chapterId"].as_str().unwrap().to_string();
+        let mut scene_ids = Vec::new();
+        for (location, outline) in [("Mountain Keep", "Mira and Dax hold the wall"), ("Harbor", second_scene)] {
+            let params = json!({"chapterId": chapter_id, "location": location, "sceneOutline": outline});
+            let scene = add_scene(conn, stories.path(), params).unwrap();
+            scene_ids.push(scene["sceneId"].as_str().unwrap().to_string());
+        }
+        Fixture { project_id, chapter_id, scene_ids, mira, stories }
+    }
+
+    struct Chapters {
+        mira: String,
+        /// The one scene of each chapter, in reading order
+        scene_ids: Vec<String>,
+        stories: TempDir,
+    }
+
+    /// Mira alone, through `count` chapters of one scene each
+    fn chapters(conn: &Connection, title: &str, count: i32) -> Chapters {
+        let stories = tempdir().unwrap();
+        let project = create_story_project(conn, stories.path(), json!({"title": title, "targetLength": "novel"})).unwrap();
+        let mira = add_character(conn, json!({"projectId": project["projectId"], "name": "Mira", "role": "protagonist"}))
+            .unwrap()["characterId"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let plot = initialize_plot_structure(conn, json!({"projectId": project["projectId"]})).unwrap();
+        let scene_ids = (1..=count)
+            .map(|number| {
+                let chapter = add_chapter(conn, json!({"actId": plot["acts"][0]["actId"], "number": number})).unwrap();
+                add_scene(conn, stories.path(), json!({"chapterId": chapter["chapterId"]})).unwrap()["sceneId"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        Chapters { mira, scene_ids, stories }
+    }
+
+    #[test]
+    fn test_check_records_alerts_once() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let fixture = fixture(&conn, "Mira and Dax crossed the Silver Gate at midnight. Sela waved.");
+        // Sela is introduced later in the book
+        let third = add_scene(&conn, fixture.stories.path(), json!({"chapterId": fixture.chapter_id, "location": "Harbor"})).unwrap();
+        conn.execute(
+            "UPDATE characters SET first_appearance_scene_id = ?1 WHERE name = 'Sela'",
+            [third["sceneId"].as_str().unwrap()],
+        )
+        .unwrap();
+
+        let result = check_scene_continuity(&conn, json!({"sceneId": fixture.scene_ids[1]})).unwrap();
+        assert_eq!(result["checkedText"], "outline");
+        let alerts = result["alerts"].as_array().unwrap();
+        let types: Vec<&str> = alerts.iter().map(|a| a["alertType"].as_str().unwrap()).collect();
+        assert_eq!(types, vec!["character_state_conflict", "timeline_contradiction", "world_rule_violation"]);
+        assert_eq!(alerts[0]["severity"], "high");
+        assert!(alerts[0]["description"].as_str().unwrap().starts_with("Dax appears in chapter 1 scene 2"));
+        assert!(alerts[1]["description"].as_str().unwrap().contains("first appearance in chapter 1 scene 3"));
+        assert_eq!(result["newAlerts"], 3);
+
+        let stored: (String, String) = conn
+            .query_row(
+                "SELECT author_decision, conflicting_elements FROM continuity_alerts WHERE id = ?1",
+                [alerts[2]["alertId"].as_str().unwrap()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(stored.0, "pending");
+        let elements: Value = serde_json::from_str(&stored.1).unwrap();
+        assert_eq!(elements["keyword"], "Silver Gate");
+
+        // Checking again finds the same problems without duplicating them
+        let again = check_scene_continuity(&conn, json!({"sceneId": fixture.scene_ids[1]})).unwrap();
+        assert_eq!(again["newAlerts"], 0);
+        assert_eq!(again["alerts"][0]["alertId"], alerts[0]["alertId"]);
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM continuity_alerts WHERE story_project_id = ?1", [&fixture.project_id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_location_jump_uses_previous_scene() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let fixture = fixture(&conn, "Mira watches the ships.");
+        conn.execute(
+            "INSERT INTO scene_characters (id, scene_id, character_id) VALUES (?1, ?2, ?3)",
+            (Uuid::new_v4().to_string(), &fixture.scene_ids[0], &fixture.mira),
+        )
+        .unwrap();
+
+        let result = check_scene_continuity(&conn, json!({"sceneId": fixture.scene_ids[1]})).unwrap();
+        let alerts = result["alerts"].as_array().unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0]["alertType"], "timeline_contradiction");
+        assert_eq!(alerts[0]["severity"], "low");
+        assert_eq!(alerts[0]["conflictingElements"]["previousLocation"], "Mountain Keep");
+
+        // The first scene has nothing before it and nobody out of place
+        let first = check_scene_continuity(&conn, json!({"sceneId": fixture.scene_ids[0]})).unwrap();
+        assert_eq!(first["alerts"].as_array().unwrap().len(), 1);
+        assert_eq!(first["alerts"][0]["alertType"], "character_state_conflict");
+    }
+
+    #[test]
+    fn test_list_and_resolve_alerts() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let fixture = fixture(&conn, "Dax crossed the Silver Gate alone.");
+        for scene_id in &fixture.scene_ids {
+            check_scene_continuity(&conn, json!({"sceneId": scene_id})).unwrap();
+        }
+        let list = |params: Value| list_continuity_alerts(&conn, params).unwrap();
+
+        let all = list(json!({"projectId": fixture.project_id}));
+        assert_eq!(all["total"], 3);
+        let positions: Vec<i64> = all["alerts"].as_array().unwrap().iter().map(|a| a["scenePosition"].as_i64().unwrap()).collect();
+        assert_eq!(positions, vec![1, 2, 2]);
+        // Most severe first within a scene
+        assert_eq!(all["alerts"][1]["severity"], "high");
+        assert_eq!(all["alerts"][2]["conflictingElements"]["keyword"], "Silver Gate");
+        assert_eq!(all["alerts"][0]["detectedBy"], DETECTOR);
+
+        let second = list(json!({"projectId": fixture.project_id, "sceneId": fixture.scene_ids[1], "severity": "medium"}));
+        assert_eq!(second["total"], 1);
+        let rule_alert = second["alerts"][0]["alertId"].as_str().unwrap().to_string();
+
+        let resolved = resolve_continuity_alert(
+            &conn,
+            json!({"alertId": rule_alert, "decision": "dismissed", "notes": "The gate was left open on purpose"}),
+        )
+        .unwrap();
+        assert_eq!(resolved["authorDecision"], "dismissed");
+        assert_eq!(resolved["authorNotes"], "The gate was left open on purpose");
+        assert!(resolved["resolvedAt"].is_string());
+
+        let pending = list(json!({"projectId": fixture.project_id, "decision": "pending"}));
+        assert_eq!(pending["total"], 2);
+        assert_eq!(pending["byDecision"], json!({"pending": 2, "revised_content": 0, "updated_fact": 0, "dismissed": 1}));
+        let page = list(json!({"projectId": fixture.project_id, "limit": 1, "offset": 1}));
+        assert_eq!(page["alerts"].as_array().unwrap().len(), 1);
+        assert_eq!(page["total"], 3);
+
+        // A dismissed alert stays dismissed when found again
+        let again = check_scene_continuity(&conn, json!({"sceneId": fixture.scene_ids[1]})).unwrap();
+        assert_eq!(again["newAlerts"], 0);
+        let statuses: Vec<&str> = again["alerts"].as_array().unwrap().iter().map(|a| a["status"].as_str().unwrap()).collect();
+        assert_eq!(statuses, vec!["open", "dismissed"]);
+
+        let err = resolve_continuity_alert(&conn, json!({"alertId": rule_alert, "decision": "pending"})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+        let err = resolve_continuity_alert(&conn, json!({"alertId": Uuid::new_v4(), "decision": "dismissed"})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+        let err = list_continuity_alerts(&conn, json!({"projectId": Uuid::new_v4()})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_rewrite_closes_and_reopens_alerts() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let fixture = fixture(&conn, "Dax keeps watch over the harbor.");
+        let scene_id = &fixture.scene_ids[1];
+        // Dax is dead, and came from the keep with no journey described
+        let alerts = check_scene_continuity(&conn, json!({"sceneId": scene_id})).unwrap()["alerts"].clone();
+        assert_eq!(alerts.as_array().unwrap().len(), 2);
+        let dead = alerts[0]["alertId"].clone();
+
+        // Writing Dax out of the scene closes both as revised
+        let rewrite = update_scene(&conn, fixture.stories.path(), json!({"sceneId": scene_id, "content": "Sela keeps watch over the harbor."})).unwrap();
+        assert_eq!(rewrite["status"], "written");
+        let closed = rewrite["continuity"]["closedAlerts"].as_array().unwrap();
+        assert_eq!(closed.len(), 2);
+        assert!(closed.iter().all(|a| a["authorDecision"] == "revised_content"));
+        let stored = list_continuity_alerts(&conn, json!({"projectId": fixture.project_id, "sceneId": scene_id})).unwrap();
+        assert_eq!(stored["byDecision"]["revised_content"], 2);
+        assert!(stored["alerts"][0]["authorNotes"].as_str().unwrap().starts_with("Closed automatically"));
+
+        // Bringing him back reopens the same alerts instead of raising new ones
+        let rewrite = update_scene(&conn, fixture.stories.path(), json!({"sceneId": scene_id, "content": "Dax and Sela keep watch."})).unwrap();
+        assert_eq!(rewrite["continuity"]["reopenedAlerts"], 2);
+        assert_eq!(rewrite["continuity"]["newAlerts"], 0);
+        assert_eq!(rewrite["continuity"]["alerts"][0]["alertId"], dead);
+        assert_eq!(rewrite["continuity"]["alerts"][0]["status"], "reopened");
+
+        // Correcting the fact instead closes it as updated_fact
+        conn.execute("UPDATE characters SET current_state = 'Recovering' WHERE name = 'Dax'", []).unwrap();
+        let check = check_scene_continuity(&conn, json!({"sceneId": scene_id})).unwrap();
+        assert_eq!(check["closedAlerts"].as_array().unwrap().len(), 1);
+        assert_eq!(check["closedAlerts"][0]["alertId"], dead);
+        assert_eq!(check["closedAlerts"][0]["authorDecision"], "updated_fact");
+
+        // Alerts recorded some other way are left alone
+        conn.execute(
+            "INSERT INTO continuity_alerts (id, story_project_id, scene_id, alert_type, description)
+             VALUES ('manual-1', ?1, ?2, 'factual_inconsistency', 'The harbor has no lighthouse')",
+            (&fixture.project_id, scene_id),
+        )
+        .unwrap();
+        update_scene(&conn, fixture.stories.path(), json!({"sceneId": scene_id, "content": "Quiet water."})).unwrap();
+        let decision: String = conn
+            .query_row("SELECT author_decision FROM continuity_alerts WHERE id = 'manual-1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(decision, "pending");
+    }
+
+    #[test]
+    fn test_dismissed_alert_survives_renumbering_and_rewording() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let fixture = fixture(&conn, "Dax keeps watch over the harbor.");
+        let scene_id = &fixture.scene_ids[1];
+        let alerts = check_scene_continuity(&conn, json!({"sceneId": scene_id})).unwrap()["alerts"].clone();
+        let dead = alerts[0]["alertId"].clone();
+        assert!(alerts[0]["description"].as_str().unwrap().contains("chapter 1 scene 2"));
+        resolve_continuity_alert(&conn, json!({"alertId": dead, "decision": "dismissed"})).unwrap();
+
+        // A scene inserted before it renumbers it, and his death is described differently
+        conn.execute("UPDATE scenes SET position = 3 WHERE id = ?1", [scene_id]).unwrap();
+        conn.execute("UPDATE characters SET current_state = 'Died holding the gate' WHERE name = 'Dax'", []).unwrap();
+        let check = check_scene_continuity(&conn, json!({"sceneId": scene_id})).unwrap();
+        assert_eq!(check["newAlerts"], 0);
+        assert!(check["closedAlerts"].as_array().unwrap().is_empty());
+        let again = check["alerts"].as_array().unwrap().iter().find(|a| a["alertId"] == dead).unwrap();
+        assert_eq!(again["status"], "dismissed");
+
+        // The stored alert reads as of the latest check and keeps the author's decision
+        let stored = list_continuity_alerts(&conn, json!({"projectId": fixture.project_id, "sceneId": scene_id})).unwrap();
+        let stored = stored["alerts"].as_array().unwrap().iter().find(|a| a["alertId"] == dead).unwrap().clone();
+        assert_eq!(stored["authorDecision"], "dismissed");
+        assert!(stored["description"].as_str().unwrap().contains("chapter 1 scene 3"));
+        assert!(stored["description"].as_str().unwrap().contains("Died holding the gate"));
+    }
+
+    #[test]
+    fn test_state_history_checked_as_of_each_scene() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = chapters(&conn, "Deaths", 4);
+        // Mira dies at the end of chapter 3; her current state already says so
+        crate::tools::record_character_state(
+            &conn,
+            json!({"characterId": story.mira, "sceneId": story.scene_ids[2], "state": {"health": "Dead, drowned"}}),
+        )
+        .unwrap();
+        conn.execute("UPDATE characters SET current_state = 'Dead, drowned' WHERE id = ?1", [&story.mira]).unwrap();
+
+        let write = |scene: usize| {
+            let content = "Mira walked along the pier.";
+            update_scene(&conn, story.stories.path(), json!({"sceneId": story.scene_ids[scene], "content": content})).unwrap()["continuity"]["alerts"]
+                .clone()
+        };
+        assert!(write(0).as_array().unwrap().is_empty());
+        // She is still alive during the scene in which she dies
+        assert!(write(2).as_array().unwrap().is_empty());
+
+        let after = write(3);
+        assert_eq!(after[0]["severity"], "high");
+        assert_eq!(after[0]["conflictingElements"]["stateSetIn"], "chapter 3 scene 1");
+        assert_eq!(after[0]["conflictingElements"]["stateField"], "health");
+    }
+
+    #[test]
+    fn test_state_history_blames_the_field_that_matched() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = chapters(&conn, "Exile", 4);
+        let record = |scene: usize, state: Value| {
+            let params = json!({"characterId": story.mira, "sceneId": story.scene_ids[scene], "state": state});
+            crate::tools::record_character_state(&conn, params).unwrap();
+        };
+        // Exiled in chapter 1; her notes change in chapter 2 without saying so again
+        record(0, json!({"location": "Exiled to the north"}));
+        record(1, json!({"notes": "Writes letters home"}));
+
+        let content = "Mira walked along the pier.";
+        let alerts = update_scene(&conn, story.stories.path(), json!({"sceneId": story.scene_ids[3], "content": content})).unwrap()["continuity"]["alerts"].clone();
+        assert_eq!(alerts.as_array().unwrap().len(), 1);
+        assert_eq!(alerts[0]["severity"], "medium");
+        assert_eq!(alerts[0]["conflictingElements"]["stateField"], "location");
+        assert_eq!(alerts[0]["conflictingElements"]["stateSetIn"], "chapter 1 scene 1");
+        assert!(alerts[0]["description"].as_str().unwrap().contains("absent after chapter 1 scene 1"));
+    }
+
+    #[test]
+    fn test_unknown_scene() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let err = check_scene_continuity(&conn, json!({"sceneId": Uuid::new_v4()})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+    }
+}
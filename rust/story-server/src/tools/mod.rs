@@ -1,11 +1,110 @@
 // MCP tool implementations for User Story 1 (MVP)
 
+pub mod arc;
 pub mod character;
+pub mod context;
+pub mod continuity;
 pub mod plot;
+pub mod progression;
 pub mod project;
+pub mod search;
+pub mod state;
+pub mod summary;
 pub mod world;
 
-pub use character::{add_character, add_character_relationship, get_character, list_characters};
-pub use plot::{add_chapter, add_scene, get_plot_structure, initialize_plot_structure};
+pub use arc::{
+    add_arc_milestone, create_character_arc, get_arc_schedule_report, get_character_arc, list_character_arcs,
+    link_milestone_scene, unlink_milestone_scene,
+};
+pub use character::{add_character, add_character_relationship, get_character, list_characters, update_character};
+pub use context::get_scene_context;
+pub use continuity::{check_scene_continuity, list_continuity_alerts, resolve_continuity_alert};
+pub use plot::{add_chapter, add_scene, get_plot_structure, initialize_plot_structure, update_scene};
+pub use progression::{
+    check_progression, disable_progression_system, enable_progression_system, get_progression_sheet, list_progression_sheets,
+    list_progression_systems, list_progression_templates, record_progression_sheet, render_status_screen,
+    validate_system_definition,
+};
 pub use project::{create_story_project, list_story_projects, load_story_project};
-pub use world::{add_world_rule, get_world_rule, list_world_rules};
+pub use search::{rebuild_search_index, search};
+pub use state::{get_character_state, list_character_states, record_character_state};
+pub use summary::{get_summary, get_summary_rollup, invalidate_summary, list_summaries, upsert_summary};
+pub use world::{add_world_rule, get_world_rule, list_world_rules, update_world_rule};
+
+use crate::error::{Result, StoryError};
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Generate the `inputSchema` advertised in `tools/list` from a tool's parameter struct
+pub fn input_schema<T: JsonSchema>() -> Value {
+    let generator = SchemaSettings::draft07()
+        .with(|s| {
+            s.inline_subschemas = true;
+            s.option_add_null_type = false;
+        })
+        .into_generator();
+    let mut schema = serde_json::to_value(generator.into_root_schema_for::<T>())
+        .expect("generated schema is valid JSON");
+
+    if let Some(obj) = schema.as_object_mut() {
+        obj.remove("$schema");
+        obj.remove("title");
+    }
+    schema
+}
+
+/// Deserialize raw tool arguments into the tool's parameter struct
+pub fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T> {
+    serde_json::from_value(params).map_err(|e| {
+        let message = e.to_string();
+        match message.strip_prefix("missing field `").and_then(|rest| rest.split('`').next()) {
+            Some(field) => StoryError::validation(format!("Missing required field: {}", field)).with_field(field),
+            None => StoryError::validation(format!("Invalid parameters: {}", message)),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    /// Sample parameters
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[serde(rename_all = "camelCase", deny_unknown_fields)]
+    struct SampleParams {
+        /// Project to act on
+        project_id: uuid::Uuid,
+        label: Option<String>,
+    }
+
+    #[test]
+    fn test_input_schema_from_struct() {
+        let schema = input_schema::<SampleParams>();
+
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["required"], json!(["projectId"]));
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(schema["properties"]["projectId"]["format"], "uuid");
+        assert_eq!(schema["properties"]["projectId"]["description"], "Project to act on");
+        assert_eq!(schema["properties"]["label"]["type"], "string");
+        assert!(schema.get("$schema").is_none());
+    }
+
+    #[test]
+    fn test_parse_params() {
+        let id = uuid::Uuid::new_v4();
+        let params: SampleParams = parse_params(json!({"projectId": id.to_string(), "label": "x"})).unwrap();
+        assert_eq!(params.project_id, id);
+        assert_eq!(params.label.as_deref(), Some("x"));
+    }
+
+    #[test]
+    fn test_parse_params_missing_field() {
+        let err = parse_params::<SampleParams>(json!({"label": "x"})).unwrap_err();
+        assert_eq!(err.to_string(), "Validation error: Missing required field: projectId");
+    }
+}
@@ -1,29 +1,77 @@
+use super::parse_params;
+use crate::context::tokens;
 use crate::error::{Result, StoryError};
 use crate::models::{PlotStructure, StructureType};
 use chrono::Utc;
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
+use schemars::JsonSchema;
+use serde::Deserialize;
 use serde_json::{json, Value};
 use uuid::Uuid;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Parameters for `initializePlotStructure`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct InitializePlotStructureParams {
+    pub project_id: Uuid,
+    /// Defaults to three_act
+    pub structure_type: Option<StructureType>,
+}
 
-/// Initialize plot structure for a story project
-pub fn initialize_plot_structure(conn: &Connection, params: Value) -> Result<Value> {
-    let project_id_str = params
-        .get("projectId")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: projectId"))?;
+/// Parameters for `addChapter`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AddChapterParams {
+    /// Act the chapter belongs to
+    pub act_id: Uuid,
+    /// Chapter number, unique within the story
+    #[schemars(range(min = 1))]
+    pub number: i32,
+    pub title: Option<String>,
+}
+
+/// Parameters for `addScene`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AddSceneParams {
+    pub chapter_id: Uuid,
+    pub title: Option<String>,
+    pub location: Option<String>,
+    pub time_description: Option<String>,
+    pub scene_outline: Option<String>,
+    /// Scene prose; when present it is also written to the story folder
+    pub content: Option<String>,
+}
 
-    let project_id = Uuid::parse_str(project_id_str)
-        .map_err(|_| StoryError::validation("Invalid UUID format for projectId"))?;
+/// Parameters for `updateScene`; omitted fields are left unchanged
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdateSceneParams {
+    pub scene_id: Uuid,
+    pub title: Option<String>,
+    pub location: Option<String>,
+    pub time_description: Option<String>,
+    pub scene_outline: Option<String>,
+    /// New prose; replaces the scene file, or removes it when empty, and re-checks the scene's continuity alerts
+    pub content: Option<String>,
+}
 
-    let structure_type_str = params
-        .get("structureType")
-        .and_then(|v| v.as_str())
-        .unwrap_or("three_act");
+/// Parameters for `getPlotStructure`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GetPlotStructureParams {
+    pub project_id: Uuid,
+    /// Also list each chapter's scenes with what their content and summaries cost to load into context
+    pub include_tokens: Option<bool>,
+}
 
-    let structure_type = StructureType::from_str(structure_type_str)
-        .ok_or_else(|| StoryError::validation(format!("Invalid structureType: {}", structure_type_str)))?;
+/// Initialize plot structure for a story project
+pub fn initialize_plot_structure(conn: &Connection, params: Value) -> Result<Value> {
+    let params: InitializePlotStructureParams = parse_params(params)?;
+    let project_id = params.project_id;
+    let structure_type = params.structure_type.unwrap_or(StructureType::ThreeAct);
 
     let plot_structure = PlotStructure {
         id: Uuid::new_v4(),
@@ -46,7 +94,16 @@ pub fn initialize_plot_structure(conn: &Connection, params: Value) -> Result<Val
         ),
     ).map_err(|e| {
         if e.to_string().contains("UNIQUE constraint failed") {
-            StoryError::duplicate("Plot structure already exists for this project")
+            let err = StoryError::duplicate("Plot structure already exists for this project")
+                .with_field("projectId");
+            match conn.query_row(
+                "SELECT id FROM plot_structures WHERE story_project_id = ?1",
+                [project_id.to_string()],
+                |row| row.get::<_, String>(0),
+            ) {
+                Ok(existing_id) => err.with_entity("plot_structure", existing_id),
+                Err(_) => err,
+            }
         } else {
             StoryError::DatabaseError(e)
         }
@@ -110,19 +167,32 @@ pub fn initialize_plot_structure(conn: &Connection, params: Value) -> Result<Val
 
 /// Add a chapter to an act
 pub fn add_chapter(conn: &Connection, params: Value) -> Result<Value> {
-    let act_id_str = params
-        .get("actId")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: actId"))?;
-
-    let act_id = Uuid::parse_str(act_id_str)
-        .map_err(|_| StoryError::validation("Invalid UUID format for actId"))?;
-
-    let title = params.get("title").and_then(|v| v.as_str());
-    let number = params
-        .get("number")
-        .and_then(|v| v.as_i64())
-        .ok_or_else(|| StoryError::validation("Missing required field: number"))?;
+    let params: AddChapterParams = parse_params(params)?;
+    let act_id = params.act_id;
+    let title = params.title.as_deref();
+    let number = params.number;
+
+    // Chapters are numbered through the whole story, not per act, so that
+    // `story://project/{id}/chapter/{n}` names exactly one of them
+    let existing: Option<String> = conn
+        .query_row(
+            "SELECT c.id
+             FROM chapters c
+             JOIN acts a ON c.act_id = a.id
+             JOIN plot_structures ps ON a.plot_structure_id = ps.id
+             WHERE c.number = ?2 AND ps.story_project_id = (
+                 SELECT ps.story_project_id FROM acts a
+                 JOIN plot_structures ps ON a.plot_structure_id = ps.id
+                 WHERE a.id = ?1)",
+            (act_id.to_string(), number),
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(existing_id) = existing {
+        return Err(StoryError::duplicate(format!("Chapter {} already exists in this story", number))
+            .with_field("number")
+            .with_entity("chapter", existing_id));
+    }
 
     // Get current max position for global chapter ordering
     let position: i32 = conn
@@ -142,7 +212,7 @@ pub fn add_chapter(conn: &Connection, params: Value) -> Result<Value> {
             chapter_id.to_string(),
             act_id.to_string(),
             title,
-            number as i32,
+            number,
             position,
             "planned",
             0,
@@ -151,7 +221,16 @@ pub fn add_chapter(conn: &Connection, params: Value) -> Result<Value> {
         ),
     ).map_err(|e| {
         if e.to_string().contains("UNIQUE constraint failed") {
-            StoryError::duplicate(format!("Chapter {} already exists in this act", number))
+            let err = StoryError::duplicate(format!("Chapter {} already exists in this story", number))
+                .with_field("number");
+            match conn.query_row(
+                "SELECT id FROM chapters WHERE act_id = ?1 AND number = ?2",
+                (act_id.to_string(), number),
+                |row| row.get::<_, String>(0),
+            ) {
+                Ok(existing_id) => err.with_entity("chapter", existing_id),
+                Err(_) => err,
+            }
         } else {
             StoryError::DatabaseError(e)
         }
@@ -169,21 +248,15 @@ pub fn add_chapter(conn: &Connection, params: Value) -> Result<Value> {
     }))
 }
 
-/// Add a scene to a chapter
-pub fn add_scene(conn: &Connection, params: Value) -> Result<Value> {
-    let chapter_id_str = params
-        .get("chapterId")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: chapterId"))?;
-
-    let chapter_id = Uuid::parse_str(chapter_id_str)
-        .map_err(|_| StoryError::validation("Invalid UUID format for chapterId"))?;
-
-    let title = params.get("title").and_then(|v| v.as_str());
-    let location = params.get("location").and_then(|v| v.as_str());
-    let time_description = params.get("timeDescription").and_then(|v| v.as_str());
-    let scene_outline = params.get("sceneOutline").and_then(|v| v.as_str());
-    let content = params.get("content").and_then(|v| v.as_str()).unwrap_or("");
+/// Add a scene to a chapter; prose is written under `stories_root`
+pub fn add_scene(conn: &Connection, stories_root: &Path, params: Value) -> Result<Value> {
+    let params: AddSceneParams = parse_params(params)?;
+    let chapter_id = params.chapter_id;
+    let title = params.title.as_deref();
+    let location = params.location.as_deref();
+    let time_description = params.time_description.as_deref();
+    let scene_outline = params.scene_outline.as_deref();
+    let content = params.content.as_deref().unwrap_or("");
 
     // Get current max position in chapter
     let position: i32 = conn
@@ -194,33 +267,13 @@ pub fn add_scene(conn: &Connection, params: Value) -> Result<Value> {
         )
         .unwrap_or(1);
 
-    // Get project info and chapter number for file path
-    let (project_title, series_json, chapter_number): (String, Option<String>, i32) = conn
-        .query_row(
-            "SELECT sp.title, sp.metadata, c.number
-             FROM chapters c
-             JOIN acts a ON c.act_id = a.id
-             JOIN plot_structures ps ON a.plot_structure_id = ps.id
-             JOIN story_projects sp ON ps.story_project_id = sp.id
-             WHERE c.id = ?1",
-            [chapter_id.to_string()],
-            |row| Ok((
-                row.get(0)?,
-                row.get(1)?,
-                row.get(2)?
-            )),
-        )
-        .map_err(|_| StoryError::not_found("Chapter not found or project info unavailable"))?;
-
-    // Extract series from metadata JSON
-    let series = if let Some(metadata_str) = series_json {
-        serde_json::from_str::<Value>(&metadata_str)
-            .ok()
-            .and_then(|v| v.get("series").and_then(|s| s.as_str()).map(|s| s.to_string()))
-            .unwrap_or_else(|| "standalone".to_string())
-    } else {
-        "standalone".to_string()
-    };
+    let chapter_number: i32 = conn
+        .query_row("SELECT number FROM chapters WHERE id = ?1", [chapter_id.to_string()], |row| row.get(0))
+        .map_err(|_| {
+            StoryError::not_found("Chapter not found or project info unavailable")
+                .with_field("chapterId")
+                .with_entity("chapter", chapter_id)
+        })?;
 
     // Calculate word count if content is provided
     let word_count = if !content.is_empty() {
@@ -229,35 +282,6 @@ pub fn add_scene(conn: &Connection, params: Value) -> Result<Value> {
         0
     };
 
-    // Write content to file if provided
-    let mut file_path_str = String::new();
-    if !content.is_empty() {
-        let sanitized_title = project_title.replace("/", "-").replace("\\", "-");
-        let sanitized_series = series.replace("/", "-").replace("\\", "-");
-
-        let story_path = PathBuf::from("stories")
-            .join(&sanitized_series)
-            .join(&sanitized_title)
-            .join("chapters")
-            .join(format!("chapter-{:02}", chapter_number));
-
-        let scenes_path = story_path.join("scenes");
-
-        // Create directories
-        if let Err(e) = fs::create_dir_all(&scenes_path) {
-            log::warn!("Failed to create scene directories: {}", e);
-        }
-
-        // Write scene file
-        let scene_file = scenes_path.join(format!("scene-{:02}.txt", position));
-        if let Err(e) = fs::write(&scene_file, content) {
-            log::warn!("Failed to write scene file: {}", e);
-        } else {
-            file_path_str = scene_file.to_string_lossy().to_string();
-            log::info!("Wrote scene content to: {}", file_path_str);
-        }
-    }
-
     let scene_id = Uuid::new_v4();
 
     conn.execute(
@@ -282,6 +306,19 @@ pub fn add_scene(conn: &Connection, params: Value) -> Result<Value> {
 
     log::info!("Created scene: {} (position {})", scene_id, position);
 
+    // Written once the scene is stored, so a failed insert leaves no file behind
+    let file_path_str = if !content.is_empty() {
+        match write_scene_file(conn, stories_root, &chapter_id.to_string(), chapter_number, position, content) {
+            Err(StoryError::IoError(e)) => {
+                log::warn!("Failed to write scene file: {}", e);
+                String::new()
+            }
+            written => written?,
+        }
+    } else {
+        String::new()
+    };
+
     let mut response = json!({
         "sceneId": scene_id.to_string(),
         "title": title,
@@ -299,15 +336,207 @@ pub fn add_scene(conn: &Connection, params: Value) -> Result<Value> {
 
     Ok(response)
 }
+
+/// Rewrite a scene's outline, details or prose. New content replaces the
+/// scene file, cleared content removes it, and the scene's continuity alerts
+/// are checked again; if the file can't be written, neither the scene nor
+/// its alerts change. Scene files live under `stories_root`.
+pub fn update_scene(conn: &Connection, stories_root: &Path, params: Value) -> Result<Value> {
+    let params: UpdateSceneParams = parse_params(params)?;
+    let scene_id = params.scene_id.to_string();
+
+    if params.title.is_none()
+        && params.location.is_none()
+        && params.time_description.is_none()
+        && params.scene_outline.is_none()
+        && params.content.is_none()
+    {
+        return Err(StoryError::validation("No fields to update"));
+    }
+
+    let (chapter_id, chapter_number, position, old_content, old_outline, status): (String, i32, i32, String, Option<String>, String) = conn
+        .query_row(
+            "SELECT s.chapter_id, c.number, s.position, s.content, s.scene_outline, s.status
+             FROM scenes s JOIN chapters c ON s.chapter_id = c.id
+             WHERE s.id = ?1",
+            [&scene_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
+        )
+        .map_err(|_| {
+            StoryError::not_found(format!("Scene not found: {}", scene_id))
+                .with_field("sceneId")
+                .with_entity("scene", &scene_id)
+        })?;
+
+    let content = params.content.as_deref();
+    let rewritten = content.is_some_and(|c| c != old_content);
+    // Until there is prose, the outline is what continuity checks read
+    let unwritten = content.unwrap_or(&old_content).trim().is_empty();
+    let outline_changed = params.scene_outline.as_ref().is_some_and(|o| Some(o) != old_outline.as_ref());
+    let word_count = content.map(|c| c.split_whitespace().count() as i32);
+    let status = match content {
+        Some(c) if !c.is_empty() && status == "planned" => "written".to_string(),
+        _ => status,
+    };
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "UPDATE scenes SET
+            title = COALESCE(?2, title),
+            location = COALESCE(?3, location),
+            time_description = COALESCE(?4, time_description),
+            scene_outline = COALESCE(?5, scene_outline),
+            content = COALESCE(?6, content),
+            word_count = COALESCE(?7, word_count),
+            status = ?8,
+            updated_at = ?9
+         WHERE id = ?1",
+        (
+            &scene_id,
+            &params.title,
+            &params.location,
+            &params.time_description,
+            &params.scene_outline,
+            content,
+            word_count,
+            &status,
+            Utc::now().to_rfc3339(),
+        ),
+    )?;
+
+    let mut response = tx.query_row(
+        "SELECT title, position, location, time_description, scene_outline, status, word_count FROM scenes WHERE id = ?1",
+        [&scene_id],
+        |row| {
+            Ok(json!({
+                "sceneId": scene_id,
+                "title": row.get::<_, Option<String>>(0)?,
+                "position": row.get::<_, i32>(1)?,
+                "location": row.get::<_, Option<String>>(2)?,
+                "timeDescription": row.get::<_, Option<String>>(3)?,
+                "sceneOutline": row.get::<_, Option<String>>(4)?,
+                "status": row.get::<_, String>(5)?,
+                "wordCount": row.get::<_, i32>(6)?
+            }))
+        },
+    )?;
+
+    // Alerts about the old text may no longer apply, and fixed ones may be back
+    if rewritten || (unwritten && outline_changed) {
+        let recheck = super::continuity::recheck_scene(&tx, &scene_id, true)?;
+        response["continuity"] = json!({
+            "alerts": recheck.alerts,
+            "newAlerts": recheck.raised,
+            "reopenedAlerts": recheck.reopened,
+            "closedAlerts": recheck.closed
+        });
+    }
+
+    // New prose is staged before the commit so a failed write rolls the update back,
+    // and only replaces the scene file once the update is committed
+    let file = scene_file(&tx, stories_root, &chapter_id, chapter_number, position)?;
+    let staged = match content.filter(|c| rewritten && !c.is_empty()) {
+        Some(c) => Some(stage_scene_file(&file, c)?),
+        None => None,
+    };
+    if let Err(e) = tx.commit() {
+        if let Some(staged) = &staged {
+            let _ = fs::remove_file(staged);
+        }
+        return Err(e.into());
+    }
+    match staged {
+        Some(staged) => {
+            fs::rename(&staged, &file)?;
+            log::info!("Wrote scene content to: {}", file.display());
+            response["filePath"] = json!(file.to_string_lossy());
+        }
+        None if rewritten => match fs::remove_file(&file) {
+            Ok(()) => log::info!("Removed cleared scene file: {}", file.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("Failed to remove cleared scene file {}: {}", file.display(), e),
+        },
+        None => {}
+    }
+
+    log::info!("Updated scene: {}{}", scene_id, if rewritten { " (content rewritten)" } else { "" });
+
+    Ok(response)
+}
+
+/// Where a scene's prose is kept under the story folder
+fn scene_file(
+    conn: &Connection,
+    stories_root: &Path,
+    chapter_id: &str,
+    chapter_number: i32,
+    position: i32,
+) -> Result<PathBuf> {
+    let (project_title, series_json): (String, Option<String>) = conn.query_row(
+        "SELECT sp.title, sp.metadata
+         FROM chapters c
+         JOIN acts a ON c.act_id = a.id
+         JOIN plot_structures ps ON a.plot_structure_id = ps.id
+         JOIN story_projects sp ON ps.story_project_id = sp.id
+         WHERE c.id = ?1",
+        [chapter_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    // Extract series from metadata JSON
+    let series = if let Some(metadata_str) = series_json {
+        serde_json::from_str::<Value>(&metadata_str)
+            .ok()
+            .and_then(|v| v.get("series").and_then(|s| s.as_str()).map(|s| s.to_string()))
+            .unwrap_or_else(|| "standalone".to_string())
+    } else {
+        "standalone".to_string()
+    };
+
+    let sanitized_title = project_title.replace("/", "-").replace("\\", "-");
+    let sanitized_series = series.replace("/", "-").replace("\\", "-");
+
+    let story_path = stories_root
+        .join(&sanitized_series)
+        .join(&sanitized_title)
+        .join("chapters")
+        .join(format!("chapter-{:02}", chapter_number));
+
+    Ok(story_path.join("scenes").join(format!("scene-{:02}.txt", position)))
+}
+
+/// Write a scene's prose under the story folder, returning the path
+fn write_scene_file(
+    conn: &Connection,
+    stories_root: &Path,
+    chapter_id: &str,
+    chapter_number: i32,
+    position: i32,
+    content: &str,
+) -> Result<String> {
+    let file = scene_file(conn, stories_root, chapter_id, chapter_number, position)?;
+    fs::rename(stage_scene_file(&file, content)?, &file)?;
+    let file_path = file.to_string_lossy().to_string();
+    log::info!("Wrote scene content to: {}", file_path);
+    Ok(file_path)
+}
+
+/// Write prose next to `file`, returning the staged path to rename over it
+fn stage_scene_file(file: &Path, content: &str) -> Result<PathBuf> {
+    if let Some(folder) = file.parent() {
+        fs::create_dir_all(folder)?;
+    }
+    let staged = file.with_extension("txt.partial");
+    fs::write(&staged, content)?;
+    Ok(staged)
+}
+
+
 /// Get complete plot structure for a project
 pub fn get_plot_structure(conn: &Connection, params: Value) -> Result<Value> {
-    let project_id_str = params
-        .get("projectId")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: projectId"))?;
-
-    let project_id = Uuid::parse_str(project_id_str)
-        .map_err(|_| StoryError::validation("Invalid UUID format for projectId"))?;
+    let GetPlotStructureParams { project_id, include_tokens } = parse_params(params)?;
+    let include_tokens = include_tokens.unwrap_or(false);
+    let mut totals = TokenTotals::default();
 
     // Get plot structure
     let mut stmt = conn.prepare(
@@ -321,6 +550,8 @@ pub fn get_plot_structure(conn: &Connection, params: Value) -> Result<Value> {
         .map_err(|e| {
             if e == rusqlite::Error::QueryReturnedNoRows {
                 StoryError::not_found("Plot structure not found for this project")
+                    .with_field("projectId")
+                    .with_entity("project", project_id)
             } else {
                 StoryError::DatabaseError(e)
             }
@@ -349,7 +580,7 @@ pub fn get_plot_structure(conn: &Connection, params: Value) -> Result<Value> {
             "SELECT id, title, number, status, word_count FROM chapters WHERE act_id = ?1 ORDER BY number"
         )?;
 
-        let chapters = chapter_stmt
+        let mut chapters = chapter_stmt
             .query_map([&act_id], |row| {
                 Ok(json!({
                     "chapterId": row.get::<_, String>(0)?,
@@ -361,20 +592,88 @@ pub fn get_plot_structure(conn: &Connection, params: Value) -> Result<Value> {
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
-        acts.push(json!({
+        let mut act = json!({
             "actId": act_id,
             "name": name,
             "position": position,
-            "description": description,
-            "chapters": chapters
-        }));
+            "description": description
+        });
+        if include_tokens {
+            let mut act_totals = TokenTotals::default();
+            for chapter in &mut chapters {
+                let chapter_id = chapter["chapterId"].as_str().unwrap_or_default().to_string();
+                let (scenes, chapter_totals) = scene_tokens(conn, &chapter_id)?;
+                chapter["scenes"] = json!(scenes);
+                chapter_totals.write_to(chapter);
+                act_totals.add(chapter_totals);
+            }
+            act_totals.write_to(&mut act);
+            totals.add(act_totals);
+        }
+        act["chapters"] = json!(chapters);
+        acts.push(act);
     }
 
-    Ok(json!({
+    let mut response = json!({
         "plotStructureId": plot_id,
         "structureType": structure_type,
         "acts": acts
-    }))
+    });
+    if include_tokens {
+        totals.write_to(&mut response);
+        response["tokenCounting"] = json!(tokens::counter().method());
+    }
+    Ok(response)
+}
+
+/// What a part of the manuscript costs to load in full, and as summaries
+#[derive(Debug, Default, Clone, Copy)]
+struct TokenTotals {
+    content: usize,
+    summary: usize,
+}
+
+impl TokenTotals {
+    fn add(&mut self, other: TokenTotals) {
+        self.content += other.content;
+        self.summary += other.summary;
+    }
+
+    fn write_to(self, value: &mut Value) {
+        value["contentTokens"] = json!(self.content);
+        value["summaryTokens"] = json!(self.summary);
+    }
+}
+
+/// A chapter's scenes in order, each with its token costs, and the chapter's totals
+fn scene_tokens(conn: &Connection, chapter_id: &str) -> Result<(Vec<Value>, TokenTotals)> {
+    let mut stmt = conn.prepare(
+        "SELECT s.id, s.position, s.title, s.content, COALESCE(ss.summary_text, s.summary)
+         FROM scenes s
+         LEFT JOIN story_summaries ss ON ss.scope = 'scene' AND ss.reference_id = s.id
+         WHERE s.chapter_id = ?1
+         ORDER BY s.position",
+    )?;
+    let mut totals = TokenTotals::default();
+    let scenes = stmt
+        .query_map([chapter_id], |row| {
+            let content: String = row.get(3)?;
+            let summary: Option<String> = row.get(4)?;
+            let scene_totals = TokenTotals {
+                content: tokens::count(&content),
+                summary: tokens::cost([summary.as_deref()]),
+            };
+            totals.add(scene_totals);
+            let mut scene = json!({
+                "sceneId": row.get::<_, String>(0)?,
+                "position": row.get::<_, i32>(1)?,
+                "title": row.get::<_, Option<String>>(2)?
+            });
+            scene_totals.write_to(&mut scene);
+            Ok(scene)
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok((scenes, totals))
 }
 
 #[cfg(test)]
@@ -382,6 +681,7 @@ mod tests {
     use super::*;
     use crate::db;
     use crate::tools::project::create_story_project;
+    use crate::tools::{add_character, check_scene_continuity};
     use tempfile::tempdir;
 
     #[test]
@@ -390,7 +690,7 @@ mod tests {
         let db_path = dir.path().join("test.db");
         let conn = db::initialize_database(&db_path).unwrap();
 
-        let project = create_story_project(&conn, json!({"title": "Test", "targetLength": "novel"})).unwrap();
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Test", "targetLength": "novel"})).unwrap();
         let project_id = project.get("projectId").unwrap().as_str().unwrap();
 
         let params = json!({
@@ -415,7 +715,7 @@ mod tests {
         let db_path = dir.path().join("test.db");
         let conn = db::initialize_database(&db_path).unwrap();
 
-        let project = create_story_project(&conn, json!({"title": "Test", "targetLength": "novel"})).unwrap();
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Test", "targetLength": "novel"})).unwrap();
         let project_id = project.get("projectId").unwrap().as_str().unwrap();
 
         let plot = initialize_plot_structure(&conn, json!({"projectId": project_id})).unwrap();
@@ -430,7 +730,7 @@ mod tests {
         let chapter_id = chapter.get("chapterId").unwrap().as_str().unwrap();
 
         // Add scene
-        let scene_result = add_scene(&conn, json!({"chapterId": chapter_id, "title": "Opening Scene"}));
+        let scene_result = add_scene(&conn, dir.path(), json!({"chapterId": chapter_id, "title": "Opening Scene"}));
         assert!(scene_result.is_ok());
 
         let scene = scene_result.unwrap();
@@ -438,13 +738,110 @@ mod tests {
         assert_eq!(scene.get("position").unwrap(), 1);
     }
 
+    #[test]
+    fn test_chapter_numbers_unique_per_story() {
+        let stories = tempdir().unwrap();
+        let conn = db::initialize_database(":memory:").unwrap();
+        let project = create_story_project(&conn, stories.path(), json!({"title": "Numbering", "targetLength": "novel"})).unwrap();
+        let plot = initialize_plot_structure(&conn, json!({"projectId": project["projectId"]})).unwrap();
+        let first = add_chapter(&conn, json!({"actId": plot["acts"][0]["actId"], "number": 1})).unwrap();
+
+        // Another act of the same story can't take the number
+        let err = add_chapter(&conn, json!({"actId": plot["acts"][1]["actId"], "number": 1})).unwrap_err();
+        assert!(matches!(err, StoryError::DuplicateEntry(_)));
+        let detail = err.detail().unwrap();
+        assert_eq!(detail.field.as_deref(), Some("number"));
+        assert_eq!(detail.entity_type.as_deref(), Some("chapter"));
+        assert_eq!(detail.entity_id.as_deref(), first["chapterId"].as_str());
+
+        // Another story can
+        let other = create_story_project(&conn, stories.path(), json!({"title": "Numbering Again", "targetLength": "novel"})).unwrap();
+        let plot = initialize_plot_structure(&conn, json!({"projectId": other["projectId"]})).unwrap();
+        add_chapter(&conn, json!({"actId": plot["acts"][0]["actId"], "number": 1})).unwrap();
+    }
+
+    #[test]
+    fn test_update_scene() {
+        let dir = tempdir().unwrap();
+        let conn = db::initialize_database(dir.path().join("test.db")).unwrap();
+
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Update Scene Test", "targetLength": "novel"})).unwrap();
+        let plot = initialize_plot_structure(&conn, json!({"projectId": project["projectId"]})).unwrap();
+        let chapter = add_chapter(&conn, json!({"actId": plot["acts"][0]["actId"], "number": 1})).unwrap();
+        let scene = add_scene(&conn, dir.path(), json!({"chapterId": chapter["chapterId"], "title": "Arrival", "location": "Dock"})).unwrap();
+        let scene_id = scene["sceneId"].as_str().unwrap();
+
+        // Details only: no rewrite, so no continuity pass
+        let updated = update_scene(&conn, dir.path(), json!({"sceneId": scene_id, "timeDescription": "Dusk"})).unwrap();
+        assert_eq!(updated["title"], "Arrival");
+        assert_eq!(updated["timeDescription"], "Dusk");
+        assert_eq!(updated["status"], "planned");
+        assert!(updated.get("continuity").is_none());
+
+        let updated = update_scene(&conn, dir.path(), json!({"sceneId": scene_id, "content": "The ship came in at dusk."})).unwrap();
+        assert_eq!(updated["status"], "written");
+        assert_eq!(updated["wordCount"], 6);
+        assert_eq!(updated["location"], "Dock");
+        let file = PathBuf::from(updated["filePath"].as_str().unwrap());
+        assert!(file.ends_with("scene-01.txt"));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "The ship came in at dusk.");
+        assert!(!file.with_extension("txt.partial").exists());
+        assert_eq!(updated["continuity"]["newAlerts"], 0);
+
+        // Clearing the prose removes the file rather than leaving it stale
+        let cleared = update_scene(&conn, dir.path(), json!({"sceneId": scene_id, "content": ""})).unwrap();
+        assert_eq!(cleared["wordCount"], 0);
+        assert!(cleared.get("filePath").is_none());
+        assert!(!file.exists());
+
+        let err = update_scene(&conn, dir.path(), json!({"sceneId": scene_id})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+        let err = update_scene(&conn, dir.path(), json!({"sceneId": Uuid::new_v4(), "title": "Lost"})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_update_scene_rolled_back_when_file_write_fails() {
+        let stories = tempdir().unwrap();
+        let conn = db::initialize_database(":memory:").unwrap();
+        let project = create_story_project(&conn, stories.path(), json!({"title": "Unwritable Scene Test", "targetLength": "novel"})).unwrap();
+        add_character(&conn, json!({"projectId": project["projectId"], "name": "Dax", "role": "supporting", "currentState": "Died at the keep"}))
+            .unwrap();
+        let plot = initialize_plot_structure(&conn, json!({"projectId": project["projectId"]})).unwrap();
+        let chapter = add_chapter(&conn, json!({"actId": plot["acts"][0]["actId"], "number": 1})).unwrap();
+        let scene = add_scene(&conn, stories.path(), json!({"chapterId": chapter["chapterId"], "title": "Arrival", "sceneOutline": "Dax keeps watch"}))
+            .unwrap();
+        let scene_id = scene["sceneId"].as_str().unwrap();
+        let raised = check_scene_continuity(&conn, json!({"sceneId": scene_id})).unwrap();
+        assert_eq!(raised["alerts"].as_array().unwrap().len(), 1);
+
+        // A plain file where the chapter folder belongs makes the write fail
+        let story_dir = stories.path().join("standalone/Unwritable Scene Test");
+        fs::create_dir_all(story_dir.join("chapters")).unwrap();
+        fs::write(story_dir.join("chapters/chapter-01"), "").unwrap();
+        let result = update_scene(&conn, stories.path(), json!({"sceneId": scene_id, "title": "Landfall", "content": "Sela keeps watch."}));
+        assert!(matches!(result.unwrap_err(), StoryError::IoError(_)));
+
+        // Neither the scene nor the alert its rewrite would have closed changed
+        let (title, content, status): (String, String, String) = conn
+            .query_row("SELECT title, content, status FROM scenes WHERE id = ?1", [scene_id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!((title.as_str(), content.as_str(), status.as_str()), ("Arrival", "", "planned"));
+        let decision: String = conn
+            .query_row("SELECT author_decision FROM continuity_alerts WHERE scene_id = ?1", [scene_id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(decision, "pending");
+    }
+
     #[test]
     fn test_get_plot_structure() {
         let dir = tempdir().unwrap();
         let db_path = dir.path().join("test.db");
         let conn = db::initialize_database(&db_path).unwrap();
 
-        let project = create_story_project(&conn, json!({"title": "Test", "targetLength": "novel"})).unwrap();
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Test", "targetLength": "novel"})).unwrap();
         let project_id = project.get("projectId").unwrap().as_str().unwrap();
 
         initialize_plot_structure(&conn, json!({"projectId": project_id})).unwrap();
@@ -456,4 +853,45 @@ mod tests {
         assert!(structure.get("plotStructureId").is_some());
         assert!(structure.get("acts").unwrap().as_array().is_some());
     }
+
+    #[test]
+    fn test_get_plot_structure_with_tokens() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let conn = db::initialize_database(&db_path).unwrap();
+
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Token Costs", "targetLength": "novel"})).unwrap();
+        let project_id = project.get("projectId").unwrap().as_str().unwrap();
+        let plot = initialize_plot_structure(&conn, json!({"projectId": project_id})).unwrap();
+        let act_id = plot["acts"][0]["actId"].as_str().unwrap();
+        let chapter = add_chapter(&conn, json!({"actId": act_id, "number": 1})).unwrap();
+        let chapter_id = chapter["chapterId"].as_str().unwrap();
+
+        let long = "The storm came in off the water and did not stop for three days. ".repeat(20);
+        add_scene(&conn, dir.path(), json!({"chapterId": chapter_id, "content": long})).unwrap();
+        let short = add_scene(&conn, dir.path(), json!({"chapterId": chapter_id, "sceneOutline": "Morning after"})).unwrap();
+        conn.execute(
+            "UPDATE scenes SET summary = 'The town wakes to wreckage' WHERE id = ?1",
+            [short["sceneId"].as_str().unwrap()],
+        )
+        .unwrap();
+
+        let plain = get_plot_structure(&conn, json!({"projectId": project_id})).unwrap();
+        assert!(plain["acts"][0]["chapters"][0].get("scenes").is_none());
+
+        let structure = get_plot_structure(&conn, json!({"projectId": project_id, "includeTokens": true})).unwrap();
+        let chapter = &structure["acts"][0]["chapters"][0];
+        let scenes = chapter["scenes"].as_array().unwrap();
+        assert_eq!(scenes.len(), 2);
+        assert_eq!(scenes[0]["contentTokens"], tokens::count(&long));
+        assert_eq!(scenes[0]["summaryTokens"], 0);
+        assert_eq!(scenes[1]["contentTokens"], 0);
+        assert_eq!(scenes[1]["summaryTokens"], tokens::count("The town wakes to wreckage"));
+
+        // Totals roll up through the chapter and act to the whole structure
+        assert_eq!(chapter["contentTokens"], tokens::count(&long));
+        assert_eq!(structure["acts"][0]["summaryTokens"], scenes[1]["summaryTokens"]);
+        assert_eq!(structure["acts"][1]["contentTokens"], 0);
+        assert_eq!(structure["contentTokens"], chapter["contentTokens"]);
+    }
 }
@@ -0,0 +1,1062 @@
+use super::continuity::record;
+use super::parse_params;
+use super::state::{character_project, scene_order, scene_ref};
+use crate::error::{Result, StoryError};
+use crate::models::{ProgressionSheet, SystemType};
+use crate::systems::custom::format_number;
+use crate::systems::{
+    check_advancement, Advancement, CustomTemplate, DefinitionFormat, StatusFormat, StatusScreen, SystemTemplate,
+};
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+/// `detected_by` value of the alerts raised by progression rule checks
+pub const DETECTOR: &str = "progression_check";
+
+/// Parameters for `listProgressionTemplates`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ListProgressionTemplatesParams {}
+
+/// Parameters for `enableProgressionSystem`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct EnableProgressionSystemParams {
+    pub project_id: Uuid,
+    pub system_type: SystemType,
+    /// Defaults to the system type
+    #[schemars(length(max = 100))]
+    pub system_name: Option<String>,
+    /// Template in the shape `listProgressionTemplates` shows for the type;
+    /// defaults to the built-in template, or the system's current one when re-enabling
+    pub template_data: Option<Value>,
+    /// A custom system written in TOML or JSON, in place of templateData
+    pub definition: Option<String>,
+    /// Defaults to `json` when the definition starts with `{`, `toml` otherwise
+    pub definition_format: Option<DefinitionFormat>,
+}
+
+/// Parameters for `validateSystemDefinition`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ValidateSystemDefinitionParams {
+    /// A custom system written in TOML or JSON
+    pub definition: String,
+    /// Defaults to `json` when the definition starts with `{`, `toml` otherwise
+    pub definition_format: Option<DefinitionFormat>,
+}
+
+/// Parameters for `disableProgressionSystem`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DisableProgressionSystemParams {
+    pub system_id: Uuid,
+}
+
+/// Parameters for `listProgressionSystems`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ListProgressionSystemsParams {
+    pub project_id: Uuid,
+}
+
+/// Parameters for `recordProgressionSheet`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RecordProgressionSheetParams {
+    pub system_id: Uuid,
+    pub character_id: Uuid,
+    /// Scene at the end of which the sheet applies
+    pub scene_id: Uuid,
+    /// Only what changed; omitted fields and stats carry over from earlier scenes
+    pub sheet: ProgressionSheet,
+}
+
+/// Parameters for `getProgressionSheet`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GetProgressionSheetParams {
+    pub system_id: Uuid,
+    pub character_id: Uuid,
+    /// Sheet at the end of this scene; defaults to the end of the manuscript
+    pub as_of_scene_id: Option<Uuid>,
+}
+
+/// Parameters for `listProgressionSheets`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ListProgressionSheetsParams {
+    pub system_id: Uuid,
+    pub character_id: Uuid,
+}
+
+/// Parameters for `checkProgression`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CheckProgressionParams {
+    pub system_id: Uuid,
+    /// Only this character; defaults to every character with a sheet under the system
+    pub character_id: Option<Uuid>,
+}
+
+/// Parameters for `renderStatusScreen`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RenderStatusScreenParams {
+    pub system_id: Uuid,
+    pub character_id: Uuid,
+    /// Screen at the end of this scene; defaults to the end of the manuscript
+    pub as_of_scene_id: Option<Uuid>,
+    /// Defaults to `text`
+    pub format: Option<StatusFormat>,
+}
+
+/// A project's progression system with its parsed template
+pub(crate) struct System {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub template: SystemTemplate,
+    pub enabled: bool,
+}
+
+/// A recorded sheet snapshot with its scene's place in the manuscript
+pub(crate) struct SheetSnapshot {
+    pub id: String,
+    pub scene_id: String,
+    pub order: (i32, i32, i32),
+    pub sheet: ProgressionSheet,
+    pub recorded_at: String,
+}
+
+/// The built-in template of each system type
+pub fn list_progression_templates(_conn: &Connection, params: Value) -> Result<Value> {
+    let _: ListProgressionTemplatesParams = parse_params(params)?;
+    let templates: Vec<Value> = [SystemType::GameStats, SystemType::Cultivation, SystemType::MagicTiers, SystemType::SkillTrees]
+        .into_iter()
+        .filter_map(SystemTemplate::builtin)
+        .map(|template| {
+            json!({
+                "systemType": template.system_type(),
+                "templateData": template
+            })
+        })
+        .collect();
+    Ok(json!({ "templates": templates }))
+}
+
+/// Check a custom system definition without storing it, showing the template it
+/// becomes and the derived stats of a character at starting values
+pub fn validate_system_definition(_conn: &Connection, params: Value) -> Result<Value> {
+    let params: ValidateSystemDefinitionParams = parse_params(params)?;
+    let template = parse_definition(&params.definition, params.definition_format)?;
+    let start = ProgressionSheet {
+        tier: template.tiers.first().map(|tier| tier.name.clone()),
+        ..ProgressionSheet::default()
+    };
+    let derived: Vec<Value> = template
+        .derived(&start)
+        .into_iter()
+        .map(|(name, value)| json!({"name": name, "value": value, "display": format_number(value)}))
+        .collect();
+    Ok(json!({
+        "valid": true,
+        "systemType": SystemType::Custom,
+        "templateData": template,
+        "derivedAtStart": derived
+    }))
+}
+
+/// Parse a custom system definition, guessing the format from its first character when not given
+fn parse_definition(definition: &str, format: Option<DefinitionFormat>) -> Result<CustomTemplate> {
+    let format = format.unwrap_or(if definition.trim_start().starts_with('{') {
+        DefinitionFormat::Json
+    } else {
+        DefinitionFormat::Toml
+    });
+    CustomTemplate::parse(definition, format)
+}
+
+/// Enable a progression system for a project, creating it or re-enabling one of the same name
+pub fn enable_progression_system(conn: &Connection, params: Value) -> Result<Value> {
+    let params: EnableProgressionSystemParams = parse_params(params)?;
+    let project_id = params.project_id.to_string();
+    let name = params.system_name.unwrap_or_else(|| params.system_type.to_string());
+    if name.trim().is_empty() || name.chars().count() > 100 {
+        return Err(StoryError::validation("System name must be 1 to 100 characters").with_field("systemName"));
+    }
+
+    let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM story_projects WHERE id = ?1)", [&project_id], |row| {
+        row.get(0)
+    })?;
+    if !exists {
+        return Err(StoryError::not_found(format!("Project not found: {}", project_id))
+            .with_field("projectId")
+            .with_entity("project", &project_id));
+    }
+
+    let template = match (params.template_data, params.definition) {
+        (Some(_), Some(_)) => {
+            return Err(StoryError::validation("Give either templateData or definition, not both").with_field("definition"))
+        }
+        (None, Some(_)) if params.system_type != SystemType::Custom => {
+            return Err(StoryError::validation("Only custom systems take a definition").with_field("definition"))
+        }
+        (None, Some(definition)) => Some(SystemTemplate::Custom(parse_definition(&definition, params.definition_format)?)),
+        (Some(data), None) => Some(SystemTemplate::from_value(params.system_type, data)?),
+        (None, None) if params.system_type == SystemType::Custom => {
+            return Err(StoryError::validation("Custom systems need templateData or a definition").with_field("definition"))
+        }
+        (None, None) => None,
+    };
+    let template_json = |template: &SystemTemplate| serde_json::to_string(template).expect("template serializes");
+    let now = Utc::now().to_rfc3339();
+
+    let existing: Option<(String, String)> = conn
+        .query_row(
+            "SELECT id, system_type FROM progression_systems WHERE story_project_id = ?1 AND system_name = ?2",
+            (&project_id, &name),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let (system_id, created) = match existing {
+        Some((id, system_type)) if system_type != params.system_type.to_string() => {
+            return Err(StoryError::duplicate(format!("A {} system named '{}' already exists", system_type, name))
+                .with_field("systemName")
+                .with_entity("progression_system", id))
+        }
+        Some((id, _)) => {
+            conn.execute(
+                "UPDATE progression_systems SET enabled = 1, template_data = COALESCE(?2, template_data), updated_at = ?3
+                 WHERE id = ?1",
+                (&id, template.as_ref().map(template_json), &now),
+            )?;
+            (id, false)
+        }
+        None => {
+            let template = match template {
+                Some(template) => template,
+                None => SystemTemplate::builtin(params.system_type).expect("built-in template for non-custom type"),
+            };
+            let id = Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO progression_systems (id, story_project_id, system_name, system_type, template_data, enabled,
+                     created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6, ?6)",
+                (&id, &project_id, &name, params.system_type.to_string(), template_json(&template), &now),
+            )?;
+            (id, true)
+        }
+    };
+
+    log::info!(
+        "{} {} system '{}' ({}) for project {}",
+        if created { "Enabled" } else { "Re-enabled" },
+        params.system_type,
+        name,
+        system_id,
+        project_id
+    );
+
+    let mut response = system_json(&load_system(conn, &system_id)?);
+    response["created"] = json!(created);
+    Ok(response)
+}
+
+/// Disable a progression system; its sheets are kept
+pub fn disable_progression_system(conn: &Connection, params: Value) -> Result<Value> {
+    let params: DisableProgressionSystemParams = parse_params(params)?;
+    let system_id = params.system_id.to_string();
+    load_system(conn, &system_id)?;
+
+    conn.execute(
+        "UPDATE progression_systems SET enabled = 0, updated_at = ?2 WHERE id = ?1",
+        (&system_id, Utc::now().to_rfc3339()),
+    )?;
+    log::info!("Disabled progression system {}", system_id);
+
+    Ok(system_json(&load_system(conn, &system_id)?))
+}
+
+/// Number of enabled progression systems across every project. The sheet tools
+/// are only offered to clients while there is at least one.
+pub fn enabled_system_count(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row("SELECT COUNT(*) FROM progression_systems WHERE enabled = 1", [], |row| row.get(0))?)
+}
+
+/// A project's progression systems, enabled or not
+pub fn list_progression_systems(conn: &Connection, params: Value) -> Result<Value> {
+    let params: ListProgressionSystemsParams = parse_params(params)?;
+    let project_id = params.project_id.to_string();
+
+    let mut stmt =
+        conn.prepare("SELECT id FROM progression_systems WHERE story_project_id = ?1 ORDER BY created_at, system_name")?;
+    let ids = stmt
+        .query_map([&project_id], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let systems = ids
+        .iter()
+        .map(|id| load_system(conn, id).map(|system| system_json(&system)))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(json!({
+        "projectId": project_id,
+        "systems": systems
+    }))
+}
+
+/// Record a character's sheet at a scene, replacing any sheet already recorded there
+pub fn record_progression_sheet(conn: &Connection, params: Value) -> Result<Value> {
+    let params: RecordProgressionSheetParams = parse_params(params)?;
+    let character_id = params.character_id.to_string();
+    let scene_id = params.scene_id.to_string();
+
+    if params.sheet.fields().is_empty() {
+        return Err(StoryError::validation("Sheet must set at least one field").with_field("sheet"));
+    }
+    let system = load_system(conn, &params.system_id.to_string())?;
+    if !system.enabled {
+        return Err(StoryError::invalid_state(format!("Progression system '{}' is disabled", system.name))
+            .with_field("systemId")
+            .with_entity("progression_system", &system.id));
+    }
+    check_character_system(conn, &system, &character_id)?;
+    let order = scene_order(conn, &scene_id, &system.project_id)?;
+
+    // A partial sheet is checked as it will read: merged over the snapshots before it,
+    // so a stage is held to the realm carried over from earlier
+    let mut resolved = ProgressionSheet::default();
+    for snapshot in sheets(conn, &system.id, &character_id)?.iter().take_while(|s| s.order < order) {
+        resolved.apply(&snapshot.sheet);
+    }
+    resolved.apply(&params.sheet);
+    system.template.check_sheet(&resolved)?;
+
+    let tx = conn.unchecked_transaction()?;
+
+    let existing: Option<String> = conn
+        .query_row(
+            "SELECT id FROM progression_sheets WHERE progression_system_id = ?1 AND character_id = ?2 AND scene_id = ?3",
+            (&system.id, &character_id, &scene_id),
+            |row| row.get(0),
+        )
+        .optional()?;
+    let snapshot_id = existing.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    let sheet = serde_json::to_string(&params.sheet).expect("sheet serializes");
+
+    conn.execute(
+        "INSERT INTO progression_sheets (id, progression_system_id, character_id, scene_id, sheet, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(progression_system_id, character_id, scene_id) DO UPDATE SET
+             sheet = excluded.sheet,
+             created_at = excluded.created_at",
+        (&snapshot_id, &system.id, &character_id, &scene_id, &sheet, Utc::now().to_rfc3339()),
+    )?;
+
+    log::info!(
+        "{} {} sheet of character {} at scene {}: {}",
+        if existing.is_some() { "Replaced" } else { "Recorded" },
+        system.name,
+        character_id,
+        scene_id,
+        params.sheet.fields().join(", ")
+    );
+
+    let snapshot = sheets(conn, &system.id, &character_id)?
+        .into_iter()
+        .find(|s| s.id == snapshot_id)
+        .expect("sheet was just written");
+    let mut response = sheet_json(&snapshot);
+    response["systemId"] = json!(system.id);
+    response["characterId"] = json!(character_id);
+    response["created"] = json!(existing.is_none());
+    response["progression"] = recheck_progression(conn, &system, &character_id)?;
+    tx.commit()?;
+    Ok(response)
+}
+
+/// A character's sheet at a point in the manuscript, built by applying their
+/// snapshots in reading order up to and including that scene
+pub fn get_progression_sheet(conn: &Connection, params: Value) -> Result<Value> {
+    let params: GetProgressionSheetParams = parse_params(params)?;
+    let character_id = params.character_id.to_string();
+    let system = load_system(conn, &params.system_id.to_string())?;
+    check_character_system(conn, &system, &character_id)?;
+
+    let as_of = match params.as_of_scene_id {
+        Some(scene_id) => {
+            let scene_id = scene_id.to_string();
+            let order = scene_order(conn, &scene_id, &system.project_id)?;
+            Some((scene_id, order))
+        }
+        None => None,
+    };
+
+    let mut sheet = ProgressionSheet::default();
+    let mut set_at = Map::new();
+    let mut applied = 0;
+    for snapshot in sheets(conn, &system.id, &character_id)? {
+        if as_of.as_ref().is_some_and(|(_, order)| snapshot.order > *order) {
+            break;
+        }
+        sheet.apply(&snapshot.sheet);
+        for field in snapshot.sheet.fields() {
+            set_at.insert(field, scene_ref(&snapshot.scene_id, snapshot.order));
+        }
+        applied += 1;
+    }
+
+    Ok(json!({
+        "systemId": system.id,
+        "systemName": system.name,
+        "systemType": system.template.system_type(),
+        "characterId": character_id,
+        "asOf": as_of.map(|(scene_id, order)| scene_ref(&scene_id, order)),
+        "sheet": sheet,
+        "setAt": set_at,
+        "snapshotsApplied": applied
+    }))
+}
+
+/// Every sheet snapshot of a character under a system, in manuscript order
+pub fn list_progression_sheets(conn: &Connection, params: Value) -> Result<Value> {
+    let params: ListProgressionSheetsParams = parse_params(params)?;
+    let character_id = params.character_id.to_string();
+    let system = load_system(conn, &params.system_id.to_string())?;
+    check_character_system(conn, &system, &character_id)?;
+
+    let history: Vec<Value> = sheets(conn, &system.id, &character_id)?.iter().map(sheet_json).collect();
+    Ok(json!({
+        "systemId": system.id,
+        "characterId": character_id,
+        "snapshots": history
+    }))
+}
+
+/// A character's sheet as a status screen, with changes since the snapshot before
+pub fn render_status_screen(conn: &Connection, params: Value) -> Result<Value> {
+    let params: RenderStatusScreenParams = parse_params(params)?;
+    let character_id = params.character_id.to_string();
+    let format = params.format.unwrap_or_default();
+    let system = load_system(conn, &params.system_id.to_string())?;
+    check_character_system(conn, &system, &character_id)?;
+    let name: String = conn.query_row("SELECT name FROM characters WHERE id = ?1", [&character_id], |row| row.get(0))?;
+
+    let as_of = match params.as_of_scene_id {
+        Some(scene_id) => {
+            let scene_id = scene_id.to_string();
+            let order = scene_order(conn, &scene_id, &system.project_id)?;
+            Some((scene_id, order))
+        }
+        None => None,
+    };
+
+    let mut sheet: Option<ProgressionSheet> = None;
+    let mut previous: Option<ProgressionSheet> = None;
+    let (mut latest, mut since) = (None, None);
+    for snapshot in sheets(conn, &system.id, &character_id)? {
+        if as_of.as_ref().is_some_and(|(_, order)| snapshot.order > *order) {
+            break;
+        }
+        previous = sheet.clone();
+        since = latest.take();
+        let mut current = sheet.unwrap_or_default();
+        current.apply(&snapshot.sheet);
+        sheet = Some(current);
+        latest = Some(scene_ref(&snapshot.scene_id, snapshot.order));
+    }
+    let sheet = sheet.ok_or_else(|| {
+        StoryError::not_found(format!("No {} sheet recorded for {} by this point in the manuscript", system.name, name))
+            .with_field("characterId")
+            .with_entity("character", &character_id)
+    })?;
+
+    let screen = StatusScreen {
+        title: &name,
+        system_name: &system.name,
+        template: &system.template,
+        sheet: &sheet,
+        previous: previous.as_ref(),
+    };
+    log::info!("Rendered {} status screen of character {} under system {}", format, character_id, system.id);
+
+    Ok(json!({
+        "systemId": system.id,
+        "characterId": character_id,
+        "asOf": as_of.map(|(scene_id, order)| scene_ref(&scene_id, order)),
+        "format": format,
+        "screen": screen.render(format),
+        "sheetFrom": latest,
+        "changesSince": since,
+        "deltas": screen.deltas()
+    }))
+}
+
+/// Check characters' sheets against the system's rules, step by step through the manuscript,
+/// recording violations as continuity alerts and closing alerts no longer found
+pub fn check_progression(conn: &Connection, params: Value) -> Result<Value> {
+    let params: CheckProgressionParams = parse_params(params)?;
+    let system = load_system(conn, &params.system_id.to_string())?;
+    if !system.enabled {
+        return Err(StoryError::invalid_state(format!("Progression system '{}' is disabled", system.name))
+            .with_field("systemId")
+            .with_entity("progression_system", &system.id));
+    }
+
+    let character_ids = match params.character_id {
+        Some(character_id) => {
+            let character_id = character_id.to_string();
+            check_character_system(conn, &system, &character_id)?;
+            vec![character_id]
+        }
+        None => {
+            let mut stmt = conn.prepare(
+                "SELECT DISTINCT p.character_id FROM progression_sheets p
+                 JOIN characters ch ON p.character_id = ch.id
+                 WHERE p.progression_system_id = ?1
+                 ORDER BY ch.name",
+            )?;
+            let ids = stmt
+                .query_map([&system.id], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            ids
+        }
+    };
+
+    let characters = character_ids
+        .iter()
+        .map(|character_id| recheck_progression(conn, &system, character_id))
+        .collect::<Result<Vec<_>>>()?;
+    log::info!("Checked progression of {} character(s) under system {}", characters.len(), system.id);
+
+    Ok(json!({
+        "systemId": system.id,
+        "systemName": system.name,
+        "characters": characters
+    }))
+}
+
+/// Check every step of a character's sheets and reconcile the alerts raised for them
+pub(crate) fn recheck_progression(conn: &Connection, system: &System, character_id: &str) -> Result<Value> {
+    let name: String = conn.query_row("SELECT name FROM characters WHERE id = ?1", [character_id], |row| row.get(0))?;
+    let now = Utc::now().to_rfc3339();
+
+    let mut alerts = Vec::new();
+    let mut found = Vec::new();
+    let (mut raised, mut reopened) = (0, 0);
+    let mut resolved: Option<ProgressionSheet> = None;
+    for snapshot in sheets(conn, &system.id, character_id)? {
+        let mut current = resolved.clone().unwrap_or_default();
+        current.apply(&snapshot.sheet);
+        let label = format!("chapter {} scene {}", snapshot.order.1, snapshot.order.2);
+        let step = Advancement {
+            system_id: &system.id,
+            character_id,
+            character: &name,
+            label: &label,
+            previous: resolved.as_ref(),
+            current: &current,
+        };
+        for finding in check_advancement(&system.template, &step) {
+            let (alert_id, status) = record(conn, &system.project_id, &snapshot.scene_id, DETECTOR, &finding, &now)?;
+            match status {
+                "new" => raised += 1,
+                "reopened" => reopened += 1,
+                _ => {}
+            }
+            alerts.push(json!({
+                "alertId": alert_id,
+                "sceneId": snapshot.scene_id,
+                "alertType": finding.alert_type,
+                "severity": finding.severity,
+                "description": finding.description,
+                "conflictingElements": finding.conflicting_elements,
+                "suggestedResolution": finding.suggested_resolution,
+                "status": status
+            }));
+            found.push(alert_id);
+        }
+        resolved = Some(current);
+    }
+
+    // Which character and system an alert is about is kept in its conflicting elements
+    let mut stmt = conn.prepare(
+        "SELECT id, alert_type, description, conflicting_elements FROM continuity_alerts
+         WHERE story_project_id = ?1 AND detected_by = ?2 AND author_decision = 'pending'",
+    )?;
+    let open = stmt
+        .query_map((&system.project_id, DETECTOR), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut closed = Vec::new();
+    for (alert_id, alert_type, description, elements) in open {
+        let elements: Value = elements.and_then(|e| serde_json::from_str(&e).ok()).unwrap_or_default();
+        if found.contains(&alert_id) || elements["systemId"] != system.id.as_str() || elements["characterId"] != character_id {
+            continue;
+        }
+        conn.execute(
+            "UPDATE continuity_alerts
+             SET author_decision = 'updated_fact', resolved_at = ?2,
+                 author_notes = COALESCE(author_notes || char(10), '') || ?3
+             WHERE id = ?1",
+            (&alert_id, &now, "Closed automatically: no longer found when the progression was checked again"),
+        )?;
+        closed.push(json!({
+            "alertId": alert_id,
+            "alertType": alert_type,
+            "description": description,
+            "authorDecision": "updated_fact"
+        }));
+    }
+
+    Ok(json!({
+        "characterId": character_id,
+        "name": name,
+        "alerts": alerts,
+        "newAlerts": raised,
+        "reopenedAlerts": reopened,
+        "closedAlerts": closed
+    }))
+}
+
+pub(crate) fn load_system(conn: &Connection, system_id: &str) -> Result<System> {
+    let (project_id, name, system_type, template_data, enabled): (String, String, String, Option<String>, bool) = conn
+        .query_row(
+            "SELECT story_project_id, system_name, system_type, template_data, enabled
+             FROM progression_systems WHERE id = ?1",
+            [system_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+        )
+        .optional()?
+        .ok_or_else(|| {
+            StoryError::not_found(format!("Progression system not found: {}", system_id))
+                .with_field("systemId")
+                .with_entity("progression_system", system_id)
+        })?;
+
+    // Rows written outside this server may hold no template or another shape
+    let unreadable = |reason: String| {
+        StoryError::invalid_state(format!("Progression system '{}' has an unusable template: {}", name, reason))
+            .with_entity("progression_system", system_id)
+    };
+    let system_type = SystemType::from_str(&system_type).ok_or_else(|| unreadable(format!("unknown type {}", system_type)))?;
+    let template = match template_data {
+        Some(data) => {
+            let data = serde_json::from_str(&data).map_err(|e| unreadable(e.to_string()))?;
+            SystemTemplate::from_value(system_type, data).map_err(|e| unreadable(e.to_string()))?
+        }
+        None => SystemTemplate::builtin(system_type).ok_or_else(|| unreadable("no template data".to_string()))?,
+    };
+
+    Ok(System {
+        id: system_id.to_string(),
+        project_id,
+        name,
+        template,
+        enabled,
+    })
+}
+
+/// Fails unless the character exists and belongs to the system's project
+fn check_character_system(conn: &Connection, system: &System, character_id: &str) -> Result<()> {
+    if character_project(conn, character_id)? != system.project_id {
+        return Err(StoryError::validation("Character belongs to a different project than the system")
+            .with_field("characterId")
+            .with_entity("character", character_id));
+    }
+    Ok(())
+}
+
+/// A character's sheet snapshots under a system, in manuscript order
+pub(crate) fn sheets(conn: &Connection, system_id: &str, character_id: &str) -> Result<Vec<SheetSnapshot>> {
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.scene_id, a.position, c.number, s.position, p.sheet, p.created_at
+         FROM progression_sheets p
+         JOIN scenes s ON p.scene_id = s.id
+         JOIN chapters c ON s.chapter_id = c.id
+         JOIN acts a ON c.act_id = a.id
+         WHERE p.progression_system_id = ?1 AND p.character_id = ?2
+         ORDER BY a.position, c.number, s.position",
+    )?;
+    let rows = stmt.query_map((system_id, character_id), |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            (row.get(2)?, row.get(3)?, row.get(4)?),
+            row.get::<_, String>(5)?,
+            row.get::<_, String>(6)?,
+        ))
+    })?;
+
+    let mut snapshots = Vec::new();
+    for row in rows {
+        let (id, scene_id, order, stored, recorded_at) = row?;
+        let sheet = serde_json::from_str(&stored).unwrap_or_else(|e| {
+            log::warn!("Unreadable progression sheet {}: {}", id, e);
+            ProgressionSheet::default()
+        });
+        snapshots.push(SheetSnapshot { id, scene_id, order, sheet, recorded_at });
+    }
+    Ok(snapshots)
+}
+
+fn system_json(system: &System) -> Value {
+    json!({
+        "systemId": system.id,
+        "projectId": system.project_id,
+        "systemName": system.name,
+        "systemType": system.template.system_type(),
+        "enabled": system.enabled,
+        "templateData": system.template
+    })
+}
+
+fn sheet_json(snapshot: &SheetSnapshot) -> Value {
+    json!({
+        "snapshotId": snapshot.id,
+        "sceneId": snapshot.scene_id,
+        "chapterNumber": snapshot.order.1,
+        "scenePosition": snapshot.order.2,
+        "sheet": snapshot.sheet,
+        "fields": snapshot.sheet.fields(),
+        "recordedAt": snapshot.recorded_at
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::tools::{add_chapter, add_character, add_scene, create_story_project, initialize_plot_structure};
+    use tempfile::tempdir;
+
+    struct Story {
+        project_id: String,
+        character_id: String,
+        scenes: Vec<String>,
+    }
+
+    fn story(conn: &Connection) -> Story {
+        let stories = tempdir().unwrap();
+        let project = create_story_project(conn, stories.path(), json!({"title": "Levels", "targetLength": "novel"})).unwrap();
+        let project_id = project["projectId"].as_str().unwrap().to_string();
+        let character = add_character(conn, json!({"projectId": project_id, "name": "Kai", "role": "protagonist"})).unwrap();
+        let plot = initialize_plot_structure(conn, json!({"projectId": project_id})).unwrap();
+        let chapter = add_chapter(conn, json!({"actId": plot["acts"][0]["actId"], "number": 1})).unwrap();
+        let scenes = (0..3)
+            .map(|_| {
+                add_scene(conn, stories.path(), json!({"chapterId": chapter["chapterId"]})).unwrap()["sceneId"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        Story {
+            project_id,
+            character_id: character["characterId"].as_str().unwrap().to_string(),
+            scenes,
+        }
+    }
+
+    #[test]
+    fn test_enable_progression_system() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn);
+
+        let system = enable_progression_system(&conn, json!({"projectId": story.project_id, "systemType": "game_stats"})).unwrap();
+        assert_eq!(system["created"], true);
+        assert_eq!(system["systemName"], "game_stats");
+        assert_eq!(system["templateData"]["stats"][0]["name"], "STR");
+
+        let disabled = disable_progression_system(&conn, json!({"systemId": system["systemId"]})).unwrap();
+        assert_eq!(disabled["enabled"], false);
+
+        // Enabling again under the same name brings it back with a new template
+        let again = enable_progression_system(
+            &conn,
+            json!({
+                "projectId": story.project_id,
+                "systemType": "game_stats",
+                "templateData": {"stats": [{"name": "Might", "starting": 3}], "pointsPerLevel": 2}
+            }),
+        )
+        .unwrap();
+        assert_eq!(again["created"], false);
+        assert_eq!(again["systemId"], system["systemId"]);
+        assert_eq!(again["enabled"], true);
+        assert_eq!(again["templateData"]["stats"][0]["name"], "Might");
+
+        let err = enable_progression_system(
+            &conn,
+            json!({"projectId": story.project_id, "systemType": "cultivation", "systemName": "game_stats"}),
+        )
+        .unwrap_err();
+        assert!(matches!(err, StoryError::DuplicateEntry(_)));
+        let err = enable_progression_system(
+            &conn,
+            json!({"projectId": story.project_id, "systemType": "skill_trees", "templateData": {"skills": []}}),
+        )
+        .unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+        let err = enable_progression_system(&conn, json!({"projectId": story.project_id, "systemType": "custom"})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+
+        // Names are limited to 100 characters, however many bytes they take
+        let named = |name: String| json!({"projectId": story.project_id, "systemType": "cultivation", "systemName": name});
+        enable_progression_system(&conn, named("修".repeat(100))).unwrap();
+        let err = enable_progression_system(&conn, named("修".repeat(101))).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+
+        enable_progression_system(&conn, json!({"projectId": story.project_id, "systemType": "cultivation"})).unwrap();
+        let systems = list_progression_systems(&conn, json!({"projectId": story.project_id})).unwrap();
+        assert_eq!(systems["systems"].as_array().unwrap().len(), 3);
+
+        let templates = list_progression_templates(&conn, json!({})).unwrap();
+        assert_eq!(templates["templates"].as_array().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_progression_sheets_by_scene() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn);
+        let system = enable_progression_system(&conn, json!({"projectId": story.project_id, "systemType": "game_stats"})).unwrap();
+        let record = |scene: usize, sheet: Value| {
+            record_progression_sheet(
+                &conn,
+                json!({"systemId": system["systemId"], "characterId": story.character_id, "sceneId": story.scenes[scene], "sheet": sheet}),
+            )
+        };
+
+        // Recorded out of order; reading follows the manuscript
+        record(2, json!({"level": 3, "stats": {"STR": 12}})).unwrap();
+        let first = record(0, json!({"level": 1, "stats": {"STR": 5, "AGI": 5}, "skills": ["Slash"]})).unwrap();
+        assert_eq!(first["created"], true);
+        let replaced = record(0, json!({"level": 1, "stats": {"STR": 6, "AGI": 5}, "skills": ["Slash"]})).unwrap();
+        assert_eq!(replaced["created"], false);
+        assert_eq!(replaced["snapshotId"], first["snapshotId"]);
+
+        let get = |as_of: Option<usize>| {
+            let mut params = json!({"systemId": system["systemId"], "characterId": story.character_id});
+            if let Some(scene) = as_of {
+                params["asOfSceneId"] = json!(story.scenes[scene]);
+            }
+            get_progression_sheet(&conn, params).unwrap()
+        };
+        let early = get(Some(1));
+        assert_eq!(early["sheet"]["level"], 1);
+        assert_eq!(early["sheet"]["stats"]["STR"], 6);
+        assert_eq!(early["snapshotsApplied"], 1);
+
+        let latest = get(None);
+        assert_eq!(latest["sheet"]["level"], 3);
+        assert_eq!(latest["sheet"]["stats"]["STR"], 12);
+        assert_eq!(latest["sheet"]["stats"]["AGI"], 5);
+        assert_eq!(latest["sheet"]["skills"][0], "Slash");
+        assert_eq!(latest["setAt"]["stats.STR"]["sceneId"], story.scenes[2]);
+        assert_eq!(latest["setAt"]["stats.AGI"]["sceneId"], story.scenes[0]);
+
+        let history = list_progression_sheets(&conn, json!({"systemId": system["systemId"], "characterId": story.character_id})).unwrap();
+        assert_eq!(history["snapshots"].as_array().unwrap().len(), 2);
+
+        // A character from another project has no sheet under this system
+        let elsewhere = create_story_project(&conn, tempdir().unwrap().path(), json!({"title": "Elsewhere", "targetLength": "novel"})).unwrap();
+        let stranger = add_character(&conn, json!({"projectId": elsewhere["projectId"], "name": "Orrin", "role": "minor"})).unwrap();
+        let params = json!({"systemId": system["systemId"], "characterId": stranger["characterId"]});
+        for err in [get_progression_sheet(&conn, params.clone()).unwrap_err(), list_progression_sheets(&conn, params).unwrap_err()] {
+            assert!(matches!(err, StoryError::ValidationError(_)));
+            assert_eq!(err.detail().unwrap().field.as_deref(), Some("characterId"));
+        }
+
+        let err = record(1, json!({"stats": {"DEX": 3}})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+        let err = record(1, json!({})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+        let err = record(1, json!({"level": 1, "stats": {"STR": i64::MIN}})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+
+        disable_progression_system(&conn, json!({"systemId": system["systemId"]})).unwrap();
+        let err = record(1, json!({"level": 2})).unwrap_err();
+        assert!(matches!(err, StoryError::InvalidState(_)));
+    }
+
+    #[test]
+    fn test_partial_sheets_checked_against_earlier_snapshots() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn);
+        let system = enable_progression_system(&conn, json!({"projectId": story.project_id, "systemType": "cultivation"})).unwrap();
+        let record = |scene: usize, sheet: Value| {
+            record_progression_sheet(
+                &conn,
+                json!({"systemId": system["systemId"], "characterId": story.character_id, "sceneId": story.scenes[scene], "sheet": sheet}),
+            )
+        };
+
+        // Core Formation has three stages, whether or not the sheet names the realm again
+        record(0, json!({"realm": "Core Formation", "stage": 1})).unwrap();
+        let err = record(1, json!({"stage": 50})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+        record(1, json!({"stage": 3})).unwrap();
+
+        // Only snapshots before the scene count, not the one being replaced or later ones
+        record(2, json!({"realm": "Nascent Soul", "stage": 1})).unwrap();
+        let err = record(1, json!({"stage": 4})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+        let history = list_progression_sheets(&conn, json!({"systemId": system["systemId"], "characterId": story.character_id})).unwrap();
+        assert_eq!(history["snapshots"][1]["sheet"]["stage"], 3);
+    }
+
+    #[test]
+    fn test_progression_violations_become_alerts() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn);
+        let system = enable_progression_system(&conn, json!({"projectId": story.project_id, "systemType": "cultivation"})).unwrap();
+        let record = |scene: usize, sheet: Value| {
+            record_progression_sheet(
+                &conn,
+                json!({"systemId": system["systemId"], "characterId": story.character_id, "sceneId": story.scenes[scene], "sheet": sheet}),
+            )
+            .unwrap()
+        };
+
+        record(0, json!({"realm": "Qi Condensation", "stage": 9}));
+        let skipped = record(1, json!({"realm": "Core Formation", "stage": 1}));
+        let alerts = skipped["progression"]["alerts"].as_array().unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0]["severity"], "high");
+        assert_eq!(alerts[0]["sceneId"], story.scenes[1]);
+        assert_eq!(skipped["progression"]["newAlerts"], 1);
+
+        let stored: (String, String) = conn
+            .query_row("SELECT detected_by, alert_type FROM continuity_alerts", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(stored, (DETECTOR.to_string(), "world_rule_violation".to_string()));
+
+        // Checking again finds the same alert still open
+        let checked = check_progression(&conn, json!({"systemId": system["systemId"]})).unwrap();
+        assert_eq!(checked["characters"][0]["alerts"][0]["status"], "open");
+
+        // Filling in the missing realm closes it
+        let fixed = record(1, json!({"realm": "Foundation Establishment", "stage": 1}));
+        assert!(fixed["progression"]["alerts"].as_array().unwrap().is_empty());
+        assert_eq!(fixed["progression"]["closedAlerts"][0]["authorDecision"], "updated_fact");
+        let pending: i64 = conn
+            .query_row("SELECT COUNT(*) FROM continuity_alerts WHERE author_decision = 'pending'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(pending, 0);
+
+        disable_progression_system(&conn, json!({"systemId": system["systemId"]})).unwrap();
+        let err = check_progression(&conn, json!({"systemId": system["systemId"]})).unwrap_err();
+        assert!(matches!(err, StoryError::InvalidState(_)));
+    }
+
+    #[test]
+    fn test_custom_system_from_definition() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn);
+        let definition = r#"
+pointsPerLevel = 3
+formulas = ["max_hp = 10 * vitality + level * 5"]
+
+[[attributes]]
+name = "vitality"
+starting = 5
+
+[[tiers]]
+name = "E-Rank"
+
+[[tiers]]
+name = "D-Rank"
+requirements = ["level >= 10"]
+"#;
+
+        let checked = validate_system_definition(&conn, json!({"definition": definition})).unwrap();
+        assert_eq!(checked["templateData"]["attributes"][0]["name"], "vitality");
+        assert_eq!(checked["derivedAtStart"][0], json!({"name": "max_hp", "value": 55.0, "display": "55"}));
+        let err = validate_system_definition(&conn, json!({"definition": "formulas = [\"hp = 10 * vitality\"]"})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+
+        let system = enable_progression_system(
+            &conn,
+            json!({"projectId": story.project_id, "systemType": "custom", "systemName": "Hunter", "definition": definition}),
+        )
+        .unwrap();
+        assert_eq!(system["templateData"]["tiers"][1]["requirements"][0], "level >= 10");
+        let err = enable_progression_system(
+            &conn,
+            json!({"projectId": story.project_id, "systemType": "game_stats", "definition": definition}),
+        )
+        .unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+
+        let record = |scene: usize, sheet: Value| {
+            record_progression_sheet(
+                &conn,
+                json!({"systemId": system["systemId"], "characterId": story.character_id, "sceneId": story.scenes[scene], "sheet": sheet}),
+            )
+            .unwrap()
+        };
+        record(0, json!({"level": 8, "tier": "E-Rank", "stats": {"vitality": 20}}));
+        let early = record(1, json!({"level": 9, "tier": "D-Rank", "stats": {"vitality": 22}}));
+        let alerts = early["progression"]["alerts"].as_array().unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0]["description"].as_str().unwrap().contains("level >= 10"));
+
+        let screen = render_status_screen(
+            &conn,
+            json!({"systemId": system["systemId"], "characterId": story.character_id, "format": "markdown"}),
+        )
+        .unwrap();
+        assert!(screen["screen"].as_str().unwrap().contains("| max_hp | 265 | +25 |"));
+        assert_eq!(screen["deltas"]["derived"]["max_hp"], 25.0);
+    }
+
+    #[test]
+    fn test_render_status_screen() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn);
+        let system = enable_progression_system(&conn, json!({"projectId": story.project_id, "systemType": "game_stats"})).unwrap();
+        for (scene, sheet) in [
+            (0, json!({"level": 1, "stats": {"STR": 5}, "skills": ["Slash"]})),
+            (2, json!({"level": 2, "stats": {"STR": 9}, "skills": ["Slash", "Dash"]})),
+        ] {
+            record_progression_sheet(
+                &conn,
+                json!({"systemId": system["systemId"], "characterId": story.character_id, "sceneId": story.scenes[scene], "sheet": sheet}),
+            )
+            .unwrap();
+        }
+        let render = |as_of: usize, format: &str| {
+            render_status_screen(
+                &conn,
+                json!({"systemId": system["systemId"], "characterId": story.character_id, "asOfSceneId": story.scenes[as_of], "format": format}),
+            )
+            .unwrap()
+        };
+
+        let latest = render(2, "markdown");
+        assert!(latest["screen"].as_str().unwrap().contains("| Level | 2 | +1 |"));
+        assert_eq!(latest["deltas"]["stats"]["STR"], 4);
+        assert_eq!(latest["deltas"]["skillsUnlocked"], json!(["Dash"]));
+        assert_eq!(latest["changesSince"]["sceneId"], story.scenes[0]);
+
+        // Between snapshots the screen shows the earlier one, with nothing before it
+        let early = render(1, "text");
+        assert!(early["screen"].as_str().unwrap().starts_with('╔'));
+        assert_eq!(early["sheetFrom"]["sceneId"], story.scenes[0]);
+        assert!(early["deltas"].is_null());
+
+        let other = add_character(&conn, json!({"projectId": story.project_id, "name": "Ren", "role": "minor"})).unwrap();
+        let err = render_status_screen(&conn, json!({"systemId": system["systemId"], "characterId": other["characterId"]})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+    }
+}
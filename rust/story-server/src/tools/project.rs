@@ -1,40 +1,56 @@
+use super::parse_params;
 use crate::error::{Result, StoryError};
 use crate::models::{ProjectLength, ProjectStatus, StoryProject};
 use chrono::Utc;
 use rusqlite::Connection;
+use schemars::JsonSchema;
+use serde::Deserialize;
 use serde_json::{json, Value};
 use uuid::Uuid;
 use std::fs;
-use std::path::PathBuf;
-
-/// Create a new story project
-pub fn create_story_project(conn: &Connection, params: Value) -> Result<Value> {
-    // Extract parameters
-    let title = params
-        .get("title")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: title"))?;
-
-    let genre = params.get("genre").and_then(|v| v.as_str());
-
-    let target_length_str = params
-        .get("targetLength")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: targetLength"))?;
+use std::path::Path;
+
+/// Folder that story folders and scene files are written under; `stories` when unset
+pub const STORIES_DIR_ENV: &str = "STORY_STORIES_DIR";
+
+/// Parameters for `createStoryProject`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CreateStoryProjectParams {
+    /// Unique project title
+    #[schemars(length(max = 200))]
+    pub title: String,
+    pub genre: Option<String>,
+    pub target_length: ProjectLength,
+    pub description: Option<String>,
+    /// Series folder the project is stored under (defaults to "standalone")
+    pub series_name: Option<String>,
+}
 
-    let intended_length = ProjectLength::from_str(target_length_str)
-        .ok_or_else(|| StoryError::validation(format!("Invalid targetLength: {}", target_length_str)))?;
+/// Parameters for `loadStoryProject`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LoadStoryProjectParams {
+    pub project_id: Uuid,
+}
 
-    let description = params.get("description").and_then(|v| v.as_str());
+/// Parameters for `listStoryProjects`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ListStoryProjectsParams {}
 
-    let series_name = params
-        .get("seriesName")
-        .and_then(|v| v.as_str())
-        .unwrap_or("standalone");
+/// Create a new story project, with its folder under `stories_root`
+pub fn create_story_project(conn: &Connection, stories_root: &Path, params: Value) -> Result<Value> {
+    let params: CreateStoryProjectParams = parse_params(params)?;
+    let title = params.title.as_str();
+    let genre = params.genre.as_deref();
+    let intended_length = params.target_length;
+    let description = params.description.as_deref();
+    let series_name = params.series_name.as_deref().unwrap_or("standalone");
 
     // Validate title length
-    if title.len() > 200 {
-        return Err(StoryError::validation("Title must be 200 characters or less"));
+    if title.chars().count() > 200 {
+        return Err(StoryError::validation("Title must be 200 characters or less").with_field("title"));
     }
 
     // Create project metadata
@@ -74,7 +90,13 @@ pub fn create_story_project(conn: &Connection, params: Value) -> Result<Value> {
         ),
     ).map_err(|e| {
         if e.to_string().contains("UNIQUE constraint failed") {
-            StoryError::duplicate(format!("A project with title '{}' already exists", title))
+            let err = StoryError::duplicate(format!("A project with title '{}' already exists", title))
+                .with_field("title");
+            // Point the client at the existing project so it can offer to load it instead
+            match conn.query_row("SELECT id FROM story_projects WHERE title = ?1", [title], |row| row.get::<_, String>(0)) {
+                Ok(existing_id) => err.with_entity("project", existing_id),
+                Err(_) => err,
+            }
         } else {
             StoryError::DatabaseError(e)
         }
@@ -82,11 +104,11 @@ pub fn create_story_project(conn: &Connection, params: Value) -> Result<Value> {
 
     log::info!("Created story project: {} ({})", project.title, project.id);
 
-    // Create folder structure: stories/{series_name}/{title}/chapters/
+    // Create folder structure: {stories root}/{series_name}/{title}/chapters/
     let sanitized_title = project.title.replace("/", "-").replace("\\", "-");
     let sanitized_series = series_name.replace("/", "-").replace("\\", "-");
     
-    let story_path = PathBuf::from("stories")
+    let story_path = stories_root
         .join(&sanitized_series)
         .join(&sanitized_title);
     
@@ -112,7 +134,7 @@ pub fn create_story_project(conn: &Connection, params: Value) -> Result<Value> {
         log::warn!("Failed to write metadata.json: {}", e);
     }
     
-    let story_folder = format!("stories/{}/{}", sanitized_series, sanitized_title);
+    let story_folder = story_path.to_string_lossy().to_string();
 
     // Return project details
     Ok(json!({
@@ -130,13 +152,7 @@ pub fn create_story_project(conn: &Connection, params: Value) -> Result<Value> {
 
 /// Load an existing story project
 pub fn load_story_project(conn: &Connection, params: Value) -> Result<Value> {
-    let project_id_str = params
-        .get("projectId")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: projectId"))?;
-
-    let project_id = Uuid::parse_str(project_id_str)
-        .map_err(|_| StoryError::validation("Invalid UUID format for projectId"))?;
+    let LoadStoryProjectParams { project_id } = parse_params(params)?;
 
     // Query project
     let mut stmt = conn.prepare(
@@ -164,6 +180,8 @@ pub fn load_story_project(conn: &Connection, params: Value) -> Result<Value> {
     }).map_err(|e| {
         if e == rusqlite::Error::QueryReturnedNoRows {
             StoryError::not_found(format!("Project not found: {}", project_id))
+                .with_field("projectId")
+                .with_entity("project", project_id)
         } else {
             StoryError::DatabaseError(e)
         }
@@ -185,7 +203,9 @@ pub fn load_story_project(conn: &Connection, params: Value) -> Result<Value> {
 }
 
 /// List all story projects
-pub fn list_story_projects(conn: &Connection, _params: Value) -> Result<Value> {
+pub fn list_story_projects(conn: &Connection, params: Value) -> Result<Value> {
+    let ListStoryProjectsParams {} = parse_params(params)?;
+
     let mut stmt = conn.prepare(
         "SELECT id, title, genre, intended_length, status, word_count, updated_at
          FROM story_projects
@@ -231,7 +251,7 @@ mod tests {
             "targetLength": "novel"
         });
 
-        let result = create_story_project(&conn, params);
+        let result = create_story_project(&conn, dir.path(), params);
         assert!(result.is_ok());
 
         let response = result.unwrap();
@@ -251,13 +271,40 @@ mod tests {
             "targetLength": "novel"
         });
 
-        create_story_project(&conn, params.clone()).unwrap();
-        let result = create_story_project(&conn, params);
+        create_story_project(&conn, dir.path(), params.clone()).unwrap();
+        let result = create_story_project(&conn, dir.path(), params);
 
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), StoryError::DuplicateEntry(_)));
     }
 
+    #[test]
+    fn test_title_length_counts_characters() {
+        let dir = tempdir().unwrap();
+        let conn = db::initialize_database(dir.path().join("test.db")).unwrap();
+
+        // 200 characters, 400 bytes
+        create_story_project(&conn, dir.path(), json!({"title": "é".repeat(200), "targetLength": "novel"})).unwrap();
+        let err = create_story_project(&conn, dir.path(), json!({"title": "é".repeat(201), "targetLength": "novel"})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+    }
+
+    #[test]
+    fn test_duplicate_title_reports_existing_project() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let conn = db::initialize_database(&db_path).unwrap();
+
+        let params = json!({"title": "Existing", "targetLength": "novel"});
+        let created = create_story_project(&conn, dir.path(), params.clone()).unwrap();
+
+        let err = create_story_project(&conn, dir.path(), params).unwrap_err();
+        let detail = err.detail().unwrap();
+        assert_eq!(detail.field.as_deref(), Some("title"));
+        assert_eq!(detail.entity_type.as_deref(), Some("project"));
+        assert_eq!(detail.entity_id.as_deref(), created["projectId"].as_str());
+    }
+
     #[test]
     fn test_load_story_project() {
         let dir = tempdir().unwrap();
@@ -269,7 +316,7 @@ mod tests {
             "targetLength": "novel"
         });
 
-        let create_result = create_story_project(&conn, create_params).unwrap();
+        let create_result = create_story_project(&conn, dir.path(), create_params).unwrap();
         let project_id = create_result.get("projectId").unwrap().as_str().unwrap();
 
         let load_params = json!({"projectId": project_id});
@@ -286,8 +333,8 @@ mod tests {
         let db_path = dir.path().join("test.db");
         let conn = db::initialize_database(&db_path).unwrap();
 
-        create_story_project(&conn, json!({"title": "Project 1", "targetLength": "novel"})).unwrap();
-        create_story_project(&conn, json!({"title": "Project 2", "targetLength": "novella"})).unwrap();
+        create_story_project(&conn, dir.path(), json!({"title": "Project 1", "targetLength": "novel"})).unwrap();
+        create_story_project(&conn, dir.path(), json!({"title": "Project 2", "targetLength": "novella"})).unwrap();
 
         let result = list_story_projects(&conn, json!({}));
         assert!(result.is_ok());
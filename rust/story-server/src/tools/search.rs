@@ -0,0 +1,369 @@
+use super::parse_params;
+use crate::db::fts::{self, SearchIndex};
+use crate::error::{Result, StoryError};
+use rusqlite::Connection;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+const DEFAULT_LIMIT: u32 = 20;
+
+/// Parameters for `search`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SearchParams {
+    pub project_id: Uuid,
+    /// Words that must all appear; "double quotes" match a phrase, a trailing * matches a prefix
+    pub query: String,
+    /// Kinds of entity to search; all of them when omitted
+    pub entity_types: Option<Vec<SearchIndex>>,
+    /// Results per page, default 20
+    #[schemars(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+    /// Results to skip, for the next page
+    pub offset: Option<u32>,
+}
+
+/// Parameters for `rebuildSearchIndex`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RebuildSearchIndexParams {
+    /// Indexes to rebuild; all of them when omitted
+    pub indexes: Option<Vec<SearchIndex>>,
+}
+
+/// Full-text search over a project's characters, world rules, scenes and summaries,
+/// with the matching text highlighted in `**bold**`. Each kind is ranked on its own,
+/// as bm25() scores from different indexes can't be compared, and results take the
+/// best match of every kind, then the second best of every kind, and so on.
+pub fn search(conn: &Connection, params: Value) -> Result<Value> {
+    let params: SearchParams = parse_params(params)?;
+    let project_id = params.project_id;
+    let expression = match_expression(&params.query)
+        .ok_or_else(|| StoryError::validation("Query must contain at least one word").with_field("query"))?;
+    let entity_types = params.entity_types.unwrap_or_else(|| SearchIndex::ALL.to_vec());
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    let offset = params.offset.unwrap_or(0);
+
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM story_projects WHERE id = ?1)",
+        [project_id.to_string()],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Err(StoryError::not_found(format!("Project not found: {}", project_id))
+            .with_field("projectId")
+            .with_entity("project", project_id));
+    }
+
+    let mut kinds: Vec<SearchIndex> = Vec::new();
+    for index in entity_types {
+        if !kinds.contains(&index) {
+            kinds.push(index);
+        }
+    }
+    let matches = kinds.iter().map(|&index| match_query(index)).collect::<Vec<_>>().join(" UNION ALL ");
+
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM ({})", matches),
+        (&expression, project_id.to_string()),
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT entity_type, entity_id, title, snippet, rank, relevance FROM ({})
+         ORDER BY rank, relevance DESC, entity_type, entity_id
+         LIMIT ?3 OFFSET ?4",
+        matches
+    ))?;
+    let results = stmt
+        .query_map((&expression, project_id.to_string(), limit, offset), |row| {
+            Ok(json!({
+                "entityType": row.get::<_, String>(0)?,
+                "entityId": row.get::<_, String>(1)?,
+                "title": row.get::<_, String>(2)?,
+                "snippet": row.get::<_, String>(3)?,
+                "rank": row.get::<_, i64>(4)?,
+                "relevance": row.get::<_, f64>(5)?
+            }))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    log::info!("Search for {:?} in project {} found {} result(s)", params.query, project_id, total);
+
+    let mut response = json!({
+        "query": params.query,
+        "total": total,
+        "results": results
+    });
+    let next = offset as i64 + results.len() as i64;
+    if next < total {
+        response["nextOffset"] = json!(next);
+    }
+    Ok(response)
+}
+
+/// Matches in one index for `?1` (the FTS5 expression) within project `?2`, with
+/// their rank in that index and their bm25() score relative to its best match
+fn match_query(index: SearchIndex) -> String {
+    let (title, join) = match index {
+        SearchIndex::Characters => ("e.name", "JOIN characters e ON e.rowid = f.rowid WHERE e.story_project_id = ?2"),
+        SearchIndex::WorldRules => ("e.name", "JOIN world_rules e ON e.rowid = f.rowid WHERE e.story_project_id = ?2"),
+        SearchIndex::Scenes => (
+            "COALESCE(e.title, 'Chapter ' || ch.number || ', scene ' || e.position)",
+            "JOIN scenes e ON e.rowid = f.rowid
+             JOIN chapters ch ON ch.id = e.chapter_id
+             JOIN acts a ON a.id = ch.act_id
+             JOIN plot_structures ps ON ps.id = a.plot_structure_id
+             WHERE ps.story_project_id = ?2",
+        ),
+        SearchIndex::Summaries => (
+            "e.scope || ' summary'",
+            "JOIN story_summaries e ON e.rowid = f.rowid WHERE e.story_project_id = ?2",
+        ),
+    };
+    // bm25() scores are negative, and lower for better matches. LIMIT -1 keeps SQLite
+    // from flattening the inner query, which would move bm25() where it can't run.
+    format!(
+        "SELECT entity_type, entity_id, title, snippet,
+                ROW_NUMBER() OVER (ORDER BY score, entity_id) AS rank,
+                COALESCE(score / NULLIF(MIN(score) OVER (), 0), 1.0) AS relevance
+         FROM (SELECT '{kind}' AS entity_type, e.id AS entity_id, {title} AS title,
+                      snippet(f.{table}, -1, '**', '**', '…', 16) AS snippet, bm25(f.{table}) AS score
+               FROM {table} f {join} AND f.{table} MATCH ?1
+               LIMIT -1)",
+        kind = index,
+        table = index.table(),
+    )
+}
+
+/// Turn what a writer typed into an FTS5 expression. Every term is quoted, so
+/// punctuation such as `?` or `-` is never read as query syntax.
+fn match_expression(query: &str) -> Option<String> {
+    let quote = |text: &str| format!("\"{}\"", text.replace('"', "\"\""));
+    let has_word = |text: &str| text.chars().any(char::is_alphanumeric);
+
+    let mut terms = Vec::new();
+    let mut rest = query;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(after) = rest.strip_prefix('"') {
+            let (phrase, tail) = after.split_once('"').unwrap_or((after, ""));
+            if has_word(phrase) {
+                terms.push(quote(phrase));
+            }
+            rest = tail;
+        } else {
+            let end = rest.find(|c: char| c.is_whitespace() || c == '"').unwrap_or(rest.len());
+            let word = &rest[..end];
+            rest = &rest[end..];
+            match word.strip_suffix('*') {
+                Some(prefix) if has_word(prefix) => terms.push(format!("{}*", quote(prefix))),
+                _ if has_word(word) => terms.push(quote(word)),
+                _ => {}
+            }
+        }
+    }
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Rebuild full-text search indexes from their tables, reporting whether each had drifted
+pub fn rebuild_search_index(conn: &Connection, params: Value) -> Result<Value> {
+    let params: RebuildSearchIndexParams = parse_params(params)?;
+    let indexes = params.indexes.unwrap_or_else(|| SearchIndex::ALL.to_vec());
+
+    let mut results = Vec::new();
+    for index in indexes {
+        let was_consistent = fts::is_consistent(conn, index)?;
+        fts::rebuild(conn, index)?;
+        results.push(json!({
+            "index": index,
+            "wasConsistent": was_consistent,
+            "documentCount": fts::document_count(conn, index)?
+        }));
+    }
+
+    Ok(json!({ "indexes": results }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    /// A project whose character, world rule and scene mention the Silver Gate, plus a summary about a gate
+    fn silver_gate_project(conn: &Connection, stories: &Path, title: &str) -> String {
+        use crate::tools::{add_character, add_chapter, add_scene, add_world_rule, create_story_project, initialize_plot_structure};
+
+        let project = create_story_project(conn, stories, json!({"title": title, "targetLength": "novel"})).unwrap();
+        let project_id = project["projectId"].as_str().unwrap().to_string();
+        add_character(
+            conn,
+            json!({"projectId": project_id, "name": "Mira", "role": "protagonist", "backstory": "Born beneath the Silver Gate"}),
+        )
+        .unwrap();
+        add_world_rule(
+            conn,
+            json!({"projectId": project_id, "name": "Gatekeeping", "description": "Only the crowned may open the Silver Gate", "scope": "universal"}),
+        )
+        .unwrap();
+        let plot = initialize_plot_structure(conn, json!({"projectId": project_id})).unwrap();
+        let chapter = add_chapter(conn, json!({"actId": plot["acts"][0]["actId"], "number": 1})).unwrap();
+        add_scene(
+            conn,
+            stories,
+            json!({"chapterId": chapter["chapterId"], "sceneOutline": "Mira reaches the gate", "content": "The Silver Gate was shut. Rain fell on the silver."}),
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO story_summaries (id, story_project_id, scope, summary_text) VALUES (?1, ?2, 'overall', 'A girl walks to a gate')",
+            (Uuid::new_v4().to_string(), &project_id),
+        )
+        .unwrap();
+        project_id
+    }
+
+    #[test]
+    fn test_search_across_entities() {
+        let stories = tempdir().unwrap();
+        let conn = db::initialize_database(":memory:").unwrap();
+        let project_id = silver_gate_project(&conn, stories.path(), "Gates");
+        silver_gate_project(&conn, stories.path(), "Other Gates");
+
+        let result = search(&conn, json!({"projectId": project_id, "query": "\"Silver Gate\""})).unwrap();
+        assert_eq!(result["total"], 3);
+        let mut kinds: Vec<&str> = result["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["entityType"].as_str().unwrap())
+            .collect();
+        kinds.sort();
+        assert_eq!(kinds, vec!["characters", "scenes", "world_rules"]);
+        assert!(result.get("nextOffset").is_none());
+
+        // One match of each kind, so each is the best of its kind
+        assert!(result["results"].as_array().unwrap().iter().all(|r| r["rank"] == 1 && r["relevance"] == 1.0));
+
+        let scene = result["results"].as_array().unwrap().iter().find(|r| r["entityType"] == "scenes").unwrap();
+        assert!(scene["snippet"].as_str().unwrap().contains("**Silver Gate**"));
+        assert_eq!(scene["title"], "Chapter 1, scene 1");
+
+        let summaries = search(&conn, json!({"projectId": project_id, "query": "gate", "entityTypes": ["summaries"]})).unwrap();
+        assert_eq!(summaries["total"], 1);
+        assert_eq!(summaries["results"][0]["title"], "overall summary");
+    }
+
+    #[test]
+    fn test_search_pagination() {
+        let stories = tempdir().unwrap();
+        let conn = db::initialize_database(":memory:").unwrap();
+        let project_id = silver_gate_project(&conn, stories.path(), "Pages");
+
+        let first = search(&conn, json!({"projectId": project_id, "query": "gate", "limit": 2})).unwrap();
+        assert_eq!(first["total"], 4);
+        assert_eq!(first["results"].as_array().unwrap().len(), 2);
+        assert_eq!(first["nextOffset"], 2);
+
+        let second = search(&conn, json!({"projectId": project_id, "query": "gate", "limit": 2, "offset": 2})).unwrap();
+        assert_eq!(second["results"].as_array().unwrap().len(), 2);
+        assert!(second.get("nextOffset").is_none());
+
+        let ids = |page: &Value| -> Vec<String> {
+            page["results"].as_array().unwrap().iter().map(|r| r["entityId"].as_str().unwrap().to_string()).collect()
+        };
+        assert!(ids(&first).iter().all(|id| !ids(&second).contains(id)));
+    }
+
+    #[test]
+    fn test_search_ranks_each_kind_separately() {
+        let stories = tempdir().unwrap();
+        let conn = db::initialize_database(":memory:").unwrap();
+        let project_id = silver_gate_project(&conn, stories.path(), "Ranks");
+        let chapter_id: String = conn.query_row("SELECT id FROM chapters", [], |row| row.get(0)).unwrap();
+        for content in ["Gate after gate after gate.", "The gate, the gate."] {
+            crate::tools::add_scene(&conn, stories.path(), json!({"chapterId": chapter_id, "content": content})).unwrap();
+        }
+
+        // Scenes that say "gate" more often don't crowd out the other kinds
+        let result = search(&conn, json!({"projectId": project_id, "query": "gate", "limit": 4})).unwrap();
+        assert_eq!(result["total"], 6);
+        let mut kinds: Vec<&str> = result["results"].as_array().unwrap().iter().map(|r| r["entityType"].as_str().unwrap()).collect();
+        kinds.sort();
+        assert_eq!(kinds, vec!["characters", "scenes", "summaries", "world_rules"]);
+
+        let scenes = search(&conn, json!({"projectId": project_id, "query": "gate", "entityTypes": ["scenes"]})).unwrap();
+        let ranks: Vec<i64> = scenes["results"].as_array().unwrap().iter().map(|r| r["rank"].as_i64().unwrap()).collect();
+        assert_eq!(ranks, vec![1, 2, 3]);
+        let relevance: Vec<f64> = scenes["results"].as_array().unwrap().iter().map(|r| r["relevance"].as_f64().unwrap()).collect();
+        assert_eq!(relevance[0], 1.0);
+        assert!(relevance.windows(2).all(|w| w[0] >= w[1] && w[1] > 0.0));
+    }
+
+    #[test]
+    fn test_search_query_syntax_is_escaped() {
+        let stories = tempdir().unwrap();
+        let conn = db::initialize_database(":memory:").unwrap();
+        let project_id = silver_gate_project(&conn, stories.path(), "Escapes");
+
+        // Punctuation that would be FTS5 syntax is just text
+        let result = search(&conn, json!({"projectId": project_id, "query": "where is the Silver-Gate?"})).unwrap();
+        assert_eq!(result["total"], 0);
+        let result = search(&conn, json!({"projectId": project_id, "query": "crown*"})).unwrap();
+        assert_eq!(result["results"][0]["entityType"], "world_rules");
+
+        let err = search(&conn, json!({"projectId": project_id, "query": " ?! "})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+
+        let err = search(&conn, json!({"projectId": Uuid::new_v4(), "query": "gate"})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_match_expression() {
+        assert_eq!(match_expression("silver gate").unwrap(), "\"silver\" \"gate\"");
+        assert_eq!(match_expression("\"Silver Gate\" crown*").unwrap(), "\"Silver Gate\" \"crown\"*");
+        assert_eq!(match_expression("OR NOT (x)").unwrap(), "\"OR\" \"NOT\" \"(x)\"");
+        assert_eq!(match_expression("\"unterminated").unwrap(), "\"unterminated\"");
+        assert!(match_expression("  * ? \"\" ").is_none());
+    }
+
+    #[test]
+    fn test_rebuild_search_index() {
+        let stories = tempdir().unwrap();
+        let conn = db::initialize_database(":memory:").unwrap();
+        let project = crate::tools::create_story_project(&conn, stories.path(), json!({"title": "Search", "targetLength": "novel"})).unwrap();
+        crate::tools::add_character(
+            &conn,
+            json!({"projectId": project["projectId"], "name": "Mira", "role": "protagonist"}),
+        )
+        .unwrap();
+
+        let result = rebuild_search_index(&conn, json!({})).unwrap();
+        let indexes = result["indexes"].as_array().unwrap();
+        assert_eq!(indexes.len(), SearchIndex::ALL.len());
+        assert_eq!(indexes[0]["index"], "characters");
+        assert_eq!(indexes[0]["wasConsistent"], true);
+        assert_eq!(indexes[0]["documentCount"], 1);
+
+        // Drift introduced behind the triggers is reported, then fixed
+        conn.execute("INSERT INTO characters_fts (characters_fts) VALUES ('delete-all')", []).unwrap();
+        let result = rebuild_search_index(&conn, json!({"indexes": ["characters"]})).unwrap();
+        assert_eq!(result["indexes"].as_array().unwrap().len(), 1);
+        assert_eq!(result["indexes"][0]["wasConsistent"], false);
+        assert!(fts::is_consistent(&conn, SearchIndex::Characters).unwrap());
+    }
+
+    #[test]
+    fn test_rebuild_search_index_rejects_unknown_index() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let err = rebuild_search_index(&conn, json!({"indexes": ["plots"]})).unwrap_err();
+        assert!(matches!(err, crate::error::StoryError::ValidationError(_)));
+    }
+}
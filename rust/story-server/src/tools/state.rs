@@ -0,0 +1,390 @@
+use super::parse_params;
+use crate::error::{Result, StoryError};
+use crate::models::CharacterState;
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Parameters for `recordCharacterState`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RecordCharacterStateParams {
+    pub character_id: Uuid,
+    /// Scene at the end of which the character is in this state
+    pub scene_id: Uuid,
+    /// Only what changed; omitted fields carry over from earlier scenes
+    pub state: CharacterState,
+}
+
+/// Parameters for `getCharacterState`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GetCharacterStateParams {
+    pub character_id: Uuid,
+    /// State at the end of this scene; defaults to the end of the manuscript
+    pub as_of_scene_id: Option<Uuid>,
+}
+
+/// Parameters for `listCharacterStates`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ListCharacterStatesParams {
+    pub character_id: Uuid,
+}
+
+/// A recorded snapshot with its scene's place in the manuscript
+struct Snapshot {
+    id: String,
+    scene_id: String,
+    order: (i32, i32, i32),
+    state: CharacterState,
+    recorded_at: String,
+}
+
+/// Record a character's state at a scene, replacing any snapshot already recorded there
+pub fn record_character_state(conn: &Connection, params: Value) -> Result<Value> {
+    let params: RecordCharacterStateParams = parse_params(params)?;
+    let character_id = params.character_id.to_string();
+    let scene_id = params.scene_id.to_string();
+
+    if params.state.fields().is_empty() {
+        return Err(StoryError::validation("State must set at least one field").with_field("state"));
+    }
+    let project_id = character_project(conn, &character_id)?;
+    scene_order(conn, &scene_id, &project_id)?;
+
+    let existing: Option<String> = conn
+        .query_row(
+            "SELECT id FROM character_state_history WHERE character_id = ?1 AND scene_id = ?2",
+            (&character_id, &scene_id),
+            |row| row.get(0),
+        )
+        .optional()?;
+    let snapshot_id = existing.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    let snapshot = serde_json::to_string(&params.state).expect("state serializes");
+
+    conn.execute(
+        "INSERT INTO character_state_history (id, character_id, scene_id, state_snapshot, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(character_id, scene_id) DO UPDATE SET
+             state_snapshot = excluded.state_snapshot,
+             created_at = excluded.created_at",
+        (&snapshot_id, &character_id, &scene_id, &snapshot, Utc::now().to_rfc3339()),
+    )?;
+
+    log::info!(
+        "{} state of character {} at scene {}: {}",
+        if existing.is_some() { "Replaced" } else { "Recorded" },
+        character_id,
+        scene_id,
+        params.state.fields().join(", ")
+    );
+
+    let snapshot = snapshots(conn, &character_id)?
+        .into_iter()
+        .find(|s| s.id == snapshot_id)
+        .expect("snapshot was just written");
+    let mut response = snapshot_json(&snapshot);
+    response["characterId"] = json!(character_id);
+    response["created"] = json!(existing.is_none());
+    Ok(response)
+}
+
+/// A character's state at a point in the manuscript, built by applying their
+/// snapshots in reading order up to and including that scene
+pub fn get_character_state(conn: &Connection, params: Value) -> Result<Value> {
+    let params: GetCharacterStateParams = parse_params(params)?;
+    let character_id = params.character_id.to_string();
+    let project_id = character_project(conn, &character_id)?;
+
+    let (name, current_state): (String, Option<String>) = conn.query_row(
+        "SELECT name, current_state FROM characters WHERE id = ?1",
+        [&character_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    let as_of = match params.as_of_scene_id {
+        Some(scene_id) => {
+            let scene_id = scene_id.to_string();
+            let order = scene_order(conn, &scene_id, &project_id)?;
+            Some((scene_id, order))
+        }
+        None => None,
+    };
+
+    let mut state = CharacterState::default();
+    let mut set_at = Map::new();
+    let mut applied = 0;
+    for snapshot in snapshots(conn, &character_id)? {
+        if as_of.as_ref().is_some_and(|(_, order)| snapshot.order > *order) {
+            break;
+        }
+        state.apply(&snapshot.state);
+        for field in snapshot.state.fields() {
+            set_at.insert(field.to_string(), scene_ref(&snapshot.scene_id, snapshot.order));
+        }
+        applied += 1;
+    }
+
+    Ok(json!({
+        "characterId": character_id,
+        "name": name,
+        "asOf": as_of.map(|(scene_id, order)| scene_ref(&scene_id, order)),
+        "state": state,
+        "setAt": set_at,
+        "snapshotsApplied": applied,
+        "currentState": current_state
+    }))
+}
+
+/// Every snapshot of a character, in manuscript order
+pub fn list_character_states(conn: &Connection, params: Value) -> Result<Value> {
+    let params: ListCharacterStatesParams = parse_params(params)?;
+    let character_id = params.character_id.to_string();
+    character_project(conn, &character_id)?;
+
+    let history: Vec<Value> = snapshots(conn, &character_id)?.iter().map(snapshot_json).collect();
+    Ok(json!({
+        "characterId": character_id,
+        "snapshots": history
+    }))
+}
+
+pub(crate) fn character_project(conn: &Connection, character_id: &str) -> Result<String> {
+    conn.query_row("SELECT story_project_id FROM characters WHERE id = ?1", [character_id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| {
+            StoryError::not_found(format!("Character not found: {}", character_id))
+                .with_field("characterId")
+                .with_entity("character", character_id)
+        })
+}
+
+/// The scene's (act position, chapter number, scene position), checking it belongs to the project
+pub(crate) fn scene_order(conn: &Connection, scene_id: &str, project_id: &str) -> Result<(i32, i32, i32)> {
+    let (scene_project, order): (String, (i32, i32, i32)) = conn
+        .query_row(
+            "SELECT ps.story_project_id, a.position, c.number, s.position
+             FROM scenes s
+             JOIN chapters c ON s.chapter_id = c.id
+             JOIN acts a ON c.act_id = a.id
+             JOIN plot_structures ps ON a.plot_structure_id = ps.id
+             WHERE s.id = ?1",
+            [scene_id],
+            |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?, row.get(3)?))),
+        )
+        .optional()?
+        .ok_or_else(|| {
+            StoryError::not_found(format!("Scene not found: {}", scene_id))
+                .with_field("sceneId")
+                .with_entity("scene", scene_id)
+        })?;
+    if scene_project != project_id {
+        return Err(StoryError::validation("Scene belongs to a different project than the character")
+            .with_field("sceneId")
+            .with_entity("scene", scene_id));
+    }
+    Ok(order)
+}
+
+fn snapshots(conn: &Connection, character_id: &str) -> Result<Vec<Snapshot>> {
+    let mut stmt = conn.prepare(
+        "SELECT h.id, h.scene_id, a.position, c.number, s.position, h.state_snapshot, h.created_at
+         FROM character_state_history h
+         JOIN scenes s ON h.scene_id = s.id
+         JOIN chapters c ON s.chapter_id = c.id
+         JOIN acts a ON c.act_id = a.id
+         WHERE h.character_id = ?1
+         ORDER BY a.position, c.number, s.position",
+    )?;
+    let rows = stmt.query_map([character_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            (row.get(2)?, row.get(3)?, row.get(4)?),
+            row.get::<_, String>(5)?,
+            row.get::<_, String>(6)?,
+        ))
+    })?;
+
+    let mut snapshots = Vec::new();
+    for row in rows {
+        let (id, scene_id, order, stored, recorded_at) = row?;
+        // Rows written outside this server may hold other JSON; read what we can
+        let state = serde_json::from_str(&stored).unwrap_or_else(|e| {
+            log::warn!("Unreadable state snapshot {}: {}", id, e);
+            CharacterState::default()
+        });
+        snapshots.push(Snapshot { id, scene_id, order, state, recorded_at });
+    }
+    Ok(snapshots)
+}
+
+/// Where each state field was last set: the (act, chapter, scene) order of the snapshot
+pub(crate) type SetAt = HashMap<&'static str, (i32, i32, i32)>;
+
+/// A character's state going into the scene at `order`: their snapshots before it
+/// applied in reading order, and where each field was last set. `None` when the
+/// character has no snapshots at all, so callers can fall back to `current_state`.
+pub(crate) fn state_before(
+    conn: &Connection,
+    character_id: &str,
+    order: (i32, i32, i32),
+) -> Result<Option<(CharacterState, SetAt)>> {
+    let history = snapshots(conn, character_id)?;
+    if history.is_empty() {
+        return Ok(None);
+    }
+    let mut state = CharacterState::default();
+    let mut set_at = HashMap::new();
+    for snapshot in history.iter().take_while(|snapshot| snapshot.order < order) {
+        state.apply(&snapshot.state);
+        for field in snapshot.state.fields() {
+            set_at.insert(field, snapshot.order);
+        }
+    }
+    Ok(Some((state, set_at)))
+}
+
+pub(crate) fn scene_ref(scene_id: &str, order: (i32, i32, i32)) -> Value {
+    json!({
+        "sceneId": scene_id,
+        "chapterNumber": order.1,
+        "scenePosition": order.2
+    })
+}
+
+fn snapshot_json(snapshot: &Snapshot) -> Value {
+    json!({
+        "snapshotId": snapshot.id,
+        "sceneId": snapshot.scene_id,
+        "chapterNumber": snapshot.order.1,
+        "scenePosition": snapshot.order.2,
+        "state": snapshot.state,
+        "fields": snapshot.state.fields(),
+        "recordedAt": snapshot.recorded_at
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::tools::{add_chapter, add_character, add_scene, create_story_project, initialize_plot_structure};
+    use tempfile::tempdir;
+
+    struct Story {
+        mira: String,
+        /// Two chapters of two scenes, in reading order
+        scenes: Vec<String>,
+    }
+
+    fn story(conn: &Connection) -> Story {
+        let stories = tempdir().unwrap();
+        let project = create_story_project(conn, stories.path(), json!({"title": "State", "targetLength": "novel"})).unwrap();
+        let mira = add_character(conn, json!({"projectId": project["projectId"], "name": "Mira", "role": "protagonist"}))
+            .unwrap()["characterId"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let plot = initialize_plot_structure(conn, json!({"projectId": project["projectId"]})).unwrap();
+        let mut scenes = Vec::new();
+        for number in 1..=2 {
+            let chapter = add_chapter(conn, json!({"actId": plot["acts"][0]["actId"], "number": number})).unwrap();
+            for _ in 0..2 {
+                let scene = add_scene(conn, stories.path(), json!({"chapterId": chapter["chapterId"]})).unwrap();
+                scenes.push(scene["sceneId"].as_str().unwrap().to_string());
+            }
+        }
+        Story { mira, scenes }
+    }
+
+    #[test]
+    fn test_state_as_of_scene() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn);
+        let record = |scene: &str, state: Value| {
+            record_character_state(&conn, json!({"characterId": story.mira, "sceneId": scene, "state": state})).unwrap()
+        };
+
+        // Recorded out of order; reading order is what counts
+        record(&story.scenes[2], json!({"location": "Harbor", "possessions": ["Map"]}));
+        let first = record(
+            &story.scenes[0],
+            json!({"location": "Keep", "health": "Unhurt", "possessions": ["Map", "Knife"], "relationships": [{"character": "Dax", "standing": "Trusts him"}]}),
+        );
+        assert_eq!(first["created"], true);
+        assert_eq!(first["chapterNumber"], 1);
+        assert_eq!(first["fields"], json!(["location", "health", "possessions", "relationships"]));
+
+        let as_of = |scene: &str| get_character_state(&conn, json!({"characterId": story.mira, "asOfSceneId": scene})).unwrap();
+
+        let early = as_of(&story.scenes[1]);
+        assert_eq!(early["state"]["location"], "Keep");
+        assert_eq!(early["state"]["possessions"], json!(["Map", "Knife"]));
+        assert_eq!(early["snapshotsApplied"], 1);
+        assert_eq!(early["setAt"]["location"]["sceneId"], story.scenes[0]);
+
+        let later = as_of(&story.scenes[3]);
+        assert_eq!(later["state"]["location"], "Harbor");
+        assert_eq!(later["state"]["possessions"], json!(["Map"]));
+        // Carried over from chapter 1
+        assert_eq!(later["state"]["health"], "Unhurt");
+        assert_eq!(later["state"]["relationships"][0]["character"], "Dax");
+        assert_eq!(later["setAt"]["location"]["chapterNumber"], 2);
+        assert_eq!(later["setAt"]["health"]["chapterNumber"], 1);
+        assert_eq!(later["asOf"]["scenePosition"], 2);
+
+        // No scene means the end of the manuscript
+        let latest = get_character_state(&conn, json!({"characterId": story.mira})).unwrap();
+        assert_eq!(latest["state"], later["state"]);
+        assert!(latest["asOf"].is_null());
+
+        let history = list_character_states(&conn, json!({"characterId": story.mira})).unwrap();
+        let scenes: Vec<&str> = history["snapshots"].as_array().unwrap().iter().map(|s| s["sceneId"].as_str().unwrap()).collect();
+        assert_eq!(scenes, vec![story.scenes[0].as_str(), story.scenes[2].as_str()]);
+    }
+
+    #[test]
+    fn test_recording_again_replaces_snapshot() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn);
+        let params = |state: Value| json!({"characterId": story.mira, "sceneId": story.scenes[0], "state": state});
+
+        let first = record_character_state(&conn, params(json!({"health": "Bruised"}))).unwrap();
+        let second = record_character_state(&conn, params(json!({"health": "Healed", "notes": "Rested a week"}))).unwrap();
+        assert_eq!(second["created"], false);
+        assert_eq!(second["snapshotId"], first["snapshotId"]);
+        assert_eq!(second["state"], json!({"health": "Healed", "notes": "Rested a week"}));
+
+        let history = list_character_states(&conn, json!({"characterId": story.mira})).unwrap();
+        assert_eq!(history["snapshots"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_record_state_validation() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn);
+
+        let err = record_character_state(&conn, json!({"characterId": story.mira, "sceneId": story.scenes[0], "state": {}})).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+
+        let other = create_story_project(&conn, tempdir().unwrap().path(), json!({"title": "Other", "targetLength": "novel"})).unwrap();
+        let stranger = add_character(&conn, json!({"projectId": other["projectId"], "name": "Orrin", "role": "minor"})).unwrap();
+        let err = record_character_state(
+            &conn,
+            json!({"characterId": stranger["characterId"], "sceneId": story.scenes[0], "state": {"health": "Fine"}}),
+        )
+        .unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+
+        let err = get_character_state(&conn, json!({"characterId": story.mira, "asOfSceneId": Uuid::new_v4()})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+        let err = list_character_states(&conn, json!({"characterId": Uuid::new_v4()})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+    }
+}
@@ -0,0 +1,638 @@
+use super::parse_params;
+use crate::context::tokens;
+use crate::error::{Result, StoryError};
+use crate::models::SummaryScope;
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension, Row};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const SUMMARY_COLUMNS: &str = "id, story_project_id, scope, reference_id, summary_text, key_events, character_developments,
+     word_count, ai_generated, generated_at, stale, stale_reason";
+
+/// Parameters for `upsertSummary`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpsertSummaryParams {
+    pub project_id: Uuid,
+    pub scope: SummaryScope,
+    /// Scene, chapter or act summarized; omitted for the overall summary
+    pub reference_id: Option<Uuid>,
+    pub summary_text: String,
+    pub key_events: Option<Vec<String>>,
+    /// How characters changed over the summarized part
+    pub character_developments: Option<Vec<String>>,
+    /// Defaults to true
+    pub ai_generated: Option<bool>,
+}
+
+/// Parameters for `getSummary`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GetSummaryParams {
+    pub project_id: Uuid,
+    pub scope: SummaryScope,
+    /// Scene, chapter or act summarized; omitted for the overall summary
+    pub reference_id: Option<Uuid>,
+}
+
+/// Parameters for `listSummaries`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ListSummariesParams {
+    pub project_id: Uuid,
+    /// Only summaries of this scope
+    pub scope: Option<SummaryScope>,
+    /// Only summaries that need rewriting
+    pub stale_only: Option<bool>,
+    /// Also report what each summary costs to load into context
+    pub include_tokens: Option<bool>,
+}
+
+/// Parameters for `invalidateSummary`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct InvalidateSummaryParams {
+    pub project_id: Uuid,
+    pub scope: SummaryScope,
+    /// Scene, chapter or act summarized; omitted for the overall summary
+    pub reference_id: Option<Uuid>,
+    /// Why the summary needs rewriting
+    pub reason: Option<String>,
+}
+
+/// Parameters for `getSummaryRollup`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GetSummaryRollupParams {
+    pub project_id: Uuid,
+}
+
+/// Create or replace the summary of a scene, chapter, act or the whole project
+pub fn upsert_summary(conn: &Connection, params: Value) -> Result<Value> {
+    let params: UpsertSummaryParams = parse_params(params)?;
+    let project_id = params.project_id;
+    let scope = params.scope;
+    let reference_id = check_reference(conn, project_id, scope, params.reference_id)?;
+
+    if params.summary_text.trim().is_empty() {
+        return Err(StoryError::validation("Summary text must not be empty").with_field("summaryText"));
+    }
+
+    let key_events = params.key_events.map(|list| serde_json::to_string(&list).unwrap());
+    let character_developments = params.character_developments.map(|list| serde_json::to_string(&list).unwrap());
+    let word_count = params.summary_text.split_whitespace().count() as i32;
+    let ai_generated = params.ai_generated.unwrap_or(true);
+    let now = Utc::now().to_rfc3339();
+
+    let existing = find_summary_id(conn, project_id, scope, reference_id.as_deref())?;
+    let summary_id = match &existing {
+        Some(id) => {
+            conn.execute(
+                "UPDATE story_summaries
+                 SET summary_text = ?2, key_events = ?3, character_developments = ?4, word_count = ?5,
+                     ai_generated = ?6, generated_at = ?7, stale = 0, stale_reason = NULL
+                 WHERE id = ?1",
+                (id, &params.summary_text, &key_events, &character_developments, word_count, ai_generated, &now),
+            )?;
+            id.clone()
+        }
+        None => {
+            let id = Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO story_summaries (id, story_project_id, scope, reference_id, summary_text, key_events,
+                     character_developments, word_count, ai_generated, generated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                (
+                    &id,
+                    project_id.to_string(),
+                    scope.to_string(),
+                    &reference_id,
+                    &params.summary_text,
+                    &key_events,
+                    &character_developments,
+                    word_count,
+                    ai_generated,
+                    &now,
+                ),
+            )?;
+            id
+        }
+    };
+
+    log::info!(
+        "{} {} summary {} for project {}",
+        if existing.is_some() { "Updated" } else { "Created" },
+        scope,
+        summary_id,
+        project_id
+    );
+
+    let mut response = load_summary(conn, &summary_id)?;
+    response["created"] = json!(existing.is_none());
+    Ok(response)
+}
+
+/// Get the summary of a scene, chapter, act or the whole project
+pub fn get_summary(conn: &Connection, params: Value) -> Result<Value> {
+    let GetSummaryParams { project_id, scope, reference_id } = parse_params(params)?;
+    let reference_id = check_reference(conn, project_id, scope, reference_id)?;
+
+    match find_summary_id(conn, project_id, scope, reference_id.as_deref())? {
+        Some(id) => load_summary(conn, &id),
+        None => Err(summary_not_found(scope, reference_id.as_deref())),
+    }
+}
+
+/// List a project's summaries in story order, optionally only one scope or only stale ones
+pub fn list_summaries(conn: &Connection, params: Value) -> Result<Value> {
+    let params: ListSummariesParams = parse_params(params)?;
+    let project_id = params.project_id;
+    let include_tokens = params.include_tokens.unwrap_or(false);
+    ensure_project(conn, project_id)?;
+
+    let mut summaries: Vec<Value> = project_summaries(conn, project_id)?
+        .into_iter()
+        .filter(|s| match params.scope {
+            Some(scope) => s["scope"] == scope.to_string(),
+            None => true,
+        })
+        .filter(|s| !params.stale_only.unwrap_or(false) || s["stale"] == true)
+        .collect();
+    if include_tokens {
+        for summary in &mut summaries {
+            summary["tokens"] = json!(summary_tokens(summary));
+        }
+    }
+
+    log::info!("Listed {} summaries for project {}", summaries.len(), project_id);
+
+    let mut response = json!({
+        "summaries": summaries
+    });
+    if include_tokens {
+        response["tokenCounting"] = json!(tokens::counter().method());
+    }
+    Ok(response)
+}
+
+/// Mark a summary as needing to be rewritten
+pub fn invalidate_summary(conn: &Connection, params: Value) -> Result<Value> {
+    let params: InvalidateSummaryParams = parse_params(params)?;
+    let project_id = params.project_id;
+    let scope = params.scope;
+    let reference_id = check_reference(conn, project_id, scope, params.reference_id)?;
+
+    let summary_id = find_summary_id(conn, project_id, scope, reference_id.as_deref())?
+        .ok_or_else(|| summary_not_found(scope, reference_id.as_deref()))?;
+    let reason = params.reason.unwrap_or_else(|| "Invalidated by the author".to_string());
+    conn.execute(
+        "UPDATE story_summaries SET stale = 1, stale_reason = ?2 WHERE id = ?1",
+        (&summary_id, &reason),
+    )?;
+
+    log::info!("Invalidated {} summary {}: {}", scope, summary_id, reason);
+
+    load_summary(conn, &summary_id)
+}
+
+/// The project's summaries as a tree: overall, then each act, its chapters and their scenes.
+/// Parts without a summary are included with `summary: null` so gaps are visible.
+pub fn get_summary_rollup(conn: &Connection, params: Value) -> Result<Value> {
+    let GetSummaryRollupParams { project_id } = parse_params(params)?;
+    ensure_project(conn, project_id)?;
+
+    let mut by_reference: HashMap<(String, Option<String>), Value> = project_summaries(conn, project_id)?
+        .into_iter()
+        .map(|s| {
+            let key = (
+                s["scope"].as_str().unwrap_or_default().to_string(),
+                s["referenceId"].as_str().map(str::to_string),
+            );
+            (key, s)
+        })
+        .collect();
+    let mut take = |scope: SummaryScope, id: Option<&str>| {
+        by_reference.remove(&(scope.to_string(), id.map(str::to_string))).unwrap_or(Value::Null)
+    };
+
+    let mut acts_stmt = conn.prepare(
+        "SELECT a.id, a.name, a.position
+         FROM acts a JOIN plot_structures ps ON a.plot_structure_id = ps.id
+         WHERE ps.story_project_id = ?1
+         ORDER BY a.position",
+    )?;
+    let acts = acts_stmt
+        .query_map([project_id.to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i32>(2)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut chapters_stmt = conn.prepare("SELECT id, number, title FROM chapters WHERE act_id = ?1 ORDER BY number")?;
+    let mut scenes_stmt = conn.prepare("SELECT id, position, title FROM scenes WHERE chapter_id = ?1 ORDER BY position")?;
+
+    let mut act_nodes = Vec::new();
+    for (act_id, name, position) in acts {
+        let chapters = chapters_stmt
+            .query_map([&act_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i32>(1)?, row.get::<_, Option<String>>(2)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut chapter_nodes = Vec::new();
+        for (chapter_id, number, title) in chapters {
+            let scenes = scenes_stmt
+                .query_map([&chapter_id], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, i32>(1)?, row.get::<_, Option<String>>(2)?))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let scene_nodes: Vec<Value> = scenes
+                .into_iter()
+                .map(|(scene_id, position, title)| {
+                    json!({
+                        "sceneId": scene_id,
+                        "position": position,
+                        "title": title,
+                        "summary": take(SummaryScope::Scene, Some(&scene_id))
+                    })
+                })
+                .collect();
+            chapter_nodes.push(json!({
+                "chapterId": chapter_id,
+                "number": number,
+                "title": title,
+                "summary": take(SummaryScope::Chapter, Some(&chapter_id)),
+                "scenes": scene_nodes
+            }));
+        }
+        act_nodes.push(json!({
+            "actId": act_id,
+            "name": name,
+            "position": position,
+            "summary": take(SummaryScope::Act, Some(&act_id)),
+            "chapters": chapter_nodes
+        }));
+    }
+
+    let mut rollup = json!({
+        "projectId": project_id.to_string(),
+        "overall": take(SummaryScope::Overall, None),
+        "acts": act_nodes
+    });
+    let (mut missing, mut stale) = (0, 0);
+    count_gaps(&rollup, &mut missing, &mut stale);
+    rollup["missingCount"] = json!(missing);
+    rollup["staleCount"] = json!(stale);
+    Ok(rollup)
+}
+
+/// Count the nodes under `node` with no summary and with a stale one
+fn count_gaps(node: &Value, missing: &mut usize, stale: &mut usize) {
+    for key in ["overall", "summary"] {
+        match node.get(key) {
+            Some(Value::Null) => *missing += 1,
+            Some(summary) if summary["stale"] == true => *stale += 1,
+            _ => {}
+        }
+    }
+    for key in ["acts", "chapters", "scenes"] {
+        for child in node.get(key).and_then(Value::as_array).into_iter().flatten() {
+            count_gaps(child, missing, stale);
+        }
+    }
+}
+
+/// Tokens to load a summary with its key events and character developments
+pub fn summary_tokens(summary: &Value) -> usize {
+    let list = |key: &str| summary[key].as_array().map(|items| Value::Array(items.clone()).to_string());
+    let key_events = list("keyEvents");
+    let developments = list("characterDevelopments");
+    tokens::cost([summary["summaryText"].as_str(), key_events.as_deref(), developments.as_deref()])
+}
+
+/// Check the reference fits the scope and belongs to the project; returns it as stored
+fn check_reference(
+    conn: &Connection,
+    project_id: Uuid,
+    scope: SummaryScope,
+    reference_id: Option<Uuid>,
+) -> Result<Option<String>> {
+    let lookup = match (scope, reference_id) {
+        (SummaryScope::Overall, None) => {
+            ensure_project(conn, project_id)?;
+            return Ok(None);
+        }
+        (SummaryScope::Overall, Some(_)) => {
+            return Err(StoryError::validation("The overall summary has no reference").with_field("referenceId"));
+        }
+        (_, None) => {
+            return Err(StoryError::validation(format!("A {} summary needs the {} as referenceId", scope, scope))
+                .with_field("referenceId"));
+        }
+        (SummaryScope::Scene, Some(_)) => {
+            "SELECT ps.story_project_id FROM scenes s
+             JOIN chapters c ON s.chapter_id = c.id
+             JOIN acts a ON c.act_id = a.id
+             JOIN plot_structures ps ON a.plot_structure_id = ps.id
+             WHERE s.id = ?1"
+        }
+        (SummaryScope::Chapter, Some(_)) => {
+            "SELECT ps.story_project_id FROM chapters c
+             JOIN acts a ON c.act_id = a.id
+             JOIN plot_structures ps ON a.plot_structure_id = ps.id
+             WHERE c.id = ?1"
+        }
+        (SummaryScope::Act, Some(_)) => {
+            "SELECT ps.story_project_id FROM acts a
+             JOIN plot_structures ps ON a.plot_structure_id = ps.id
+             WHERE a.id = ?1"
+        }
+    };
+    let reference_id = reference_id.expect("scoped summaries have a reference").to_string();
+
+    let owner: Option<String> = conn.query_row(lookup, [&reference_id], |row| row.get(0)).optional()?;
+    if owner != Some(project_id.to_string()) {
+        return Err(StoryError::not_found(format!("{} not found in this project: {}", capitalize(scope), reference_id))
+            .with_field("referenceId")
+            .with_entity(scope.to_string(), reference_id));
+    }
+    Ok(Some(reference_id))
+}
+
+fn capitalize(scope: SummaryScope) -> String {
+    let s = scope.to_string();
+    let mut chars = s.chars();
+    chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+fn ensure_project(conn: &Connection, project_id: Uuid) -> Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM story_projects WHERE id = ?1)",
+        [project_id.to_string()],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Err(StoryError::not_found(format!("Project not found: {}", project_id))
+            .with_field("projectId")
+            .with_entity("project", project_id));
+    }
+    Ok(())
+}
+
+fn find_summary_id(
+    conn: &Connection,
+    project_id: Uuid,
+    scope: SummaryScope,
+    reference_id: Option<&str>,
+) -> Result<Option<String>> {
+    // `IS` so the overall summary's NULL reference matches
+    let id = conn
+        .query_row(
+            "SELECT id FROM story_summaries WHERE story_project_id = ?1 AND scope = ?2 AND reference_id IS ?3",
+            (project_id.to_string(), scope.to_string(), reference_id),
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(id)
+}
+
+fn summary_not_found(scope: SummaryScope, reference_id: Option<&str>) -> StoryError {
+    match reference_id {
+        Some(id) => StoryError::not_found(format!("No summary for {}: {}", scope, id))
+            .with_field("referenceId")
+            .with_entity(scope.to_string(), id),
+        None => StoryError::not_found("No overall summary for this project").with_field("scope"),
+    }
+}
+
+fn load_summary(conn: &Connection, summary_id: &str) -> Result<Value> {
+    let summary = conn.query_row(
+        &format!("SELECT {} FROM story_summaries WHERE id = ?1", SUMMARY_COLUMNS),
+        [summary_id],
+        summary_json,
+    )?;
+    Ok(summary)
+}
+
+/// Every summary of the project: overall first, then in story order by act, chapter and scene
+fn project_summaries(conn: &Connection, project_id: Uuid) -> Result<Vec<Value>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM story_summaries ss
+         LEFT JOIN scenes s ON ss.scope = 'scene' AND s.id = ss.reference_id
+         LEFT JOIN chapters c ON c.id = CASE ss.scope WHEN 'chapter' THEN ss.reference_id ELSE s.chapter_id END
+         LEFT JOIN acts a ON a.id = CASE ss.scope WHEN 'act' THEN ss.reference_id ELSE c.act_id END
+         WHERE ss.story_project_id = ?1
+         ORDER BY ss.scope != 'overall', a.position, c.number IS NOT NULL, c.number, s.position IS NOT NULL, s.position",
+        SUMMARY_COLUMNS
+            .split(',')
+            .map(|column| format!("ss.{}", column.trim()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))?;
+    let summaries = stmt
+        .query_map([project_id.to_string()], summary_json)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(summaries)
+}
+
+fn summary_json(row: &Row) -> rusqlite::Result<Value> {
+    let list = |text: Option<String>| text.and_then(|t| serde_json::from_str::<Value>(&t).ok());
+    Ok(json!({
+        "summaryId": row.get::<_, String>(0)?,
+        "projectId": row.get::<_, String>(1)?,
+        "scope": row.get::<_, String>(2)?,
+        "referenceId": row.get::<_, Option<String>>(3)?,
+        "summaryText": row.get::<_, String>(4)?,
+        "keyEvents": list(row.get(5)?),
+        "characterDevelopments": list(row.get(6)?),
+        "wordCount": row.get::<_, i32>(7)?,
+        "aiGenerated": row.get::<_, bool>(8)?,
+        "generatedAt": row.get::<_, String>(9)?,
+        "stale": row.get::<_, bool>(10)?,
+        "staleReason": row.get::<_, Option<String>>(11)?
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::tools::{add_chapter, add_scene, create_story_project, initialize_plot_structure};
+    use tempfile::tempdir;
+
+    struct Story {
+        project_id: String,
+        act_id: String,
+        chapter_id: String,
+        scene_ids: Vec<String>,
+    }
+
+    /// A project with one chapter of two scenes in its first act
+    fn story(conn: &Connection, title: &str) -> Story {
+        let stories = tempdir().unwrap();
+        let project = create_story_project(conn, stories.path(), json!({"title": title, "targetLength": "novel"})).unwrap();
+        let project_id = project["projectId"].as_str().unwrap().to_string();
+        let plot = initialize_plot_structure(conn, json!({"projectId": project_id})).unwrap();
+        let act_id = plot["acts"][0]["actId"].as_str().unwrap().to_string();
+        let chapter = add_chapter(conn, json!({"actId": act_id, "number": 1})).unwrap();
+        let chapter_id = chapter["chapterId"].as_str().unwrap().to_string();
+        let scene_ids = ["Storm", "Calm"]
+            .iter()
+            .map(|title| {
+                add_scene(conn, stories.path(), json!({"chapterId": chapter_id, "title": title})).unwrap()["sceneId"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        Story { project_id, act_id, chapter_id, scene_ids }
+    }
+
+    fn upsert(conn: &Connection, story: &Story, scope: &str, reference_id: Option<&str>, text: &str) -> Value {
+        let mut params = json!({"projectId": story.project_id, "scope": scope, "summaryText": text});
+        if let Some(id) = reference_id {
+            params["referenceId"] = json!(id);
+        }
+        upsert_summary(conn, params).unwrap()
+    }
+
+    #[test]
+    fn test_upsert_and_get_summary() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn, "Summaries");
+        let scene_id = story.scene_ids[0].as_str();
+
+        let created = upsert_summary(
+            &conn,
+            json!({"projectId": story.project_id, "scope": "scene", "referenceId": scene_id, "summaryText": "The storm breaks the pier",
+                   "keyEvents": ["Pier destroyed"], "characterDevelopments": ["Mira stops trusting the sea"], "aiGenerated": false}),
+        )
+        .unwrap();
+        assert_eq!(created["created"], true);
+        assert_eq!(created["wordCount"], 5);
+        assert_eq!(created["keyEvents"], json!(["Pier destroyed"]));
+        assert_eq!(created["aiGenerated"], false);
+
+        let updated = upsert(&conn, &story, "scene", Some(scene_id), "The storm takes the pier");
+        assert_eq!(updated["created"], false);
+        assert_eq!(updated["summaryId"], created["summaryId"]);
+
+        let fetched = get_summary(&conn, json!({"projectId": story.project_id, "scope": "scene", "referenceId": scene_id})).unwrap();
+        assert_eq!(fetched["summaryText"], "The storm takes the pier");
+        assert_eq!(fetched["keyEvents"], Value::Null);
+
+        // The overall summary has no reference, and is still unique
+        let overall = upsert(&conn, &story, "overall", None, "A town rebuilds");
+        let again = upsert(&conn, &story, "overall", None, "A town rebuilds after the storm");
+        assert_eq!(again["summaryId"], overall["summaryId"]);
+        let fetched = get_summary(&conn, json!({"projectId": story.project_id, "scope": "overall"})).unwrap();
+        assert_eq!(fetched["summaryText"], "A town rebuilds after the storm");
+
+        let err = get_summary(&conn, json!({"projectId": story.project_id, "scope": "chapter", "referenceId": story.chapter_id})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_summary_references_are_checked() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn, "Checked");
+        let other = self::story(&conn, "Elsewhere");
+
+        let attempt = |params: Value| upsert_summary(&conn, params).unwrap_err();
+        let err = attempt(json!({"projectId": story.project_id, "scope": "scene", "summaryText": "x"}));
+        assert!(matches!(err, StoryError::ValidationError(_)));
+        let err = attempt(json!({"projectId": story.project_id, "scope": "overall", "referenceId": story.act_id, "summaryText": "x"}));
+        assert!(matches!(err, StoryError::ValidationError(_)));
+        let err = attempt(json!({"projectId": story.project_id, "scope": "act", "referenceId": story.act_id, "summaryText": "  "}));
+        assert!(matches!(err, StoryError::ValidationError(_)));
+
+        // A chapter of another project, or a scene ID given as a chapter
+        let err = attempt(json!({"projectId": story.project_id, "scope": "chapter", "referenceId": other.chapter_id, "summaryText": "x"}));
+        assert!(matches!(err, StoryError::NotFound(_)));
+        assert!(err.to_string().contains("Chapter not found in this project"));
+        let err = attempt(json!({"projectId": story.project_id, "scope": "chapter", "referenceId": story.scene_ids[0], "summaryText": "x"}));
+        assert!(matches!(err, StoryError::NotFound(_)));
+        let err = attempt(json!({"projectId": Uuid::new_v4(), "scope": "overall", "summaryText": "x"}));
+        assert!(matches!(err, StoryError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_scene_edits_mark_summaries_stale() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn, "Stale");
+        let other = self::story(&conn, "Untouched");
+        upsert(&conn, &story, "scene", Some(&story.scene_ids[0]), "Storm");
+        upsert(&conn, &story, "scene", Some(&story.scene_ids[1]), "Calm");
+        upsert(&conn, &story, "chapter", Some(&story.chapter_id), "Storm then calm");
+        upsert(&conn, &story, "act", Some(&story.act_id), "Setup");
+        upsert(&conn, &story, "overall", None, "Whole story");
+        upsert(&conn, &other, "overall", None, "Another story");
+
+        // Saving the same content changes nothing
+        conn.execute("UPDATE scenes SET content = content WHERE id = ?1", [&story.scene_ids[0]]).unwrap();
+        let stale = list_summaries(&conn, json!({"projectId": story.project_id, "staleOnly": true})).unwrap();
+        assert_eq!(stale["summaries"], json!([]));
+
+        conn.execute("UPDATE scenes SET content = 'Thunder.' WHERE id = ?1", [&story.scene_ids[0]]).unwrap();
+        let stale = list_summaries(&conn, json!({"projectId": story.project_id, "staleOnly": true})).unwrap();
+        let scopes: Vec<&str> = stale["summaries"].as_array().unwrap().iter().map(|s| s["scope"].as_str().unwrap()).collect();
+        assert_eq!(scopes, vec!["overall", "act", "chapter", "scene"]);
+        assert_eq!(stale["summaries"][3]["referenceId"], story.scene_ids[0].as_str());
+        assert_eq!(stale["summaries"][3]["staleReason"], "Scene content changed");
+        assert_eq!(stale["summaries"][2]["staleReason"], "Content of a scene it covers changed");
+        let other_stale = list_summaries(&conn, json!({"projectId": other.project_id, "staleOnly": true})).unwrap();
+        assert_eq!(other_stale["summaries"], json!([]));
+
+        // Rewriting a summary clears the mark
+        let rewritten = upsert(&conn, &story, "scene", Some(&story.scene_ids[0]), "Thunder");
+        assert_eq!(rewritten["stale"], false);
+        assert_eq!(rewritten["staleReason"], Value::Null);
+
+        let invalidated = invalidate_summary(
+            &conn,
+            json!({"projectId": story.project_id, "scope": "scene", "referenceId": story.scene_ids[1], "reason": "Mira's name changed"}),
+        )
+        .unwrap();
+        assert_eq!(invalidated["stale"], true);
+        assert_eq!(invalidated["staleReason"], "Mira's name changed");
+
+        let scenes = list_summaries(&conn, json!({"projectId": story.project_id, "scope": "scene", "includeTokens": true})).unwrap();
+        let summaries = scenes["summaries"].as_array().unwrap();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0]["summaryText"], "Thunder");
+        assert_eq!(summaries[0]["tokens"], tokens::count("Thunder"));
+        assert_eq!(scenes["tokenCounting"], tokens::counter().method());
+    }
+
+    #[test]
+    fn test_summary_rollup() {
+        let conn = db::initialize_database(":memory:").unwrap();
+        let story = story(&conn, "Rollup");
+        upsert(&conn, &story, "overall", None, "Whole story");
+        upsert(&conn, &story, "chapter", Some(&story.chapter_id), "Storm then calm");
+        upsert(&conn, &story, "scene", Some(&story.scene_ids[1]), "Calm");
+        conn.execute("UPDATE scenes SET content = 'Still water.' WHERE id = ?1", [&story.scene_ids[1]]).unwrap();
+
+        let rollup = get_summary_rollup(&conn, json!({"projectId": story.project_id})).unwrap();
+        assert_eq!(rollup["overall"]["summaryText"], "Whole story");
+        let acts = rollup["acts"].as_array().unwrap();
+        assert_eq!(acts.len(), 3);
+        assert_eq!(acts[0]["summary"], Value::Null);
+
+        let chapter = &acts[0]["chapters"][0];
+        assert_eq!(chapter["summary"]["summaryText"], "Storm then calm");
+        assert_eq!(chapter["scenes"][0]["title"], "Storm");
+        assert_eq!(chapter["scenes"][0]["summary"], Value::Null);
+        assert_eq!(chapter["scenes"][1]["summary"]["summaryText"], "Calm");
+
+        // Three acts and the first scene lack summaries; the edit staled the rest
+        assert_eq!(rollup["missingCount"], 4);
+        assert_eq!(rollup["staleCount"], 3);
+
+        let err = get_summary_rollup(&conn, json!({"projectId": Uuid::new_v4()})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+    }
+}
@@ -1,54 +1,86 @@
+use super::parse_params;
+use crate::context::tokens;
 use crate::error::{Result, StoryError};
 use crate::models::{RuleScope, WorldRule};
 use chrono::Utc;
 use rusqlite::Connection;
+use schemars::JsonSchema;
+use serde::Deserialize;
 use serde_json::{json, Value};
 use uuid::Uuid;
 
+/// Keywords that trigger a world rule, as a list or a single string
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Keywords {
+    List(Vec<String>),
+    Text(String),
+}
+
+impl Keywords {
+    /// Lists are stored as JSON arrays, plain strings as-is
+    fn into_stored(self) -> String {
+        match self {
+            Keywords::List(list) => serde_json::to_string(&list).unwrap(),
+            Keywords::Text(s) => s,
+        }
+    }
+}
+
+/// Parameters for `addWorldRule`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AddWorldRuleParams {
+    pub project_id: Uuid,
+    #[schemars(length(max = 100))]
+    pub name: String,
+    pub description: String,
+    pub scope: RuleScope,
+    pub examples: Option<String>,
+    pub keywords: Option<Keywords>,
+}
+
+/// Parameters for `getWorldRule`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GetWorldRuleParams {
+    pub rule_id: Uuid,
+}
+
+/// Parameters for `updateWorldRule`; omitted fields are left unchanged
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdateWorldRuleParams {
+    pub rule_id: Uuid,
+    pub description: Option<String>,
+    pub scope: Option<RuleScope>,
+    pub examples: Option<String>,
+    pub keywords: Option<Keywords>,
+}
+
+/// Parameters for `listWorldRules`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ListWorldRulesParams {
+    pub project_id: Uuid,
+    /// Also report what each rule costs to load into context
+    pub include_tokens: Option<bool>,
+}
+
 /// Add a new world rule to a story project
 pub fn add_world_rule(conn: &Connection, params: Value) -> Result<Value> {
-    let project_id_str = params
-        .get("projectId")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: projectId"))?;
-
-    let project_id = Uuid::parse_str(project_id_str)
-        .map_err(|_| StoryError::validation("Invalid UUID format for projectId"))?;
-
-    let name = params
-        .get("name")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: name"))?;
-
-    let description = params
-        .get("description")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: description"))?;
-
-    let scope_str = params
-        .get("scope")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: scope"))?;
-
-    let scope = RuleScope::from_str(scope_str)
-        .ok_or_else(|| StoryError::validation(format!("Invalid scope: {}", scope_str)))?;
-
-    let examples = params.get("examples").and_then(|v| v.as_str());
-
-    // Handle keywords (could be array or string)
-    let keywords = params.get("keywords").map(|v| {
-        if let Some(arr) = v.as_array() {
-            serde_json::to_string(&arr).unwrap()
-        } else if let Some(s) = v.as_str() {
-            s.to_string()
-        } else {
-            serde_json::to_string(&v).unwrap()
-        }
-    });
+    let params: AddWorldRuleParams = parse_params(params)?;
+    let project_id = params.project_id;
+    let name = params.name.as_str();
+    let description = params.description.as_str();
+    let scope = params.scope;
+    let examples = params.examples.as_deref();
+
+    let keywords = params.keywords.map(Keywords::into_stored);
 
     // Validate lengths
-    if name.len() > 100 {
-        return Err(StoryError::validation("Name must be 100 characters or less"));
+    if name.chars().count() > 100 {
+        return Err(StoryError::validation("Name must be 100 characters or less").with_field("name"));
     }
 
     let rule = WorldRule {
@@ -80,7 +112,16 @@ pub fn add_world_rule(conn: &Connection, params: Value) -> Result<Value> {
         ),
     ).map_err(|e| {
         if e.to_string().contains("UNIQUE constraint failed") {
-            StoryError::duplicate(format!("World rule '{}' already exists in this project", name))
+            let err = StoryError::duplicate(format!("World rule '{}' already exists in this project", name))
+                .with_field("name");
+            match conn.query_row(
+                "SELECT id FROM world_rules WHERE story_project_id = ?1 AND name = ?2",
+                (project_id.to_string(), name),
+                |row| row.get::<_, String>(0),
+            ) {
+                Ok(existing_id) => err.with_entity("world_rule", existing_id),
+                Err(_) => err,
+            }
         } else {
             StoryError::DatabaseError(e)
         }
@@ -101,13 +142,7 @@ pub fn add_world_rule(conn: &Connection, params: Value) -> Result<Value> {
 
 /// Get a world rule by ID
 pub fn get_world_rule(conn: &Connection, params: Value) -> Result<Value> {
-    let rule_id_str = params
-        .get("ruleId")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: ruleId"))?;
-
-    let rule_id = Uuid::parse_str(rule_id_str)
-        .map_err(|_| StoryError::validation("Invalid UUID format for ruleId"))?;
+    let GetWorldRuleParams { rule_id } = parse_params(params)?;
 
     let mut stmt = conn.prepare(
         "SELECT id, story_project_id, name, description, scope, examples, keywords, created_at, updated_at
@@ -133,6 +168,8 @@ pub fn get_world_rule(conn: &Connection, params: Value) -> Result<Value> {
     }).map_err(|e| {
         if e == rusqlite::Error::QueryReturnedNoRows {
             StoryError::not_found(format!("World rule not found: {}", rule_id))
+                .with_field("ruleId")
+                .with_entity("world_rule", rule_id)
         } else {
             StoryError::DatabaseError(e)
         }
@@ -149,18 +186,51 @@ pub fn get_world_rule(conn: &Connection, params: Value) -> Result<Value> {
     }))
 }
 
+/// Update a world rule's description, scope, examples or keywords
+pub fn update_world_rule(conn: &Connection, params: Value) -> Result<Value> {
+    let params: UpdateWorldRuleParams = parse_params(params)?;
+    let rule_id = params.rule_id;
+
+    if params.description.is_none() && params.scope.is_none() && params.examples.is_none() && params.keywords.is_none() {
+        return Err(StoryError::validation("No fields to update"));
+    }
+
+    let updated = conn.execute(
+        "UPDATE world_rules SET
+            description = COALESCE(?2, description),
+            scope = COALESCE(?3, scope),
+            examples = COALESCE(?4, examples),
+            keywords = COALESCE(?5, keywords),
+            updated_at = ?6
+         WHERE id = ?1",
+        (
+            rule_id.to_string(),
+            &params.description,
+            params.scope.as_ref().map(|s| s.to_string()),
+            &params.examples,
+            params.keywords.map(Keywords::into_stored),
+            Utc::now().to_rfc3339(),
+        ),
+    )?;
+
+    if updated == 0 {
+        return Err(StoryError::not_found(format!("World rule not found: {}", rule_id))
+            .with_field("ruleId")
+            .with_entity("world_rule", rule_id));
+    }
+
+    log::info!("Updated world rule: {}", rule_id);
+
+    get_world_rule(conn, json!({ "ruleId": rule_id }))
+}
+
 /// List all world rules in a project
 pub fn list_world_rules(conn: &Connection, params: Value) -> Result<Value> {
-    let project_id_str = params
-        .get("projectId")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| StoryError::validation("Missing required field: projectId"))?;
-
-    let project_id = Uuid::parse_str(project_id_str)
-        .map_err(|_| StoryError::validation("Invalid UUID format for projectId"))?;
+    let ListWorldRulesParams { project_id, include_tokens } = parse_params(params)?;
+    let include_tokens = include_tokens.unwrap_or(false);
 
     let mut stmt = conn.prepare(
-        "SELECT id, name, description, scope, keywords
+        "SELECT id, name, description, scope, keywords, examples
          FROM world_rules
          WHERE story_project_id = ?1
          ORDER BY scope, name"
@@ -169,21 +239,37 @@ pub fn list_world_rules(conn: &Connection, params: Value) -> Result<Value> {
     let rules = stmt
         .query_map([project_id.to_string()], |row| {
             let keywords_str: Option<String> = row.get(4)?;
-            Ok(json!({
+            let name: String = row.get(1)?;
+            let description: String = row.get(2)?;
+            let mut rule = json!({
                 "ruleId": row.get::<_, String>(0)?,
-                "name": row.get::<_, String>(1)?,
-                "description": row.get::<_, String>(2)?,
+                "name": name,
+                "description": description,
                 "scope": row.get::<_, String>(3)?,
                 "keywords": keywords_str.as_ref().and_then(|k| serde_json::from_str::<Value>(k).ok())
-            }))
+            });
+            if include_tokens {
+                let examples: Option<String> = row.get(5)?;
+                rule["tokens"] = json!(tokens::world_rule_cost(
+                    &name,
+                    &description,
+                    examples.as_deref(),
+                    keywords_str.as_deref(),
+                ));
+            }
+            Ok(rule)
         })?
         .collect::<std::result::Result<Vec<_>, _>>()?;
 
     log::info!("Listed {} world rules for project {}", rules.len(), project_id);
 
-    Ok(json!({
+    let mut response = json!({
         "rules": rules
-    }))
+    });
+    if include_tokens {
+        response["tokenCounting"] = json!(tokens::counter().method());
+    }
+    Ok(response)
 }
 
 #[cfg(test)]
@@ -199,7 +285,7 @@ mod tests {
         let db_path = dir.path().join("test.db");
         let conn = db::initialize_database(&db_path).unwrap();
 
-        let project = create_story_project(&conn, json!({"title": "Test", "targetLength": "novel"})).unwrap();
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Test", "targetLength": "novel"})).unwrap();
         let project_id = project.get("projectId").unwrap().as_str().unwrap();
 
         let params = json!({
@@ -219,13 +305,46 @@ mod tests {
         assert_eq!(response.get("scope").unwrap(), "universal");
     }
 
+    #[test]
+    fn test_name_length_counts_characters() {
+        let dir = tempdir().unwrap();
+        let conn = db::initialize_database(dir.path().join("test.db")).unwrap();
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Test", "targetLength": "novel"})).unwrap();
+        let named = |name: String| {
+            json!({"projectId": project["projectId"], "name": name, "description": "Old magic", "scope": "universal"})
+        };
+
+        // 100 characters, 200 bytes
+        add_world_rule(&conn, named("ß".repeat(100))).unwrap();
+        let err = add_world_rule(&conn, named("ø".repeat(101))).unwrap_err();
+        assert!(matches!(err, StoryError::ValidationError(_)));
+    }
+
+    #[test]
+    fn test_update_world_rule() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let conn = db::initialize_database(&db_path).unwrap();
+
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Test", "targetLength": "novel"})).unwrap();
+        let project_id = project.get("projectId").unwrap().as_str().unwrap();
+        let rule = add_world_rule(&conn, json!({"projectId": project_id, "name": "Tides", "description": "Sea rises at dusk", "scope": "regional"})).unwrap();
+
+        let response = update_world_rule(&conn, json!({"ruleId": rule["ruleId"], "keywords": ["sea", "tide"]})).unwrap();
+        assert_eq!(response["keywords"], json!(["sea", "tide"]));
+        assert_eq!(response["description"], "Sea rises at dusk");
+
+        let err = update_world_rule(&conn, json!({"ruleId": Uuid::new_v4(), "scope": "universal"})).unwrap_err();
+        assert!(matches!(err, StoryError::NotFound(_)));
+    }
+
     #[test]
     fn test_list_world_rules() {
         let dir = tempdir().unwrap();
         let db_path = dir.path().join("test.db");
         let conn = db::initialize_database(&db_path).unwrap();
 
-        let project = create_story_project(&conn, json!({"title": "Test", "targetLength": "novel"})).unwrap();
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Test", "targetLength": "novel"})).unwrap();
         let project_id = project.get("projectId").unwrap().as_str().unwrap();
 
         add_world_rule(&conn, json!({"projectId": project_id, "name": "Rule 1", "description": "Test", "scope": "universal"})).unwrap();
@@ -238,4 +357,20 @@ mod tests {
         let rules = response.get("rules").unwrap().as_array().unwrap();
         assert_eq!(rules.len(), 2);
     }
+
+    #[test]
+    fn test_list_world_rules_with_tokens() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let conn = db::initialize_database(&db_path).unwrap();
+
+        let project = create_story_project(&conn, dir.path(), json!({"title": "Test", "targetLength": "novel"})).unwrap();
+        let project_id = project.get("projectId").unwrap().as_str().unwrap();
+
+        add_world_rule(&conn, json!({"projectId": project_id, "name": "Tides", "description": "The sea remembers names", "scope": "universal", "examples": "A drowned sailor returns"})).unwrap();
+
+        let response = list_world_rules(&conn, json!({"projectId": project_id, "includeTokens": true})).unwrap();
+        let expected = tokens::count("Tides") + tokens::count("The sea remembers names") + tokens::count("A drowned sailor returns");
+        assert_eq!(response["rules"][0]["tokens"], expected);
+    }
 }